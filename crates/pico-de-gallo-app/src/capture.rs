@@ -0,0 +1,291 @@
+//! Files of logic analyzer captures, as read by PulseView.
+
+use pico_de_gallo_lib::{CAPTURE_CHANNEL_NAMES, CAPTURE_CHANNELS};
+use std::io::{self, Write};
+
+/// Timescales of the VCD, from 10^0 to 10^9 units per second.
+const TIMESCALES: [&str; 10] = [
+    "1 s", "100 ms", "10 ms", "1 ms", "100 us", "10 us", "1 us", "100 ns", "10 ns", "1 ns",
+];
+
+/// Write `samples` taken at `rate` Hz as a Value Change Dump.
+///
+/// The timescale is the longest power of ten not exceeding the sample
+/// period, so that importers don't blow the capture up into many more
+/// samples than were taken.
+pub fn write_vcd(w: &mut impl Write, rate: u32, samples: &[u16]) -> io::Result<()> {
+    let exponent = (0..TIMESCALES.len())
+        .find(|&n| 10u64.pow(n as u32) >= u64::from(rate))
+        .unwrap_or(TIMESCALES.len() - 1);
+    let units = 10u128.pow(exponent as u32);
+    let time = |sample: usize| sample as u128 * units / u128::from(rate);
+    // Identifiers from a, avoiding the $ of keywords
+    let id = |channel: usize| char::from(b'a' + channel as u8);
+
+    writeln!(w, "$version gallo {} $end", env!("CARGO_PKG_VERSION"))?;
+    writeln!(w, "$timescale {} $end", TIMESCALES[exponent])?;
+    writeln!(w, "$scope module gallo $end")?;
+    for (channel, name) in CAPTURE_CHANNEL_NAMES.iter().enumerate() {
+        writeln!(w, "$var wire 1 {} {} $end", id(channel), name)?;
+    }
+    writeln!(w, "$upscope $end")?;
+    writeln!(w, "$enddefinitions $end")?;
+
+    let mut previous = None;
+    for (index, &sample) in samples.iter().enumerate() {
+        let changed = match previous {
+            Some(previous) => sample ^ previous,
+            None => u16::MAX,
+        };
+        previous = Some(sample);
+
+        if changed == 0 {
+            continue;
+        }
+
+        writeln!(w, "#{}", time(index))?;
+        for channel in (0..CAPTURE_CHANNELS).filter(|channel| changed & (1 << channel) != 0) {
+            writeln!(w, "{}{}", (sample >> channel) & 1, id(channel))?;
+        }
+    }
+
+    // Where the last sample ends
+    writeln!(w, "#{}", time(samples.len()))
+}
+
+/// Write `samples` taken at `rate` Hz as a sigrok session, i.e. a ZIP
+/// archive holding the metadata and raw samples of a single device.
+pub fn write_sigrok(w: &mut impl Write, rate: u32, samples: &[u16]) -> io::Result<()> {
+    let mut metadata = format!(
+        "[global]\n\n[device 1]\ncapturefile=logic-1\ntotal probes={}\nsamplerate={}\nunitsize=2\n",
+        CAPTURE_CHANNELS, rate
+    );
+    for (channel, name) in CAPTURE_CHANNEL_NAMES.iter().enumerate() {
+        metadata += &format!("probe{}={}\n", channel + 1, name);
+    }
+
+    let data = samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect::<Vec<_>>();
+
+    write_zip(
+        w,
+        &[
+            ("version", b"2".as_slice()),
+            ("metadata", metadata.as_bytes()),
+            ("logic-1-1", &data),
+        ],
+    )
+}
+
+/// Write `files` as a ZIP archive, stored without compression.
+fn write_zip(w: &mut impl Write, files: &[(&str, &[u8])]) -> io::Result<()> {
+    // 1980-01-01 00:00, the earliest MS-DOS date
+    const TIME: u16 = 0;
+    const DATE: u16 = (1 << 5) | 1;
+
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+
+    for &(name, data) in files {
+        let offset = archive.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+
+        // Fields shared by the local header and the directory entry:
+        // version needed, flags, method, time, date, CRC and sizes
+        let mut common = Vec::new();
+        common.extend_from_slice(&10u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&TIME.to_le_bytes());
+        common.extend_from_slice(&DATE.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        archive.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        archive.extend_from_slice(&common);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(data);
+
+        directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        // Version made by
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&common);
+        // Comment length, disk number, internal and external attributes
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+
+    let entries = (files.len() as u16).to_le_bytes();
+    let directory_offset = archive.len() as u32;
+    let directory_size = directory.len() as u32;
+
+    archive.extend_from_slice(&directory);
+    archive.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    // Disk numbers
+    archive.extend_from_slice(&[0; 4]);
+    archive.extend_from_slice(&entries);
+    archive.extend_from_slice(&entries);
+    archive.extend_from_slice(&directory_size.to_le_bytes());
+    archive.extend_from_slice(&directory_offset.to_le_bytes());
+    // Comment length
+    archive.extend_from_slice(&[0; 2]);
+
+    w.write_all(&archive)
+}
+
+/// CRC-32 of ZIP archives, reflected with polynomial 0x04c11db7.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Timescale and timestamps of the VCD of `samples`.
+    fn vcd(rate: u32, samples: &[u16]) -> (String, Vec<u128>) {
+        let mut out = Vec::new();
+        write_vcd(&mut out, rate, samples).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let timescale = out
+            .lines()
+            .find_map(|line| line.strip_prefix("$timescale ")?.strip_suffix(" $end"))
+            .unwrap()
+            .to_string();
+        let timestamps = out
+            .lines()
+            .filter_map(|line| line.strip_prefix('#'))
+            .map(|time| time.parse().unwrap())
+            .collect();
+
+        (timescale, timestamps)
+    }
+
+    /// Name and data of every file of a stored ZIP `archive`, checking
+    /// their CRC.
+    fn unzip(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let u16_at = |offset: usize| usize::from(u16::from_le_bytes([archive[offset], archive[offset + 1]]));
+        let u32_at = |offset: usize| u32::from_le_bytes(archive[offset..offset + 4].try_into().unwrap());
+
+        let mut files = Vec::new();
+        let mut offset = 0;
+        while u32_at(offset) == 0x0403_4b50 {
+            let crc = u32_at(offset + 14);
+            let size = u32_at(offset + 18) as usize;
+            let name_len = u16_at(offset + 26);
+            let name = &archive[offset + 30..offset + 30 + name_len];
+            let data = &archive[offset + 30 + name_len..offset + 30 + name_len + size];
+
+            assert_eq!(crc32(data), crc);
+            files.push((String::from_utf8(name.to_vec()).unwrap(), data.to_vec()));
+            offset += 30 + name_len + size;
+        }
+
+        files
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn zip_is_byte_exact() {
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            // Local header: signature, version needed, flags, method, time, date
+            0x50, 0x4b, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00,
+            // CRC-32, sizes, name and extra field lengths
+            0xac, 0x2a, 0x93, 0xd8, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            // Name and data
+            b'a', b'h', b'i',
+            // Directory entry: signature, versions made by and needed, flags, method, time, date
+            0x50, 0x4b, 0x01, 0x02, 0x14, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00,
+            // CRC-32, sizes, name, extra field and comment lengths
+            0xac, 0x2a, 0x93, 0xd8, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+            // Disk number, attributes, offset of the local header
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // Name
+            b'a',
+            // End of directory: signature, disk numbers, entries
+            0x50, 0x4b, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00,
+            // Directory size and offset, comment length
+            0x2f, 0x00, 0x00, 0x00, 0x21, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let mut archive = Vec::new();
+        write_zip(&mut archive, &[("a", b"hi")]).unwrap();
+        assert_eq!(archive, expected);
+    }
+
+    #[test]
+    fn sigrok_session_files() {
+        let mut archive = Vec::new();
+        write_sigrok(&mut archive, 1_000_000, &[0x0001, 0x8002]).unwrap();
+        let files = unzip(&archive);
+
+        let names: Vec<_> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["version", "metadata", "logic-1-1"]);
+        assert_eq!(files[0].1, b"2");
+
+        let metadata = String::from_utf8(files[1].1.clone()).unwrap();
+        assert!(metadata.contains("samplerate=1000000\n"));
+        assert!(metadata.contains("unitsize=2\n"));
+        assert!(metadata.contains(&format!("total probes={}\n", CAPTURE_CHANNELS)));
+
+        assert_eq!(files[2].1, [0x01, 0x00, 0x02, 0x80]);
+    }
+
+    #[test]
+    fn vcd_timescale() {
+        assert_eq!(vcd(1, &[0]).0, "1 s");
+        assert_eq!(vcd(1_000_000, &[0]).0, "1 us");
+        assert_eq!(vcd(50_000_000, &[0]).0, "10 ns");
+        assert_eq!(vcd(10_000_001, &[0]).0, "10 ns");
+    }
+
+    #[test]
+    fn vcd_dumps_changes_only() {
+        let (_, timestamps) = vcd(1_000_000, &[0, 0, 1, 1, 0]);
+        assert_eq!(timestamps, [0, 2, 4, 5]);
+    }
+
+    #[test]
+    fn vcd_timestamps_increase() {
+        let samples: Vec<u16> = (0..1000).map(|n| if n % 2 == 0 { 0 } else { u16::MAX }).collect();
+
+        for rate in [50_000_000, 10_000_001, 1_000_001, 101] {
+            let (_, timestamps) = vcd(rate, &samples);
+            assert_eq!(timestamps.len(), samples.len() + 1, "{rate} Hz");
+            assert!(timestamps.windows(2).all(|pair| pair[0] < pair[1]), "{rate} Hz");
+        }
+
+        // 10 ns per 20 ns sample
+        let (_, timestamps) = vcd(50_000_000, &samples);
+        assert_eq!(timestamps[1], 2);
+        assert_eq!(timestamps[1000], 2000);
+    }
+}
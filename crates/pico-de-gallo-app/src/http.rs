@@ -0,0 +1,355 @@
+//! HTTP/JSON front-end for Pico de Gallo.
+//!
+//! Ping, version, bus configuration, raw I2C and SPI reads and writes,
+//! and GPIO are served as JSON routes, for the benefit of tooling which
+//! can't link against `pico-de-gallo-lib`. The other requests of the
+//! device are only available through the library and the CLI.
+
+use crate::{i2c_address, parse_address};
+use axum::extract::{FromRef, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use futures::Stream;
+use pico_de_gallo_lib::{
+    ConfigError, Configuration, GpioState, I2cAddress, I2cConfig, PicoDeGallo, PicoDeGalloError, SpiConfig, VersionInfo,
+};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::sync::Semaphore;
+
+/// Number of GPIO event streams served at once, each of them keeping a
+/// wait pending on the device.
+const MAX_GPIO_STREAMS: usize = 4;
+
+/// Serve the HTTP API for `gallo` on `addr`.
+pub async fn serve<A: ToSocketAddrs>(gallo: PicoDeGallo, addr: A) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    axum::serve(listener, router(gallo)).await
+}
+
+#[derive(Clone)]
+struct AppState {
+    gallo: PicoDeGallo,
+    gpio_streams: Arc<Semaphore>,
+}
+
+impl FromRef<AppState> for PicoDeGallo {
+    fn from_ref(state: &AppState) -> Self {
+        state.gallo.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Semaphore> {
+    fn from_ref(state: &AppState) -> Self {
+        state.gpio_streams.clone()
+    }
+}
+
+fn router(gallo: PicoDeGallo) -> Router {
+    let state = AppState {
+        gallo,
+        gpio_streams: Arc::new(Semaphore::new(MAX_GPIO_STREAMS)),
+    };
+
+    Router::new()
+        .route("/version", get(version))
+        .route("/ping", post(ping))
+        .route("/config", get(get_config))
+        .route("/i2c/config", put(i2c_set_config))
+        .route("/i2c/{address}/read", post(i2c_read))
+        .route("/i2c/{address}/write", post(i2c_write))
+        .route("/spi/config", put(spi_set_config))
+        .route("/spi/read", post(spi_read))
+        .route("/spi/write", post(spi_write))
+        .route("/spi/flush", post(spi_flush))
+        .route("/gpio/{pin}", get(gpio_get).put(gpio_put))
+        .route("/gpio/{pin}/wait-high", post(gpio_wait_for_high))
+        .route("/gpio/{pin}/wait-low", post(gpio_wait_for_low))
+        .route("/gpio/{pin}/wait-rising", post(gpio_wait_for_rising_edge))
+        .route("/gpio/{pin}/wait-falling", post(gpio_wait_for_falling_edge))
+        .route("/gpio/{pin}/wait-any", post(gpio_wait_for_any_edge))
+        .route("/gpio/{pin}/events", get(gpio_events))
+        .with_state(state)
+}
+
+// ----------------------------- Errors -----------------------------
+
+struct HttpError {
+    status: StatusCode,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        (self.status, Json(ErrorBody { error: self.message })).into_response()
+    }
+}
+
+impl<E: Debug> From<PicoDeGalloError<E>> for HttpError {
+    fn from(value: PicoDeGalloError<E>) -> Self {
+        match value {
+            PicoDeGalloError::Comms(e) => Self {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                message: format!("{:?}", e),
+            },
+            PicoDeGalloError::Endpoint(e) => Self {
+                status: StatusCode::BAD_GATEWAY,
+                message: format!("{:?}", e),
+            },
+        }
+    }
+}
+
+impl From<ConfigError> for HttpError {
+    fn from(value: ConfigError) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: value.to_string(),
+        }
+    }
+}
+
+fn parse_i2c_address(address: &str, ten_bit: bool) -> Result<I2cAddress, HttpError> {
+    let address = parse_address(address).map_err(|e| HttpError {
+        status: StatusCode::BAD_REQUEST,
+        message: format!("invalid address {}: {}", address, e),
+    })?;
+
+    Ok(i2c_address(address, ten_bit))
+}
+
+// ----------------------------- Bodies -----------------------------
+
+#[derive(Serialize, Deserialize)]
+struct Ping {
+    id: u32,
+}
+
+#[derive(Deserialize)]
+struct ReadRequest {
+    count: u16,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Data {
+    data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Gpio {
+    state: GpioState,
+}
+
+#[derive(Serialize)]
+struct ActualFrequency {
+    /// Bus frequency achieved by the dividers, in Hz
+    actual_frequency: u32,
+}
+
+#[derive(Deserialize)]
+struct BusQuery {
+    /// Bus number, the first bus when left out
+    #[serde(default)]
+    bus: u8,
+}
+
+#[derive(Deserialize)]
+struct I2cQuery {
+    /// Bus number, the first bus when left out
+    #[serde(default)]
+    bus: u8,
+    /// Use 10-bit addressing for addresses up to 0x7f too
+    #[serde(default)]
+    ten_bit: bool,
+}
+
+// ----------------------------- Handlers -----------------------------
+
+async fn version(State(gallo): State<PicoDeGallo>) -> Result<Json<VersionInfo>, HttpError> {
+    Ok(Json(gallo.version().await?))
+}
+
+async fn ping(State(gallo): State<PicoDeGallo>, Json(req): Json<Ping>) -> Result<Json<Ping>, HttpError> {
+    let id = gallo.ping(req.id).await?;
+    Ok(Json(Ping { id }))
+}
+
+async fn get_config(State(gallo): State<PicoDeGallo>) -> Result<Json<Configuration>, HttpError> {
+    Ok(Json(gallo.get_config().await?))
+}
+
+async fn i2c_set_config(
+    State(gallo): State<PicoDeGallo>,
+    Query(query): Query<BusQuery>,
+    Json(req): Json<I2cConfig>,
+) -> Result<Json<ActualFrequency>, HttpError> {
+    req.validate()?;
+    let actual_frequency = gallo.i2c_set_config(query.bus, req).await?;
+    Ok(Json(ActualFrequency { actual_frequency }))
+}
+
+async fn spi_set_config(
+    State(gallo): State<PicoDeGallo>,
+    Query(query): Query<BusQuery>,
+    Json(req): Json<SpiConfig>,
+) -> Result<Json<ActualFrequency>, HttpError> {
+    req.validate()?;
+    let actual_frequency = gallo.spi_set_config(query.bus, req).await?;
+    Ok(Json(ActualFrequency { actual_frequency }))
+}
+
+async fn i2c_read(
+    State(gallo): State<PicoDeGallo>,
+    Path(address): Path<String>,
+    Query(query): Query<I2cQuery>,
+    Json(req): Json<ReadRequest>,
+) -> Result<Json<Data>, HttpError> {
+    let address = parse_i2c_address(&address, query.ten_bit)?;
+    let data = gallo.i2c_read(query.bus, address, req.count).await?;
+    Ok(Json(Data { data }))
+}
+
+async fn i2c_write(
+    State(gallo): State<PicoDeGallo>,
+    Path(address): Path<String>,
+    Query(query): Query<I2cQuery>,
+    Json(req): Json<Data>,
+) -> Result<StatusCode, HttpError> {
+    let address = parse_i2c_address(&address, query.ten_bit)?;
+    gallo.i2c_write(query.bus, address, &req.data).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn spi_read(
+    State(gallo): State<PicoDeGallo>,
+    Query(query): Query<BusQuery>,
+    Json(req): Json<ReadRequest>,
+) -> Result<Json<Data>, HttpError> {
+    let data = gallo.spi_read(query.bus, req.count).await?;
+    Ok(Json(Data { data }))
+}
+
+async fn spi_write(
+    State(gallo): State<PicoDeGallo>,
+    Query(query): Query<BusQuery>,
+    Json(req): Json<Data>,
+) -> Result<StatusCode, HttpError> {
+    gallo.spi_write(query.bus, &req.data).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn spi_flush(State(gallo): State<PicoDeGallo>, Query(query): Query<BusQuery>) -> Result<StatusCode, HttpError> {
+    gallo.spi_flush(query.bus).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn gpio_get(State(gallo): State<PicoDeGallo>, Path(pin): Path<u8>) -> Result<Json<Gpio>, HttpError> {
+    let state = gallo.gpio_get(pin).await?;
+    Ok(Json(Gpio { state }))
+}
+
+async fn gpio_put(
+    State(gallo): State<PicoDeGallo>,
+    Path(pin): Path<u8>,
+    Json(req): Json<Gpio>,
+) -> Result<StatusCode, HttpError> {
+    gallo.gpio_put(pin, req.state).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn gpio_wait_for_high(State(gallo): State<PicoDeGallo>, Path(pin): Path<u8>) -> Result<StatusCode, HttpError> {
+    gallo.gpio_wait_for_high(pin).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn gpio_wait_for_low(State(gallo): State<PicoDeGallo>, Path(pin): Path<u8>) -> Result<StatusCode, HttpError> {
+    gallo.gpio_wait_for_low(pin).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn gpio_wait_for_rising_edge(
+    State(gallo): State<PicoDeGallo>,
+    Path(pin): Path<u8>,
+) -> Result<StatusCode, HttpError> {
+    gallo.gpio_wait_for_rising_edge(pin).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn gpio_wait_for_falling_edge(
+    State(gallo): State<PicoDeGallo>,
+    Path(pin): Path<u8>,
+) -> Result<StatusCode, HttpError> {
+    gallo.gpio_wait_for_falling_edge(pin).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn gpio_wait_for_any_edge(
+    State(gallo): State<PicoDeGallo>,
+    Path(pin): Path<u8>,
+) -> Result<StatusCode, HttpError> {
+    gallo.gpio_wait_for_any_edge(pin).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Stream the state of the GPIO as server-sent `state` events,
+/// starting with the current state, then after every edge.
+///
+/// The pin is switched to an input, no longer driving a level set by
+/// `PUT /gpio/{pin}`, and stays busy while an edge is awaited. The
+/// state is read once the edge is reported, so a pulse shorter than a
+/// round trip to the device shows up as two events with the same
+/// state.
+async fn gpio_events(
+    State(gallo): State<PicoDeGallo>,
+    State(streams): State<Arc<Semaphore>>,
+    Path(pin): Path<u8>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, HttpError> {
+    let permit = streams.try_acquire_owned().map_err(|_| HttpError {
+        status: StatusCode::TOO_MANY_REQUESTS,
+        message: format!("at most {} GPIO streams are served at once", MAX_GPIO_STREAMS),
+    })?;
+
+    let stream = futures::stream::unfold(
+        (gallo, permit, false, false),
+        move |(gallo, permit, started, failed)| async move {
+            if failed {
+                return None;
+            }
+
+            let state = async {
+                if started {
+                    gallo
+                        .gpio_wait_for_any_edge(pin)
+                        .await
+                        .map_err(|e| format!("{:?}", e))?;
+                }
+                gallo.gpio_get(pin).await.map_err(|e| format!("{:?}", e))
+            };
+
+            match state.await {
+                Ok(state) => {
+                    let event = Event::default().event("state").json_data(Gpio { state }).ok()?;
+                    Some((Ok(event), (gallo, permit, true, false)))
+                }
+                Err(e) => {
+                    // Report the failure, then end the stream
+                    let event = Event::default().event("error").data(e);
+                    Some((Ok(event), (gallo, permit, true, true)))
+                }
+            }
+        },
+    );
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -1,11 +1,27 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use color_eyre::{Result, eyre::eyre};
-use pico_de_gallo_lib::{PicoDeGallo, SpiPhase, SpiPolarity};
+#[cfg(unix)]
+use pico_de_gallo_lib::default_broker_socket;
+use pico_de_gallo_lib::{
+    CAPTURE_CHANNEL_NAMES, CAPTURE_CHANNELS, CaptureTrigger, GPIO_MEASURE_MAX_GATE_MS, GpioEdge, I2C_TARGET_REGISTERS,
+    I2C_TEN_BIT_ADDRESS_MAX, I2cAddress, I2cConfig, I2cScanMode, I2cTargetAccess, I2cTriggerAction, NUM_I2C_BUSES,
+    NUM_SPI_BUSES, OneWireFail, OneWireRom, OneWireRomParseError, PicoDeGallo, PicoDeGalloError, RemoteServer, Rgb,
+    Rgbw, SmbusOperation, SpiBitOrder, SpiConfig, SpiPhase, SpiPolarity, SpiTargetFail, SpiWordSize,
+    i2c_address_is_reserved,
+    pmbus::{self, command},
+};
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
 use tabled::builder::Builder;
 use tabled::settings::object::Rows;
 use tabled::settings::{Alignment, Style};
 
+mod capture;
+#[cfg(feature = "http")]
+mod http;
+
 #[derive(Parser, Debug)]
 #[command(
     name = "Pico De Gallo",
@@ -18,6 +34,15 @@ pub struct Cli {
     #[arg(short, long)]
     serial_number: Option<String>,
 
+    /// Address of a Pico de Gallo shared with `gallo serve`
+    #[arg(short, long, conflicts_with = "serial_number")]
+    remote: Option<String>,
+
+    /// Socket of a Pico de Gallo shared with `gallo daemon`
+    #[cfg(unix)]
+    #[arg(long, conflicts_with_all = ["serial_number", "remote"])]
+    socket: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -29,35 +54,173 @@ enum Commands {
 
     /// I2C access methods
     I2c {
+        /// I2C bus number
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(..NUM_I2C_BUSES as i64))]
+        bus: u8,
+
         /// I2C commands
         #[command(subcommand)]
         command: Option<I2cCommands>,
     },
 
+    /// SMBus access methods, run at 100 kHz
+    Smbus {
+        /// I2C bus number
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(..NUM_I2C_BUSES as i64))]
+        bus: u8,
+
+        /// SMBus device address
+        #[arg(short, long, value_parser(parse_byte))]
+        address: u8,
+
+        /// Append a Packet Error Code to writes and check the one
+        /// ending reads
+        #[arg(long, default_value_t = false)]
+        pec: bool,
+
+        /// SMBus commands
+        #[command(subcommand)]
+        command: Option<SmbusCommands>,
+    },
+
+    /// Summary of the telemetry and status of a PMBus device
+    Pmbus {
+        /// I2C bus number
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(..NUM_I2C_BUSES as i64))]
+        bus: u8,
+
+        /// PMBus device address
+        #[arg(short, long, value_parser(parse_byte))]
+        address: u8,
+
+        /// Append a Packet Error Code to writes and check the one
+        /// ending reads
+        #[arg(long, default_value_t = false)]
+        pec: bool,
+
+        /// Select this page, i.e. output, first
+        #[arg(long, value_parser(parse_byte))]
+        page: Option<u8>,
+    },
+
     /// SPI access methods
     Spi {
+        /// SPI bus number
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(..NUM_SPI_BUSES as i64))]
+        bus: u8,
+
         /// SPI commands
         #[command(subcommand)]
         command: Option<SpiCommands>,
     },
 
-    /// Set bus parameters for I2C and SPI
-    SetConfig {
-        /// I2C frequency
-        #[arg(long)]
-        i2c_frequency: u32,
+    /// GPIO access methods
+    Gpio {
+        /// GPIO pin, 0 to 7
+        #[arg(short, long)]
+        pin: u8,
 
-        /// SPI frequency
-        #[arg(long)]
-        spi_frequency: u32,
+        /// GPIO commands
+        #[command(subcommand)]
+        command: Option<GpioCommands>,
+    },
+
+    /// 1-Wire access methods, on a GPIO with an external pull-up
+    Onewire {
+        /// GPIO pin of the bus
+        #[arg(short, long)]
+        pin: u8,
+
+        /// 1-Wire commands
+        #[command(subcommand)]
+        command: Option<OnewireCommands>,
+    },
+
+    /// Set a chain of WS2812 style addressable LEDs
+    Leds {
+        /// GPIO pin wired to the data input of the first LED
+        #[arg(short, long)]
+        pin: u8,
 
-        /// SPI phase first transition
-        #[arg(long, default_value_t)]
-        spi_first_transition: bool,
+        /// Colors from the first LED onwards, in hex as RRGGBB, or
+        /// RRGGBBWW for RGBW LEDs
+        #[arg(short, long, num_args(1..), value_parser(parse_color), required = true)]
+        colors: Vec<Rgbw>,
 
-        /// SPI polarity idle low
-        #[arg(long, default_value_t)]
-        spi_idle_low: bool,
+        /// Number of LEDs, the colors repeating over them
+        #[arg(short = 'n', long)]
+        count: Option<usize>,
+
+        /// The LEDs are RGBW, such as the SK6812 RGBW
+        #[arg(long, default_value_t = false)]
+        rgbw: bool,
+    },
+
+    /// Sample the bus pins and GPIOs as a logic analyzer, saving the
+    /// samples for PulseView
+    Capture {
+        /// File written, a sigrok session if it ends in .sr, a Value
+        /// Change Dump otherwise
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Sample rate in Hz, with an optional k or M suffix
+        #[arg(short, long, value_parser(parse_rate), default_value = "1M")]
+        rate: u32,
+
+        /// Number of samples
+        #[arg(short = 'n', long, default_value_t = 10_000)]
+        samples: u32,
+
+        /// Start on a channel, as CHANNEL:rising, falling, high or low.
+        /// Channels are numbered or named, e.g. i2c0_scl or gpio3
+        #[arg(short, long, value_parser(parse_trigger), conflicts_with = "pattern")]
+        trigger: Option<CaptureTrigger>,
+
+        /// Start on the levels of consecutive channels, as
+        /// CHANNEL:LEVELS from that channel on, e.g. i2c0_sda:01
+        #[arg(short, long, value_parser(parse_pattern))]
+        pattern: Option<CaptureTrigger>,
+
+        /// Wait for the pattern to be absent first
+        #[arg(long, default_value_t = false, requires = "pattern")]
+        edge: bool,
+
+        /// File format, from the extension by default
+        #[arg(short, long, value_enum)]
+        format: Option<CaptureFormat>,
+    },
+
+    /// Decode the traffic of another controller on a bus, without
+    /// taking part, printing it until interrupted
+    Sniff {
+        /// Bus sniffed
+        #[command(subcommand)]
+        command: SniffCommands,
+    },
+
+    /// Share Pico de Gallo with remote clients over TCP
+    Serve {
+        /// Address to listen on, e.g. 0.0.0.0:5151 to accept any host
+        /// on the network. There is no authentication.
+        #[arg(short, long, default_value = "127.0.0.1:5151")]
+        address: String,
+    },
+
+    /// Serve an HTTP/JSON API for Pico de Gallo
+    #[cfg(feature = "http")]
+    Http {
+        /// Address to listen on
+        #[arg(short, long, default_value = "127.0.0.1:8080")]
+        address: String,
+    },
+
+    /// Run a broker letting multiple local clients share Pico de Gallo
+    #[cfg(unix)]
+    Daemon {
+        /// Path of the Unix socket to listen on
+        #[arg(long, default_value_os_t = default_broker_socket())]
+        socket: PathBuf,
     },
 }
 
@@ -68,13 +231,16 @@ enum I2cCommands {
         /// Attempt reserved addresses
         #[arg(short, long, default_value_t = false)]
         reserved: bool,
+
+        /// How each address is probed
+        #[arg(short, long, value_enum, default_value_t = ScanMode::Write)]
+        mode: ScanMode,
     },
 
     /// Read bytes through the I2C bus from device at given address
     Read {
-        /// I2C slave address
-        #[arg(short, long, value_parser(parse_byte))]
-        address: u8,
+        #[command(flatten)]
+        target: I2cTarget,
 
         /// Number of bytes to read
         #[arg(short, long)]
@@ -83,9 +249,8 @@ enum I2cCommands {
 
     /// Write bytes through I2C bus to device at given address
     Write {
-        /// I2C slave address
-        #[arg(short, long, value_parser(parse_byte))]
-        address: u8,
+        #[command(flatten)]
+        target: I2cTarget,
 
         /// Bytes to transfer
         #[arg(short, long, num_args(1..), value_parser(parse_byte))]
@@ -94,9 +259,8 @@ enum I2cCommands {
 
     /// Write bytes follwed by read bytes
     WriteRead {
-        /// I2C slave address
-        #[arg(short, long, value_parser(parse_byte))]
-        address: u8,
+        #[command(flatten)]
+        target: I2cTarget,
 
         /// Bytes to transfer
         #[arg(short, long, num_args(1..), value_parser(parse_byte))]
@@ -106,6 +270,186 @@ enum I2cCommands {
         #[arg(short, long)]
         count: usize,
     },
+
+    /// Clock out a target holding SDA low and issue a STOP
+    Recover,
+
+    /// Ask which device asserts SMBALERT# through the SMBus Alert
+    /// Response Address
+    Alert {
+        /// Check the Packet Error Code following the address
+        #[arg(long, default_value_t = false)]
+        pec: bool,
+    },
+
+    /// Read a device on every edge of a GPIO, until interrupted
+    Trigger {
+        /// GPIO pin to watch
+        #[arg(short, long)]
+        pin: u8,
+
+        /// Edge firing the trigger
+        #[arg(short, long, value_enum, default_value_t = Edge::Falling)]
+        edge: Edge,
+
+        /// Device to read, rather than asking which devices assert
+        /// SMBALERT#
+        #[arg(short, long, value_parser(parse_byte))]
+        address: Option<u8>,
+
+        /// Register to write before reading
+        #[arg(short, long, value_parser(parse_byte), requires = "address")]
+        register: Option<u8>,
+
+        /// Number of bytes to read
+        #[arg(short, long, default_value_t = 1, requires = "address")]
+        count: u8,
+
+        /// Check the Packet Error Code of alert responses
+        #[arg(long, default_value_t = false, conflicts_with = "address")]
+        pec: bool,
+    },
+
+    /// Emulate a target backed by a register file, printing every
+    /// access of a controller until interrupted
+    Target {
+        /// 7-bit address to answer at
+        #[arg(short, long, value_parser(parse_byte))]
+        address: u8,
+
+        /// Bytes to store in the register file beforehand
+        #[arg(short, long, num_args(1..), value_parser(parse_byte))]
+        load: Vec<u8>,
+
+        /// First register to store the loaded bytes at
+        #[arg(short, long, value_parser(parse_byte), default_value_t = 0, requires = "load")]
+        register: u8,
+
+        /// Also answer writes to the general call address
+        #[arg(short, long, default_value_t = false)]
+        general_call: bool,
+
+        /// Print the register file once stopped
+        #[arg(short, long, default_value_t = false)]
+        dump: bool,
+    },
+
+    /// Show the I2C bus configuration, updating it first if requested
+    Config {
+        /// SCL frequency in Hz
+        #[arg(short, long)]
+        frequency: Option<u32>,
+    },
+}
+
+#[derive(Args, Debug)]
+struct I2cTarget {
+    /// I2C slave address, 10-bit above 0x7f
+    #[arg(short, long, value_parser(parse_address))]
+    address: u16,
+
+    /// Use 10-bit addressing for addresses up to 0x7f too
+    #[arg(long)]
+    ten_bit: bool,
+}
+
+impl I2cTarget {
+    fn address(&self) -> I2cAddress {
+        i2c_address(self.address, self.ten_bit)
+    }
+}
+
+/// Addresses above 0x7f are always 10-bit, `ten_bit` makes the lower ones 10-bit too.
+fn i2c_address(address: u16, ten_bit: bool) -> I2cAddress {
+    match u8::try_from(address) {
+        Ok(address) if address <= 0x7f && !ten_bit => I2cAddress::SevenBit(address),
+        _ => I2cAddress::TenBit(address),
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum SmbusCommands {
+    /// Address the device, the R/W bit being the only data
+    Quick {
+        /// Set the R/W bit
+        #[arg(short, long, default_value_t = false)]
+        read: bool,
+    },
+
+    /// Send a byte without command code
+    SendByte {
+        /// Byte to send
+        #[arg(short, long, value_parser(parse_byte))]
+        value: u8,
+    },
+
+    /// Receive a byte without command code
+    ReceiveByte,
+
+    /// Write a byte to a command code
+    WriteByte {
+        /// Command code
+        #[arg(short, long, value_parser(parse_byte))]
+        command: u8,
+
+        /// Byte to write
+        #[arg(short, long, value_parser(parse_byte))]
+        value: u8,
+    },
+
+    /// Read a byte from a command code
+    ReadByte {
+        /// Command code
+        #[arg(short, long, value_parser(parse_byte))]
+        command: u8,
+    },
+
+    /// Write a word to a command code
+    WriteWord {
+        /// Command code
+        #[arg(short, long, value_parser(parse_byte))]
+        command: u8,
+
+        /// Word to write
+        #[arg(short, long, value_parser(parse_word))]
+        value: u16,
+    },
+
+    /// Read a word from a command code
+    ReadWord {
+        /// Command code
+        #[arg(short, long, value_parser(parse_byte))]
+        command: u8,
+    },
+
+    /// Write a block of up to 32 bytes to a command code
+    BlockWrite {
+        /// Command code
+        #[arg(short, long, value_parser(parse_byte))]
+        command: u8,
+
+        /// Bytes to transfer
+        #[arg(short, long, num_args(1..), value_parser(parse_byte))]
+        bytes: Vec<u8>,
+    },
+
+    /// Read a block from a command code
+    BlockRead {
+        /// Command code
+        #[arg(short, long, value_parser(parse_byte))]
+        command: u8,
+    },
+
+    /// Write a word to a command code and read a word back
+    ProcessCall {
+        /// Command code
+        #[arg(short, long, value_parser(parse_byte))]
+        command: u8,
+
+        /// Word to write
+        #[arg(short, long, value_parser(parse_word))]
+        value: u16,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -134,6 +478,232 @@ enum SpiCommands {
         #[arg(short, long, num_args(1..), value_parser(parse_byte))]
         bytes: Vec<u8>,
     },
+
+    /// Emulate a target answering from a buffer or a command table,
+    /// printing the bytes of every selection until interrupted
+    Target {
+        /// Clock transition data is captured on
+        #[arg(long, value_enum, default_value_t = Phase::Second)]
+        phase: Phase,
+
+        /// Clock level while idle
+        #[arg(long, value_enum, default_value_t = Polarity::High)]
+        polarity: Polarity,
+
+        /// Byte answered past the end of responses
+        #[arg(short, long, value_parser(parse_byte), default_value_t = 0xff)]
+        fill: u8,
+
+        /// Bytes answered from the start of every selection
+        #[arg(short, long, num_args(1..), value_parser(parse_byte))]
+        load: Vec<u8>,
+
+        /// Command and the response following it, in hex separated by a
+        /// colon, e.g. 9f:ef4018
+        #[arg(short, long, value_parser(parse_target_command))]
+        command: Vec<TargetCommand>,
+    },
+
+    /// Show the SPI bus configuration, updating it first if requested
+    Config {
+        /// SCK frequency in Hz
+        #[arg(short, long)]
+        frequency: Option<u32>,
+
+        /// Clock transition data is captured on
+        #[arg(long)]
+        phase: Option<Phase>,
+
+        /// Clock level while idle
+        #[arg(long)]
+        polarity: Option<Polarity>,
+
+        /// Bits per word
+        #[arg(long, value_parser(parse_word_size))]
+        word_size: Option<SpiWordSize>,
+
+        /// Order in which the bits of each word are shifted out
+        #[arg(long)]
+        bit_order: Option<BitOrder>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SniffCommands {
+    /// Print every I2C transfer: START, address, data bytes, each
+    /// followed by its acknowledge, and STOP
+    I2c {
+        /// I2C bus number
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(..NUM_I2C_BUSES as i64))]
+        bus: u8,
+    },
+
+    /// Print the MOSI and MISO bytes of every SPI selection
+    Spi {
+        /// SPI bus number
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(..NUM_SPI_BUSES as i64))]
+        bus: u8,
+
+        /// Clock transition data is captured on
+        #[arg(long, value_enum, default_value_t = Phase::Second)]
+        phase: Phase,
+
+        /// Clock level while idle
+        #[arg(long, value_enum, default_value_t = Polarity::High)]
+        polarity: Polarity,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum GpioCommands {
+    /// Measure the frequency, period, duty cycle and pulse width of the
+    /// signal on the pin, e.g. a clock, PWM or tachometer output
+    Measure {
+        /// Gate time in milliseconds, longer ones being more precise
+        #[arg(short, long, default_value_t = 1000)]
+        #[arg(value_parser = clap::value_parser!(u32).range(1..=GPIO_MEASURE_MAX_GATE_MS as i64))]
+        gate: u32,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum OnewireCommands {
+    /// List the ROM codes of the devices on the bus
+    Search {
+        /// Only list the devices in an alarm state
+        #[arg(short, long, default_value_t = false)]
+        alarm: bool,
+    },
+
+    /// Address a device, write a command and read bytes back
+    Read {
+        /// ROM code of the device, every device is addressed without it
+        #[arg(short, long, value_parser(parse_rom))]
+        rom: Option<OneWireRom>,
+
+        /// Bytes to write before reading
+        #[arg(short, long, num_args(1..), value_parser(parse_byte))]
+        bytes: Vec<u8>,
+
+        /// Number of bytes to read
+        #[arg(short, long)]
+        count: u16,
+    },
+
+    /// Address a device and write bytes
+    Write {
+        /// ROM code of the device, every device is addressed without it
+        #[arg(short, long, value_parser(parse_rom))]
+        rom: Option<OneWireRom>,
+
+        /// Bytes to write
+        #[arg(short, long, num_args(1..), value_parser(parse_byte), required = true)]
+        bytes: Vec<u8>,
+
+        /// Drive the bus high for this many milliseconds afterwards,
+        /// powering parasite devices
+        #[arg(short, long, default_value_t = 0)]
+        strong_pullup: u16,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct TargetCommand {
+    command: Vec<u8>,
+    response: Vec<u8>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ScanMode {
+    /// Zero-length write, no data reaches the devices
+    Write,
+    /// One byte read, for devices ignoring zero-length writes
+    Read,
+}
+
+impl From<ScanMode> for I2cScanMode {
+    fn from(value: ScanMode) -> Self {
+        match value {
+            ScanMode::Write => I2cScanMode::Write,
+            ScanMode::Read => I2cScanMode::Read,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Edge {
+    Rising,
+    Falling,
+    Any,
+}
+
+impl From<Edge> for GpioEdge {
+    fn from(value: Edge) -> Self {
+        match value {
+            Edge::Rising => GpioEdge::Rising,
+            Edge::Falling => GpioEdge::Falling,
+            Edge::Any => GpioEdge::Any,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum CaptureFormat {
+    /// Value Change Dump
+    Vcd,
+    /// sigrok session
+    Sigrok,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Phase {
+    /// CPHA=0
+    First,
+    /// CPHA=1
+    Second,
+}
+
+impl From<Phase> for SpiPhase {
+    fn from(value: Phase) -> Self {
+        match value {
+            Phase::First => SpiPhase::CaptureOnFirstTransition,
+            Phase::Second => SpiPhase::CaptureOnSecondTransition,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Polarity {
+    /// CPOL=0
+    Low,
+    /// CPOL=1
+    High,
+}
+
+impl From<Polarity> for SpiPolarity {
+    fn from(value: Polarity) -> Self {
+        match value {
+            Polarity::Low => SpiPolarity::IdleLow,
+            Polarity::High => SpiPolarity::IdleHigh,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum BitOrder {
+    /// Most significant bit first
+    Msb,
+    /// Least significant bit first
+    Lsb,
+}
+
+impl From<BitOrder> for SpiBitOrder {
+    fn from(value: BitOrder) -> Self {
+        match value {
+            BitOrder::Msb => SpiBitOrder::MsbFirst,
+            BitOrder::Lsb => SpiBitOrder::LsbFirst,
+        }
+    }
 }
 
 impl Cli {
@@ -141,39 +711,169 @@ impl Cli {
         match &self.command {
             None => Ok(()),
             Some(Commands::Version) => self.version().await,
-            Some(Commands::I2c { command }) => match command {
+            Some(Commands::I2c { bus, command }) => match command {
+                None => Ok(()),
+                Some(I2cCommands::Scan { reserved, mode }) => self.i2c_scan(*bus, *reserved, *mode).await,
+                Some(I2cCommands::Read { target, count }) => self.i2c_read(*bus, target.address(), count).await,
+                Some(I2cCommands::Write { target, bytes }) => self.i2c_write(*bus, target.address(), bytes).await,
+                Some(I2cCommands::WriteRead { target, bytes, count }) => {
+                    self.i2c_write_then_read(*bus, target.address(), bytes, count).await
+                }
+                Some(I2cCommands::Recover) => self.i2c_recover(*bus).await,
+                Some(I2cCommands::Alert { pec }) => self.i2c_alert(*bus, *pec).await,
+                Some(I2cCommands::Trigger {
+                    pin,
+                    edge,
+                    address,
+                    register,
+                    count,
+                    pec,
+                }) => {
+                    let action = match address {
+                        Some(address) => I2cTriggerAction::Read {
+                            address: *address,
+                            register: *register,
+                            count: *count,
+                        },
+                        None => I2cTriggerAction::AlertResponse { pec: *pec },
+                    };
+
+                    self.i2c_trigger(*bus, *pin, (*edge).into(), action).await
+                }
+                Some(I2cCommands::Target {
+                    address,
+                    load,
+                    register,
+                    general_call,
+                    dump,
+                }) => {
+                    self.i2c_target(*bus, *address, load, *register, *general_call, *dump)
+                        .await
+                }
+                Some(I2cCommands::Config { frequency }) => self.i2c_config(*bus, *frequency).await,
+            },
+            Some(Commands::Smbus { command: None, .. }) => Ok(()),
+            Some(Commands::Smbus {
+                bus,
+                address,
+                pec,
+                command: Some(command),
+            }) => self.smbus(*bus, *address, *pec, command).await,
+            Some(Commands::Pmbus {
+                bus,
+                address,
+                pec,
+                page,
+            }) => self.pmbus(*bus, *address, *pec, *page).await,
+            Some(Commands::Spi { bus, command }) => match command {
                 None => Ok(()),
-                Some(I2cCommands::Scan { reserved }) => self.i2c_scan(*reserved).await,
-                Some(I2cCommands::Read { address, count }) => self.i2c_read(address, count).await,
-                Some(I2cCommands::Write { address, bytes }) => self.i2c_write(address, bytes).await,
-                Some(I2cCommands::WriteRead { address, bytes, count }) => {
-                    self.i2c_write_then_read(address, bytes, count).await
+                Some(SpiCommands::Read { count }) => self.spi_read(*bus, count).await,
+                Some(SpiCommands::Write { bytes }) => self.spi_write(*bus, bytes).await,
+                Some(SpiCommands::WriteRead { count, bytes }) => self.spi_write_then_read(*bus, bytes, count).await,
+                Some(SpiCommands::Target {
+                    phase,
+                    polarity,
+                    fill,
+                    load,
+                    command,
+                }) => {
+                    self.spi_target(*bus, (*polarity).into(), (*phase).into(), *fill, load, command)
+                        .await
+                }
+                Some(SpiCommands::Config {
+                    frequency,
+                    phase,
+                    polarity,
+                    word_size,
+                    bit_order,
+                }) => {
+                    self.spi_config(*bus, *frequency, *phase, *polarity, *word_size, *bit_order)
+                        .await
                 }
             },
-            Some(Commands::Spi { command }) => match command {
+            Some(Commands::Gpio { command: None, .. }) => Ok(()),
+            Some(Commands::Gpio {
+                pin,
+                command: Some(GpioCommands::Measure { gate }),
+            }) => self.gpio_measure(*pin, *gate).await,
+            Some(Commands::Onewire { pin, command }) => match command {
                 None => Ok(()),
-                Some(SpiCommands::Read { count }) => self.spi_read(count).await,
-                Some(SpiCommands::Write { bytes }) => self.spi_write(bytes).await,
-                Some(SpiCommands::WriteRead { count, bytes }) => self.spi_write_then_read(bytes, count).await,
+                Some(OnewireCommands::Search { alarm }) => self.onewire_search(*pin, *alarm).await,
+                Some(OnewireCommands::Read { rom, bytes, count }) => self.onewire_read(*pin, *rom, bytes, *count).await,
+                Some(OnewireCommands::Write {
+                    rom,
+                    bytes,
+                    strong_pullup,
+                }) => self.onewire_write(*pin, *rom, bytes, *strong_pullup).await,
             },
-            Some(Commands::SetConfig {
-                i2c_frequency,
-                spi_frequency,
-                spi_first_transition,
-                spi_idle_low,
+            Some(Commands::Leds {
+                pin,
+                colors,
+                count,
+                rgbw,
+            }) => self.leds(*pin, colors, *count, *rgbw).await,
+            Some(Commands::Capture {
+                output,
+                rate,
+                samples,
+                trigger,
+                pattern,
+                edge,
+                format,
             }) => {
-                self.set_config(*i2c_frequency, *spi_frequency, *spi_first_transition, *spi_idle_low)
-                    .await
+                let trigger = match (trigger, pattern) {
+                    (Some(trigger), _) => *trigger,
+                    (
+                        None,
+                        Some(CaptureTrigger::Pattern {
+                            first, count, value, ..
+                        }),
+                    ) => CaptureTrigger::Pattern {
+                        first: *first,
+                        count: *count,
+                        value: *value,
+                        edge: *edge,
+                    },
+                    _ => CaptureTrigger::Immediate,
+                };
+
+                self.capture(output, *rate, *samples, trigger, *format).await
             }
+            Some(Commands::Sniff { command }) => match command {
+                SniffCommands::I2c { bus } => self.sniff_i2c(*bus).await,
+                SniffCommands::Spi { bus, phase, polarity } => {
+                    self.sniff_spi(*bus, (*polarity).into(), (*phase).into()).await
+                }
+            },
+            Some(Commands::Serve { address }) => self.serve(address).await,
+            #[cfg(feature = "http")]
+            Some(Commands::Http { address }) => self.http(address).await,
+            #[cfg(unix)]
+            Some(Commands::Daemon { socket }) => self.daemon(socket).await,
         }
     }
 
-    async fn version(&self) -> Result<()> {
-        let pg = if self.serial_number.is_some() {
-            PicoDeGallo::new_with_serial_number(self.serial_number.as_ref().unwrap())
+    async fn connect(&self) -> Result<PicoDeGallo> {
+        #[cfg(unix)]
+        if let Some(socket) = &self.socket {
+            return PicoDeGallo::connect_unix(socket)
+                .await
+                .map_err(|e| eyre!("failed to connect to {}: {}", socket.display(), e));
+        }
+
+        if let Some(address) = &self.remote {
+            PicoDeGallo::connect_tcp(address.as_str())
+                .await
+                .map_err(|e| eyre!("failed to connect to {}: {}", address, e))
+        } else if let Some(serial_number) = &self.serial_number {
+            Ok(PicoDeGallo::new_with_serial_number(serial_number))
         } else {
-            PicoDeGallo::new()
-        };
+            Ok(PicoDeGallo::new())
+        }
+    }
+
+    async fn version(&self) -> Result<()> {
+        let pg = self.connect().await?;
 
         match pg.version().await {
             Ok(version) => {
@@ -187,11 +887,12 @@ impl Cli {
         }
     }
 
-    async fn i2c_scan(&self, reserved: bool) -> Result<()> {
-        let pg = if self.serial_number.is_some() {
-            PicoDeGallo::new_with_serial_number(self.serial_number.as_ref().unwrap())
-        } else {
-            PicoDeGallo::new()
+    async fn i2c_scan(&self, bus: u8, reserved: bool, mode: ScanMode) -> Result<()> {
+        let pg = self.connect().await?;
+
+        let found = match pg.i2c_scan(bus, mode.into(), reserved).await {
+            Ok(found) => found,
+            Err(_) => return Err(eyre!("i2c scan failed")),
         };
 
         let mut builder = Builder::with_capacity(17, 8);
@@ -206,21 +907,12 @@ impl Cli {
 
             for lo in 0..=15 {
                 let address = hi << 4 | lo;
-                let stat = match address {
-                    0x00..=0x07 | 0x78..=0x7f => {
-                        if reserved {
-                            match pg.i2c_read(address, 1).await {
-                                Ok(_) => format!("{:02x}", address),
-                                Err(_) => "--".to_string(),
-                            }
-                        } else {
-                            "RR".to_string()
-                        }
-                    }
-                    _ => match pg.i2c_read(address, 1).await {
-                        Ok(_) => format!("{:02x}", address),
-                        Err(_) => "--".to_string(),
-                    },
+                let stat = if found.contains(address) {
+                    format!("{:02x}", address)
+                } else if i2c_address_is_reserved(address) && !reserved {
+                    "RR".to_string()
+                } else {
+                    "--".to_string()
                 };
 
                 row.push(stat);
@@ -238,14 +930,10 @@ impl Cli {
         Ok(())
     }
 
-    async fn i2c_read(&self, address: &u8, count: &usize) -> Result<()> {
-        let pg = if self.serial_number.is_some() {
-            PicoDeGallo::new_with_serial_number(self.serial_number.as_ref().unwrap())
-        } else {
-            PicoDeGallo::new()
-        };
+    async fn i2c_read(&self, bus: u8, address: I2cAddress, count: &usize) -> Result<()> {
+        let pg = self.connect().await?;
 
-        let buf = match pg.i2c_read(*address, *count as u16).await {
+        let buf = match pg.i2c_read(bus, address, *count as u16).await {
             Ok(data) => data,
             Err(_) => return Err(eyre!("i2c_read failed")),
         };
@@ -263,42 +951,328 @@ impl Cli {
         Ok(())
     }
 
-    async fn i2c_write(&self, address: &u8, bytes: &[u8]) -> Result<()> {
-        let pg = if self.serial_number.is_some() {
-            PicoDeGallo::new_with_serial_number(self.serial_number.as_ref().unwrap())
-        } else {
-            PicoDeGallo::new()
-        };
+    async fn i2c_write(&self, bus: u8, address: I2cAddress, bytes: &[u8]) -> Result<()> {
+        let pg = self.connect().await?;
 
-        if pg.i2c_write(*address, bytes).await.is_ok() {
+        if pg.i2c_write(bus, address, bytes).await.is_ok() {
             Ok(())
         } else {
             Err(eyre!("i2c_write failed"))
         }
     }
 
-    async fn i2c_write_then_read(&self, address: &u8, bytes: &[u8], count: &usize) -> Result<()> {
-        self.i2c_write(address, bytes).await?;
-        self.i2c_read(address, count).await
+    async fn i2c_write_then_read(&self, bus: u8, address: I2cAddress, bytes: &[u8], count: &usize) -> Result<()> {
+        self.i2c_write(bus, address, bytes).await?;
+        self.i2c_read(bus, address, count).await
     }
 
-    async fn spi_read(&self, count: &usize) -> Result<()> {
-        let pg = if self.serial_number.is_some() {
-            PicoDeGallo::new_with_serial_number(self.serial_number.as_ref().unwrap())
-        } else {
-            PicoDeGallo::new()
-        };
-
-        let buf = match pg.spi_read(*count as u16).await {
-            Ok(data) => data,
-            Err(_) => return Err(eyre!("spi read failed")),
-        };
+    async fn i2c_recover(&self, bus: u8) -> Result<()> {
+        let pg = self.connect().await?;
 
-        for (i, b) in buf.iter().enumerate() {
-            if i > 0 && i % 16 == 0 {
-                println!();
+        match pg.i2c_recover(bus).await {
+            Ok(true) => {
+                println!("bus is free");
+                Ok(())
             }
-
+            Ok(false) => Err(eyre!("bus is still held low")),
+            Err(_) => Err(eyre!("i2c recover failed")),
+        }
+    }
+
+    async fn i2c_alert(&self, bus: u8, pec: bool) -> Result<()> {
+        let pg = self.connect().await?;
+
+        match pg.smbus_alert_response(bus, pec).await {
+            Ok(Some(address)) => println!("{:#04x}", address),
+            Ok(None) => println!("no device asserts SMBALERT#"),
+            Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("i2c alert failed: {}", e)),
+            Err(_) => return Err(eyre!("i2c alert failed")),
+        }
+
+        Ok(())
+    }
+
+    async fn i2c_trigger(&self, bus: u8, pin: u8, edge: GpioEdge, action: I2cTriggerAction) -> Result<()> {
+        let pg = self.connect().await?;
+
+        let Ok(mut events) = pg.i2c_trigger_events(16).await else {
+            return Err(eyre!("i2c trigger failed"));
+        };
+
+        let slot = match pg.i2c_trigger_arm(pin, edge, bus, action).await {
+            Ok(slot) => slot,
+            Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("i2c trigger failed: {}", e)),
+            Err(_) => return Err(eyre!("i2c trigger failed")),
+        };
+
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = tokio::signal::ctrl_c() => None,
+            };
+
+            let Some(event) = event else {
+                break;
+            };
+
+            // Triggers armed by other clients
+            if event.slot != slot {
+                continue;
+            }
+
+            let seconds = event.timestamp_us / 1_000_000;
+            let micros = event.timestamp_us % 1_000_000;
+
+            match (event.result, action) {
+                (Ok(data), I2cTriggerAction::AlertResponse { .. }) => {
+                    println!("[{}.{:06}] {:#04x}", seconds, micros, data[0])
+                }
+                (Ok(data), I2cTriggerAction::Read { .. }) => {
+                    let bytes = data.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>();
+                    println!("[{}.{:06}] {}", seconds, micros, bytes.join(" "));
+                }
+                (Err(e), _) => println!("[{}.{:06}] {}", seconds, micros, e),
+            }
+        }
+
+        let _ = pg.i2c_trigger_disarm(slot).await;
+
+        Ok(())
+    }
+
+    async fn i2c_target(
+        &self,
+        bus: u8,
+        address: u8,
+        load: &[u8],
+        register: u8,
+        general_call: bool,
+        dump: bool,
+    ) -> Result<()> {
+        let pg = self.connect().await?;
+
+        if !load.is_empty() {
+            match pg.i2c_target_load(bus, register, load).await {
+                Ok(()) => {}
+                Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("i2c target failed: {}", e)),
+                Err(_) => return Err(eyre!("i2c target failed")),
+            }
+        }
+
+        let Ok(mut events) = pg.i2c_target_events(64).await else {
+            return Err(eyre!("i2c target failed"));
+        };
+
+        match pg.i2c_target_start(bus, address, general_call).await {
+            Ok(()) => {}
+            Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("i2c target failed: {}", e)),
+            Err(_) => return Err(eyre!("i2c target failed")),
+        }
+
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = tokio::signal::ctrl_c() => None,
+            };
+
+            let Some(event) = event else {
+                break;
+            };
+
+            // Targets started by other clients
+            if event.bus != bus {
+                continue;
+            }
+
+            let seconds = event.timestamp_us / 1_000_000;
+            let micros = event.timestamp_us % 1_000_000;
+            let hex = |data: &[u8]| data.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+
+            match event.access {
+                I2cTargetAccess::Write { register, data } if data.is_empty() => {
+                    println!("[{}.{:06}] select {:#04x}", seconds, micros, register)
+                }
+                I2cTargetAccess::Write { register, data } => {
+                    println!("[{}.{:06}] write {:#04x}: {}", seconds, micros, register, hex(&data))
+                }
+                I2cTargetAccess::Read { register, count } => {
+                    println!("[{}.{:06}] read {:#04x}: {} bytes", seconds, micros, register, count)
+                }
+                I2cTargetAccess::GeneralCall { data } => {
+                    println!("[{}.{:06}] general call: {}", seconds, micros, hex(&data))
+                }
+                I2cTargetAccess::Aborted => println!("[{}.{:06}] aborted", seconds, micros),
+            }
+        }
+
+        if pg.i2c_target_stop(bus).await.is_err() {
+            return Err(eyre!("i2c target stop failed"));
+        }
+
+        if dump {
+            let Ok(registers) = pg.i2c_target_dump(bus, 0, I2C_TARGET_REGISTERS as u16).await else {
+                return Err(eyre!("i2c target dump failed"));
+            };
+
+            for (row, chunk) in registers.chunks(16).enumerate() {
+                let bytes = chunk.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>();
+                println!("{:02x}: {}", row * 16, bytes.join(" "));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn smbus(&self, bus: u8, address: u8, pec: bool, command: &SmbusCommands) -> Result<()> {
+        let pg = self.connect().await?;
+
+        let operation = match command {
+            SmbusCommands::Quick { read } => SmbusOperation::QuickCommand { read: *read },
+            SmbusCommands::SendByte { value } => SmbusOperation::SendByte(*value),
+            SmbusCommands::ReceiveByte => SmbusOperation::ReceiveByte,
+            SmbusCommands::WriteByte { command, value } => SmbusOperation::WriteByte {
+                command: *command,
+                value: *value,
+            },
+            SmbusCommands::ReadByte { command } => SmbusOperation::ReadByte { command: *command },
+            SmbusCommands::WriteWord { command, value } => SmbusOperation::WriteWord {
+                command: *command,
+                value: *value,
+            },
+            SmbusCommands::ReadWord { command } => SmbusOperation::ReadWord { command: *command },
+            SmbusCommands::BlockWrite { command, bytes } => SmbusOperation::BlockWrite {
+                command: *command,
+                data: bytes,
+            },
+            SmbusCommands::BlockRead { command } => SmbusOperation::BlockRead { command: *command },
+            SmbusCommands::ProcessCall { command, value } => SmbusOperation::ProcessCall {
+                command: *command,
+                value: *value,
+            },
+        };
+
+        let data = match pg.smbus(bus, address, operation, pec).await {
+            Ok(data) => data,
+            Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("smbus failed: {}", e)),
+            Err(_) => return Err(eyre!("smbus failed")),
+        };
+
+        match command {
+            SmbusCommands::ReceiveByte | SmbusCommands::ReadByte { .. } => println!("{:#04x}", data[0]),
+            SmbusCommands::ReadWord { .. } | SmbusCommands::ProcessCall { .. } => {
+                println!("{:#06x}", u16::from_le_bytes([data[0], data[1]]))
+            }
+            SmbusCommands::BlockRead { .. } => {
+                for (i, b) in data.iter().enumerate() {
+                    if i > 0 && i % 16 == 0 {
+                        println!();
+                    }
+
+                    print!("{:02x} ", b);
+                }
+
+                println!();
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    async fn pmbus(&self, bus: u8, address: u8, pec: bool, page: Option<u8>) -> Result<()> {
+        let pg = self.connect().await?;
+        let device = pmbus::Device::new(&pg, bus, address, pec);
+
+        if let Some(page) = page
+            && device.set_page(page).await.is_err()
+        {
+            return Err(eyre!("pmbus page select failed"));
+        }
+
+        let mut builder = Builder::default();
+        builder.push_record(["Reading", "Value"]);
+
+        for (name, command) in [("MFR_ID", command::MFR_ID), ("MFR_MODEL", command::MFR_MODEL)] {
+            if let Ok(text) = device.read_string(command).await {
+                builder.push_record([name, text.trim()]);
+            }
+        }
+
+        let vout = match device.read_vout().await {
+            Ok(Some(volts)) => format!("{:.3} V", volts),
+            Ok(None) => String::from("not linear"),
+            Err(_) => String::from("n/a"),
+        };
+
+        builder.push_record([String::from("READ_VIN"), reading(device.read_vin().await, "V")]);
+        builder.push_record([String::from("READ_IIN"), reading(device.read_iin().await, "A")]);
+        builder.push_record([String::from("READ_PIN"), reading(device.read_pin().await, "W")]);
+        builder.push_record([String::from("READ_VOUT"), vout]);
+        builder.push_record([String::from("READ_IOUT"), reading(device.read_iout().await, "A")]);
+        builder.push_record([String::from("READ_POUT"), reading(device.read_pout().await, "W")]);
+        for sensor in 1..=3 {
+            builder.push_record([
+                format!("READ_TEMPERATURE_{}", sensor),
+                reading(device.read_temperature(sensor).await, "°C"),
+            ]);
+        }
+
+        let mut table = builder.build();
+        table.with(Style::rounded());
+        println!("{}", table);
+
+        let (word, flags) = match device.read_status(command::STATUS_WORD).await {
+            Ok(status) => status,
+            Err(_) => return Err(eyre!("pmbus status read failed")),
+        };
+
+        let mut builder = Builder::default();
+        builder.push_record(["Status", "Value", "Flags"]);
+        builder.push_record([String::from("STATUS_WORD"), format!("{:#06x}", word), flags.join(" ")]);
+
+        // Registers detailing the groups flagged in STATUS_WORD
+        for (flag, command) in [
+            ("VOUT", command::STATUS_VOUT),
+            ("IOUT_POUT", command::STATUS_IOUT),
+            ("INPUT", command::STATUS_INPUT),
+            ("TEMPERATURE", command::STATUS_TEMPERATURE),
+            ("CML", command::STATUS_CML),
+            ("OTHER", command::STATUS_OTHER),
+            ("FANS", command::STATUS_FANS_1_2),
+            ("MFR_SPECIFIC", command::STATUS_MFR_SPECIFIC),
+        ] {
+            if !flags.contains(&flag) {
+                continue;
+            }
+
+            let name = pmbus::command_name(command).unwrap_or_default();
+            let row = match device.read_status(command).await {
+                Ok((value, flags)) => [name.into(), format!("{:#04x}", value), flags.join(" ")],
+                Err(_) => [name.into(), String::from("n/a"), String::new()],
+            };
+            builder.push_record(row);
+        }
+
+        let mut table = builder.build();
+        table.with(Style::rounded());
+        println!("{}", table);
+
+        Ok(())
+    }
+
+    async fn spi_read(&self, bus: u8, count: &usize) -> Result<()> {
+        let pg = self.connect().await?;
+
+        let buf = match pg.spi_read(bus, *count as u16).await {
+            Ok(data) => data,
+            Err(_) => return Err(eyre!("spi read failed")),
+        };
+
+        for (i, b) in buf.iter().enumerate() {
+            if i > 0 && i % 16 == 0 {
+                println!();
+            }
+
             print!("{:02x} ", b);
         }
 
@@ -307,60 +1281,557 @@ impl Cli {
         Ok(())
     }
 
-    async fn spi_write(&self, bytes: &[u8]) -> Result<()> {
-        let pg = if self.serial_number.is_some() {
-            PicoDeGallo::new_with_serial_number(self.serial_number.as_ref().unwrap())
-        } else {
-            PicoDeGallo::new()
-        };
+    async fn spi_write(&self, bus: u8, bytes: &[u8]) -> Result<()> {
+        let pg = self.connect().await?;
 
-        if pg.spi_write(bytes).await.is_ok() {
+        if pg.spi_write(bus, bytes).await.is_ok() {
             Ok(())
         } else {
             Err(eyre!("spi write failed"))
         }
     }
 
-    async fn spi_write_then_read(&self, bytes: &[u8], count: &usize) -> Result<()> {
-        self.spi_write(bytes).await?;
-        self.spi_read(count).await
+    async fn spi_write_then_read(&self, bus: u8, bytes: &[u8], count: &usize) -> Result<()> {
+        self.spi_write(bus, bytes).await?;
+        self.spi_read(bus, count).await
+    }
+
+    async fn i2c_config(&self, bus: u8, frequency: Option<u32>) -> Result<()> {
+        let pg = self.connect().await?;
+        let index = usize::from(bus);
+
+        if let Some(frequency) = frequency {
+            let config = I2cConfig::builder().frequency(frequency).build()?;
+
+            match pg.i2c_set_config(bus, config).await {
+                Ok(_) => {}
+                Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("i2c config failed: {}", e)),
+                Err(_) => return Err(eyre!("i2c config failed")),
+            }
+        }
+
+        let config = match pg.get_config().await {
+            Ok(config) => config,
+            Err(_) => return Err(eyre!("get config failed")),
+        };
+
+        println!(
+            "frequency: {} Hz (actual {} Hz)",
+            config.i2c[index].frequency, config.i2c_actual_frequency[index]
+        );
+
+        Ok(())
     }
 
-    async fn set_config(
+    async fn spi_target(
         &self,
-        i2c_frequency: u32,
-        spi_frequency: u32,
-        spi_first_transition: bool,
-        spi_idle_low: bool,
+        bus: u8,
+        polarity: SpiPolarity,
+        phase: SpiPhase,
+        fill: u8,
+        load: &[u8],
+        commands: &[TargetCommand],
     ) -> Result<()> {
-        let pg = if self.serial_number.is_some() {
-            PicoDeGallo::new_with_serial_number(self.serial_number.as_ref().unwrap())
-        } else {
-            PicoDeGallo::new()
+        let pg = self.connect().await?;
+
+        let setup = async {
+            pg.spi_target_clear(bus).await?;
+            pg.spi_target_load(bus, load).await?;
+            for TargetCommand { command, response } in commands {
+                pg.spi_target_command(bus, command, response).await?;
+            }
+
+            Ok::<_, PicoDeGalloError<SpiTargetFail>>(())
         };
 
-        let spi_polarity = if spi_idle_low {
-            SpiPolarity::IdleLow
-        } else {
-            SpiPolarity::IdleHigh
+        match setup.await {
+            Ok(()) => {}
+            Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("spi target failed: {}", e)),
+            Err(_) => return Err(eyre!("spi target failed")),
+        }
+
+        let Ok(mut events) = pg.spi_target_events(64).await else {
+            return Err(eyre!("spi target failed"));
         };
 
-        let spi_phase = if spi_first_transition {
-            SpiPhase::CaptureOnFirstTransition
+        match pg.spi_target_start(bus, polarity, phase, fill).await {
+            Ok(()) => {}
+            Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("spi target failed: {}", e)),
+            Err(_) => return Err(eyre!("spi target failed")),
+        }
+
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = tokio::signal::ctrl_c() => None,
+            };
+
+            let Some(event) = event else {
+                break;
+            };
+
+            // Targets started by other clients
+            if event.bus != bus {
+                continue;
+            }
+
+            let seconds = event.timestamp_us / 1_000_000;
+            let micros = event.timestamp_us % 1_000_000;
+            let mut line = event
+                .mosi
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            if event.count as usize > event.mosi.len() {
+                line += &format!(" ... ({} bytes)", event.count);
+            }
+
+            if let Some(command) = event.command {
+                line += &format!(" [command {}]", command);
+            }
+
+            println!("[{}.{:06}] {}", seconds, micros, line);
+        }
+
+        if pg.spi_target_stop(bus).await.is_err() {
+            return Err(eyre!("spi target stop failed"));
+        }
+
+        Ok(())
+    }
+
+    async fn sniff_i2c(&self, bus: u8) -> Result<()> {
+        let pg = self.connect().await?;
+
+        let Ok(mut events) = pg.i2c_sniff_events(64).await else {
+            return Err(eyre!("sniff failed"));
+        };
+
+        match pg.sniff_i2c_start(bus).await {
+            Ok(()) => {}
+            Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("sniff failed: {}", e)),
+            Err(_) => return Err(eyre!("sniff failed")),
+        }
+
+        let ack = |ack: bool| if ack { "ACK" } else { "NACK" };
+
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = tokio::signal::ctrl_c() => None,
+            };
+
+            let Some(event) = event else {
+                break;
+            };
+
+            let seconds = event.timestamp_us / 1_000_000;
+            let micros = event.timestamp_us % 1_000_000;
+            let mut line = format!(
+                "START 0x{:02x} {} {}",
+                event.address,
+                if event.read { "R" } else { "W" },
+                ack(event.ack)
+            );
+
+            for byte in &event.data {
+                line += &format!(" {:02x} {}", byte.value, ack(byte.ack));
+            }
+
+            if event.count as usize > event.data.len() {
+                line += &format!(" ... ({} bytes)", event.count);
+            }
+
+            if event.stop {
+                line += " STOP";
+            }
+
+            println!("[{}.{:06}] {}", seconds, micros, line);
+        }
+
+        if pg.sniff_stop().await.is_err() {
+            return Err(eyre!("sniff stop failed"));
+        }
+
+        Ok(())
+    }
+
+    async fn sniff_spi(&self, bus: u8, polarity: SpiPolarity, phase: SpiPhase) -> Result<()> {
+        let pg = self.connect().await?;
+
+        let Ok(mut events) = pg.spi_sniff_events(64).await else {
+            return Err(eyre!("sniff failed"));
+        };
+
+        match pg.sniff_spi_start(bus, polarity, phase).await {
+            Ok(()) => {}
+            Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("sniff failed: {}", e)),
+            Err(_) => return Err(eyre!("sniff failed")),
+        }
+
+        let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = tokio::signal::ctrl_c() => None,
+            };
+
+            let Some(event) = event else {
+                break;
+            };
+
+            let seconds = event.timestamp_us / 1_000_000;
+            let micros = event.timestamp_us % 1_000_000;
+            let mut line = format!("MOSI {} | MISO {}", hex(&event.mosi), hex(&event.miso));
+
+            if event.count as usize > event.mosi.len() {
+                line += &format!(" ... ({} bytes)", event.count);
+            }
+
+            println!("[{}.{:06}] {}", seconds, micros, line);
+        }
+
+        if pg.sniff_stop().await.is_err() {
+            return Err(eyre!("sniff stop failed"));
+        }
+
+        Ok(())
+    }
+
+    async fn spi_config(
+        &self,
+        bus: u8,
+        frequency: Option<u32>,
+        phase: Option<Phase>,
+        polarity: Option<Polarity>,
+        word_size: Option<SpiWordSize>,
+        bit_order: Option<BitOrder>,
+    ) -> Result<()> {
+        let pg = self.connect().await?;
+        let index = usize::from(bus);
+
+        let get_config = async || match pg.get_config().await {
+            Ok(config) => Ok(config),
+            Err(_) => Err(eyre!("get config failed")),
+        };
+
+        let mut config = get_config().await?;
+
+        if frequency.is_some() || phase.is_some() || polarity.is_some() || word_size.is_some() || bit_order.is_some() {
+            // Options left out keep their current value
+            let spi = SpiConfig::builder()
+                .frequency(frequency.unwrap_or(config.spi[index].frequency))
+                .phase(phase.map_or(config.spi[index].phase, Into::into))
+                .polarity(polarity.map_or(config.spi[index].polarity, Into::into))
+                .word_size(word_size.unwrap_or(config.spi[index].word_size))
+                .bit_order(bit_order.map_or(config.spi[index].bit_order, Into::into))
+                .build()?;
+
+            match pg.spi_set_config(bus, spi).await {
+                Ok(_) => {}
+                Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("spi config failed: {}", e)),
+                Err(_) => return Err(eyre!("spi config failed")),
+            }
+
+            config = get_config().await?;
+        }
+
+        let phase = match config.spi[index].phase {
+            SpiPhase::CaptureOnFirstTransition => "first transition (CPHA=0)",
+            SpiPhase::CaptureOnSecondTransition => "second transition (CPHA=1)",
+        };
+        let polarity = match config.spi[index].polarity {
+            SpiPolarity::IdleLow => "idle low (CPOL=0)",
+            SpiPolarity::IdleHigh => "idle high (CPOL=1)",
+        };
+
+        println!(
+            "frequency: {} Hz (actual {} Hz)",
+            config.spi[index].frequency, config.spi_actual_frequency[index]
+        );
+        let bit_order = match config.spi[index].bit_order {
+            SpiBitOrder::MsbFirst => "MSB first",
+            SpiBitOrder::LsbFirst => "LSB first",
+        };
+
+        println!("phase:     {}", phase);
+        println!("polarity:  {}", polarity);
+        println!("word size: {} bits", config.spi[index].word_size as u8);
+        println!("bit order: {}", bit_order);
+
+        Ok(())
+    }
+
+    async fn gpio_measure(&self, pin: u8, gate: u32) -> Result<()> {
+        let pg = self.connect().await?;
+
+        let measurement = match pg.gpio_measure(pin, gate).await {
+            Ok(measurement) => measurement,
+            Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("gpio measure failed: {}", e)),
+            Err(_) => return Err(eyre!("gpio measure failed")),
+        };
+
+        let mut builder = Builder::default();
+        builder.push_record(["Measurement", "Value"]);
+        builder.push_record([String::from("Frequency"), si(measurement.frequency(), "Hz")]);
+        builder.push_record([
+            String::from("Period"),
+            measurement
+                .period()
+                .map_or(String::from("n/a"), |period| si(period, "s")),
+        ]);
+        builder.push_record([
+            String::from("Duty cycle"),
+            format!("{:.2} %", measurement.duty_cycle() * 100.0),
+        ]);
+        builder.push_record([
+            String::from("Pulse width"),
+            measurement
+                .pulse_width()
+                .map_or(String::from("n/a"), |width| si(width, "s")),
+        ]);
+        builder.push_record([
+            String::from("Edges"),
+            format!("{} in {}", measurement.edges, si(measurement.gate_us as f64 / 1e6, "s")),
+        ]);
+
+        let mut table = builder.build();
+        table.with(Style::rounded());
+        println!("{}", table);
+
+        Ok(())
+    }
+
+    async fn onewire_search(&self, pin: u8, alarm: bool) -> Result<()> {
+        let pg = self.connect().await?;
+
+        let found = if alarm {
+            pg.onewire_alarm_search(pin).await
         } else {
-            SpiPhase::CaptureOnSecondTransition
+            pg.onewire_search(pin).await
         };
 
-        if pg
-            .set_config(i2c_frequency, spi_frequency, spi_phase, spi_polarity)
-            .await
-            .is_err()
-        {
-            Err(eyre!("set config failed"))
+        match found {
+            Ok(roms) => {
+                for rom in roms {
+                    println!("{}", rom);
+                }
+
+                Ok(())
+            }
+            Err(PicoDeGalloError::Endpoint(e)) => Err(eyre!("onewire search failed: {}", e)),
+            Err(_) => Err(eyre!("onewire search failed")),
+        }
+    }
+
+    async fn onewire_read(&self, pin: u8, rom: Option<OneWireRom>, bytes: &[u8], count: u16) -> Result<()> {
+        let pg = self.connect().await?;
+
+        let read = async {
+            if !pg.onewire_select(pin, rom).await? {
+                return Ok(None);
+            }
+
+            if !bytes.is_empty() {
+                pg.onewire_write(pin, bytes).await?;
+            }
+
+            pg.onewire_read(pin, count).await.map(Some)
+        };
+
+        let buf = match read.await {
+            Ok(Some(data)) => data,
+            Ok(None) => return Err(eyre!("no device present")),
+            Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("onewire read failed: {}", e)),
+            Err(_) => return Err(eyre!("onewire read failed")),
+        };
+
+        for (i, b) in buf.iter().enumerate() {
+            if i > 0 && i % 16 == 0 {
+                println!();
+            }
+
+            print!("{:02x} ", b);
+        }
+
+        println!();
+
+        Ok(())
+    }
+
+    async fn onewire_write(&self, pin: u8, rom: Option<OneWireRom>, bytes: &[u8], strong_pullup: u16) -> Result<()> {
+        let pg = self.connect().await?;
+
+        let write = async {
+            if !pg.onewire_select(pin, rom).await? {
+                return Ok(false);
+            }
+
+            pg.onewire_write_then_power(pin, bytes, strong_pullup).await?;
+
+            Ok::<_, PicoDeGalloError<OneWireFail>>(true)
+        };
+
+        match write.await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(eyre!("no device present")),
+            Err(PicoDeGalloError::Endpoint(e)) => Err(eyre!("onewire write failed: {}", e)),
+            Err(_) => Err(eyre!("onewire write failed")),
+        }
+    }
+
+    async fn leds(&self, pin: u8, colors: &[Rgbw], count: Option<usize>, rgbw: bool) -> Result<()> {
+        let pg = self.connect().await?;
+
+        if !rgbw && colors.iter().any(|c| c.w != 0) {
+            return Err(eyre!("white needs --rgbw"));
+        }
+
+        let colors = colors
+            .iter()
+            .cycle()
+            .take(count.unwrap_or(colors.len()))
+            .copied()
+            .collect::<Vec<_>>();
+
+        let written = if rgbw {
+            pg.leds_write_rgbw(pin, &colors).await
         } else {
-            Ok(())
+            let colors = colors.iter().map(|c| Rgb::new(c.r, c.g, c.b)).collect::<Vec<_>>();
+            pg.leds_write(pin, &colors).await
+        };
+
+        match written {
+            Ok(()) => Ok(()),
+            Err(PicoDeGalloError::Endpoint(e)) => Err(eyre!("leds write failed: {}", e)),
+            Err(_) => Err(eyre!("leds write failed")),
         }
     }
+
+    async fn capture(
+        &self,
+        output: &Path,
+        rate: u32,
+        samples: u32,
+        trigger: CaptureTrigger,
+        format: Option<CaptureFormat>,
+    ) -> Result<()> {
+        let format = format.unwrap_or(match output.extension() {
+            Some(extension) if extension == "sr" => CaptureFormat::Sigrok,
+            _ => CaptureFormat::Vcd,
+        });
+
+        let pg = self.connect().await?;
+
+        if trigger != CaptureTrigger::Immediate {
+            println!("Waiting for the trigger");
+        }
+
+        let capture = pg.capture(rate, samples, trigger);
+        tokio::pin!(capture);
+
+        let captured = tokio::select! {
+            captured = &mut capture => captured,
+            _ = tokio::signal::ctrl_c() => {
+                let _ = pg.capture_stop().await;
+                capture.await
+            }
+        };
+
+        let captured = match captured {
+            Ok(captured) => captured,
+            Err(PicoDeGalloError::Endpoint(e)) => return Err(eyre!("capture failed: {}", e)),
+            Err(_) => return Err(eyre!("capture failed")),
+        };
+
+        if captured.samples.is_empty() {
+            return Err(eyre!("capture stopped"));
+        }
+
+        let file = File::create(output).map_err(|e| eyre!("failed to create {}: {}", output.display(), e))?;
+        let mut w = BufWriter::new(file);
+        match format {
+            CaptureFormat::Vcd => capture::write_vcd(&mut w, captured.rate, &captured.samples),
+            CaptureFormat::Sigrok => capture::write_sigrok(&mut w, captured.rate, &captured.samples),
+        }
+        .and_then(|()| w.flush())
+        .map_err(|e| eyre!("failed to write {}: {}", output.display(), e))?;
+
+        println!(
+            "Saved {} samples at {} Hz to {}",
+            captured.samples.len(),
+            captured.rate,
+            output.display()
+        );
+
+        Ok(())
+    }
+
+    async fn serve(&self, address: &str) -> Result<()> {
+        let pg = self.connect().await?;
+
+        println!("Serving Pico de Gallo on {}", address);
+
+        RemoteServer::new(pg)
+            .serve_tcp(address)
+            .await
+            .map_err(|e| eyre!("serve failed: {}", e))
+    }
+
+    #[cfg(feature = "http")]
+    async fn http(&self, address: &str) -> Result<()> {
+        let pg = self.connect().await?;
+
+        println!("Serving HTTP API on http://{}", address);
+
+        http::serve(pg, address)
+            .await
+            .map_err(|e| eyre!("http server failed: {}", e))
+    }
+
+    #[cfg(unix)]
+    async fn daemon(&self, socket: &Path) -> Result<()> {
+        let pg = self.connect().await?;
+
+        println!("Serving Pico de Gallo on {}", socket.display());
+
+        RemoteServer::new(pg)
+            .serve_unix(socket)
+            .await
+            .map_err(|e| eyre!("daemon failed: {}", e))
+    }
+}
+
+/// Format a PMBus reading, `n/a` if it couldn't be read.
+fn reading<E>(value: Result<f64, E>, unit: &str) -> String {
+    match value {
+        Ok(value) => format!("{:.3} {}", value, unit),
+        Err(_) => String::from("n/a"),
+    }
+}
+
+/// Format `value` with the SI prefix bringing it within 1 to 1000,
+/// down to nano.
+fn si(value: f64, unit: &str) -> String {
+    const PREFIXES: [(f64, &str); 7] = [
+        (1e9, "G"),
+        (1e6, "M"),
+        (1e3, "k"),
+        (1.0, ""),
+        (1e-3, "m"),
+        (1e-6, "µ"),
+        (1e-9, "n"),
+    ];
+
+    let (scale, prefix) = PREFIXES
+        .into_iter()
+        .find(|&(scale, _)| value.abs() >= scale)
+        .unwrap_or((1.0, ""));
+
+    format!("{:.6} {}{}", value / scale, prefix, unit)
 }
 
 fn parse_byte(s: &str) -> Result<u8, ParseIntError> {
@@ -372,3 +1843,182 @@ fn parse_byte(s: &str) -> Result<u8, ParseIntError> {
         s.parse::<u8>()
     }
 }
+
+fn parse_word(s: &str) -> Result<u16, ParseIntError> {
+    if let Some(hex) = s.strip_prefix("0x") {
+        u16::from_str_radix(hex, 16)
+    } else if let Some(bin) = s.strip_prefix("0b") {
+        u16::from_str_radix(bin, 2)
+    } else {
+        s.parse::<u16>()
+    }
+}
+
+fn parse_address(s: &str) -> Result<u16, String> {
+    let address = if let Some(hex) = s.strip_prefix("0x") {
+        u16::from_str_radix(hex, 16)
+    } else if let Some(bin) = s.strip_prefix("0b") {
+        u16::from_str_radix(bin, 2)
+    } else {
+        s.parse::<u16>()
+    }
+    .map_err(|e| e.to_string())?;
+
+    if address > I2C_TEN_BIT_ADDRESS_MAX {
+        return Err(format!("expected at most {:#x}", I2C_TEN_BIT_ADDRESS_MAX));
+    }
+
+    Ok(address)
+}
+
+fn parse_target_command(s: &str) -> Result<TargetCommand, String> {
+    let hex = |s: &str| {
+        if !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!("expected hex digits in {:?}", s));
+        }
+
+        if !s.len().is_multiple_of(2) {
+            return Err(format!("odd number of hex digits in {:?}", s));
+        }
+
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<u8>, _>>()
+    };
+
+    let (command, response) = s
+        .split_once(':')
+        .ok_or_else(|| String::from("expected COMMAND:RESPONSE"))?;
+
+    Ok(TargetCommand {
+        command: hex(command)?,
+        response: hex(response)?,
+    })
+}
+
+fn parse_color(s: &str) -> Result<Rgbw, String> {
+    let s = s.strip_prefix('#').unwrap_or(s);
+    if !s.is_ascii() || (s.len() != 6 && s.len() != 8) {
+        return Err(String::from("expected RRGGBB or RRGGBBWW"));
+    }
+
+    let byte = |i: usize| match s.get(i..i + 2) {
+        Some(hex) => u8::from_str_radix(hex, 16).map_err(|e| e.to_string()),
+        None => Ok(0),
+    };
+
+    Ok(Rgbw::new(byte(0)?, byte(2)?, byte(4)?, byte(6)?))
+}
+
+fn parse_rom(s: &str) -> Result<OneWireRom, String> {
+    s.parse().map_err(|e: OneWireRomParseError| e.to_string())
+}
+
+fn parse_rate(s: &str) -> Result<u32, String> {
+    let (number, multiplier) = match s.strip_suffix('k') {
+        Some(number) => (number, 1e3),
+        None => match s.strip_suffix('M') {
+            Some(number) => (number, 1e6),
+            None => (s, 1.0),
+        },
+    };
+
+    let rate = number.parse::<f64>().map_err(|e| e.to_string())? * multiplier;
+    if rate.fract() != 0.0 || !(1.0..=f64::from(u32::MAX)).contains(&rate) {
+        return Err(String::from("expected a whole number of Hz"));
+    }
+
+    Ok(rate as u32)
+}
+
+/// Channel numbered or named as in [`CAPTURE_CHANNEL_NAMES`].
+fn parse_channel(s: &str) -> Result<u8, String> {
+    if let Some(channel) = CAPTURE_CHANNEL_NAMES.iter().position(|name| *name == s) {
+        return Ok(channel as u8);
+    }
+
+    match s.parse::<u8>() {
+        Ok(channel) if usize::from(channel) < CAPTURE_CHANNELS => Ok(channel),
+        _ => Err(format!(
+            "expected 0 to {} or one of {}",
+            CAPTURE_CHANNELS - 1,
+            CAPTURE_CHANNEL_NAMES.join(", ")
+        )),
+    }
+}
+
+fn parse_trigger(s: &str) -> Result<CaptureTrigger, String> {
+    let (channel, condition) = s
+        .split_once(':')
+        .ok_or_else(|| String::from("expected CHANNEL:CONDITION"))?;
+    let channel = parse_channel(channel)?;
+
+    match condition {
+        "rising" => Ok(CaptureTrigger::rising(channel)),
+        "falling" => Ok(CaptureTrigger::falling(channel)),
+        "high" => Ok(CaptureTrigger::level(channel, true)),
+        "low" => Ok(CaptureTrigger::level(channel, false)),
+        _ => Err(String::from("expected rising, falling, high or low")),
+    }
+}
+
+fn parse_pattern(s: &str) -> Result<CaptureTrigger, String> {
+    let (channel, levels) = s
+        .split_once(':')
+        .ok_or_else(|| String::from("expected CHANNEL:LEVELS"))?;
+    let first = parse_channel(channel)?;
+
+    if levels.is_empty() || usize::from(first) + levels.len() > CAPTURE_CHANNELS {
+        return Err(format!(
+            "expected 1 to {} levels",
+            CAPTURE_CHANNELS - usize::from(first)
+        ));
+    }
+
+    let mut value = 0;
+    for (i, level) in levels.chars().enumerate() {
+        match level {
+            '0' => {}
+            '1' => value |= 1 << i,
+            _ => return Err(String::from("levels are 0 or 1")),
+        }
+    }
+
+    Ok(CaptureTrigger::Pattern {
+        first,
+        count: levels.len() as u8,
+        value,
+        edge: false,
+    })
+}
+
+fn parse_word_size(s: &str) -> Result<SpiWordSize, String> {
+    match s {
+        "8" => Ok(SpiWordSize::Eight),
+        "16" => Ok(SpiWordSize::Sixteen),
+        _ => Err(String::from("expected 8 or 16")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_command() {
+        let target = parse_target_command("a50f:0102").unwrap();
+        assert_eq!(target.command, [0xa5, 0x0f]);
+        assert_eq!(target.response, [0x01, 0x02]);
+
+        let target = parse_target_command(":").unwrap();
+        assert!(target.command.is_empty() && target.response.is_empty());
+    }
+
+    #[test]
+    fn target_command_rejects_non_hex() {
+        for s in ["a50f", "a5f:00", "a5:0x", "+1:00", "aé0:00", "00:é0"] {
+            assert!(parse_target_command(s).is_err(), "{s:?}");
+        }
+    }
+}
@@ -1,9 +1,8 @@
-use futures::executor::block_on;
 use pico_de_gallo_lib as lib;
 use std::ffi::CStr;
 use std::os::raw::c_char;
 
-pub struct PicoDeGallo(lib::PicoDeGallo);
+pub struct PicoDeGallo(lib::blocking::PicoDeGallo);
 
 // ----------------------------- Status Codes -----------------------------
 
@@ -39,6 +38,8 @@ pub enum Status {
     SetConfigFailed = -13,
     /// Version failed
     VersionFailed = -14,
+    /// Get config failed
+    GetConfigFailed = -15,
 }
 
 // ----------------------------- Library Lifetime -----------------------------
@@ -49,7 +50,7 @@ pub enum Status {
 /// device.
 #[unsafe(no_mangle)]
 pub extern "C" fn gallo_init() -> *const PicoDeGallo {
-    let gallo = Box::new(PicoDeGallo(lib::PicoDeGallo::new()));
+    let gallo = Box::new(PicoDeGallo(lib::blocking::PicoDeGallo::new()));
 
     Box::into_raw(gallo) as *const PicoDeGallo
 }
@@ -82,13 +83,49 @@ pub unsafe extern "C" fn gallo_init_with_serial_number(
         return std::ptr::null();
     }
 
-    let gallo = Box::new(PicoDeGallo(lib::PicoDeGallo::new_with_serial_number(
-        serial_number.unwrap(),
-    )));
+    let gallo = Box::new(PicoDeGallo(
+        lib::blocking::PicoDeGallo::new_with_serial_number(serial_number.unwrap()),
+    ));
 
     Box::into_raw(gallo) as *const PicoDeGallo
 }
 
+/// gallo_init_tcp - Initialize the library context for a device
+/// shared over TCP, e.g. by `gallo serve`, listening on `c_address`
+/// (such as "192.168.1.10:5151").
+///
+/// Returns an opaque representation of the underlying PicoDeGallo
+/// device or NULL if the connection could not be established.
+///
+/// # Safety
+///
+/// `c_address` must point to a valid c-string with a
+/// NULL-terminator.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gallo_init_tcp(c_address: *const c_char) -> *const PicoDeGallo {
+    if c_address.is_null() {
+        eprintln!("NULL address received");
+        return std::ptr::null();
+    }
+
+    // Safety: Pointer is not null due to the check above. Caller must
+    // make sure to pass a null-terminated string.
+    let address = unsafe { CStr::from_ptr(c_address).to_str() };
+
+    let Ok(address) = address else {
+        eprintln!("Invalid UTF-8 string");
+        return std::ptr::null();
+    };
+
+    match lib::blocking::PicoDeGallo::connect_tcp(address) {
+        Ok(gallo) => Box::into_raw(Box::new(PicoDeGallo(gallo))) as *const PicoDeGallo,
+        Err(e) => {
+            eprintln!("Failed to connect to {}: {}", address, e);
+            std::ptr::null()
+        }
+    }
+}
+
 /// gallo_free - Releases and destroys the library context created by `gallo_init`.
 ///
 /// # Safety
@@ -125,7 +162,7 @@ pub unsafe extern "C" fn gallo_ping(gallo: *mut PicoDeGallo, id: &mut u32) -> St
     // pointer to `PicoDeGallo` returned by `gallo_init()`.
     let gallo = unsafe { Box::from_raw(gallo) };
 
-    let result = block_on(gallo.0.ping(*id));
+    let result = gallo.0.ping(*id);
     match result {
         Ok(back) => {
             *id = back;
@@ -137,7 +174,8 @@ pub unsafe extern "C" fn gallo_ping(gallo: *mut PicoDeGallo, id: &mut u32) -> St
 
 // ----------------------------- I2c endpoints -----------------------------
 
-/// gallo_i2c_read - Read `len` bytes from the device at `address` into `buf`.
+/// gallo_i2c_read_bus - Read `len` bytes from the device at `address` on
+/// I2c `bus` into `buf`.
 ///
 /// Returns `Status::Ok` in case of success or various error codes.
 ///
@@ -147,8 +185,9 @@ pub unsafe extern "C" fn gallo_ping(gallo: *mut PicoDeGallo, id: &mut u32) -> St
 /// `PicoDeGallo` returned by `gallo_init()` and `buf` must be valid
 /// for `len` bytes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn gallo_i2c_read(
+pub unsafe extern "C" fn gallo_i2c_read_bus(
     gallo: *mut PicoDeGallo,
+    bus: u8,
     address: u8,
     buf: *mut u8,
     len: usize,
@@ -175,7 +214,7 @@ pub unsafe extern "C" fn gallo_i2c_read(
     // Safety: caller must ensure buf is valid for len bytes.
     let buf = unsafe { std::slice::from_raw_parts_mut(buf, len) };
 
-    let result = block_on(gallo.0.i2c_read(address, len as u16));
+    let result = gallo.0.i2c_read(bus, address, len as u16);
 
     match result {
         Ok(data) => {
@@ -186,7 +225,28 @@ pub unsafe extern "C" fn gallo_i2c_read(
     }
 }
 
-/// gallo_i2c_write - Write `len` bytes from `buf` to the device at `address`.
+/// gallo_i2c_read - Read `len` bytes from the device at `address` on
+/// the first I2c bus into `buf`, see `gallo_i2c_read_bus()`.
+///
+/// Returns `Status::Ok` in case of success or various error codes.
+///
+/// # Safety
+///
+/// Same as `gallo_i2c_read_bus()`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gallo_i2c_read(
+    gallo: *mut PicoDeGallo,
+    address: u8,
+    buf: *mut u8,
+    len: usize,
+) -> Status {
+    // Safety: the caller upholds the requirements of
+    // `gallo_i2c_read_bus()`.
+    unsafe { gallo_i2c_read_bus(gallo, 0, address, buf, len) }
+}
+
+/// gallo_i2c_write_bus - Write `len` bytes from `buf` to the device at
+/// `address` on I2c `bus`.
 ///
 /// Returns `Status::Ok` in case of success or various error codes.
 ///
@@ -196,8 +256,9 @@ pub unsafe extern "C" fn gallo_i2c_read(
 /// `PicoDeGallo` returned by `gallo_init()` and `buf` must be valid
 /// for `len` bytes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn gallo_i2c_write(
+pub unsafe extern "C" fn gallo_i2c_write_bus(
     gallo: *mut PicoDeGallo,
+    bus: u8,
     address: u8,
     buf: *const u8,
     len: usize,
@@ -224,7 +285,7 @@ pub unsafe extern "C" fn gallo_i2c_write(
     // Safety: caller must ensure buf is valid for len bytes.
     let buf = unsafe { std::slice::from_raw_parts(buf, len) };
 
-    let result = block_on(gallo.0.i2c_write(address, buf));
+    let result = gallo.0.i2c_write(bus, address, buf);
 
     match result {
         Ok(()) => Status::Ok,
@@ -232,7 +293,28 @@ pub unsafe extern "C" fn gallo_i2c_write(
     }
 }
 
-/// gallo_i2c_write_read - Perform a write followed by a read.
+/// gallo_i2c_write - Write `len` bytes from `buf` to the device at
+/// `address` on the first I2c bus, see `gallo_i2c_write_bus()`.
+///
+/// Returns `Status::Ok` in case of success or various error codes.
+///
+/// # Safety
+///
+/// Same as `gallo_i2c_write_bus()`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gallo_i2c_write(
+    gallo: *mut PicoDeGallo,
+    address: u8,
+    buf: *const u8,
+    len: usize,
+) -> Status {
+    // Safety: the caller upholds the requirements of
+    // `gallo_i2c_write_bus()`.
+    unsafe { gallo_i2c_write_bus(gallo, 0, address, buf, len) }
+}
+
+/// gallo_i2c_write_read_bus - Perform a write followed by a read on I2c
+/// `bus`.
 ///
 /// Returns `Status::Ok` in case of success or various error codes.
 ///
@@ -242,8 +324,9 @@ pub unsafe extern "C" fn gallo_i2c_write(
 /// `PicoDeGallo` returned by `gallo_init()`, `txbuf` must be valid
 /// for `txlen` bytes, and `rxbuf` must be valid for `rxlen` bytes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn gallo_i2c_write_read(
+pub unsafe extern "C" fn gallo_i2c_write_read_bus(
     gallo: *mut PicoDeGallo,
+    bus: u8,
     address: u8,
     txbuf: *const u8,
     txlen: usize,
@@ -275,12 +358,12 @@ pub unsafe extern "C" fn gallo_i2c_write_read(
     // Safety: caller must ensure rxbuf is valid for rxlen bytes.
     let rxbuf = unsafe { std::slice::from_raw_parts_mut(rxbuf, rxlen) };
 
-    let result = block_on(gallo.0.i2c_write(address, txbuf));
+    let result = gallo.0.i2c_write(bus, address, txbuf);
     if result.is_err() {
         return Status::I2cWriteFailed;
     }
 
-    let result = block_on(gallo.0.i2c_read(address, rxlen as u16));
+    let result = gallo.0.i2c_read(bus, address, rxlen as u16);
     match result {
         Ok(data) => {
             rxbuf.copy_from_slice(&data);
@@ -290,9 +373,31 @@ pub unsafe extern "C" fn gallo_i2c_write_read(
     }
 }
 
+/// gallo_i2c_write_read - Perform a write followed by a read on the
+/// first I2c bus, see `gallo_i2c_write_read_bus()`.
+///
+/// Returns `Status::Ok` in case of success or various error codes.
+///
+/// # Safety
+///
+/// Same as `gallo_i2c_write_read_bus()`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gallo_i2c_write_read(
+    gallo: *mut PicoDeGallo,
+    address: u8,
+    txbuf: *const u8,
+    txlen: usize,
+    rxbuf: *mut u8,
+    rxlen: usize,
+) -> Status {
+    // Safety: the caller upholds the requirements of
+    // `gallo_i2c_write_read_bus()`.
+    unsafe { gallo_i2c_write_read_bus(gallo, 0, address, txbuf, txlen, rxbuf, rxlen) }
+}
+
 // ----------------------------- Spi endpoints -----------------------------
 
-/// gallo_spi_read - Read `len` bytes.
+/// gallo_spi_read_bus - Read `len` bytes from Spi `bus`.
 ///
 /// Returns `Status::Ok` in case of success or various error codes.
 ///
@@ -302,8 +407,9 @@ pub unsafe extern "C" fn gallo_i2c_write_read(
 /// `PicoDeGallo` returned by `gallo_init()` and `buf` must be valid
 /// for `len` bytes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn gallo_spi_read(
+pub unsafe extern "C" fn gallo_spi_read_bus(
     gallo: *mut PicoDeGallo,
+    bus: u8,
     buf: *mut u8,
     len: usize,
 ) -> Status {
@@ -329,7 +435,7 @@ pub unsafe extern "C" fn gallo_spi_read(
     // Safety: caller must ensure buf is valid for len bytes.
     let buf = unsafe { std::slice::from_raw_parts_mut(buf, len) };
 
-    let result = block_on(gallo.0.spi_read(len as u16));
+    let result = gallo.0.spi_read(bus, len as u16);
 
     match result {
         Ok(data) => {
@@ -340,7 +446,26 @@ pub unsafe extern "C" fn gallo_spi_read(
     }
 }
 
-/// gallo_spi_write - Write `len` bytes from `buf`.
+/// gallo_spi_read - Read `len` bytes from the first Spi bus, see
+/// `gallo_spi_read_bus()`.
+///
+/// Returns `Status::Ok` in case of success or various error codes.
+///
+/// # Safety
+///
+/// Same as `gallo_spi_read_bus()`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gallo_spi_read(
+    gallo: *mut PicoDeGallo,
+    buf: *mut u8,
+    len: usize,
+) -> Status {
+    // Safety: the caller upholds the requirements of
+    // `gallo_spi_read_bus()`.
+    unsafe { gallo_spi_read_bus(gallo, 0, buf, len) }
+}
+
+/// gallo_spi_write_bus - Write `len` bytes from `buf` to Spi `bus`.
 ///
 /// Returns `Status::Ok` in case of success or various error codes.
 ///
@@ -350,8 +475,9 @@ pub unsafe extern "C" fn gallo_spi_read(
 /// `PicoDeGallo` returned by `gallo_init()` and `buf` must be valid
 /// for `len` bytes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn gallo_spi_write(
+pub unsafe extern "C" fn gallo_spi_write_bus(
     gallo: *mut PicoDeGallo,
+    bus: u8,
     buf: *const u8,
     len: usize,
 ) -> Status {
@@ -377,7 +503,7 @@ pub unsafe extern "C" fn gallo_spi_write(
     // Safety: caller must ensure buf is valid for len bytes.
     let buf = unsafe { std::slice::from_raw_parts(buf, len) };
 
-    let result = block_on(gallo.0.spi_write(buf));
+    let result = gallo.0.spi_write(bus, buf);
 
     match result {
         Ok(()) => Status::Ok,
@@ -385,7 +511,26 @@ pub unsafe extern "C" fn gallo_spi_write(
     }
 }
 
-/// gallo_spi_flush - Flush the SPI interface.
+/// gallo_spi_write - Write `len` bytes from `buf` to the first Spi
+/// bus, see `gallo_spi_write_bus()`.
+///
+/// Returns `Status::Ok` in case of success or various error codes.
+///
+/// # Safety
+///
+/// Same as `gallo_spi_write_bus()`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gallo_spi_write(
+    gallo: *mut PicoDeGallo,
+    buf: *const u8,
+    len: usize,
+) -> Status {
+    // Safety: the caller upholds the requirements of
+    // `gallo_spi_write_bus()`.
+    unsafe { gallo_spi_write_bus(gallo, 0, buf, len) }
+}
+
+/// gallo_spi_flush_bus - Flush Spi `bus`.
 ///
 /// Returns `Status::Ok` in case of success or various error codes.
 ///
@@ -394,7 +539,7 @@ pub unsafe extern "C" fn gallo_spi_write(
 /// Caller must ensure that `gallo` is a valid, opaque pointer to
 /// `PicoDeGallo` returned by `gallo_init()`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn gallo_spi_flush(gallo: *mut PicoDeGallo) -> Status {
+pub unsafe extern "C" fn gallo_spi_flush_bus(gallo: *mut PicoDeGallo, bus: u8) -> Status {
     if gallo.is_null() {
         eprintln!("Unexpected NULL context");
         return Status::Uninitialized;
@@ -404,7 +549,7 @@ pub unsafe extern "C" fn gallo_spi_flush(gallo: *mut PicoDeGallo) -> Status {
     // pointer to `PicoDeGallo` returned by `gallo_init()`.
     let gallo = unsafe { Box::from_raw(gallo) };
 
-    let result = block_on(gallo.0.spi_flush());
+    let result = gallo.0.spi_flush(bus);
 
     match result {
         Ok(()) => Status::Ok,
@@ -412,6 +557,21 @@ pub unsafe extern "C" fn gallo_spi_flush(gallo: *mut PicoDeGallo) -> Status {
     }
 }
 
+/// gallo_spi_flush - Flush the first Spi bus, see
+/// `gallo_spi_flush_bus()`.
+///
+/// Returns `Status::Ok` in case of success or various error codes.
+///
+/// # Safety
+///
+/// Same as `gallo_spi_flush_bus()`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gallo_spi_flush(gallo: *mut PicoDeGallo) -> Status {
+    // Safety: the caller upholds the requirements of
+    // `gallo_spi_flush_bus()`.
+    unsafe { gallo_spi_flush_bus(gallo, 0) }
+}
+
 // ----------------------------- Gpio endpoints -----------------------------
 
 /// gallo_gpio_get - Get the state of a given GPIO pin.
@@ -437,7 +597,7 @@ pub unsafe extern "C" fn gallo_gpio_get(
     // pointer to `PicoDeGallo` returned by `gallo_init()`.
     let gallo = unsafe { Box::from_raw(gallo) };
 
-    let result = block_on(gallo.0.gpio_get(pin));
+    let result = gallo.0.gpio_get(pin);
 
     match result {
         Ok(s) => {
@@ -472,7 +632,7 @@ pub unsafe extern "C" fn gallo_gpio_put(gallo: *mut PicoDeGallo, pin: u8, state:
     } else {
         lib::GpioState::Low
     };
-    let result = block_on(gallo.0.gpio_put(pin, s));
+    let result = gallo.0.gpio_put(pin, s);
 
     match result {
         Ok(()) => Status::Ok,
@@ -499,7 +659,7 @@ pub unsafe extern "C" fn gallo_gpio_wait_for_high(gallo: *mut PicoDeGallo, pin:
     // pointer to `PicoDeGallo` returned by `gallo_init()`.
     let gallo = unsafe { Box::from_raw(gallo) };
 
-    let result = block_on(gallo.0.gpio_wait_for_high(pin));
+    let result = gallo.0.gpio_wait_for_high(pin);
 
     match result {
         Ok(()) => Status::Ok,
@@ -526,7 +686,7 @@ pub unsafe extern "C" fn gallo_gpio_wait_for_low(gallo: *mut PicoDeGallo, pin: u
     // pointer to `PicoDeGallo` returned by `gallo_init()`.
     let gallo = unsafe { Box::from_raw(gallo) };
 
-    let result = block_on(gallo.0.gpio_wait_for_low(pin));
+    let result = gallo.0.gpio_wait_for_low(pin);
 
     match result {
         Ok(()) => Status::Ok,
@@ -556,7 +716,7 @@ pub unsafe extern "C" fn gallo_gpio_wait_for_rising_edge(
     // pointer to `PicoDeGallo` returned by `gallo_init()`.
     let gallo = unsafe { Box::from_raw(gallo) };
 
-    let result = block_on(gallo.0.gpio_wait_for_rising_edge(pin));
+    let result = gallo.0.gpio_wait_for_rising_edge(pin);
 
     match result {
         Ok(()) => Status::Ok,
@@ -586,7 +746,7 @@ pub unsafe extern "C" fn gallo_gpio_wait_for_falling_edge(
     // pointer to `PicoDeGallo` returned by `gallo_init()`.
     let gallo = unsafe { Box::from_raw(gallo) };
 
-    let result = block_on(gallo.0.gpio_wait_for_falling_edge(pin));
+    let result = gallo.0.gpio_wait_for_falling_edge(pin);
 
     match result {
         Ok(()) => Status::Ok,
@@ -613,7 +773,7 @@ pub unsafe extern "C" fn gallo_gpio_wait_for_any_edge(gallo: *mut PicoDeGallo, p
     // pointer to `PicoDeGallo` returned by `gallo_init()`.
     let gallo = unsafe { Box::from_raw(gallo) };
 
-    let result = block_on(gallo.0.gpio_wait_for_any_edge(pin));
+    let result = gallo.0.gpio_wait_for_any_edge(pin);
 
     match result {
         Ok(()) => Status::Ok,
@@ -621,16 +781,15 @@ pub unsafe extern "C" fn gallo_gpio_wait_for_any_edge(gallo: *mut PicoDeGallo, p
     }
 }
 
-// ----------------------------- Set config endpoint -----------------------------
+// ----------------------------- Config endpoints -----------------------------
 
-/// gallo_set_config - Sets the configuration parameters for the
-/// underlying I2c and Spi buses.
+/// gallo_i2c_set_config - Sets the configuration parameters for I2c
+/// `bus`.
 ///
-/// `spi_phase`: false means "Capture on first transition" or CPHA=0,
-/// true means "Capture on second transition" or CPHA=1.
+/// `frequency`: SCL frequency in Hz.
 ///
-/// `spi_polarity`: false means "Idle low" or CPOL=0, true means "Idle
-/// high" or CPOL=1.
+/// `actual_frequency`: receives the SCL frequency actually achieved,
+/// in Hz.
 ///
 /// Returns `Status::Ok` in case of success or various error codes.
 ///
@@ -639,46 +798,196 @@ pub unsafe extern "C" fn gallo_gpio_wait_for_any_edge(gallo: *mut PicoDeGallo, p
 /// Caller must ensure that `gallo` is a valid, opaque pointer to
 /// `PicoDeGallo` returned by `gallo_init()`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn gallo_set_config(
+pub unsafe extern "C" fn gallo_i2c_set_config(
     gallo: *mut PicoDeGallo,
-    i2c_frequency: u32,
-    spi_frequency: u32,
-    spi_phase: bool,
-    spi_polarity: bool,
+    bus: u8,
+    frequency: u32,
+    actual_frequency: &mut u32,
 ) -> Status {
     if gallo.is_null() {
         eprintln!("Unexpected NULL context");
         return Status::Uninitialized;
     }
 
+    let Ok(config) = lib::I2cConfig::builder().frequency(frequency).build() else {
+        return Status::InvalidArgument;
+    };
+
     // Safety: caller must ensure that `gallo` is a valid opaque
     // pointer to `PicoDeGallo` returned by `gallo_init()`.
     let gallo = unsafe { Box::from_raw(gallo) };
 
-    let phase = if spi_phase {
+    let result = gallo.0.i2c_set_config(bus, config);
+
+    match result {
+        Ok(actual) => {
+            *actual_frequency = actual;
+
+            Status::Ok
+        }
+        Err(_) => Status::SetConfigFailed,
+    }
+}
+
+/// gallo_spi_set_config - Sets the configuration parameters for Spi
+/// `bus`.
+///
+/// `frequency`: SCK frequency in Hz.
+///
+/// `phase`: false means "Capture on first transition" or CPHA=0,
+/// true means "Capture on second transition" or CPHA=1.
+///
+/// `polarity`: false means "Idle low" or CPOL=0, true means "Idle
+/// high" or CPOL=1.
+///
+/// `word_size`: bits per word, either 8 or 16.
+///
+/// `lsb_first`: false shifts out the most significant bit of each word
+/// first, true the least significant one.
+///
+/// `actual_frequency`: receives the SCK frequency actually achieved,
+/// in Hz.
+///
+/// Returns `Status::Ok` in case of success or various error codes.
+///
+/// # Safety
+///
+/// Caller must ensure that `gallo` is a valid, opaque pointer to
+/// `PicoDeGallo` returned by `gallo_init()`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gallo_spi_set_config(
+    gallo: *mut PicoDeGallo,
+    bus: u8,
+    frequency: u32,
+    phase: bool,
+    polarity: bool,
+    word_size: u8,
+    lsb_first: bool,
+    actual_frequency: &mut u32,
+) -> Status {
+    if gallo.is_null() {
+        eprintln!("Unexpected NULL context");
+        return Status::Uninitialized;
+    }
+
+    let phase = if phase {
         lib::SpiPhase::CaptureOnSecondTransition
     } else {
         lib::SpiPhase::CaptureOnFirstTransition
     };
 
-    let polarity = if spi_polarity {
+    let polarity = if polarity {
         lib::SpiPolarity::IdleHigh
     } else {
         lib::SpiPolarity::IdleLow
     };
 
-    let result = block_on(
-        gallo
-            .0
-            .set_config(i2c_frequency, spi_frequency, phase, polarity),
-    );
+    let word_size = match word_size {
+        8 => lib::SpiWordSize::Eight,
+        16 => lib::SpiWordSize::Sixteen,
+        _ => return Status::InvalidArgument,
+    };
+
+    let bit_order = if lsb_first {
+        lib::SpiBitOrder::LsbFirst
+    } else {
+        lib::SpiBitOrder::MsbFirst
+    };
+
+    let Ok(config) = lib::SpiConfig::builder()
+        .frequency(frequency)
+        .phase(phase)
+        .polarity(polarity)
+        .word_size(word_size)
+        .bit_order(bit_order)
+        .build()
+    else {
+        return Status::InvalidArgument;
+    };
+
+    // Safety: caller must ensure that `gallo` is a valid opaque
+    // pointer to `PicoDeGallo` returned by `gallo_init()`.
+    let gallo = unsafe { Box::from_raw(gallo) };
+
+    let result = gallo.0.spi_set_config(bus, config);
 
     match result {
-        Ok(()) => Status::Ok,
+        Ok(actual) => {
+            *actual_frequency = actual;
+
+            Status::Ok
+        }
         Err(_) => Status::SetConfigFailed,
     }
 }
 
+/// gallo_get_config - Gets the current configuration parameters of
+/// I2c `i2c_bus` and Spi `spi_bus`.
+///
+/// `i2c_frequency` and `spi_frequency` receive the frequencies as
+/// requested, `i2c_actual_frequency` and `spi_actual_frequency` the
+/// ones actually achieved. `spi_phase`, `spi_polarity`,
+/// `spi_word_size` and `spi_lsb_first` are encoded as in
+/// `gallo_spi_set_config()`.
+///
+/// Returns `Status::Ok` in case of success or various error codes.
+///
+/// # Safety
+///
+/// Caller must ensure that `gallo` is a valid, opaque pointer to
+/// `PicoDeGallo` returned by `gallo_init()`.
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn gallo_get_config(
+    gallo: *mut PicoDeGallo,
+    i2c_bus: u8,
+    spi_bus: u8,
+    i2c_frequency: &mut u32,
+    i2c_actual_frequency: &mut u32,
+    spi_frequency: &mut u32,
+    spi_actual_frequency: &mut u32,
+    spi_phase: &mut bool,
+    spi_polarity: &mut bool,
+    spi_word_size: &mut u8,
+    spi_lsb_first: &mut bool,
+) -> Status {
+    if gallo.is_null() {
+        eprintln!("Unexpected NULL context");
+        return Status::Uninitialized;
+    }
+
+    let (i2c_bus, spi_bus) = (usize::from(i2c_bus), usize::from(spi_bus));
+    if i2c_bus >= lib::NUM_I2C_BUSES || spi_bus >= lib::NUM_SPI_BUSES {
+        eprintln!("Invalid bus");
+        return Status::InvalidArgument;
+    }
+
+    // Safety: caller must ensure that `gallo` is a valid opaque
+    // pointer to `PicoDeGallo` returned by `gallo_init()`.
+    let gallo = unsafe { Box::from_raw(gallo) };
+
+    let result = gallo.0.get_config();
+
+    match result {
+        Ok(config) => {
+            let i2c = config.i2c[i2c_bus];
+            let spi = config.spi[spi_bus];
+
+            *i2c_frequency = i2c.frequency;
+            *i2c_actual_frequency = config.i2c_actual_frequency[i2c_bus];
+            *spi_frequency = spi.frequency;
+            *spi_actual_frequency = config.spi_actual_frequency[spi_bus];
+            *spi_phase = spi.phase == lib::SpiPhase::CaptureOnSecondTransition;
+            *spi_polarity = spi.polarity == lib::SpiPolarity::IdleHigh;
+            *spi_word_size = spi.word_size as u8;
+            *spi_lsb_first = spi.bit_order == lib::SpiBitOrder::LsbFirst;
+
+            Status::Ok
+        }
+        Err(_) => Status::GetConfigFailed,
+    }
+}
+
 // ----------------------------- Version endpoint -----------------------------
 
 #[unsafe(no_mangle)]
@@ -705,7 +1014,7 @@ pub unsafe extern "C" fn gallo_version(
     // pointer to `PicoDeGallo` returned by `gallo_init()`.
     let gallo = unsafe { Box::from_raw(gallo) };
 
-    let result = block_on(gallo.0.version());
+    let result = gallo.0.version();
 
     match result {
         Ok(lib::VersionInfo {
@@ -0,0 +1,480 @@
+//! I2C and SPI buses.
+//!
+//! Each controller is a distinct type in `embassy_rp`, these wrap
+//! either of them so that buses can be picked by their index.
+
+use crate::smbus;
+use embassy_embedded_hal::SetConfig;
+use embassy_rp::i2c::{self, I2c};
+use embassy_rp::pac;
+use embassy_rp::peripherals::{I2C0, I2C1, SPI0, SPI1};
+use embassy_rp::spi::{self, Spi};
+use embassy_time::{Duration, Instant, Timer};
+use pico_de_gallo_internal::{SmbusFail, SmbusOperation};
+
+/// IO bank function handing a pin to SIO.
+const FUNCSEL_SIO: u8 = 5;
+/// IO bank function handing a pin to its I2C controller.
+const FUNCSEL_I2C: u8 = 3;
+
+/// Half a period of SCL when driven through SIO, which runs at 100 kHz.
+const HALF_PERIOD: Duration = Duration::from_micros(5);
+/// How long a target may stretch SCL when driven through SIO.
+const STRETCH_TIMEOUT: Duration = Duration::from_millis(1);
+
+macro_rules! with_i2c {
+    ($bus:expr, $i2c:ident => $body:expr) => {
+        match $bus {
+            I2cBus::I2c0($i2c) => $body,
+            I2cBus::I2c1($i2c) => $body,
+        }
+    };
+}
+
+macro_rules! with_spi {
+    ($bus:expr, $spi:ident => $body:expr) => {
+        match $bus {
+            SpiBus::Spi0($spi) => $body,
+            SpiBus::Spi1($spi) => $body,
+        }
+    };
+}
+
+pub enum I2cBus {
+    I2c0(I2c<'static, I2C0, i2c::Async>),
+    I2c1(I2c<'static, I2C1, i2c::Async>),
+}
+
+impl I2cBus {
+    pub fn blocking_read(&mut self, address: u8, read: &mut [u8]) -> Result<(), i2c::Error> {
+        with_i2c!(self, i2c => i2c.blocking_read(address, read))
+    }
+
+    pub fn blocking_write(&mut self, address: u8, write: &[u8]) -> Result<(), i2c::Error> {
+        with_i2c!(self, i2c => i2c.blocking_write(address, write))
+    }
+
+    pub fn blocking_write_read(&mut self, address: u8, write: &[u8], read: &mut [u8]) -> Result<(), i2c::Error> {
+        with_i2c!(self, i2c => i2c.blocking_write_read(address, write, read))
+    }
+
+    pub fn set_config(&mut self, config: &i2c::Config) -> Result<(), i2c::ConfigError> {
+        with_i2c!(self, i2c => SetConfig::set_config(i2c, config))
+    }
+
+    /// Program the controller back into controller mode with `config`,
+    /// after target emulation took it over.
+    pub fn restore_controller(&mut self, config: &i2c::Config) -> Result<(), i2c::ConfigError> {
+        let regs = match self {
+            I2cBus::I2c0(_) => pac::I2C0,
+            I2cBus::I2c1(_) => pac::I2C1,
+        };
+
+        regs.ic_enable().write(|w| w.set_enable(false));
+        regs.ic_con().write(|w| {
+            w.set_speed(pac::i2c::vals::Speed::FAST);
+            w.set_master_mode(true);
+            w.set_ic_slave_disable(true);
+            w.set_ic_restart_en(true);
+            w.set_tx_empty_ctrl(true);
+        });
+        regs.ic_intr_mask().write_value(Default::default());
+        regs.ic_clr_intr().read();
+        regs.ic_tx_tl().write(|w| w.set_tx_tl(0));
+        regs.ic_rx_tl().write(|w| w.set_rx_tl(0));
+
+        self.set_config(config)
+    }
+
+    /// Take `scl` and `sda` over as GPIOs, clock up to nine pulses until
+    /// the target releases SDA and issue a STOP. The controller gets the
+    /// pins back afterwards, reprogrammed with `config`.
+    ///
+    /// Returns whether both lines were left high.
+    pub async fn recover(&mut self, scl: usize, sda: usize, config: &i2c::Config) -> bool {
+        let free = {
+            let scl = OpenDrain::take(scl);
+            let sda = OpenDrain::take(sda);
+
+            // Nine pulses get a target through the rest of its byte
+            // and the ACK bit
+            for _ in 0..9 {
+                if sda.is_high() {
+                    break;
+                }
+
+                scl.low();
+                Timer::after(HALF_PERIOD).await;
+                if !scl.release_clock().await {
+                    break;
+                }
+                Timer::after(HALF_PERIOD).await;
+            }
+
+            // STOP, SDA rising while SCL is high
+            scl.low();
+            Timer::after(HALF_PERIOD).await;
+            sda.low();
+            Timer::after(HALF_PERIOD).await;
+            scl.release_clock().await;
+            Timer::after(HALF_PERIOD).await;
+            sda.release();
+            Timer::after(HALF_PERIOD).await;
+
+            scl.is_high() && sda.is_high()
+        };
+
+        // Reprogramming the controller also clears whatever state the
+        // aborted transfer left it in. The configuration was accepted
+        // before, it can't fail now.
+        let _ = self.set_config(config);
+
+        free
+    }
+
+    /// Address the target at `address` for writing through `scl` and
+    /// `sda`, then STOP without sending any data. The controller can't
+    /// issue zero-length writes, the pins are taken over as GPIOs.
+    ///
+    /// Returns whether the target acknowledged.
+    pub async fn probe_write(&mut self, scl: usize, sda: usize, address: u8) -> bool {
+        let bus = SoftI2c::take(scl, sda);
+        if !bus.is_idle() {
+            return false;
+        }
+
+        let acked = match bus.start().await {
+            Ok(()) => bus.write_byte(address << 1).await.is_ok(),
+            Err(_) => false,
+        };
+        bus.stop().await;
+
+        acked
+    }
+
+    /// Read `read` from the target at 10-bit `address` through `scl`
+    /// and `sda`, taken over as GPIOs since the controller only
+    /// supports 7-bit addresses.
+    pub async fn ten_bit_read(
+        &mut self,
+        scl: usize,
+        sda: usize,
+        address: u16,
+        read: &mut [u8],
+    ) -> Result<(), TransferFailed> {
+        ten_bit_transfer(scl, sda, address, &[], read).await
+    }
+
+    /// Write `write` to the target at 10-bit `address` through `scl`
+    /// and `sda`, taken over as GPIOs since the controller only
+    /// supports 7-bit addresses.
+    pub async fn ten_bit_write(
+        &mut self,
+        scl: usize,
+        sda: usize,
+        address: u16,
+        write: &[u8],
+    ) -> Result<(), TransferFailed> {
+        ten_bit_transfer(scl, sda, address, write, &mut []).await
+    }
+
+    /// Run an SMBus `operation` with the target at `address` through
+    /// `scl` and `sda`, taken over as GPIOs. Returns the number of
+    /// bytes read into `buf`.
+    pub async fn smbus(
+        &mut self,
+        scl: usize,
+        sda: usize,
+        address: u8,
+        pec: bool,
+        operation: &SmbusOperation<'_>,
+        buf: &mut [u8],
+    ) -> Result<usize, SmbusFail> {
+        let bus = SoftI2c::take(scl, sda);
+        smbus::run(&bus, address, pec, operation, buf).await
+    }
+
+    /// Read the SMBus Alert Response Address through `scl` and `sda`,
+    /// taken over as GPIOs. Returns the address of the device which
+    /// answered.
+    pub async fn alert_response(&mut self, scl: usize, sda: usize, pec: bool) -> Result<u8, SmbusFail> {
+        let bus = SoftI2c::take(scl, sda);
+        smbus::alert_response(&bus, pec).await
+    }
+}
+
+async fn ten_bit_transfer(
+    scl: usize,
+    sda: usize,
+    address: u16,
+    write: &[u8],
+    read: &mut [u8],
+) -> Result<(), TransferFailed> {
+    let bus = SoftI2c::take(scl, sda);
+    if !bus.is_idle() {
+        return Err(TransferFailed);
+    }
+
+    let result = bus.ten_bit_transfer(address, write, read).await;
+    bus.stop().await;
+
+    result
+}
+
+/// I2C line driven as an open drain output through SIO, handed back
+/// to the I2C controller when dropped.
+struct OpenDrain {
+    pin: usize,
+}
+
+impl OpenDrain {
+    fn take(pin: usize) -> Self {
+        let line = Self { pin };
+
+        // Output value stays low, the line is driven by toggling the
+        // output enable
+        pac::SIO.gpio_out(0).value_clr().write_value(line.mask());
+        pac::SIO.gpio_oe(0).value_clr().write_value(line.mask());
+        pac::IO_BANK0.gpio(pin).ctrl().modify(|w| w.set_funcsel(FUNCSEL_SIO));
+
+        line
+    }
+
+    fn mask(&self) -> u32 {
+        1 << self.pin
+    }
+
+    fn low(&self) {
+        pac::SIO.gpio_oe(0).value_set().write_value(self.mask());
+    }
+
+    fn release(&self) {
+        pac::SIO.gpio_oe(0).value_clr().write_value(self.mask());
+    }
+
+    fn is_high(&self) -> bool {
+        pac::SIO.gpio_in(0).read() & self.mask() != 0
+    }
+
+    /// Release the clock and wait for a stretching target to let it
+    /// go. Returns `false` if SCL is still held low after
+    /// `STRETCH_TIMEOUT`.
+    async fn release_clock(&self) -> bool {
+        self.release();
+
+        let deadline = Instant::now() + STRETCH_TIMEOUT;
+        while !self.is_high() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+
+            Timer::after_micros(1).await;
+        }
+
+        true
+    }
+}
+
+impl Drop for OpenDrain {
+    fn drop(&mut self) {
+        self.release();
+        pac::IO_BANK0
+            .gpio(self.pin)
+            .ctrl()
+            .modify(|w| w.set_funcsel(FUNCSEL_I2C));
+    }
+}
+
+/// A bit banged transfer failed, either the target didn't acknowledge
+/// or it held SCL low for too long.
+#[derive(Debug)]
+pub struct TransferFailed;
+
+/// I2C controller bit banged through SIO, for transfers the I2C
+/// controller can't issue. Every method but `stop` leaves SCL low.
+pub struct SoftI2c {
+    scl: OpenDrain,
+    sda: OpenDrain,
+}
+
+impl SoftI2c {
+    pub fn take(scl: usize, sda: usize) -> Self {
+        Self {
+            scl: OpenDrain::take(scl),
+            sda: OpenDrain::take(sda),
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.scl.is_high() && self.sda.is_high()
+    }
+
+    /// START, or repeated START in the middle of a transfer, SDA
+    /// falling while SCL is high.
+    pub async fn start(&self) -> Result<(), TransferFailed> {
+        self.sda.release();
+        Timer::after(HALF_PERIOD).await;
+        if !self.scl.release_clock().await {
+            return Err(TransferFailed);
+        }
+        Timer::after(HALF_PERIOD).await;
+        self.sda.low();
+        Timer::after(HALF_PERIOD).await;
+        self.scl.low();
+
+        Ok(())
+    }
+
+    /// STOP, SDA rising while SCL is high.
+    pub async fn stop(&self) {
+        self.sda.low();
+        Timer::after(HALF_PERIOD).await;
+        self.scl.release_clock().await;
+        Timer::after(HALF_PERIOD).await;
+        self.sda.release();
+        Timer::after(HALF_PERIOD).await;
+    }
+
+    async fn write_bit(&self, bit: bool) -> Result<(), TransferFailed> {
+        if bit {
+            self.sda.release();
+        } else {
+            self.sda.low();
+        }
+
+        Timer::after(HALF_PERIOD).await;
+        if !self.scl.release_clock().await {
+            return Err(TransferFailed);
+        }
+        Timer::after(HALF_PERIOD).await;
+        self.scl.low();
+
+        Ok(())
+    }
+
+    async fn read_bit(&self) -> Result<bool, TransferFailed> {
+        self.sda.release();
+        Timer::after(HALF_PERIOD).await;
+        if !self.scl.release_clock().await {
+            return Err(TransferFailed);
+        }
+        Timer::after(HALF_PERIOD).await;
+        let bit = self.sda.is_high();
+        self.scl.low();
+
+        Ok(bit)
+    }
+
+    /// Shift `byte` out, MSB first. Fails unless the target pulls SDA
+    /// low during the ninth clock.
+    pub async fn write_byte(&self, byte: u8) -> Result<(), TransferFailed> {
+        for bit in (0..8).rev() {
+            self.write_bit(byte & (1 << bit) != 0).await?;
+        }
+
+        if self.read_bit().await? {
+            Err(TransferFailed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Shift a byte in, MSB first, acknowledging it if `ack` is set.
+    pub async fn read_byte(&self, ack: bool) -> Result<u8, TransferFailed> {
+        self.read_byte_with(|_| ack).await
+    }
+
+    /// Shift a byte in, MSB first, acknowledging it if `ack` returns
+    /// `true` for it.
+    pub async fn read_byte_with(&self, ack: impl FnOnce(u8) -> bool) -> Result<u8, TransferFailed> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | u8::from(self.read_bit().await?);
+        }
+
+        self.write_bit(!ack(byte)).await?;
+
+        Ok(byte)
+    }
+
+    /// Write `write` then read `read` from the target at 10-bit
+    /// `address`, STOP is left to the caller.
+    async fn ten_bit_transfer(&self, address: u16, write: &[u8], read: &mut [u8]) -> Result<(), TransferFailed> {
+        // 0b11110 followed by the two high bits of the address and the
+        // R/W bit, then the low byte of the address
+        let header = 0xf0 | ((address >> 7) as u8 & 0x06);
+
+        self.start().await?;
+        self.write_byte(header).await?;
+        self.write_byte(address as u8).await?;
+
+        for &byte in write {
+            self.write_byte(byte).await?;
+        }
+
+        if !read.is_empty() {
+            // A repeated START addresses the same target for reading
+            // with the header alone
+            self.start().await?;
+            self.write_byte(header | 1).await?;
+
+            // The last byte is not acknowledged, ending the read
+            let last = read.len() - 1;
+            for (i, byte) in read.iter_mut().enumerate() {
+                *byte = self.read_byte(i != last).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub enum SpiBus {
+    Spi0(Spi<'static, SPI0, spi::Async>),
+    Spi1(Spi<'static, SPI1, spi::Async>),
+}
+
+impl SpiBus {
+    pub fn blocking_read(&mut self, read: &mut [u8]) -> Result<(), spi::Error> {
+        with_spi!(self, spi => spi.blocking_read(read))
+    }
+
+    pub fn blocking_write(&mut self, write: &[u8]) -> Result<(), spi::Error> {
+        with_spi!(self, spi => spi.blocking_write(write))
+    }
+
+    pub fn blocking_transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), spi::Error> {
+        with_spi!(self, spi => spi.blocking_transfer(read, write))
+    }
+
+    pub fn blocking_transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), spi::Error> {
+        with_spi!(self, spi => spi.blocking_transfer_in_place(words))
+    }
+
+    pub fn flush(&mut self) -> Result<(), spi::Error> {
+        with_spi!(self, spi => spi.flush())
+    }
+
+    pub fn set_config(&mut self, config: &spi::Config) {
+        with_spi!(self, spi => spi.set_config(config))
+    }
+
+    /// Program the block back into controller mode with `config`, after
+    /// target emulation took it over.
+    pub fn restore_controller(&mut self, config: &spi::Config) {
+        let regs = match self {
+            SpiBus::Spi0(_) => pac::SPI0,
+            SpiBus::Spi1(_) => pac::SPI1,
+        };
+
+        regs.cr1().write(|w| w.set_sse(false));
+        // As left by `Spi::new`
+        regs.dmacr().write(|w| {
+            w.set_rxdmae(true);
+            w.set_txdmae(true);
+        });
+        regs.cr1().write(|w| w.set_sse(true));
+
+        self.set_config(config);
+    }
+}
@@ -0,0 +1,256 @@
+//! Logic analyzer, sampling pins 2 to 17 with the state machines of
+//! PIO2.
+//!
+//! The sampling state machine waits on IRQ flag 0, which the trigger
+//! state machine raises once its pattern shows up, or the CPU right
+//! away. Samples are then moved to RAM by DMA until the buffer is full.
+//! The pins are only read, they stay with whoever drives them.
+//!
+//! Programs are loaded for each capture only, PIO2 being lent to the
+//! bus sniffer and GPIO measurements in between.
+
+use crate::Irqs;
+use core::sync::atomic::{AtomicBool, Ordering};
+use embassy_futures::select::{Either, select};
+use embassy_rp::Peri;
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::pac;
+use embassy_rp::peripherals::{DMA_CH5, PIO2};
+use embassy_rp::pio::{Common, Config, FifoJoin, Pio, ShiftConfig, ShiftDirection, StateMachine};
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::signal::Signal;
+use fixed::types::U24F8;
+use pico_de_gallo_internal::{
+    CAPTURE_CHANNELS, CAPTURE_FIRST_PIN, CAPTURE_MAX_RATE, CAPTURE_MAX_SAMPLES, CAPTURE_MIN_RATE, CaptureFail,
+    CaptureTrigger,
+};
+use static_cell::ConstStaticCell;
+
+/// Two samples per word, the first in the low half.
+const BUFFER_WORDS: usize = CAPTURE_MAX_SAMPLES as usize / 2;
+
+static BUFFER: ConstStaticCell<[u32; BUFFER_WORDS]> = ConstStaticCell::new([0; BUFFER_WORDS]);
+
+static STOP: Signal<ThreadModeRawMutex, ()> = Signal::new();
+
+/// Set while a capture runs, rather than PIO2 being lent.
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Capture checked against the limits, ready to run.
+pub struct Armed {
+    divider: U24F8,
+    samples: usize,
+    trigger: CaptureTrigger,
+}
+
+impl Armed {
+    /// Actual sample rate in Hz.
+    pub fn rate(&self) -> u32 {
+        (u64::from(clk_sys_freq()) * 256 / u64::from(self.divider.to_bits())) as u32
+    }
+}
+
+pub struct Capture {
+    common: Common<'static, PIO2>,
+    sampler: StateMachine<'static, PIO2, 0>,
+    trigger: StateMachine<'static, PIO2, 1>,
+    dma: Peri<'static, DMA_CH5>,
+    buffer: &'static mut [u32; BUFFER_WORDS],
+    /// Samples held by `buffer`.
+    len: usize,
+}
+
+impl Capture {
+    pub fn new(pio: Peri<'static, PIO2>, dma: Peri<'static, DMA_CH5>) -> Self {
+        let Pio { common, sm0, sm1, .. } = Pio::new(pio, Irqs);
+
+        Self {
+            common,
+            sampler: sm0,
+            trigger: sm1,
+            dma,
+            buffer: BUFFER.take(),
+            len: 0,
+        }
+    }
+
+    /// PIO2 and its first two state machines, for the bus sniffer and
+    /// GPIO measurements. Programs they load must be freed once done.
+    pub fn pio(
+        &mut self,
+    ) -> (
+        &mut Common<'static, PIO2>,
+        &mut StateMachine<'static, PIO2, 0>,
+        &mut StateMachine<'static, PIO2, 1>,
+    ) {
+        (&mut self.common, &mut self.sampler, &mut self.trigger)
+    }
+
+    /// Check a capture of `samples` at `rate` Hz once `trigger` fires.
+    pub fn arm(rate: u32, samples: u32, trigger: CaptureTrigger) -> Result<Armed, CaptureFail> {
+        if !(CAPTURE_MIN_RATE..=CAPTURE_MAX_RATE).contains(&rate) {
+            return Err(CaptureFail::InvalidRate);
+        }
+        let divider = (u64::from(clk_sys_freq()) * 256).div_ceil(u64::from(rate));
+        if !(256..=65536 * 256).contains(&divider) {
+            return Err(CaptureFail::InvalidRate);
+        }
+
+        if samples == 0 || samples > CAPTURE_MAX_SAMPLES {
+            return Err(CaptureFail::InvalidLength);
+        }
+
+        if let CaptureTrigger::Pattern { first, count, .. } = trigger
+            && (count == 0 || usize::from(first) + usize::from(count) > CAPTURE_CHANNELS)
+        {
+            return Err(CaptureFail::InvalidTrigger);
+        }
+
+        Ok(Armed {
+            divider: U24F8::from_bits(divider as u32),
+            samples: samples.next_multiple_of(2) as usize,
+            trigger,
+        })
+    }
+
+    /// Run `armed` until the buffer is full or the capture is stopped.
+    /// Returns the number of samples captured, none once stopped.
+    pub async fn run(&mut self, armed: &Armed) -> usize {
+        self.len = 0;
+        RUNNING.store(true, Ordering::Release);
+
+        let sampler_program = pio::pio_asm!("    wait 1 irq 0", ".wrap_target", "    in pins, 16", ".wrap",);
+        // The pattern, then whether it must be absent first, come
+        // through the TX FIFO
+        let trigger_program = pio::pio_asm!(
+            "    pull block",
+            "    mov y, osr",
+            "    pull block",
+            "    mov x, osr",
+            "    jmp !x present",
+            "absent:",
+            "    mov x, pins",
+            "    jmp x!=y present",
+            "    jmp absent",
+            "present:",
+            "    mov x, pins",
+            "    jmp x!=y present",
+            "    irq set 0",
+            "done:",
+            "    jmp done",
+        );
+        // Nothing else is loaded, they fit
+        let sampler_program = self.common.load_program(&sampler_program.program);
+        let trigger_program = self.common.load_program(&trigger_program.program);
+
+        // Pins no peripheral claimed are still isolated, reading low
+        for pin in CAPTURE_FIRST_PIN..CAPTURE_FIRST_PIN + CAPTURE_CHANNELS as u8 {
+            pac::PADS_BANK0.gpio(usize::from(pin)).modify(|w| {
+                w.set_ie(true);
+                w.set_iso(false);
+            });
+        }
+
+        // Pins are read without mapping them, which would hand them to
+        // PIO2, so their base is programmed directly
+        let mut config = Config::default();
+        config.use_program(&sampler_program, &[]);
+        config.clock_divider = armed.divider;
+        config.fifo_join = FifoJoin::RxOnly;
+        config.shift_in = ShiftConfig {
+            auto_fill: true,
+            threshold: 32,
+            direction: ShiftDirection::Right,
+        };
+        self.sampler.set_config(&config);
+        pac::PIO2.sm(0).pinctrl().modify(|w| w.set_in_base(CAPTURE_FIRST_PIN));
+        pac::PIO2
+            .sm(0)
+            .shiftctrl()
+            .modify(|w| w.set_in_count(CAPTURE_CHANNELS as u8));
+        self.sampler.clear_fifos();
+        self.sampler.restart();
+
+        // Left over by a capture stopped early
+        pac::PIO2.irq().write(|w| w.set_irq(1));
+        STOP.reset();
+
+        self.sampler.set_enable(true);
+
+        // Draining the FIFO before the trigger may fire, as it stalls
+        // sampling once full
+        let words = armed.samples / 2;
+        let transfer = self
+            .sampler
+            .rx()
+            .dma_pull(self.dma.reborrow(), &mut self.buffer[..words], false);
+
+        match armed.trigger {
+            CaptureTrigger::Immediate => pac::PIO2.irq_force().write(|w| w.set_irq_force(1)),
+            CaptureTrigger::Pattern {
+                first,
+                count,
+                value,
+                edge,
+            } => {
+                let mut config = Config::default();
+                config.use_program(&trigger_program, &[]);
+                self.trigger.set_config(&config);
+                // Pins past the pattern read as 0
+                pac::PIO2
+                    .sm(1)
+                    .pinctrl()
+                    .modify(|w| w.set_in_base(CAPTURE_FIRST_PIN + first));
+                pac::PIO2.sm(1).shiftctrl().modify(|w| w.set_in_count(count));
+                self.trigger.clear_fifos();
+                self.trigger.restart();
+
+                // Room for both in the empty FIFO
+                let _ = self.trigger.tx().try_push(u32::from(value));
+                let _ = self.trigger.tx().try_push(u32::from(edge));
+                self.trigger.set_enable(true);
+            }
+        }
+
+        let stopped = matches!(select(transfer, STOP.wait()).await, Either::Second(_));
+
+        self.sampler.set_enable(false);
+        self.trigger.set_enable(false);
+
+        // SAFETY: both state machines are stopped
+        unsafe {
+            self.common.free_instr(sampler_program.used_memory);
+            self.common.free_instr(trigger_program.used_memory);
+        }
+
+        if !stopped {
+            self.len = armed.samples;
+        }
+        RUNNING.store(false, Ordering::Release);
+
+        self.len
+    }
+
+    /// `count` samples of the last capture from `offset` on.
+    pub fn samples(&self, offset: usize, count: usize) -> Result<impl Iterator<Item = u16> + '_, CaptureFail> {
+        if offset.checked_add(count).is_none_or(|end| end > self.len) {
+            return Err(CaptureFail::InvalidLength);
+        }
+
+        Ok(self.buffer[offset / 2..]
+            .iter()
+            .flat_map(|&word| [word as u16, (word >> 16) as u16])
+            .skip(offset % 2)
+            .take(count))
+    }
+}
+
+/// Whether a capture waits for its trigger or samples.
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::Acquire)
+}
+
+/// Stop the capture running, which then reports no samples.
+pub fn stop() {
+    STOP.signal(());
+}
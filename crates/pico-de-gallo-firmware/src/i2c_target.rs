@@ -0,0 +1,262 @@
+//! I2C target emulation.
+//!
+//! A bus emulating a target has its controller taken over by an
+//! `I2cSlave`, answering from a register file the host can load and
+//! dump at any time. Every access of the controller is published.
+
+use crate::{AppTx, Irqs};
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+use embassy_futures::select::{Either, select};
+use embassy_rp::i2c_slave::{self, Command, I2cSlave, ReadStatus};
+use embassy_rp::peripherals::{I2C0, I2C1, PIN_2, PIN_3, PIN_16, PIN_17};
+use embassy_sync::blocking_mutex::{self, raw::ThreadModeRawMutex};
+use embassy_sync::signal::Signal;
+use embassy_time::Instant;
+use pico_de_gallo_internal::{I2C_TARGET_REGISTERS, I2cTargetAccess, I2cTargetEvent, I2cTargetTopic, NUM_I2C_BUSES};
+use postcard_rpc::header::VarSeq;
+use postcard_rpc::server::Sender;
+
+type Registers = blocking_mutex::Mutex<ThreadModeRawMutex, RefCell<[u8; I2C_TARGET_REGISTERS]>>;
+
+macro_rules! with_target {
+    ($target:expr, $slave:ident => $body:expr) => {
+        match $target {
+            Target::I2c0($slave) => $body,
+            Target::I2c1($slave) => $body,
+        }
+    };
+}
+
+/// Target emulation state of an I2C bus.
+struct Slot {
+    /// Set from the start of the target until the controller is
+    /// programmed again.
+    running: AtomicBool,
+    stop: Signal<ThreadModeRawMutex, ()>,
+    stopped: Signal<ThreadModeRawMutex, ()>,
+    /// Kept across runs, so that it can be loaded beforehand.
+    registers: Registers,
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Self {
+            running: AtomicBool::new(false),
+            stop: Signal::new(),
+            stopped: Signal::new(),
+            registers: blocking_mutex::Mutex::new(RefCell::new([0; I2C_TARGET_REGISTERS])),
+        }
+    }
+}
+
+static SLOTS: [Slot; NUM_I2C_BUSES] = [const { Slot::new() }; NUM_I2C_BUSES];
+
+/// Whether `bus` emulates a target, its controller being unusable.
+pub fn is_running(bus: usize) -> bool {
+    SLOTS[bus].running.load(Ordering::Acquire)
+}
+
+/// Reserve `bus` for a target. Returns `false` if it already has one.
+pub fn claim(bus: usize) -> bool {
+    let slot = &SLOTS[bus];
+    let claimed = slot
+        .running
+        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+        .is_ok();
+
+    if claimed {
+        slot.stop.reset();
+        slot.stopped.reset();
+    }
+
+    claimed
+}
+
+/// Stop the target of `bus`, waiting for it to let go of the
+/// controller.
+pub async fn stop(bus: usize) {
+    let slot = &SLOTS[bus];
+    slot.stop.signal(());
+    slot.stopped.wait().await;
+}
+
+/// Hand `bus` back to controller requests, once the controller has been
+/// programmed again.
+pub fn release(bus: usize) {
+    SLOTS[bus].running.store(false, Ordering::Release);
+}
+
+/// Store `data` in the register file of `bus` from `register` on.
+pub fn load(bus: usize, register: usize, data: &[u8]) {
+    SLOTS[bus].registers.lock(|registers| {
+        registers.borrow_mut()[register..register + data.len()].copy_from_slice(data);
+    });
+}
+
+/// Fill `buf` from the register file of `bus`, from `register` on.
+pub fn dump(bus: usize, register: usize, buf: &mut [u8]) {
+    SLOTS[bus].registers.lock(|registers| {
+        buf.copy_from_slice(&registers.borrow()[register..register + buf.len()]);
+    });
+}
+
+/// Answer as a target at `address` on `bus` until stopped, publishing
+/// every access through `sender`.
+///
+/// The caller must hold the controller of `bus` throughout, and program
+/// it again afterwards.
+pub async fn run(bus: usize, address: u8, general_call: bool, sender: &Sender<AppTx>) {
+    let slot = &SLOTS[bus];
+    // SAFETY: the caller holds the controller, nothing else drives it
+    // or its pins until the target is dropped
+    let mut target = unsafe { Target::new(bus, address, general_call) };
+    let mut pointer = 0;
+    let mut seq_no: u16 = 0;
+
+    loop {
+        let accesses = match select(slot.stop.wait(), target.serve(&slot.registers, &mut pointer)).await {
+            Either::First(()) => break,
+            Either::Second(accesses) => accesses,
+        };
+
+        let timestamp_us = Instant::now().as_micros();
+
+        for access in accesses {
+            let event = I2cTargetEvent {
+                bus: bus as u8,
+                timestamp_us,
+                access,
+            };
+            let _ = sender.publish::<I2cTargetTopic>(VarSeq::Seq2(seq_no), &event).await;
+            seq_no = seq_no.wrapping_add(1);
+        }
+    }
+
+    drop(target);
+    slot.stopped.signal(());
+}
+
+enum Target {
+    I2c0(I2cSlave<'static, I2C0>),
+    I2c1(I2cSlave<'static, I2C1>),
+}
+
+impl Target {
+    /// Take over the controller of `bus`, wired as in `main`.
+    ///
+    /// # Safety
+    ///
+    /// Nothing else may drive the controller or its pins while the
+    /// target lives.
+    unsafe fn new(bus: usize, address: u8, general_call: bool) -> Self {
+        let mut config = i2c_slave::Config::default();
+        config.addr = address.into();
+        config.general_call = general_call;
+
+        unsafe {
+            match bus {
+                0 => Self::I2c1(I2cSlave::new(
+                    I2C1::steal(),
+                    PIN_3::steal(),
+                    PIN_2::steal(),
+                    Irqs,
+                    config,
+                )),
+                _ => Self::I2c0(I2cSlave::new(
+                    I2C0::steal(),
+                    PIN_17::steal(),
+                    PIN_16::steal(),
+                    Irqs,
+                    config,
+                )),
+            }
+        }
+    }
+
+    /// Serve one transfer of the controller, returning its accesses: a
+    /// write followed by a read for a write-read.
+    async fn serve(&mut self, registers: &Registers, pointer: &mut u8) -> heapless::Vec<I2cTargetAccess, 2> {
+        // Register number followed by every register
+        let mut buf = [0; I2C_TARGET_REGISTERS + 1];
+        let mut accesses = heapless::Vec::new();
+
+        let command = with_target!(self, slave => slave.listen(&mut buf).await);
+        let access = match command {
+            Ok(Command::Write(len)) | Err(i2c_slave::Error::PartialWrite(len)) => {
+                write(registers, pointer, &buf[..len])
+            }
+            Ok(Command::WriteRead(len)) => {
+                if let Some(access) = write(registers, pointer, &buf[..len]) {
+                    let _ = accesses.push(access);
+                }
+                Some(self.respond(registers, pointer).await)
+            }
+            Ok(Command::Read) => Some(self.respond(registers, pointer).await),
+            Ok(Command::GeneralCall(len)) | Err(i2c_slave::Error::PartialGeneralCall(len)) => {
+                let len = len.min(I2C_TARGET_REGISTERS);
+                Some(I2cTargetAccess::GeneralCall {
+                    data: heapless::Vec::from_slice(&buf[..len]).unwrap_or_default(),
+                })
+            }
+            Err(_) => Some(I2cTargetAccess::Aborted),
+        };
+
+        if let Some(access) = access {
+            let _ = accesses.push(access);
+        }
+
+        accesses
+    }
+
+    /// Answer a read from the selected register on, for as long as the
+    /// controller acknowledges.
+    async fn respond(&mut self, registers: &Registers, pointer: &mut u8) -> I2cTargetAccess {
+        let register = *pointer;
+        let mut count: u16 = 0;
+
+        loop {
+            let start = usize::from(*pointer);
+            let len = I2C_TARGET_REGISTERS - start;
+
+            let mut buf = [0; I2C_TARGET_REGISTERS];
+            registers.lock(|registers| buf[..len].copy_from_slice(&registers.borrow()[start..]));
+
+            let (sent, more) = match with_target!(self, slave => slave.respond_to_read(&buf[..len]).await) {
+                Ok(ReadStatus::Done) => (len, false),
+                Ok(ReadStatus::NeedMoreBytes) => (len, true),
+                Ok(ReadStatus::LeftoverBytes(left)) => (len - usize::from(left), false),
+                Err(_) => return I2cTargetAccess::Aborted,
+            };
+
+            count = count.saturating_add(sent as u16);
+            // Wraps around after the last register
+            *pointer = pointer.wrapping_add(sent as u8);
+
+            if !more {
+                break;
+            }
+        }
+
+        I2cTargetAccess::Read { register, count }
+    }
+}
+
+/// Store a write of the controller, its first byte selecting the
+/// register. `None` for zero-length writes, which store nothing.
+fn write(registers: &Registers, pointer: &mut u8, data: &[u8]) -> Option<I2cTargetAccess> {
+    let (&register, data) = data.split_first()?;
+
+    registers.lock(|registers| {
+        let mut registers = registers.borrow_mut();
+        for (i, &byte) in data.iter().enumerate() {
+            registers[usize::from(register.wrapping_add(i as u8))] = byte;
+        }
+    });
+    *pointer = register.wrapping_add(data.len() as u8);
+
+    Some(I2cTargetAccess::Write {
+        register,
+        data: heapless::Vec::from_slice(data).unwrap_or_default(),
+    })
+}
@@ -0,0 +1,95 @@
+//! WS2812 style addressable LEDs, driven by a state machine of PIO0.
+//!
+//! The LEDs hang off one of the GPIOs, which is handed to PIO0 for the
+//! time of a write and given back to the SIO afterwards.
+
+use crate::{Irqs, pio_pin, sio_pin};
+use embassy_rp::Peri;
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::gpio::Flex;
+use embassy_rp::peripherals::{DMA_CH4, PIO0};
+use embassy_rp::pio::{
+    Common, Config, Direction, FifoJoin, LoadedProgram, Pio, ShiftConfig, ShiftDirection, StateMachine,
+};
+use embassy_time::{Duration, Timer};
+use fixed::types::U24F8;
+
+/// PIO cycles per bit, high for 2 then 5 of them for a 1, 2 for a 0.
+const CYCLES_PER_BIT: u32 = 10;
+
+/// Low time latching the data shifted in, above the 280 µs of the
+/// WS2812B.
+const LATCH: Duration = Duration::from_micros(300);
+
+pub struct Leds {
+    common: Common<'static, PIO0>,
+    sm: StateMachine<'static, PIO0, 0>,
+    program: LoadedProgram<'static, PIO0>,
+    dma: Peri<'static, DMA_CH4>,
+}
+
+impl Leds {
+    pub fn new(pio: Peri<'static, PIO0>, dma: Peri<'static, DMA_CH4>) -> Self {
+        let Pio { mut common, sm0, .. } = Pio::new(pio, Irqs);
+
+        let program = pio::pio_asm!(
+            ".side_set 1",
+            "    set pindirs, 1  side 0",
+            ".wrap_target",
+            "bitloop:",
+            "    out x, 1        side 0 [2]",
+            "    jmp !x do_zero  side 1 [1]",
+            "    jmp bitloop     side 1 [4]",
+            "do_zero:",
+            "    nop             side 0 [4]",
+            ".wrap",
+        );
+        let program = common.load_program(&program.program);
+
+        Self {
+            common,
+            sm: sm0,
+            program,
+            dma,
+        }
+    }
+
+    /// Shift `data` out on GPIO `index`, whose `Flex` is `gpio`, MSB
+    /// first, then latch it. The pin is left driven low.
+    pub async fn write(&mut self, index: usize, gpio: &mut Flex<'static>, data: &[u8]) {
+        // SAFETY: the `Flex` of the GPIO is borrowed until it's handed
+        // back
+        let pin = unsafe { pio_pin(&mut self.common, index) };
+
+        let mut config = Config::default();
+        config.use_program(&self.program, &[&pin]);
+        config.clock_divider = U24F8::from_num(clk_sys_freq() / 1000) / U24F8::from_num(800 * CYCLES_PER_BIT);
+        config.fifo_join = FifoJoin::TxOnly;
+        config.shift_out = ShiftConfig {
+            auto_fill: true,
+            threshold: 8,
+            direction: ShiftDirection::Left,
+        };
+
+        self.sm.set_config(&config);
+        self.sm.set_pin_dirs(Direction::Out, &[&pin]);
+        self.sm.set_enable(true);
+
+        // Narrow writes to the FIFO are replicated across its word, the
+        // top byte being shifted out first
+        self.sm.tx().dma_push(self.dma.reborrow(), data, false).await;
+
+        // The state machine stalls low once the last byte is out
+        while !self.sm.tx().empty() {
+            embassy_futures::yield_now().await;
+        }
+        Timer::after(LATCH).await;
+
+        self.sm.set_enable(false);
+
+        // Low already, so that the chain sees no edge
+        gpio.set_low();
+        gpio.set_as_output();
+        sio_pin(index);
+    }
+}
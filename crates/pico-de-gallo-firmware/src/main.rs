@@ -1,42 +1,92 @@
 #![no_std]
 #![no_main]
 
+use bus::{I2cBus, SpiBus};
+use capture::Capture;
+use core::sync::atomic::{AtomicBool, Ordering};
 use defmt::info;
-use embassy_embedded_hal::SetConfig;
 use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_futures::yield_now;
 use embassy_rp::bind_interrupts;
 use embassy_rp::clocks::ClockConfig;
 use embassy_rp::gpio::{Flex, Level};
-use embassy_rp::i2c::{self, I2c};
-use embassy_rp::peripherals::{I2C1, SPI0, USB};
-use embassy_rp::spi::{self, Phase, Polarity, Spi};
+use embassy_rp::i2c;
+use embassy_rp::peripherals::{
+    I2C0, I2C1, PIN_8, PIN_9, PIN_10, PIN_11, PIN_12, PIN_13, PIN_14, PIN_15, PIO0, PIO1, PIO2, USB,
+};
+use embassy_rp::pio::{Common, Pin};
+use embassy_rp::spi::{self, Phase, Polarity};
 use embassy_rp::usb::Driver;
 use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::mutex::{Mutex, MutexGuard};
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
 use embassy_usb::{Config, UsbDevice};
+use leds::Leds;
+use onewire::OneWire;
 use pico_de_gallo_internal::{
-    ENDPOINT_LIST, GpioGet, GpioGetRequest, GpioGetResponse, GpioPut, GpioPutRequest, GpioPutResponse, GpioState,
-    GpioWaitForAny, GpioWaitForFalling, GpioWaitForHigh, GpioWaitForLow, GpioWaitForRising, GpioWaitRequest,
-    GpioWaitResponse, I2cRead, I2cReadFail, I2cReadRequest, I2cReadResponse, I2cWrite, I2cWriteFail, I2cWriteRequest,
-    I2cWriteResponse, MICROSOFT_VID, PICO_DE_GALLO_PID, PingEndpoint, SetConfiguration, SetConfigurationFail,
-    SetConfigurationRequest, SetConfigurationResponse, SpiFlush, SpiFlushFail, SpiFlushResponse, SpiPhase, SpiPolarity,
-    SpiRead, SpiReadFail, SpiReadRequest, SpiReadResponse, SpiWrite, SpiWriteFail, SpiWriteRequest, SpiWriteResponse,
-    TOPICS_IN_LIST, TOPICS_OUT_LIST, Version, VersionInfo,
+    CAPTURE_MAX_READ, CaptureEvent, CaptureFail, CaptureRead, CaptureReadRequest, CaptureReadResponse, CaptureResponse,
+    CaptureStart, CaptureStartRequest, CaptureStop, CaptureTopic, Configuration, ENDPOINT_LIST,
+    GPIO_MEASURE_MAX_GATE_MS, GetConfiguration, GpioEdge, GpioGet, GpioGetFail, GpioGetRequest, GpioGetResponse,
+    GpioMeasure, GpioMeasureFail, GpioMeasureRequest, GpioMeasureResponse, GpioPut, GpioPutFail, GpioPutRequest,
+    GpioPutResponse, GpioState, GpioWaitFail, GpioWaitForAny, GpioWaitForFalling, GpioWaitForHigh, GpioWaitForLow,
+    GpioWaitForRising, GpioWaitRequest, GpioWaitResponse, I2C_MAX_FREQUENCY, I2C_MIN_FREQUENCY, I2C_TARGET_REGISTERS,
+    I2C_TEN_BIT_ADDRESS_MAX, I2C_TRIGGER_MAX_LEN, I2C_TRIGGER_MAX_SLOTS, I2cAddress, I2cAddressMap, I2cConfig, I2cRead,
+    I2cReadFail, I2cReadRequest, I2cReadResponse, I2cRecover, I2cRecoverFail, I2cRecoverRequest, I2cRecoverResponse,
+    I2cScan, I2cScanFail, I2cScanMode, I2cScanRequest, I2cScanResponse, I2cSetConfig, I2cSetConfigFail,
+    I2cSetConfigRequest, I2cSetConfigResponse, I2cTargetDump, I2cTargetDumpRequest, I2cTargetDumpResponse,
+    I2cTargetFail, I2cTargetLoad, I2cTargetLoadRequest, I2cTargetResponse, I2cTargetStart, I2cTargetStartRequest,
+    I2cTargetStop, I2cTargetStopRequest, I2cTriggerAction, I2cTriggerArm, I2cTriggerArmFail, I2cTriggerArmRequest,
+    I2cTriggerArmResponse, I2cTriggerDisarm, I2cTriggerDisarmFail, I2cTriggerDisarmRequest, I2cTriggerDisarmResponse,
+    I2cTriggerEvent, I2cTriggerTopic, I2cWrite, I2cWriteFail, I2cWriteRequest, I2cWriteResponse, LEDS_MAX_LEN,
+    LedsFail, LedsWrite, LedsWriteRequest, LedsWriteResponse, MICROSOFT_VID, NUM_I2C_BUSES, NUM_SPI_BUSES,
+    ONEWIRE_MAX_LEN, ONEWIRE_SEARCH_MAX_DEVICES, OneWireBit, OneWireBitRequest, OneWireBitResponse, OneWireFail,
+    OneWireRead, OneWireReadRequest, OneWireReadResponse, OneWireReset, OneWireResetRequest, OneWireResetResponse,
+    OneWireSearch, OneWireSearchRequest, OneWireSearchResponse, OneWireWrite, OneWireWriteRequest,
+    OneWireWriteResponse, PICO_DE_GALLO_PID, PingEndpoint, PioExec, PioExecRequest, PioLoad, PioLoadRequest,
+    PioLoadResponse, PioPull, PioPullRequest, PioPullResponse, PioPush, PioPushRequest, PioPushResponse, PioResponse,
+    PioStart, PioStartRequest, PioStop, PioStopRequest, SPI_DEVICE_NAME_MAX_LEN, SPI_MAX_DEVICES, SPI_MAX_FREQUENCY,
+    SPI_MIN_FREQUENCY, Smbus, SmbusAlert, SmbusAlertRequest, SmbusAlertResponse, SmbusFail, SmbusRequest,
+    SmbusResponse, SniffFail, SniffI2cStart, SniffI2cStartRequest, SniffResponse, SniffSpiStart, SniffSpiStartRequest,
+    SniffStop, SpiAddDevice, SpiAddDeviceFail, SpiAddDeviceRequest, SpiAddDeviceResponse, SpiBitOrder, SpiChipSelect,
+    SpiConfig, SpiCsPolarity, SpiDeviceSlot, SpiDeviceTransaction, SpiDeviceTransactionRequest, SpiFlush, SpiFlushFail,
+    SpiFlushRequest, SpiFlushResponse, SpiOperation, SpiPhase, SpiPolarity, SpiRead, SpiReadFail, SpiReadRequest,
+    SpiReadResponse, SpiRemoveDevice, SpiRemoveDeviceFail, SpiRemoveDeviceRequest, SpiRemoveDeviceResponse,
+    SpiSetConfig, SpiSetConfigFail, SpiSetConfigRequest, SpiSetConfigResponse, SpiTargetClear, SpiTargetClearRequest,
+    SpiTargetCommand, SpiTargetCommandRequest, SpiTargetFail, SpiTargetLoad, SpiTargetLoadRequest, SpiTargetResponse,
+    SpiTargetStart, SpiTargetStartRequest, SpiTargetStop, SpiTargetStopRequest, SpiTransaction, SpiTransactionFail,
+    SpiTransactionRequest, SpiTransactionResponse, SpiWordSize, SpiWrite, SpiWriteFail, SpiWriteRequest,
+    SpiWriteResponse, TOPICS_IN_LIST, TOPICS_OUT_LIST, Version, VersionInfo, i2c_address_is_reserved,
 };
 use postcard_rpc::{
     define_dispatch,
-    header::VarHeader,
+    header::{VarHeader, VarSeq},
     server::{
-        Dispatch, Server,
+        Dispatch, Sender, Server, SpawnContext,
         impls::embassy_usb_v0_5::{
             PacketBuffers,
-            dispatch_impl::{WireRxBuf, WireRxImpl, WireSpawnImpl, WireStorage, WireTxImpl},
+            dispatch_impl::{WireRxBuf, WireRxImpl, WireSpawnImpl, WireStorage, WireTxImpl, spawn_fn},
         },
     },
 };
+use sniff::Sniffed;
 use static_cell::ConstStaticCell;
 use static_cell::StaticCell;
+use user_pio::UserPio;
 use {defmt_rtt as _, panic_probe as _};
 
+mod bus;
+mod capture;
+mod i2c_target;
+mod leds;
+mod measure;
+mod onewire;
+mod smbus;
+mod sniff;
+mod spi_target;
+mod user_pio;
+
 // Program metadata for `picotool info`.
 #[unsafe(link_section = ".bi_entries")]
 #[used]
@@ -52,24 +102,63 @@ include!(concat!(env!("OUT_DIR"), "/version.rs"));
 
 bind_interrupts!(struct Irqs {
     USBCTRL_IRQ => embassy_rp::usb::InterruptHandler<USB>;
+    I2C0_IRQ => embassy_rp::i2c::InterruptHandler<I2C0>;
     I2C1_IRQ => embassy_rp::i2c::InterruptHandler<I2C1>;
+    PIO0_IRQ_0 => embassy_rp::pio::InterruptHandler<PIO0>;
+    PIO1_IRQ_0 => embassy_rp::pio::InterruptHandler<PIO1>;
+    PIO2_IRQ_0 => embassy_rp::pio::InterruptHandler<PIO2>;
 });
 
 const NUM_GPIOS: usize = 8;
+/// Pin of GPIO 0, the others following, as wired in `main`.
+const FIRST_GPIO_PIN: usize = 8;
+/// IO bank function handing a pin to the SIO.
+const FUNCSEL_SIO: u8 = 5;
+/// SCL and SDA pins of each I2C bus, as wired in `main`.
+const I2C_PINS: [(usize, usize); NUM_I2C_BUSES] = [(3, 2), (17, 16)];
+/// Chip select input of each SPI block in target mode, along with its
+/// index among the GPIOs if it's one of them.
+const SPI_TARGET_CS: [(usize, Option<usize>); NUM_SPI_BUSES] = [(5, None), (13, Some(5))];
 const BUFFER_SIZE: usize = 512;
 
+type Shared<T> = Mutex<ThreadModeRawMutex, T>;
+
 pub struct Context {
-    i2c: I2c<'static, I2C1, i2c::Async>,
-    spi: Spi<'static, SPI0, spi::Async>,
-    gpios: [Flex<'static>; NUM_GPIOS],
+    /// Shared with triggers and targets, see `TaskContext`.
+    i2c: &'static [Shared<I2cBus>; NUM_I2C_BUSES],
+    spi: [SpiBus; NUM_SPI_BUSES],
+    /// Locked for as long as a trigger is armed on the pin, requests
+    /// fail rather than wait for it.
+    gpios: &'static [Shared<Flex<'static>>; NUM_GPIOS],
+    leds: Leds,
+    pio: UserPio,
+    /// Locked for as long as a capture runs, see `TaskContext`.
+    capture: &'static Shared<Capture>,
     buf: [u8; BUFFER_SIZE],
+    scratch: [u8; BUFFER_SIZE],
+    config: Configuration,
+    spi_devices: [Option<SpiDeviceEntry>; SPI_MAX_DEVICES],
+    /// Settings currently programmed in each SPI block, either the bus
+    /// configuration or the last device's.
+    spi_applied: [SpiConfig; NUM_SPI_BUSES],
+}
+
+/// SPI device registered by the host.
+struct SpiDeviceEntry {
+    name: heapless::String<SPI_DEVICE_NAME_MAX_LEN>,
+    bus: usize,
+    cs: SpiChipSelect,
+    config: SpiConfig,
 }
 
 impl Context {
     #[allow(clippy::too_many_arguments)]
     fn new(
-        i2c: I2c<'static, I2C1, i2c::Async>,
-        spi: Spi<'static, SPI0, spi::Async>,
+        i2c: [I2cBus; NUM_I2C_BUSES],
+        spi: [SpiBus; NUM_SPI_BUSES],
+        leds: Leds,
+        pio: UserPio,
+        capture: Capture,
         gpio0: Flex<'static>,
         gpio1: Flex<'static>,
         gpio2: Flex<'static>,
@@ -79,15 +168,74 @@ impl Context {
         gpio6: Flex<'static>,
         gpio7: Flex<'static>,
     ) -> Self {
+        static I2C: StaticCell<[Shared<I2cBus>; NUM_I2C_BUSES]> = StaticCell::new();
+        static GPIOS: StaticCell<[Shared<Flex<'static>>; NUM_GPIOS]> = StaticCell::new();
+        static CAPTURE: StaticCell<Shared<Capture>> = StaticCell::new();
+
+        let gpios = [gpio0, gpio1, gpio2, gpio3, gpio4, gpio5, gpio6, gpio7];
+
         Self {
-            i2c,
+            i2c: I2C.init(i2c.map(Mutex::new)),
             spi,
-            gpios: [gpio0, gpio1, gpio2, gpio3, gpio4, gpio5, gpio6, gpio7],
+            gpios: GPIOS.init(gpios.map(Mutex::new)),
+            leds,
+            pio,
+            capture: CAPTURE.init(Mutex::new(capture)),
             buf: [0; BUFFER_SIZE],
+            scratch: [0; BUFFER_SIZE],
+            config: Configuration {
+                i2c: [I2cConfig::default(); NUM_I2C_BUSES],
+                spi: [SpiConfig::default(); NUM_SPI_BUSES],
+                i2c_actual_frequency: [i2c_actual_frequency(I2cConfig::default().frequency).unwrap(); NUM_I2C_BUSES],
+                spi_actual_frequency: [spi_actual_frequency(SpiConfig::default().frequency).unwrap(); NUM_SPI_BUSES],
+            },
+            spi_devices: [const { None }; SPI_MAX_DEVICES],
+            spi_applied: [SpiConfig::default(); NUM_SPI_BUSES],
+        }
+    }
+}
+
+/// Part of the context handed to spawned tasks.
+#[derive(Clone, Copy)]
+pub struct TaskContext {
+    i2c: &'static [Shared<I2cBus>; NUM_I2C_BUSES],
+    gpios: &'static [Shared<Flex<'static>>; NUM_GPIOS],
+    capture: &'static Shared<Capture>,
+}
+
+impl SpawnContext for Context {
+    type SpawnCtxt = TaskContext;
+
+    fn spawn_ctxt(&mut self) -> TaskContext {
+        TaskContext {
+            i2c: self.i2c,
+            gpios: self.gpios,
+            capture: self.capture,
+        }
+    }
+}
+
+/// I2C trigger slot, armed for as long as its task runs.
+struct TriggerSlot {
+    armed: AtomicBool,
+    disarm: Signal<ThreadModeRawMutex, ()>,
+}
+
+impl TriggerSlot {
+    const fn new() -> Self {
+        Self {
+            armed: AtomicBool::new(false),
+            disarm: Signal::new(),
         }
     }
 }
 
+static TRIGGERS: [TriggerSlot; I2C_TRIGGER_MAX_SLOTS] = [const { TriggerSlot::new() }; I2C_TRIGGER_MAX_SLOTS];
+
+/// Most Alert Response Address reads following one edge, in case a
+/// device never releases SMBALERT#.
+const ALERT_RESPONSES_MAX: usize = 8;
+
 type AppDriver = Driver<'static, USB>;
 type AppStorage = WireStorage<ThreadModeRawMutex, AppDriver, 256, 256, 64, 256>;
 type BufStorage = PacketBuffers<1024, 1024>;
@@ -148,23 +296,63 @@ define_dispatch! {
     endpoints: {
         list: ENDPOINT_LIST;
 
-        | EndpointTy         | kind     | handler                       |
-        | ----------         | ----     | -------                       |
-        | PingEndpoint       | blocking | ping_handler                  |
-        | I2cRead            | async    | i2c_read_handler              |
-        | I2cWrite           | async    | i2c_write_handler             |
-        | SpiRead            | async    | spi_read_handler              |
-        | SpiWrite           | async    | spi_write_handler             |
-        | SpiFlush           | async    | spi_flush_handler             |
-        | GpioGet            | async    | gpio_get_handler              |
-        | GpioPut            | async    | gpio_put_handler              |
-        | GpioWaitForHigh    | async    | gpio_wait_for_high_handler    |
-        | GpioWaitForLow     | async    | gpio_wait_for_low_handler     |
-        | GpioWaitForRising  | async    | gpio_wait_for_rising_handler  |
-        | GpioWaitForFalling | async    | gpio_wait_for_falling_handler |
-        | GpioWaitForAny     | async    | gpio_wait_for_any_handler     |
-        | SetConfiguration   | async    | set_config_handler            |
-        | Version            | async    | version_handler               |
+        | EndpointTy           | kind     | handler                        |
+        | ----------           | ----     | -------                        |
+        | PingEndpoint         | blocking | ping_handler                   |
+        | I2cRead              | async    | i2c_read_handler               |
+        | I2cWrite             | async    | i2c_write_handler              |
+        | I2cRecover           | async    | i2c_recover_handler            |
+        | I2cScan              | async    | i2c_scan_handler               |
+        | Smbus                | async    | smbus_handler                  |
+        | SmbusAlert           | async    | smbus_alert_handler            |
+        | I2cTriggerArm        | spawn    | i2c_trigger_arm_handler        |
+        | I2cTriggerDisarm     | async    | i2c_trigger_disarm_handler     |
+        | I2cTargetStart       | spawn    | i2c_target_start_handler       |
+        | I2cTargetStop        | async    | i2c_target_stop_handler        |
+        | I2cTargetLoad        | async    | i2c_target_load_handler        |
+        | I2cTargetDump        | async    | i2c_target_dump_handler        |
+        | SpiRead              | async    | spi_read_handler               |
+        | SpiWrite             | async    | spi_write_handler              |
+        | SpiFlush             | async    | spi_flush_handler              |
+        | SpiTransaction       | async    | spi_transaction_handler        |
+        | SpiAddDevice         | async    | spi_add_device_handler         |
+        | SpiRemoveDevice      | async    | spi_remove_device_handler      |
+        | SpiDeviceTransaction | async    | spi_device_transaction_handler |
+        | SpiTargetStart       | spawn    | spi_target_start_handler       |
+        | SpiTargetStop        | async    | spi_target_stop_handler        |
+        | SpiTargetLoad        | async    | spi_target_load_handler        |
+        | SpiTargetCommand     | async    | spi_target_command_handler     |
+        | SpiTargetClear       | async    | spi_target_clear_handler       |
+        | OneWireReset         | async    | onewire_reset_handler          |
+        | OneWireBit           | async    | onewire_bit_handler            |
+        | OneWireWrite         | async    | onewire_write_handler          |
+        | OneWireRead          | async    | onewire_read_handler           |
+        | OneWireSearch        | async    | onewire_search_handler         |
+        | LedsWrite            | async    | leds_write_handler             |
+        | PioLoad              | async    | pio_load_handler               |
+        | PioStart             | async    | pio_start_handler              |
+        | PioStop              | async    | pio_stop_handler               |
+        | PioExec              | async    | pio_exec_handler               |
+        | PioPush              | async    | pio_push_handler               |
+        | PioPull              | async    | pio_pull_handler               |
+        | CaptureStart         | spawn    | capture_start_handler          |
+        | CaptureStop          | async    | capture_stop_handler           |
+        | CaptureRead          | async    | capture_read_handler           |
+        | SniffI2cStart        | spawn    | sniff_i2c_start_handler        |
+        | SniffSpiStart        | spawn    | sniff_spi_start_handler        |
+        | SniffStop            | async    | sniff_stop_handler             |
+        | GpioGet              | async    | gpio_get_handler               |
+        | GpioPut              | async    | gpio_put_handler               |
+        | GpioWaitForHigh      | spawn    | gpio_wait_for_high_handler     |
+        | GpioWaitForLow       | spawn    | gpio_wait_for_low_handler      |
+        | GpioWaitForRising    | spawn    | gpio_wait_for_rising_handler   |
+        | GpioWaitForFalling   | spawn    | gpio_wait_for_falling_handler  |
+        | GpioWaitForAny       | spawn    | gpio_wait_for_any_handler      |
+        | GpioMeasure          | spawn    | gpio_measure_handler           |
+        | I2cSetConfig         | async    | i2c_set_config_handler         |
+        | SpiSetConfig         | async    | spi_set_config_handler         |
+        | GetConfiguration     | async    | get_config_handler             |
+        | Version              | async    | version_handler                |
     };
     topics_in: {
         list: TOPICS_IN_LIST;
@@ -187,8 +375,12 @@ async fn main(spawner: Spawner) {
     let pbufs = PBUFS.take();
     let config = usb_config();
 
-    let i2c = embassy_rp::i2c::I2c::new_async(p.I2C1, p.PIN_3, p.PIN_2, Irqs, embassy_rp::i2c::Config::default());
-    let spi = embassy_rp::spi::Spi::new(
+    // Bus 0 is wired to the board's headers, bus 1 uses spare pins of
+    // the Pico: I2C0 on 16/17 and SPI1 on 26/27/28
+    let i2c_bus0 = embassy_rp::i2c::I2c::new_async(p.I2C1, p.PIN_3, p.PIN_2, Irqs, embassy_rp::i2c::Config::default());
+    let i2c_bus1 =
+        embassy_rp::i2c::I2c::new_async(p.I2C0, p.PIN_17, p.PIN_16, Irqs, embassy_rp::i2c::Config::default());
+    let spi_bus0 = embassy_rp::spi::Spi::new(
         p.SPI0,
         p.PIN_6,
         p.PIN_7,
@@ -197,6 +389,18 @@ async fn main(spawner: Spawner) {
         p.DMA_CH1,
         embassy_rp::spi::Config::default(),
     );
+    let spi_bus1 = embassy_rp::spi::Spi::new(
+        p.SPI1,
+        p.PIN_26,
+        p.PIN_27,
+        p.PIN_28,
+        p.DMA_CH2,
+        p.DMA_CH3,
+        embassy_rp::spi::Config::default(),
+    );
+
+    let i2c = [I2cBus::I2c1(i2c_bus0), I2cBus::I2c0(i2c_bus1)];
+    let spi = [SpiBus::Spi0(spi_bus0), SpiBus::Spi1(spi_bus1)];
 
     let gpio8 = embassy_rp::gpio::Flex::new(p.PIN_8);
     let gpio9 = embassy_rp::gpio::Flex::new(p.PIN_9);
@@ -207,7 +411,13 @@ async fn main(spawner: Spawner) {
     let gpio14 = embassy_rp::gpio::Flex::new(p.PIN_14);
     let gpio15 = embassy_rp::gpio::Flex::new(p.PIN_15);
 
-    let context = Context::new(i2c, spi, gpio8, gpio9, gpio10, gpio11, gpio12, gpio13, gpio14, gpio15);
+    let leds = Leds::new(p.PIO0, p.DMA_CH4);
+    let pio = UserPio::new(p.PIO1);
+    let capture = Capture::new(p.PIO2, p.DMA_CH5);
+
+    let context = Context::new(
+        i2c, spi, leds, pio, capture, gpio8, gpio9, gpio10, gpio11, gpio12, gpio13, gpio14, gpio15,
+    );
 
     let (device, tx_impl, rx_impl) = STORAGE.init(driver, config, pbufs.tx_buf.as_mut_slice());
     let dispatcher = PicoDeGallo::new(context, spawner.into());
@@ -240,16 +450,33 @@ async fn i2c_read_handler<'a>(
     _header: VarHeader,
     req: I2cReadRequest,
 ) -> I2cReadResponse<'a> {
+    let bus = usize::from(req.bus);
+    let Some(i2c) = context.i2c.get(bus) else {
+        return Err(I2cReadFail);
+    };
+    let Some(mut i2c) = i2c_controller(i2c, bus).await else {
+        return Err(I2cReadFail);
+    };
+
     if usize::from(req.count) > BUFFER_SIZE {
         return Err(I2cReadFail);
     }
 
     let len = ..usize::from(req.count);
-    context
-        .i2c
-        .blocking_read(req.address, &mut context.buf[len])
-        .map_err(|_| I2cReadFail)
-        .map(|_| &context.buf[len])
+    match req.address {
+        I2cAddress::SevenBit(address) => i2c
+            .blocking_read(address, &mut context.buf[len])
+            .map_err(|_| I2cReadFail)?,
+        I2cAddress::TenBit(address) if address <= I2C_TEN_BIT_ADDRESS_MAX => {
+            let (scl, sda) = I2C_PINS[bus];
+            i2c.ten_bit_read(scl, sda, address, &mut context.buf[len])
+                .await
+                .map_err(|_| I2cReadFail)?
+        }
+        I2cAddress::TenBit(_) => return Err(I2cReadFail),
+    }
+
+    Ok(&context.buf[len])
 }
 
 async fn i2c_write_handler<'a>(
@@ -257,10 +484,358 @@ async fn i2c_write_handler<'a>(
     _header: VarHeader,
     req: I2cWriteRequest<'a>,
 ) -> I2cWriteResponse {
-    context
-        .i2c
-        .blocking_write(req.address, req.contents)
-        .map_err(|_| I2cWriteFail)
+    let bus = usize::from(req.bus);
+    let Some(i2c) = context.i2c.get(bus) else {
+        return Err(I2cWriteFail);
+    };
+    let Some(mut i2c) = i2c_controller(i2c, bus).await else {
+        return Err(I2cWriteFail);
+    };
+
+    match req.address {
+        I2cAddress::SevenBit(address) => i2c.blocking_write(address, req.contents).map_err(|_| I2cWriteFail),
+        I2cAddress::TenBit(address) if address <= I2C_TEN_BIT_ADDRESS_MAX => {
+            let (scl, sda) = I2C_PINS[bus];
+            i2c.ten_bit_write(scl, sda, address, req.contents)
+                .await
+                .map_err(|_| I2cWriteFail)
+        }
+        I2cAddress::TenBit(_) => Err(I2cWriteFail),
+    }
+}
+
+async fn i2c_recover_handler(context: &mut Context, _header: VarHeader, req: I2cRecoverRequest) -> I2cRecoverResponse {
+    let bus = usize::from(req.bus);
+    let Some(i2c) = context.i2c.get(bus) else {
+        return Err(I2cRecoverFail);
+    };
+    let Some(mut i2c) = i2c_controller(i2c, bus).await else {
+        return Err(I2cRecoverFail);
+    };
+
+    let (scl, sda) = I2C_PINS[bus];
+    let mut i2c_config = i2c::Config::default();
+    i2c_config.frequency = context.config.i2c[bus].frequency;
+
+    Ok(i2c.recover(scl, sda, &i2c_config).await)
+}
+
+async fn i2c_scan_handler(context: &mut Context, _header: VarHeader, req: I2cScanRequest) -> I2cScanResponse {
+    let bus = usize::from(req.bus);
+    let Some(i2c) = context.i2c.get(bus) else {
+        return Err(I2cScanFail);
+    };
+    let Some(mut i2c) = i2c_controller(i2c, bus).await else {
+        return Err(I2cScanFail);
+    };
+
+    let (scl, sda) = I2C_PINS[bus];
+    let mut found = I2cAddressMap::default();
+
+    for address in (0..=0x7f).filter(|&a| req.reserved || !i2c_address_is_reserved(a)) {
+        let acked = match req.mode {
+            I2cScanMode::Write => i2c.probe_write(scl, sda, address).await,
+            I2cScanMode::Read => i2c.blocking_read(address, &mut [0]).is_ok(),
+        };
+
+        if acked {
+            found.insert(address);
+        }
+    }
+
+    Ok(found)
+}
+
+async fn smbus_handler<'a>(context: &'a mut Context, _header: VarHeader, req: SmbusRequest<'_>) -> SmbusResponse<'a> {
+    let bus = usize::from(req.bus);
+    let Some(i2c) = context.i2c.get(bus) else {
+        return Err(SmbusFail::InvalidBus);
+    };
+
+    let Some(mut i2c) = i2c_controller(i2c, bus).await else {
+        return Err(SmbusFail::BusBusy);
+    };
+
+    let (scl, sda) = I2C_PINS[bus];
+    let len = i2c
+        .smbus(scl, sda, req.address, req.pec, &req.operation, &mut context.buf)
+        .await?;
+
+    Ok(&context.buf[..len])
+}
+
+async fn smbus_alert_handler(context: &mut Context, _header: VarHeader, req: SmbusAlertRequest) -> SmbusAlertResponse {
+    let bus = usize::from(req.bus);
+    let Some(i2c) = context.i2c.get(bus) else {
+        return Err(SmbusFail::InvalidBus);
+    };
+
+    let Some(mut i2c) = i2c_controller(i2c, bus).await else {
+        return Err(SmbusFail::BusBusy);
+    };
+
+    let (scl, sda) = I2C_PINS[bus];
+    i2c.alert_response(scl, sda, req.pec).await
+}
+
+#[embassy_executor::task(pool_size = I2C_TRIGGER_MAX_SLOTS + 1)]
+async fn i2c_trigger_arm_handler(
+    context: TaskContext,
+    header: VarHeader,
+    req: I2cTriggerArmRequest,
+    sender: Sender<AppTx>,
+) {
+    let armed = i2c_trigger_claim(&context, &req);
+
+    let reply: I2cTriggerArmResponse = armed.as_ref().map(|&(slot, _)| slot as u8).map_err(|&e| e);
+    let _ = sender.reply::<I2cTriggerArm>(header.seq_no, &reply).await;
+
+    let Ok((slot, mut gpio)) = armed else {
+        return;
+    };
+
+    let trigger = &TRIGGERS[slot];
+    let i2c = &context.i2c[usize::from(req.bus)];
+    let mut seq_no: u16 = 0;
+
+    gpio.set_as_input();
+
+    loop {
+        let edge = async {
+            match req.edge {
+                GpioEdge::Rising => gpio.wait_for_rising_edge().await,
+                GpioEdge::Falling => gpio.wait_for_falling_edge().await,
+                GpioEdge::Any => gpio.wait_for_any_edge().await,
+            }
+        };
+
+        if let Either::First(()) = select(trigger.disarm.wait(), edge).await {
+            break;
+        }
+
+        let timestamp_us = Instant::now().as_micros();
+
+        for _ in 0..ALERT_RESPONSES_MAX {
+            let result = i2c_trigger_run(i2c, usize::from(req.bus), &req.action).await;
+            let answered = result.is_ok();
+
+            let event = I2cTriggerEvent {
+                slot: slot as u8,
+                timestamp_us,
+                result,
+            };
+            let _ = sender.publish::<I2cTriggerTopic>(VarSeq::Seq2(seq_no), &event).await;
+            seq_no = seq_no.wrapping_add(1);
+
+            // Other devices may still be asserting SMBALERT#
+            let alert = matches!(req.action, I2cTriggerAction::AlertResponse { .. });
+            if !(alert && answered && gpio.is_low()) {
+                break;
+            }
+        }
+    }
+
+    drop(gpio);
+    trigger.armed.store(false, Ordering::Release);
+}
+
+/// Check `req`, then reserve its pin and a trigger slot.
+fn i2c_trigger_claim(
+    context: &TaskContext,
+    req: &I2cTriggerArmRequest,
+) -> Result<(usize, MutexGuard<'static, ThreadModeRawMutex, Flex<'static>>), I2cTriggerArmFail> {
+    let gpio = context
+        .gpios
+        .get(usize::from(req.pin))
+        .ok_or(I2cTriggerArmFail::InvalidPin)?;
+
+    if usize::from(req.bus) >= NUM_I2C_BUSES {
+        return Err(I2cTriggerArmFail::InvalidBus);
+    }
+
+    if let I2cTriggerAction::Read { count, .. } = req.action
+        && usize::from(count) > I2C_TRIGGER_MAX_LEN
+    {
+        return Err(I2cTriggerArmFail::ReadTooLong);
+    }
+
+    let gpio = gpio.try_lock().map_err(|_| I2cTriggerArmFail::PinBusy)?;
+    let slot = TRIGGERS
+        .iter()
+        .position(|trigger| {
+            trigger
+                .armed
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        })
+        .ok_or(I2cTriggerArmFail::NoFreeSlot)?;
+
+    TRIGGERS[slot].disarm.reset();
+
+    Ok((slot, gpio))
+}
+
+/// Perform the `action` of a trigger which fired.
+async fn i2c_trigger_run(
+    i2c: &Shared<I2cBus>,
+    bus: usize,
+    action: &I2cTriggerAction,
+) -> Result<heapless::Vec<u8, I2C_TRIGGER_MAX_LEN>, SmbusFail> {
+    let mut i2c = i2c_controller(i2c, bus).await.ok_or(SmbusFail::BusBusy)?;
+    let mut data = heapless::Vec::new();
+
+    match *action {
+        I2cTriggerAction::Read {
+            address,
+            register,
+            count,
+        } => {
+            // The count was checked when armed
+            let _ = data.resize_default(usize::from(count));
+
+            match register {
+                Some(register) => i2c.blocking_write_read(address, &[register], &mut data),
+                None => i2c.blocking_read(address, &mut data),
+            }
+            .map_err(|_| SmbusFail::NoAcknowledge)?;
+        }
+        I2cTriggerAction::AlertResponse { pec } => {
+            let (scl, sda) = I2C_PINS[bus];
+            let address = i2c.alert_response(scl, sda, pec).await?;
+            let _ = data.push(address);
+        }
+    }
+
+    Ok(data)
+}
+
+/// Lock the controller of `bus`, unless it emulates a target or is
+/// sniffed. Handlers can't wait for either to stop, since stopping them
+/// is a request.
+async fn i2c_controller(i2c: &Shared<I2cBus>, bus: usize) -> Option<MutexGuard<'_, ThreadModeRawMutex, I2cBus>> {
+    if i2c_target::is_running(bus) || sniff::sniffed() == Some(Sniffed::I2c(bus)) {
+        return None;
+    }
+
+    Some(i2c.lock().await)
+}
+
+async fn i2c_trigger_disarm_handler(
+    _context: &mut Context,
+    _header: VarHeader,
+    req: I2cTriggerDisarmRequest,
+) -> I2cTriggerDisarmResponse {
+    let trigger = TRIGGERS
+        .get(usize::from(req.slot))
+        .filter(|trigger| trigger.armed.load(Ordering::Acquire))
+        .ok_or(I2cTriggerDisarmFail)?;
+
+    trigger.disarm.signal(());
+
+    // Only answer once the pin is released
+    while trigger.armed.load(Ordering::Acquire) {
+        yield_now().await;
+    }
+
+    Ok(())
+}
+
+#[embassy_executor::task(pool_size = NUM_I2C_BUSES + 1)]
+async fn i2c_target_start_handler(
+    context: TaskContext,
+    header: VarHeader,
+    req: I2cTargetStartRequest,
+    sender: Sender<AppTx>,
+) {
+    let bus = usize::from(req.bus);
+    let reply: I2cTargetResponse = if bus >= NUM_I2C_BUSES {
+        Err(I2cTargetFail::InvalidBus)
+    } else if req.address > 0x7f || i2c_address_is_reserved(req.address) {
+        Err(I2cTargetFail::InvalidAddress)
+    } else if sniff::sniffed() == Some(Sniffed::I2c(bus)) || !i2c_target::claim(bus) {
+        Err(I2cTargetFail::AlreadyRunning)
+    } else {
+        Ok(())
+    };
+
+    if reply.is_err() {
+        let _ = sender.reply::<I2cTargetStart>(header.seq_no, &reply).await;
+        return;
+    }
+
+    // Held until stopped, letting transfers in flight complete first
+    let controller = context.i2c[bus].lock().await;
+    let _ = sender.reply::<I2cTargetStart>(header.seq_no, &reply).await;
+
+    i2c_target::run(bus, req.address, req.general_call, &sender).await;
+    drop(controller);
+}
+
+async fn i2c_target_stop_handler(
+    context: &mut Context,
+    _header: VarHeader,
+    req: I2cTargetStopRequest,
+) -> I2cTargetResponse {
+    let bus = usize::from(req.bus);
+    let i2c = context.i2c.get(bus).ok_or(I2cTargetFail::InvalidBus)?;
+
+    if !i2c_target::is_running(bus) {
+        return Err(I2cTargetFail::NotRunning);
+    }
+
+    i2c_target::stop(bus).await;
+
+    let mut i2c_config = i2c::Config::default();
+    i2c_config.frequency = context.config.i2c[bus].frequency;
+
+    // The configuration was accepted before, it can't fail now
+    let _ = i2c.lock().await.restore_controller(&i2c_config);
+    i2c_target::release(bus);
+
+    Ok(())
+}
+
+async fn i2c_target_load_handler(
+    _context: &mut Context,
+    _header: VarHeader,
+    req: I2cTargetLoadRequest<'_>,
+) -> I2cTargetResponse {
+    let bus = usize::from(req.bus);
+    let register = usize::from(req.register);
+
+    if bus >= NUM_I2C_BUSES {
+        return Err(I2cTargetFail::InvalidBus);
+    }
+
+    if register + req.data.len() > I2C_TARGET_REGISTERS {
+        return Err(I2cTargetFail::OutOfRange);
+    }
+
+    i2c_target::load(bus, register, req.data);
+
+    Ok(())
+}
+
+async fn i2c_target_dump_handler<'a>(
+    context: &'a mut Context,
+    _header: VarHeader,
+    req: I2cTargetDumpRequest,
+) -> I2cTargetDumpResponse<'a> {
+    let bus = usize::from(req.bus);
+    let register = usize::from(req.register);
+    let count = usize::from(req.count);
+
+    if bus >= NUM_I2C_BUSES {
+        return Err(I2cTargetFail::InvalidBus);
+    }
+
+    if register + count > I2C_TARGET_REGISTERS {
+        return Err(I2cTargetFail::OutOfRange);
+    }
+
+    i2c_target::dump(bus, register, &mut context.buf[..count]);
+
+    Ok(&context.buf[..count])
 }
 
 async fn spi_read_handler<'a>(
@@ -268,16 +843,24 @@ async fn spi_read_handler<'a>(
     _header: VarHeader,
     req: SpiReadRequest,
 ) -> SpiReadResponse<'a> {
-    if usize::from(req.count) > BUFFER_SIZE {
+    let bus = usize::from(req.bus);
+    let Some(spi) = spi_controller(&mut context.spi, bus) else {
+        return Err(SpiReadFail);
+    };
+
+    let config = context.config.spi[bus];
+    let count = usize::from(req.count);
+    if count > BUFFER_SIZE || !count.is_multiple_of(config.word_size.bytes()) {
         return Err(SpiReadFail);
     }
 
-    let len = ..usize::from(req.count);
-    context
-        .spi
-        .blocking_read(&mut context.buf[len])
-        .map_err(|_| SpiReadFail)
-        .map(|_| &context.buf[len])
+    spi_apply(spi, &mut context.spi_applied[bus], &config);
+
+    let len = ..count;
+    spi.blocking_read(&mut context.buf[len]).map_err(|_| SpiReadFail)?;
+    spi_wire_order(&config, &mut context.buf[len]);
+
+    Ok(&context.buf[len])
 }
 
 async fn spi_write_handler<'a>(
@@ -285,16 +868,744 @@ async fn spi_write_handler<'a>(
     _header: VarHeader,
     req: SpiWriteRequest<'a>,
 ) -> SpiWriteResponse {
-    context.spi.blocking_write(req.contents).map_err(|_| SpiWriteFail)
+    let bus = usize::from(req.bus);
+    let Some(spi) = spi_controller(&mut context.spi, bus) else {
+        return Err(SpiWriteFail);
+    };
+
+    let config = context.config.spi[bus];
+    if !req.contents.len().is_multiple_of(config.word_size.bytes()) {
+        return Err(SpiWriteFail);
+    }
+
+    spi_apply(spi, &mut context.spi_applied[bus], &config);
+
+    if config.word_size == SpiWordSize::Eight && config.bit_order == SpiBitOrder::MsbFirst {
+        return spi.blocking_write(req.contents).map_err(|_| SpiWriteFail);
+    }
+
+    for chunk in req.contents.chunks(BUFFER_SIZE) {
+        let buf = &mut context.buf[..chunk.len()];
+        buf.copy_from_slice(chunk);
+        spi_wire_order(&config, buf);
+        spi.blocking_write(buf).map_err(|_| SpiWriteFail)?;
+    }
+
+    Ok(())
+}
+
+/// Convert words between their host encoding, little-endian, and the
+/// order their bits are shifted on the bus, as the SPI block always
+/// shifts 8-bit frames MSB first. The conversion is its own inverse.
+fn spi_wire_order(config: &SpiConfig, buf: &mut [u8]) {
+    let lsb_first = config.bit_order == SpiBitOrder::LsbFirst;
+
+    match config.word_size {
+        SpiWordSize::Eight if lsb_first => buf.iter_mut().for_each(|b| *b = b.reverse_bits()),
+        SpiWordSize::Eight => {}
+        SpiWordSize::Sixteen => {
+            for word in buf.chunks_exact_mut(2) {
+                let mut w = u16::from_le_bytes([word[0], word[1]]);
+                if lsb_first {
+                    w = w.reverse_bits();
+                }
+                word.copy_from_slice(&w.to_be_bytes());
+            }
+        }
+    }
+}
+
+async fn spi_flush_handler(context: &mut Context, _header: VarHeader, req: SpiFlushRequest) -> SpiFlushResponse {
+    let Some(spi) = spi_controller(&mut context.spi, usize::from(req.bus)) else {
+        return Err(SpiFlushFail);
+    };
+
+    spi.flush().map_err(|_| SpiFlushFail)
+}
+
+async fn spi_transaction_handler<'a>(
+    context: &'a mut Context,
+    _header: VarHeader,
+    req: SpiTransactionRequest<'_>,
+) -> SpiTransactionResponse<'a> {
+    let bus = usize::from(req.bus);
+    let Some(&config) = context.config.spi.get(bus) else {
+        return Err(SpiTransactionFail);
+    };
+
+    spi_transaction(context, bus, req.cs, config, &req.operations).await
+}
+
+async fn spi_device_transaction_handler<'a>(
+    context: &'a mut Context,
+    _header: VarHeader,
+    req: SpiDeviceTransactionRequest<'_>,
+) -> SpiTransactionResponse<'a> {
+    let Some(device) = context.spi_devices.get(usize::from(req.slot)).and_then(Option::as_ref) else {
+        return Err(SpiTransactionFail);
+    };
+
+    let (bus, cs, config) = (device.bus, device.cs, device.config);
+    spi_transaction(context, bus, cs, config, &req.operations).await
+}
+
+/// Run `operations` on `bus` with the chip select `cs` asserted and the
+/// bus set up for `config`.
+async fn spi_transaction<'a>(
+    context: &'a mut Context,
+    bus: usize,
+    cs: SpiChipSelect,
+    config: SpiConfig,
+    operations: &[SpiOperation<'_>],
+) -> SpiTransactionResponse<'a> {
+    let word = config.word_size.bytes();
+
+    // Reject the whole transaction before touching CS
+    let mut total = 0;
+    for op in operations {
+        let (read, write) = match *op {
+            SpiOperation::Read(count) => (usize::from(count), 0),
+            SpiOperation::Write(data) => (0, data.len()),
+            SpiOperation::Transfer { read, write } => (usize::from(read), write.len()),
+            SpiOperation::TransferInPlace(data) => (data.len(), data.len()),
+            SpiOperation::DelayNs(_) => (0, 0),
+        };
+
+        if !read.is_multiple_of(word) || !write.is_multiple_of(word) || write > BUFFER_SIZE {
+            return Err(SpiTransactionFail);
+        }
+
+        total += read;
+    }
+
+    let pin = usize::from(cs.pin);
+    if total > BUFFER_SIZE || pin >= NUM_GPIOS || bus >= NUM_SPI_BUSES || spi_is_taken(bus) {
+        return Err(SpiTransactionFail);
+    }
+
+    let Ok(mut gpio) = context.gpios[pin].try_lock() else {
+        return Err(SpiTransactionFail);
+    };
+
+    let (active, inactive) = match cs.polarity {
+        SpiCsPolarity::ActiveLow => (Level::Low, Level::High),
+        SpiCsPolarity::ActiveHigh => (Level::High, Level::Low),
+    };
+
+    let Context {
+        spi,
+        spi_applied,
+        buf,
+        scratch,
+        ..
+    } = context;
+
+    let spi = &mut spi[bus];
+    spi_apply(spi, &mut spi_applied[bus], &config);
+
+    gpio.set_level(inactive);
+    gpio.set_as_output();
+
+    gpio.set_level(active);
+    delay_ns(cs.setup_ns).await;
+
+    let result = spi_run_operations(spi, &config, operations, buf, scratch).await;
+
+    delay_ns(cs.hold_ns).await;
+    gpio.set_level(inactive);
+
+    let len = result?;
+    Ok(&buf[..len])
+}
+
+/// Run `operations` on the bus, storing what's read into `buf`.
+/// Returns the number of bytes read.
+async fn spi_run_operations(
+    spi: &mut SpiBus,
+    config: &SpiConfig,
+    operations: &[SpiOperation<'_>],
+    buf: &mut [u8],
+    scratch: &mut [u8],
+) -> Result<usize, SpiTransactionFail> {
+    let mut pos = 0;
+
+    for op in operations {
+        match *op {
+            SpiOperation::Read(count) => {
+                let rx = &mut buf[pos..pos + usize::from(count)];
+                spi.blocking_read(rx).map_err(|_| SpiTransactionFail)?;
+                spi_wire_order(config, rx);
+                pos += rx.len();
+            }
+            SpiOperation::Write(data) => {
+                let tx = &mut scratch[..data.len()];
+                tx.copy_from_slice(data);
+                spi_wire_order(config, tx);
+                spi.blocking_write(tx).map_err(|_| SpiTransactionFail)?;
+            }
+            SpiOperation::Transfer { read, write } => {
+                let tx = &mut scratch[..write.len()];
+                tx.copy_from_slice(write);
+                spi_wire_order(config, tx);
+                let rx = &mut buf[pos..pos + usize::from(read)];
+                spi.blocking_transfer(rx, tx).map_err(|_| SpiTransactionFail)?;
+                spi_wire_order(config, rx);
+                pos += rx.len();
+            }
+            SpiOperation::TransferInPlace(data) => {
+                let rx = &mut buf[pos..pos + data.len()];
+                rx.copy_from_slice(data);
+                spi_wire_order(config, rx);
+                spi.blocking_transfer_in_place(rx).map_err(|_| SpiTransactionFail)?;
+                spi_wire_order(config, rx);
+                pos += rx.len();
+            }
+            SpiOperation::DelayNs(ns) => delay_ns(ns).await,
+        }
+    }
+
+    // Let the last word out before CS is deasserted
+    spi.flush().map_err(|_| SpiTransactionFail)?;
+
+    Ok(pos)
+}
+
+/// The SPI block of `bus`, unless it emulates a target or is sniffed.
+fn spi_controller(spi: &mut [SpiBus; NUM_SPI_BUSES], bus: usize) -> Option<&mut SpiBus> {
+    spi.get_mut(bus).filter(|_| !spi_is_taken(bus))
+}
+
+/// Whether `bus` can't be used as a controller.
+fn spi_is_taken(bus: usize) -> bool {
+    spi_target::is_running(bus) || sniff::sniffed() == Some(Sniffed::Spi(bus))
+}
+
+#[embassy_executor::task(pool_size = NUM_SPI_BUSES + 1)]
+async fn spi_target_start_handler(
+    context: TaskContext,
+    header: VarHeader,
+    req: SpiTargetStartRequest,
+    sender: Sender<AppTx>,
+) {
+    let claimed = spi_target_claim(&context, &req);
+
+    let reply: SpiTargetResponse = claimed.as_ref().map(|_| ()).map_err(|&e| e);
+    let _ = sender.reply::<SpiTargetStart>(header.seq_no, &reply).await;
+
+    let Ok(gpio) = claimed else {
+        return;
+    };
+
+    let bus = usize::from(req.bus);
+    let (cs, _) = SPI_TARGET_CS[bus];
+    spi_target::run(bus, cs, req.polarity, req.phase, req.fill, &sender).await;
+    drop(gpio);
+}
+
+/// Check `req`, then reserve its bus and chip select pin.
+fn spi_target_claim(
+    context: &TaskContext,
+    req: &SpiTargetStartRequest,
+) -> Result<Option<MutexGuard<'static, ThreadModeRawMutex, Flex<'static>>>, SpiTargetFail> {
+    let bus = usize::from(req.bus);
+    let &(_, gpio) = SPI_TARGET_CS.get(bus).ok_or(SpiTargetFail::InvalidBus)?;
+
+    let gpio = match gpio {
+        Some(gpio) => Some(context.gpios[gpio].try_lock().map_err(|_| SpiTargetFail::PinBusy)?),
+        None => None,
+    };
+
+    if sniff::sniffed() == Some(Sniffed::Spi(bus)) || !spi_target::claim(bus) {
+        return Err(SpiTargetFail::AlreadyRunning);
+    }
+
+    Ok(gpio)
+}
+
+async fn spi_target_stop_handler(
+    context: &mut Context,
+    _header: VarHeader,
+    req: SpiTargetStopRequest,
+) -> SpiTargetResponse {
+    let bus = usize::from(req.bus);
+    let spi = context.spi.get_mut(bus).ok_or(SpiTargetFail::InvalidBus)?;
+
+    if !spi_target::is_running(bus) {
+        return Err(SpiTargetFail::NotRunning);
+    }
+
+    spi_target::stop(bus).await;
+
+    spi.restore_controller(&spi_block_config(&context.spi_applied[bus]));
+    spi_target::release(bus);
+
+    Ok(())
+}
+
+async fn spi_target_load_handler(
+    _context: &mut Context,
+    _header: VarHeader,
+    req: SpiTargetLoadRequest<'_>,
+) -> SpiTargetResponse {
+    let bus = usize::from(req.bus);
+    if bus >= NUM_SPI_BUSES {
+        return Err(SpiTargetFail::InvalidBus);
+    }
+
+    spi_target::load(bus, req.data)
+}
+
+async fn spi_target_command_handler(
+    _context: &mut Context,
+    _header: VarHeader,
+    req: SpiTargetCommandRequest<'_>,
+) -> SpiTargetResponse {
+    let bus = usize::from(req.bus);
+    if bus >= NUM_SPI_BUSES {
+        return Err(SpiTargetFail::InvalidBus);
+    }
+
+    spi_target::add_command(bus, req.command, req.response)
+}
+
+async fn spi_target_clear_handler(
+    _context: &mut Context,
+    _header: VarHeader,
+    req: SpiTargetClearRequest,
+) -> SpiTargetResponse {
+    let bus = usize::from(req.bus);
+    if bus >= NUM_SPI_BUSES {
+        return Err(SpiTargetFail::InvalidBus);
+    }
+
+    spi_target::clear(bus);
+
+    Ok(())
+}
+
+async fn spi_add_device_handler(
+    context: &mut Context,
+    _header: VarHeader,
+    req: SpiAddDeviceRequest<'_>,
+) -> SpiAddDeviceResponse {
+    let name = heapless::String::try_from(req.name)
+        .ok()
+        .filter(|name| !name.is_empty())
+        .ok_or(SpiAddDeviceFail::InvalidName {
+            max: SPI_DEVICE_NAME_MAX_LEN as u32,
+        })?;
+
+    let bus = usize::from(req.bus);
+    if bus >= NUM_SPI_BUSES {
+        return Err(SpiAddDeviceFail::InvalidBus);
+    }
+
+    if usize::from(req.cs.pin) >= NUM_GPIOS {
+        return Err(SpiAddDeviceFail::InvalidPin);
+    }
+
+    let actual_frequency = spi_actual_frequency(req.config.frequency).map_err(|e| match e {
+        SpiSetConfigFail::FrequencyOutOfRange { min, max } => SpiAddDeviceFail::FrequencyOutOfRange { min, max },
+        SpiSetConfigFail::InvalidBus => SpiAddDeviceFail::InvalidBus,
+    })?;
+
+    // A known name keeps its slot, otherwise take the first free one
+    let devices = &mut context.spi_devices;
+    let slot = devices
+        .iter()
+        .position(|device| device.as_ref().is_some_and(|device| device.name == name))
+        .or_else(|| devices.iter().position(Option::is_none))
+        .ok_or(SpiAddDeviceFail::NoFreeSlot)?;
+
+    devices[slot] = Some(SpiDeviceEntry {
+        name,
+        bus,
+        cs: req.cs,
+        config: req.config,
+    });
+
+    Ok(SpiDeviceSlot {
+        slot: slot as u8,
+        actual_frequency,
+    })
+}
+
+async fn spi_remove_device_handler(
+    context: &mut Context,
+    _header: VarHeader,
+    req: SpiRemoveDeviceRequest,
+) -> SpiRemoveDeviceResponse {
+    context
+        .spi_devices
+        .get_mut(usize::from(req.slot))
+        .and_then(Option::take)
+        .map(|_| ())
+        .ok_or(SpiRemoveDeviceFail)
+}
+
+/// Wait for `ns` nanoseconds. Short delays are busy-waited, as timer
+/// ticks are too coarse for them.
+async fn delay_ns(ns: u32) {
+    const BUSY_WAIT_MAX_NS: u32 = 100_000;
+
+    if ns == 0 {
+        return;
+    }
+
+    if ns <= BUSY_WAIT_MAX_NS {
+        let cycles = u64::from(ns) * u64::from(embassy_rp::clocks::clk_sys_freq()) / 1_000_000_000;
+        cortex_m::asm::delay(cycles as u32);
+    } else {
+        Timer::after_nanos(u64::from(ns)).await;
+    }
+}
+
+/// Hand GPIO `index` over to the PIO block of `common`.
+///
+/// # Safety
+///
+/// The caller must hold the `Flex` of the GPIO for as long as the block
+/// drives it, then hand it back with `sio_pin`.
+unsafe fn pio_pin<PIO: embassy_rp::pio::Instance>(
+    common: &mut Common<'static, PIO>,
+    index: usize,
+) -> Pin<'static, PIO> {
+    unsafe {
+        match index {
+            0 => common.make_pio_pin(PIN_8::steal()),
+            1 => common.make_pio_pin(PIN_9::steal()),
+            2 => common.make_pio_pin(PIN_10::steal()),
+            3 => common.make_pio_pin(PIN_11::steal()),
+            4 => common.make_pio_pin(PIN_12::steal()),
+            5 => common.make_pio_pin(PIN_13::steal()),
+            6 => common.make_pio_pin(PIN_14::steal()),
+            _ => common.make_pio_pin(PIN_15::steal()),
+        }
+    }
 }
 
-async fn spi_flush_handler(context: &mut Context, _header: VarHeader, _req: ()) -> SpiFlushResponse {
-    context.spi.flush().map_err(|_| SpiFlushFail)
+/// Hand GPIO `index` back to the SIO, i.e. to its `Flex`.
+fn sio_pin(index: usize) {
+    embassy_rp::pac::IO_BANK0
+        .gpio(FIRST_GPIO_PIN + index)
+        .ctrl()
+        .modify(|w| w.set_funcsel(FUNCSEL_SIO));
+}
+
+/// Lock GPIO `pin` for a 1-Wire transfer.
+fn onewire_pin(
+    context: &Context,
+    pin: u8,
+) -> Result<MutexGuard<'static, ThreadModeRawMutex, Flex<'static>>, OneWireFail> {
+    let gpios: &'static [Shared<Flex<'static>>; NUM_GPIOS] = context.gpios;
+
+    gpios
+        .get(usize::from(pin))
+        .ok_or(OneWireFail::InvalidPin)?
+        .try_lock()
+        .map_err(|_| OneWireFail::PinBusy)
+}
+
+async fn onewire_reset_handler(
+    context: &mut Context,
+    _header: VarHeader,
+    req: OneWireResetRequest,
+) -> OneWireResetResponse {
+    let mut gpio = onewire_pin(context, req.pin)?;
+
+    OneWire::new(&mut gpio).reset().await
+}
+
+async fn onewire_bit_handler(context: &mut Context, _header: VarHeader, req: OneWireBitRequest) -> OneWireBitResponse {
+    let mut gpio = onewire_pin(context, req.pin)?;
+
+    Ok(OneWire::new(&mut gpio).touch_bit(req.bit))
+}
+
+async fn onewire_write_handler(
+    context: &mut Context,
+    _header: VarHeader,
+    req: OneWireWriteRequest<'_>,
+) -> OneWireWriteResponse {
+    if req.data.len() > ONEWIRE_MAX_LEN {
+        return Err(OneWireFail::TooLong);
+    }
+
+    let mut gpio = onewire_pin(context, req.pin)?;
+    let mut bus = OneWire::new(&mut gpio);
+
+    for &byte in req.data {
+        bus.write_byte(byte).await;
+    }
+
+    if req.strong_pullup_ms > 0 {
+        bus.strong_pullup(Duration::from_millis(req.strong_pullup_ms.into()))
+            .await;
+    }
+
+    Ok(())
+}
+
+async fn onewire_read_handler<'a>(
+    context: &'a mut Context,
+    _header: VarHeader,
+    req: OneWireReadRequest,
+) -> OneWireReadResponse<'a> {
+    let count = usize::from(req.count);
+    if count > ONEWIRE_MAX_LEN {
+        return Err(OneWireFail::TooLong);
+    }
+
+    let mut gpio = onewire_pin(context, req.pin)?;
+    let mut bus = OneWire::new(&mut gpio);
+
+    for byte in &mut context.buf[..count] {
+        *byte = bus.read_byte().await;
+    }
+
+    Ok(&context.buf[..count])
+}
+
+async fn onewire_search_handler(
+    context: &mut Context,
+    _header: VarHeader,
+    req: OneWireSearchRequest,
+) -> OneWireSearchResponse {
+    let mut gpio = onewire_pin(context, req.pin)?;
+    let mut roms = heapless::Vec::<_, ONEWIRE_SEARCH_MAX_DEVICES>::new();
+
+    OneWire::new(&mut gpio).search(req.alarm, &mut roms).await?;
+
+    Ok(roms)
+}
+
+async fn leds_write_handler(context: &mut Context, _header: VarHeader, req: LedsWriteRequest<'_>) -> LedsWriteResponse {
+    if req.data.len() > LEDS_MAX_LEN {
+        return Err(LedsFail::TooLong);
+    }
+
+    let index = usize::from(req.pin);
+    let Some(gpio) = context.gpios.get(index) else {
+        return Err(LedsFail::InvalidPin);
+    };
+    let Ok(mut gpio) = gpio.try_lock() else {
+        return Err(LedsFail::PinBusy);
+    };
+
+    context.leds.write(index, &mut gpio, req.data).await;
+
+    Ok(())
+}
+
+async fn pio_load_handler(context: &mut Context, _header: VarHeader, req: PioLoadRequest) -> PioLoadResponse {
+    context.pio.load(usize::from(req.sm), &req.program)
+}
+
+async fn pio_start_handler(context: &mut Context, _header: VarHeader, req: PioStartRequest) -> PioResponse {
+    context.pio.start(usize::from(req.sm), &req.config, context.gpios)
+}
+
+async fn pio_stop_handler(context: &mut Context, _header: VarHeader, req: PioStopRequest) -> PioResponse {
+    context.pio.stop(usize::from(req.sm))
+}
+
+async fn pio_exec_handler(context: &mut Context, _header: VarHeader, req: PioExecRequest) -> PioResponse {
+    context.pio.exec(usize::from(req.sm), req.instruction)
+}
+
+async fn pio_push_handler(context: &mut Context, _header: VarHeader, req: PioPushRequest) -> PioPushResponse {
+    let timeout = Duration::from_millis(req.timeout_ms.into());
+    let pushed = context.pio.push(usize::from(req.sm), &req.data, timeout).await?;
+
+    Ok(pushed as u16)
+}
+
+async fn pio_pull_handler(context: &mut Context, _header: VarHeader, req: PioPullRequest) -> PioPullResponse {
+    let timeout = Duration::from_millis(req.timeout_ms.into());
+
+    context
+        .pio
+        .pull(usize::from(req.sm), usize::from(req.count), timeout)
+        .await
+}
+
+#[embassy_executor::task(pool_size = 2)]
+async fn capture_start_handler(
+    context: TaskContext,
+    header: VarHeader,
+    req: CaptureStartRequest,
+    sender: Sender<AppTx>,
+) {
+    let claimed = context
+        .capture
+        .try_lock()
+        .map_err(|_| CaptureFail::Running)
+        .and_then(|capture| Ok((capture, Capture::arm(req.rate, req.samples, req.trigger)?)));
+
+    let reply: CaptureResponse = claimed.as_ref().map(|_| ()).map_err(|&e| e);
+    let _ = sender.reply::<CaptureStart>(header.seq_no, &reply).await;
+
+    let Ok((mut capture, armed)) = claimed else {
+        return;
+    };
+
+    let samples = capture.run(&armed).await;
+    drop(capture);
+
+    let event = CaptureEvent {
+        samples: samples as u32,
+        rate: armed.rate(),
+        timestamp_us: Instant::now().as_micros(),
+    };
+    let _ = sender.publish::<CaptureTopic>(VarSeq::Seq2(0), &event).await;
+}
+
+async fn capture_stop_handler(_context: &mut Context, _header: VarHeader, _req: ()) -> CaptureResponse {
+    if !capture::is_running() {
+        return Err(CaptureFail::NotRunning);
+    }
+
+    capture::stop();
+
+    Ok(())
+}
+
+async fn capture_read_handler<'a>(
+    context: &'a mut Context,
+    _header: VarHeader,
+    req: CaptureReadRequest,
+) -> CaptureReadResponse<'a> {
+    let capture = context.capture.try_lock().map_err(|_| CaptureFail::Running)?;
+
+    let count = usize::from(req.count);
+    if count > CAPTURE_MAX_READ {
+        return Err(CaptureFail::InvalidLength);
+    }
+
+    let samples = capture.samples(req.offset as usize, count)?;
+    for (chunk, sample) in context.buf.chunks_exact_mut(2).zip(samples) {
+        chunk.copy_from_slice(&sample.to_le_bytes());
+    }
+
+    Ok(&context.buf[..count * 2])
+}
+
+#[embassy_executor::task(pool_size = 2)]
+async fn sniff_i2c_start_handler(
+    context: TaskContext,
+    header: VarHeader,
+    req: SniffI2cStartRequest,
+    sender: Sender<AppTx>,
+) {
+    let bus = usize::from(req.bus);
+    let claimed = if bus >= NUM_I2C_BUSES {
+        Err(SniffFail::InvalidBus)
+    } else if i2c_target::is_running(bus) {
+        Err(SniffFail::BusBusy)
+    } else {
+        sniff_claim(&context, Sniffed::I2c(bus))
+    };
+
+    let mut capture = match claimed {
+        Ok(capture) => capture,
+        Err(e) => {
+            let reply: SniffResponse = Err(e);
+            let _ = sender.reply::<SniffI2cStart>(header.seq_no, &reply).await;
+            return;
+        }
+    };
+
+    // Held until stopped, letting transfers in flight complete first
+    let controller = context.i2c[bus].lock().await;
+    let reply: SniffResponse = Ok(());
+    let _ = sender.reply::<SniffI2cStart>(header.seq_no, &reply).await;
+
+    let (scl, sda) = I2C_PINS[bus];
+    sniff::i2c(&mut capture, bus, scl as u8, sda as u8, &sender).await;
+    drop(controller);
+}
+
+#[embassy_executor::task(pool_size = 2)]
+async fn sniff_spi_start_handler(
+    context: TaskContext,
+    header: VarHeader,
+    req: SniffSpiStartRequest,
+    sender: Sender<AppTx>,
+) {
+    let bus = usize::from(req.bus);
+    let claimed = sniff_spi_claim(&context, bus);
+
+    let reply: SniffResponse = claimed.as_ref().map(|_| ()).map_err(|&e| e);
+    let _ = sender.reply::<SniffSpiStart>(header.seq_no, &reply).await;
+
+    let Ok((mut capture, gpio)) = claimed else {
+        return;
+    };
+
+    let (cs, _) = SPI_TARGET_CS[bus];
+    sniff::spi(&mut capture, bus, cs as u8, req.polarity, req.phase, &sender).await;
+    drop(gpio);
+}
+
+/// Reserve PIO2, lent by the logic analyzer, for sniffing `sniffed`.
+fn sniff_claim(
+    context: &TaskContext,
+    sniffed: Sniffed,
+) -> Result<MutexGuard<'static, ThreadModeRawMutex, Capture>, SniffFail> {
+    let capture = context.capture.try_lock().map_err(|_| SniffFail::Running)?;
+
+    if !sniff::claim(sniffed) {
+        return Err(SniffFail::Running);
+    }
+
+    Ok(capture)
+}
+
+/// Check `bus`, then reserve it along with its chip select pin.
+#[allow(clippy::type_complexity)]
+fn sniff_spi_claim(
+    context: &TaskContext,
+    bus: usize,
+) -> Result<
+    (
+        MutexGuard<'static, ThreadModeRawMutex, Capture>,
+        Option<MutexGuard<'static, ThreadModeRawMutex, Flex<'static>>>,
+    ),
+    SniffFail,
+> {
+    let &(_, gpio) = SPI_TARGET_CS.get(bus).ok_or(SniffFail::InvalidBus)?;
+
+    if spi_target::is_running(bus) {
+        return Err(SniffFail::BusBusy);
+    }
+
+    let gpio = match gpio {
+        Some(gpio) => Some(context.gpios[gpio].try_lock().map_err(|_| SniffFail::BusBusy)?),
+        None => None,
+    };
+
+    Ok((sniff_claim(context, Sniffed::Spi(bus))?, gpio))
+}
+
+async fn sniff_stop_handler(_context: &mut Context, _header: VarHeader, _req: ()) -> SniffResponse {
+    if sniff::sniffed().is_none() {
+        return Err(SniffFail::NotRunning);
+    }
+
+    sniff::stop();
+
+    // Only answer once the bus is usable again
+    while sniff::sniffed().is_some() {
+        yield_now().await;
+    }
+
+    Ok(())
 }
 
 async fn gpio_get_handler(context: &mut Context, _header: VarHeader, req: GpioGetRequest) -> GpioGetResponse {
-    let pin = req.pin;
-    let gpio = &mut context.gpios[usize::from(pin)];
+    let Some(Ok(mut gpio)) = context.gpios.get(usize::from(req.pin)).map(|gpio| gpio.try_lock()) else {
+        return Err(GpioGetFail);
+    };
 
     gpio.set_as_input();
     match gpio.get_level() {
@@ -304,8 +1615,9 @@ async fn gpio_get_handler(context: &mut Context, _header: VarHeader, req: GpioGe
 }
 
 async fn gpio_put_handler(context: &mut Context, _header: VarHeader, req: GpioPutRequest) -> GpioPutResponse {
-    let pin = req.pin;
-    let gpio = &mut context.gpios[usize::from(pin)];
+    let Some(Ok(mut gpio)) = context.gpios.get(usize::from(req.pin)).map(|gpio| gpio.try_lock()) else {
+        return Err(GpioPutFail);
+    };
 
     let level = match req.state {
         GpioState::Low => Level::Low,
@@ -318,97 +1630,248 @@ async fn gpio_put_handler(context: &mut Context, _header: VarHeader, req: GpioPu
     Ok(())
 }
 
-async fn gpio_wait_for_high_handler(
-    context: &mut Context,
-    _header: VarHeader,
-    req: GpioWaitRequest,
-) -> GpioWaitResponse {
-    let pin = req.pin;
-    let gpio = &mut context.gpios[usize::from(pin)];
+/// Level or edge a GPIO wait request is after.
+#[derive(Clone, Copy)]
+enum GpioWait {
+    High,
+    Low,
+    RisingEdge,
+    FallingEdge,
+    AnyEdge,
+}
+
+/// Wait for `wait` on the pin of `req`. The handlers run as tasks of
+/// their own, so that other requests are served meanwhile.
+async fn gpio_wait(context: &TaskContext, req: &GpioWaitRequest, wait: GpioWait) -> GpioWaitResponse {
+    let Some(Ok(mut gpio)) = context.gpios.get(usize::from(req.pin)).map(|gpio| gpio.try_lock()) else {
+        return Err(GpioWaitFail);
+    };
 
     gpio.set_as_input();
-    gpio.wait_for_high().await;
+    match wait {
+        GpioWait::High => gpio.wait_for_high().await,
+        GpioWait::Low => gpio.wait_for_low().await,
+        GpioWait::RisingEdge => gpio.wait_for_rising_edge().await,
+        GpioWait::FallingEdge => gpio.wait_for_falling_edge().await,
+        GpioWait::AnyEdge => gpio.wait_for_any_edge().await,
+    }
 
     Ok(())
 }
 
+#[embassy_executor::task(pool_size = NUM_GPIOS)]
+async fn gpio_wait_for_high_handler(
+    context: TaskContext,
+    header: VarHeader,
+    req: GpioWaitRequest,
+    sender: Sender<AppTx>,
+) {
+    let reply = gpio_wait(&context, &req, GpioWait::High).await;
+    let _ = sender.reply::<GpioWaitForHigh>(header.seq_no, &reply).await;
+}
+
+#[embassy_executor::task(pool_size = NUM_GPIOS)]
 async fn gpio_wait_for_low_handler(
-    context: &mut Context,
-    _header: VarHeader,
+    context: TaskContext,
+    header: VarHeader,
     req: GpioWaitRequest,
-) -> GpioWaitResponse {
-    let pin = req.pin;
-    let gpio = &mut context.gpios[usize::from(pin)];
+    sender: Sender<AppTx>,
+) {
+    let reply = gpio_wait(&context, &req, GpioWait::Low).await;
+    let _ = sender.reply::<GpioWaitForLow>(header.seq_no, &reply).await;
+}
 
-    gpio.set_as_input();
-    gpio.wait_for_low().await;
+#[embassy_executor::task(pool_size = NUM_GPIOS)]
+async fn gpio_wait_for_rising_handler(
+    context: TaskContext,
+    header: VarHeader,
+    req: GpioWaitRequest,
+    sender: Sender<AppTx>,
+) {
+    let reply = gpio_wait(&context, &req, GpioWait::RisingEdge).await;
+    let _ = sender.reply::<GpioWaitForRising>(header.seq_no, &reply).await;
+}
 
-    Ok(())
+#[embassy_executor::task(pool_size = NUM_GPIOS)]
+async fn gpio_wait_for_falling_handler(
+    context: TaskContext,
+    header: VarHeader,
+    req: GpioWaitRequest,
+    sender: Sender<AppTx>,
+) {
+    let reply = gpio_wait(&context, &req, GpioWait::FallingEdge).await;
+    let _ = sender.reply::<GpioWaitForFalling>(header.seq_no, &reply).await;
 }
 
-async fn gpio_wait_for_rising_handler(
-    context: &mut Context,
-    _header: VarHeader,
+#[embassy_executor::task(pool_size = NUM_GPIOS)]
+async fn gpio_wait_for_any_handler(
+    context: TaskContext,
+    header: VarHeader,
     req: GpioWaitRequest,
-) -> GpioWaitResponse {
-    let pin = req.pin;
-    let gpio = &mut context.gpios[usize::from(pin)];
+    sender: Sender<AppTx>,
+) {
+    let reply = gpio_wait(&context, &req, GpioWait::AnyEdge).await;
+    let _ = sender.reply::<GpioWaitForAny>(header.seq_no, &reply).await;
+}
 
-    gpio.set_as_input();
-    gpio.wait_for_rising_edge().await;
+#[embassy_executor::task(pool_size = 2)]
+async fn gpio_measure_handler(context: TaskContext, header: VarHeader, req: GpioMeasureRequest, sender: Sender<AppTx>) {
+    let reply = gpio_measure(&context, &req).await;
+    let _ = sender.reply::<GpioMeasure>(header.seq_no, &reply).await;
+}
 
-    Ok(())
+/// Check `req` and measure its pin, answering once the gate time is
+/// over.
+async fn gpio_measure(context: &TaskContext, req: &GpioMeasureRequest) -> GpioMeasureResponse {
+    let pin = usize::from(req.pin);
+    if pin >= NUM_GPIOS {
+        return Err(GpioMeasureFail::InvalidPin);
+    }
+
+    if !(1..=GPIO_MEASURE_MAX_GATE_MS).contains(&req.gate_ms) {
+        return Err(GpioMeasureFail::InvalidGate);
+    }
+
+    let mut capture = context.capture.try_lock().map_err(|_| GpioMeasureFail::Running)?;
+    let gate = Duration::from_millis(req.gate_ms.into());
+
+    Ok(measure::run(&mut capture, (FIRST_GPIO_PIN + pin) as u8, gate).await)
 }
 
-async fn gpio_wait_for_falling_handler(
-    context: &mut Context,
-    _header: VarHeader,
-    req: GpioWaitRequest,
-) -> GpioWaitResponse {
-    let pin = req.pin;
-    let gpio = &mut context.gpios[usize::from(pin)];
+/// SCL frequency `embassy_rp` ends up with when asked for `frequency`,
+/// following the same divider computation.
+///
+/// The I2C block holds SCL high for `hcnt + spklen + 7` cycles and low
+/// for `lcnt + 1` cycles. The rise time of SCL, which depends on the
+/// pull-ups and the load of the bus, lowers the rate further.
+fn i2c_actual_frequency(frequency: u32) -> Result<u32, I2cSetConfigFail> {
+    let fail = I2cSetConfigFail::FrequencyOutOfRange {
+        min: I2C_MIN_FREQUENCY,
+        max: I2C_MAX_FREQUENCY,
+    };
 
-    gpio.set_as_input();
-    gpio.wait_for_falling_edge().await;
+    if !(I2C_MIN_FREQUENCY..=I2C_MAX_FREQUENCY).contains(&frequency) {
+        return Err(fail);
+    }
 
-    Ok(())
+    let clk_base = embassy_rp::clocks::clk_peri_freq();
+    let period = (clk_base + frequency / 2) / frequency;
+    let lcnt = period * 3 / 5;
+    let hcnt = period - lcnt;
+
+    if !(8..=0xffff).contains(&lcnt) || !(8..=0xffff).contains(&hcnt) {
+        return Err(fail);
+    }
+
+    // Spike filter length, as programmed by `embassy_rp`
+    let spklen = if lcnt < 16 { 1 } else { lcnt / 16 };
+
+    Ok(clk_base / ((hcnt + spklen + 7) + (lcnt + 1)))
 }
 
-async fn gpio_wait_for_any_handler(
+/// SCK frequency `embassy_rp` ends up with when asked for `frequency`,
+/// following the same prescaler computation.
+fn spi_actual_frequency(frequency: u32) -> Result<u32, SpiSetConfigFail> {
+    let fail = SpiSetConfigFail::FrequencyOutOfRange {
+        min: SPI_MIN_FREQUENCY,
+        max: SPI_MAX_FREQUENCY,
+    };
+
+    if !(SPI_MIN_FREQUENCY..=SPI_MAX_FREQUENCY).contains(&frequency) {
+        return Err(fail);
+    }
+
+    // clk_peri / (presc * postdiv) with an even presc in 2..=254 and
+    // postdiv in 1..=256. embassy-rp panics if the ratio is too large.
+    let clk_peri = embassy_rp::clocks::clk_peri_freq();
+    let ratio = clk_peri.div_ceil(frequency * 2);
+    if ratio > 127 * 256 {
+        return Err(fail);
+    }
+
+    let presc = ratio.div_ceil(256);
+    let postdiv = if presc == 1 { ratio } else { ratio.div_ceil(presc) };
+
+    Ok(clk_peri / (presc * 2 * postdiv))
+}
+
+async fn i2c_set_config_handler(
     context: &mut Context,
     _header: VarHeader,
-    req: GpioWaitRequest,
-) -> GpioWaitResponse {
-    let pin = req.pin;
-    let gpio = &mut context.gpios[usize::from(pin)];
+    req: I2cSetConfigRequest,
+) -> I2cSetConfigResponse {
+    let bus = usize::from(req.bus);
+    let i2c = context.i2c.get(bus).ok_or(I2cSetConfigFail::InvalidBus)?;
+    let actual = i2c_actual_frequency(req.config.frequency)?;
 
-    gpio.set_as_input();
-    gpio.wait_for_any_edge().await;
+    // A target keeps the controller, it's programmed once stopped
+    if let Some(mut i2c) = i2c_controller(i2c, bus).await {
+        let mut i2c_config = i2c::Config::default();
+        i2c_config.frequency = req.config.frequency;
 
-    Ok(())
+        i2c.set_config(&i2c_config)
+            .map_err(|_| I2cSetConfigFail::FrequencyOutOfRange {
+                min: I2C_MIN_FREQUENCY,
+                max: I2C_MAX_FREQUENCY,
+            })?;
+    }
+    context.config.i2c[bus] = req.config;
+    context.config.i2c_actual_frequency[bus] = actual;
+
+    Ok(actual)
 }
 
-async fn set_config_handler(
+async fn spi_set_config_handler(
     context: &mut Context,
     _header: VarHeader,
-    req: SetConfigurationRequest,
-) -> SetConfigurationResponse {
-    let mut i2c_config = i2c::Config::default();
-    let mut spi_config = spi::Config::default();
+    req: SpiSetConfigRequest,
+) -> SpiSetConfigResponse {
+    let bus = usize::from(req.bus);
+    let spi = context.spi.get_mut(bus).ok_or(SpiSetConfigFail::InvalidBus)?;
+
+    // Checked before reaching embassy-rp, which panics on frequencies
+    // the prescalers can't reach
+    let actual = spi_actual_frequency(req.config.frequency)?;
+
+    // A target keeps the block, it's programmed by the next request
+    // once stopped
+    if !spi_target::is_running(bus) {
+        spi_apply(spi, &mut context.spi_applied[bus], &req.config);
+    }
+    context.config.spi[bus] = req.config;
+    context.config.spi_actual_frequency[bus] = actual;
+
+    Ok(actual)
+}
 
-    i2c_config.frequency = req.i2c_frequency;
-    spi_config.frequency = req.spi_frequency;
-    spi_config.phase = match req.spi_phase {
+/// Program the SPI block for `config`, unless it already is. Word size
+/// and bit order are handled in software, see `spi_wire_order`.
+fn spi_apply(spi: &mut SpiBus, applied: &mut SpiConfig, config: &SpiConfig) {
+    if applied.frequency == config.frequency && applied.phase == config.phase && applied.polarity == config.polarity {
+        return;
+    }
+
+    spi.set_config(&spi_block_config(config));
+    *applied = *config;
+}
+
+fn spi_block_config(config: &SpiConfig) -> spi::Config {
+    let mut spi_config = spi::Config::default();
+    spi_config.frequency = config.frequency;
+    spi_config.phase = match config.phase {
         SpiPhase::CaptureOnFirstTransition => Phase::CaptureOnFirstTransition,
         SpiPhase::CaptureOnSecondTransition => Phase::CaptureOnSecondTransition,
     };
-    spi_config.polarity = match req.spi_polarity {
+    spi_config.polarity = match config.polarity {
         SpiPolarity::IdleLow => Polarity::IdleLow,
         SpiPolarity::IdleHigh => Polarity::IdleHigh,
     };
 
-    context.spi.set_config(&spi_config);
-    context.i2c.set_config(&i2c_config).map_err(|_| SetConfigurationFail)
+    spi_config
+}
+
+async fn get_config_handler(context: &mut Context, _header: VarHeader, _req: ()) -> Configuration {
+    context.config
 }
 
 async fn version_handler(_context: &mut Context, _header: VarHeader, _req: ()) -> VersionInfo {
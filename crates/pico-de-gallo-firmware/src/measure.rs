@@ -0,0 +1,112 @@
+//! Frequency counter and duty cycle meter for GPIOs, with the first two
+//! state machines of PIO2, lent by the logic analyzer.
+//!
+//! One state machine counts rising edges, the other samples the level
+//! every three cycles and counts the samples reading high. Both count
+//! down from `u32::MAX` in X and Y, read back once the gate time is
+//! over. The pin is read without mapping it, as for captures.
+
+use crate::capture::Capture;
+use embassy_rp::pac;
+use embassy_rp::pio::Config;
+use embassy_time::{Duration, Instant, Timer};
+use pico_de_gallo_internal::GpioMeasurement;
+
+/// Measure `pin` for `gate`.
+pub async fn run(capture: &mut Capture, pin: u8, gate: Duration) -> GpioMeasurement {
+    let (common, counter, sampler) = capture.pio();
+
+    let counter_program = pio::pio_asm!(
+        "    mov x, !null",
+        ".wrap_target",
+        "edge:",
+        "    wait 0 pin 0",
+        "    wait 1 pin 0",
+        "    jmp x-- edge",
+        ".wrap",
+    );
+    // The jump pin being `pin`, either path takes three cycles
+    let sampler_program = pio::pio_asm!(
+        "    mov x, !null",
+        "    mov y, !null",
+        ".wrap_target",
+        "sample:",
+        "    jmp y-- tick",
+        "tick:",
+        "    jmp pin high",
+        "    jmp sample",
+        "high:",
+        "    jmp x-- sample",
+        ".wrap",
+    );
+    // Registers are read back through the RX FIFO
+    let read = pio::pio_asm!(
+        "    mov isr, x",
+        "    push noblock",
+        "    mov isr, y",
+        "    push noblock",
+    )
+    .program
+    .code;
+
+    // Nothing else is loaded between captures, they fit
+    let counter_program = common.load_program(&counter_program.program);
+    let sampler_program = common.load_program(&sampler_program.program);
+
+    pac::PADS_BANK0.gpio(usize::from(pin)).modify(|w| {
+        w.set_ie(true);
+        w.set_iso(false);
+    });
+
+    let mut config = Config::default();
+    config.use_program(&counter_program, &[]);
+    counter.set_config(&config);
+    pac::PIO2.sm(0).pinctrl().modify(|w| w.set_in_base(pin));
+    pac::PIO2.sm(0).shiftctrl().modify(|w| w.set_in_count(1));
+    counter.clear_fifos();
+    counter.restart();
+
+    let mut config = Config::default();
+    config.use_program(&sampler_program, &[]);
+    sampler.set_config(&config);
+    pac::PIO2.sm(1).execctrl().modify(|w| w.set_jmp_pin(pin));
+    sampler.clear_fifos();
+    sampler.restart();
+
+    let start = Instant::now();
+    counter.set_enable(true);
+    sampler.set_enable(true);
+
+    Timer::after(gate).await;
+
+    counter.set_enable(false);
+    sampler.set_enable(false);
+    let gate_us = start.elapsed().as_micros();
+
+    // SAFETY: the state machines are stopped, reading their registers
+    // leaves them alone
+    unsafe {
+        for &instruction in &read {
+            counter.exec_instr(instruction);
+            sampler.exec_instr(instruction);
+        }
+    }
+
+    // Counted down from u32::MAX
+    let edges = !counter.rx().try_pull().unwrap_or(u32::MAX);
+    let high_samples = !sampler.rx().try_pull().unwrap_or(u32::MAX);
+    let samples = !sampler.rx().try_pull().unwrap_or(u32::MAX);
+
+    // SAFETY: the state machines are stopped
+    unsafe {
+        common.free_instr(counter_program.used_memory);
+        common.free_instr(sampler_program.used_memory);
+    }
+
+    GpioMeasurement {
+        edges,
+        gate_us,
+        samples,
+        high_samples,
+    }
+}
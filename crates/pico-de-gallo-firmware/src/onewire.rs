@@ -0,0 +1,195 @@
+//! 1-Wire bus master, timed by the CPU on a GPIO.
+//!
+//! Standard speed timings, as recommended by Maxim's application note
+//! 126. The parts of a time slot which must not stretch run in critical
+//! sections.
+
+use embassy_futures::yield_now;
+use embassy_rp::gpio::{Flex, Level, Pull};
+use embassy_time::{Duration, Timer, block_for};
+use pico_de_gallo_internal::{ONEWIRE_ALARM_SEARCH, ONEWIRE_SEARCH_ROM, OneWireFail, OneWireRom};
+
+const fn us(micros: u64) -> Duration {
+    Duration::from_micros(micros)
+}
+
+pub struct OneWire<'a> {
+    pin: &'a mut Flex<'static>,
+}
+
+impl<'a> OneWire<'a> {
+    /// Release the bus, which the pull-up then keeps high.
+    pub fn new(pin: &'a mut Flex<'static>) -> Self {
+        pin.set_pull(Pull::Up);
+        pin.set_level(Level::Low);
+        pin.set_as_input();
+
+        Self { pin }
+    }
+
+    fn low(&mut self) {
+        self.pin.set_as_output();
+    }
+
+    fn release(&mut self) {
+        self.pin.set_as_input();
+    }
+
+    /// Issue a reset pulse. Returns whether a device answered with a
+    /// presence pulse.
+    pub async fn reset(&mut self) -> Result<bool, OneWireFail> {
+        if self.pin.is_low() {
+            return Err(OneWireFail::BusLow);
+        }
+
+        self.low();
+        Timer::after(us(480)).await;
+
+        let presence = critical_section::with(|_| {
+            self.release();
+            block_for(us(70));
+            self.pin.is_low()
+        });
+
+        Timer::after(us(410)).await;
+
+        if self.pin.is_low() {
+            return Err(OneWireFail::BusLow);
+        }
+
+        Ok(presence)
+    }
+
+    /// Run one time slot writing `bit`, returning the level sampled.
+    /// Writing a 1 is how a bit is read.
+    pub fn touch_bit(&mut self, bit: bool) -> bool {
+        let sampled = critical_section::with(|_| {
+            self.low();
+
+            if bit {
+                block_for(us(6));
+                self.release();
+                block_for(us(9));
+                self.pin.is_high()
+            } else {
+                block_for(us(60));
+                self.release();
+                false
+            }
+        });
+
+        // Recovery until the next slot
+        block_for(if bit { us(55) } else { us(10) });
+
+        sampled
+    }
+
+    /// Write `byte`, LSB first.
+    pub async fn write_byte(&mut self, byte: u8) {
+        for i in 0..8 {
+            self.touch_bit(byte & (1 << i) != 0);
+        }
+
+        yield_now().await;
+    }
+
+    /// Read a byte, LSB first.
+    pub async fn read_byte(&mut self) -> u8 {
+        let mut byte = 0;
+
+        for i in 0..8 {
+            if self.touch_bit(true) {
+                byte |= 1 << i;
+            }
+        }
+
+        yield_now().await;
+
+        byte
+    }
+
+    /// Drive the bus high for `duration`, powering parasite devices.
+    pub async fn strong_pullup(&mut self, duration: Duration) {
+        self.pin.set_level(Level::High);
+        self.pin.set_as_output();
+        Timer::after(duration).await;
+        self.pin.set_as_input();
+        self.pin.set_level(Level::Low);
+    }
+
+    /// Find the ROM codes of the devices on the bus, or of those in an
+    /// alarm state, until `roms` is full. See Maxim's application note
+    /// 187.
+    pub async fn search<const N: usize>(
+        &mut self,
+        alarm: bool,
+        roms: &mut heapless::Vec<OneWireRom, N>,
+    ) -> Result<(), OneWireFail> {
+        let command = if alarm {
+            ONEWIRE_ALARM_SEARCH
+        } else {
+            ONEWIRE_SEARCH_ROM
+        };
+        let mut rom = [0u8; 8];
+        // Bit, counted from 1, where the previous pass took the 0 branch
+        // last. None left once 0.
+        let mut last_discrepancy = 0;
+
+        loop {
+            if !self.reset().await? {
+                return Ok(());
+            }
+
+            self.write_byte(command).await;
+
+            let mut discrepancy = 0;
+
+            for bit in 1..=64 {
+                let (byte, mask) = ((bit - 1) / 8, 1 << ((bit - 1) % 8));
+                let id = self.touch_bit(true);
+                let complement = self.touch_bit(true);
+
+                let direction = match (id, complement) {
+                    // Nobody answers the alarm search
+                    (true, true) if bit == 1 => return Ok(()),
+                    // Devices went away
+                    (true, true) => return Err(OneWireFail::SearchFailed),
+                    (id, complement) if id != complement => id,
+                    // Devices differ on this bit
+                    _ => {
+                        let direction = if bit < last_discrepancy {
+                            rom[byte] & mask != 0
+                        } else {
+                            bit == last_discrepancy
+                        };
+
+                        if !direction {
+                            discrepancy = bit;
+                        }
+
+                        direction
+                    }
+                };
+
+                if direction {
+                    rom[byte] |= mask;
+                } else {
+                    rom[byte] &= !mask;
+                }
+
+                self.touch_bit(direction);
+            }
+
+            let found = OneWireRom(rom);
+            if !found.crc_valid() {
+                return Err(OneWireFail::SearchFailed);
+            }
+
+            if roms.push(found).is_err() || discrepancy == 0 {
+                return Ok(());
+            }
+
+            last_discrepancy = discrepancy;
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! SMBus protocols.
+//!
+//! They're bit banged at 100 kHz rather than run on the I2C controller,
+//! which can neither issue quick commands nor follow the byte count of
+//! a block read.
+
+use crate::bus::{SoftI2c, TransferFailed};
+use pico_de_gallo_internal::{SMBUS_ALERT_RESPONSE_ADDRESS, SMBUS_BLOCK_MAX_LEN, SmbusFail, SmbusOperation, smbus_pec};
+
+impl From<TransferFailed> for SmbusFail {
+    fn from(_: TransferFailed) -> Self {
+        Self::NoAcknowledge
+    }
+}
+
+/// Run `operation` with the target at `address`, storing what's read
+/// into `buf`. Returns the number of bytes read.
+pub async fn run(
+    bus: &SoftI2c,
+    address: u8,
+    pec: bool,
+    operation: &SmbusOperation<'_>,
+    buf: &mut [u8],
+) -> Result<usize, SmbusFail> {
+    if !bus.is_idle() {
+        return Err(SmbusFail::BusBusy);
+    }
+
+    let mut transfer = Transfer { bus, pec, crc: 0 };
+    let result = transfer.run(address, operation, buf).await;
+    bus.stop().await;
+
+    result
+}
+
+/// Read the Alert Response Address, returning the address of the
+/// device which answered. That device releases SMBALERT#.
+pub async fn alert_response(bus: &SoftI2c, pec: bool) -> Result<u8, SmbusFail> {
+    if !bus.is_idle() {
+        return Err(SmbusFail::BusBusy);
+    }
+
+    let mut transfer = Transfer { bus, pec, crc: 0 };
+    let result = transfer.alert_response().await;
+    bus.stop().await;
+
+    result
+}
+
+/// Transfer keeping track of the PEC of the bytes going over the wire.
+struct Transfer<'a> {
+    bus: &'a SoftI2c,
+    pec: bool,
+    crc: u8,
+}
+
+impl Transfer<'_> {
+    async fn run(&mut self, address: u8, operation: &SmbusOperation<'_>, buf: &mut [u8]) -> Result<usize, SmbusFail> {
+        match *operation {
+            SmbusOperation::QuickCommand { read } => {
+                self.bus.start().await?;
+                self.bus.write_byte((address << 1) | u8::from(read)).await?;
+                Ok(0)
+            }
+            SmbusOperation::SendByte(value) => {
+                self.start_write(address).await?;
+                self.write(value).await?;
+                self.end_write().await?;
+                Ok(0)
+            }
+            SmbusOperation::ReceiveByte => {
+                self.start_read(address).await?;
+                buf[0] = self.read(true).await?;
+                self.end_read().await?;
+                Ok(1)
+            }
+            SmbusOperation::WriteByte { command, value } => {
+                self.start_write(address).await?;
+                self.write(command).await?;
+                self.write(value).await?;
+                self.end_write().await?;
+                Ok(0)
+            }
+            SmbusOperation::WriteWord { command, value } => {
+                self.start_write(address).await?;
+                self.write(command).await?;
+                self.write_word(value).await?;
+                self.end_write().await?;
+                Ok(0)
+            }
+            SmbusOperation::ReadByte { command } => {
+                self.start_write(address).await?;
+                self.write(command).await?;
+                self.start_read(address).await?;
+                buf[0] = self.read(true).await?;
+                self.end_read().await?;
+                Ok(1)
+            }
+            SmbusOperation::ReadWord { command } => {
+                self.start_write(address).await?;
+                self.write(command).await?;
+                self.start_read(address).await?;
+                self.read_word(&mut buf[..2]).await?;
+                self.end_read().await?;
+                Ok(2)
+            }
+            SmbusOperation::BlockWrite { command, data } => {
+                if data.len() > SMBUS_BLOCK_MAX_LEN {
+                    return Err(SmbusFail::BlockTooLong);
+                }
+
+                self.start_write(address).await?;
+                self.write(command).await?;
+                self.write(data.len() as u8).await?;
+                for &byte in data {
+                    self.write(byte).await?;
+                }
+                self.end_write().await?;
+                Ok(0)
+            }
+            SmbusOperation::BlockRead { command } => {
+                self.start_write(address).await?;
+                self.write(command).await?;
+                self.start_read(address).await?;
+
+                // The count decides whether more bytes follow, it can't
+                // be acknowledged before being read
+                let pec = self.pec;
+                let count = self.bus.read_byte_with(|count| count > 0 || pec).await?;
+                self.crc = smbus_pec(self.crc, &[count]);
+
+                let count = usize::from(count);
+                if count > SMBUS_BLOCK_MAX_LEN {
+                    return Err(SmbusFail::BlockTooLong);
+                }
+
+                for (i, byte) in buf[..count].iter_mut().enumerate() {
+                    *byte = self.read(i + 1 == count).await?;
+                }
+                self.end_read().await?;
+                Ok(count)
+            }
+            SmbusOperation::ProcessCall { command, value } => {
+                self.start_write(address).await?;
+                self.write(command).await?;
+                self.write_word(value).await?;
+                self.start_read(address).await?;
+                self.read_word(&mut buf[..2]).await?;
+                self.end_read().await?;
+                Ok(2)
+            }
+        }
+    }
+
+    async fn alert_response(&mut self) -> Result<u8, SmbusFail> {
+        self.start_read(SMBUS_ALERT_RESPONSE_ADDRESS).await?;
+        let byte = self.read(true).await?;
+        self.end_read().await?;
+
+        // The address is sent as if the device was addressing itself,
+        // bit 0 is meaningless
+        Ok(byte >> 1)
+    }
+
+    async fn start_write(&mut self, address: u8) -> Result<(), SmbusFail> {
+        self.bus.start().await?;
+        self.write(address << 1).await
+    }
+
+    /// START, or repeated START after a write, addressing the target
+    /// for reading.
+    async fn start_read(&mut self, address: u8) -> Result<(), SmbusFail> {
+        self.bus.start().await?;
+        self.write((address << 1) | 1).await
+    }
+
+    async fn write(&mut self, byte: u8) -> Result<(), SmbusFail> {
+        self.crc = smbus_pec(self.crc, &[byte]);
+        Ok(self.bus.write_byte(byte).await?)
+    }
+
+    async fn write_word(&mut self, value: u16) -> Result<(), SmbusFail> {
+        let [lo, hi] = value.to_le_bytes();
+        self.write(lo).await?;
+        self.write(hi).await
+    }
+
+    /// Read a data byte, acknowledged unless it's the `last` one and
+    /// no PEC follows.
+    async fn read(&mut self, last: bool) -> Result<u8, SmbusFail> {
+        let byte = self.bus.read_byte(!last || self.pec).await?;
+        self.crc = smbus_pec(self.crc, &[byte]);
+        Ok(byte)
+    }
+
+    async fn read_word(&mut self, buf: &mut [u8]) -> Result<(), SmbusFail> {
+        buf[0] = self.read(false).await?;
+        buf[1] = self.read(true).await?;
+        Ok(())
+    }
+
+    async fn end_write(&mut self) -> Result<(), SmbusFail> {
+        if self.pec {
+            self.write(self.crc).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn end_read(&mut self) -> Result<(), SmbusFail> {
+        if self.pec {
+            let expected = self.crc;
+            if self.bus.read_byte(false).await? != expected {
+                return Err(SmbusFail::PecMismatch);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,455 @@
+//! Passive bus sniffer, decoding the traffic of another controller with
+//! the first state machine of PIO2, lent by the logic analyzer.
+//!
+//! On I2C, the state machine reports the level of SDA as SCL rises, and
+//! SDA changing while SCL is high, i.e. STARTs and STOPs, as 4-bit codes
+//! the CPU decodes into transfers. On SPI, it samples the pins of the
+//! bus on every clock edge while chip select is asserted, the CPU
+//! shifting MOSI and MISO into bytes and framing them by chip select.
+//!
+//! Pins are read without mapping them, as for captures. The SCK and
+//! MOSI outputs of the SPI block are disabled though, so that it doesn't
+//! fight the controller sniffed.
+
+use crate::AppTx;
+use crate::capture::Capture;
+use core::cell::Cell;
+use embassy_futures::select::{Either, select};
+use embassy_futures::yield_now;
+use embassy_rp::pac;
+use embassy_rp::pio::{Config, FifoJoin, ShiftConfig, ShiftDirection};
+use embassy_sync::blocking_mutex::{self, raw::ThreadModeRawMutex};
+use embassy_sync::signal::Signal;
+use embassy_time::Instant;
+use pico_de_gallo_internal::{
+    I2cSniffByte, I2cSniffEvent, I2cSniffTopic, NUM_SPI_BUSES, SNIFF_MAX_LEN, SpiPhase, SpiPolarity, SpiSniffEvent,
+    SpiSniffTopic,
+};
+use postcard_rpc::header::VarSeq;
+use postcard_rpc::server::Sender;
+
+/// Codes of the I2C program, 8 to a word from bit 0 on. Words pushed
+/// early, at a STOP, are padded with `I2C_NONE`.
+const I2C_NONE: u32 = 0;
+const I2C_LOW: u32 = 1;
+const I2C_HIGH: u32 = 2;
+const I2C_START: u32 = 3;
+const I2C_STOP: u32 = 4;
+
+/// First pin sampled on each SPI bus, along with its MISO relative to
+/// it. SCK and MOSI come at the same offsets on both buses, so that one
+/// program serves both.
+const SPI_PINS: [(u8, u8); NUM_SPI_BUSES] = [(4, 0), (24, 4)];
+const SPI_SCK: u8 = 2;
+const SPI_MOSI: u8 = 3;
+
+/// Set in every SPI sample, telling them from the padding of words
+/// pushed early.
+const SPI_SAMPLE: u8 = 0x80;
+
+/// SCK and MOSI of each SPI bus, driven by its block.
+const SPI_OUTPUTS: [[usize; 2]; NUM_SPI_BUSES] = [[6, 7], [26, 27]];
+
+/// Bus being sniffed.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Sniffed {
+    I2c(usize),
+    Spi(usize),
+}
+
+static SNIFFED: blocking_mutex::Mutex<ThreadModeRawMutex, Cell<Option<Sniffed>>> =
+    blocking_mutex::Mutex::new(Cell::new(None));
+
+static STOP: Signal<ThreadModeRawMutex, ()> = Signal::new();
+
+/// Bus being sniffed, if any. Set from the start of the sniffer until
+/// the bus is usable as a controller again.
+pub fn sniffed() -> Option<Sniffed> {
+    SNIFFED.lock(Cell::get)
+}
+
+/// Reserve `sniffed`. Returns `false` if a bus is sniffed already.
+pub fn claim(sniffed: Sniffed) -> bool {
+    SNIFFED.lock(|cell| {
+        if cell.get().is_some() {
+            return false;
+        }
+
+        cell.set(Some(sniffed));
+        STOP.reset();
+        true
+    })
+}
+
+/// Stop the sniffer running, which releases its bus once done.
+pub fn stop() {
+    STOP.signal(());
+}
+
+/// Decode I2C `bus`, wired to `scl` and `sda`, until stopped, publishing
+/// every transfer through `sender`.
+pub async fn i2c(capture: &mut Capture, bus: usize, scl: u8, sda: u8, sender: &Sender<AppTx>) {
+    let (common, sm, _) = capture.pio();
+
+    // SDA is the only input, the jump pin being SCL. Y holds the level
+    // of SDA while SCL is high, so that a change shows up.
+    let program = pio::pio_asm!(
+        "low:",
+        "    jmp pin high",
+        "    jmp low",
+        "high:",
+        "    mov x, pins",
+        "    mov y, x",
+        "    jmp !x zero",
+        "    set x, 2",
+        "    jmp bit",
+        "zero:",
+        "    set x, 1",
+        "bit:",
+        "    in x, 4",
+        "watch:",
+        "    jmp pin check",
+        "    jmp low",
+        "check:",
+        "    mov x, pins",
+        "    jmp x!=y changed",
+        "    jmp watch",
+        "changed:",
+        "    mov y, x",
+        "    set x, 4",
+        "    jmp !y start",
+        "    in x, 4",
+        "    push block",
+        "    jmp watch",
+        "start:",
+        "    set x, 3",
+        "    in x, 4",
+        "    jmp watch",
+    );
+    // Nothing else is loaded between captures, it fits
+    let program = common.load_program(&program.program);
+
+    read_pins(&[scl, sda]);
+
+    let mut config = Config::default();
+    config.use_program(&program, &[]);
+    config.fifo_join = FifoJoin::RxOnly;
+    config.shift_in = ShiftConfig {
+        auto_fill: true,
+        threshold: 32,
+        direction: ShiftDirection::Right,
+    };
+    sm.set_config(&config);
+    pac::PIO2.sm(0).pinctrl().modify(|w| w.set_in_base(sda));
+    pac::PIO2.sm(0).shiftctrl().modify(|w| w.set_in_count(1));
+    pac::PIO2.sm(0).execctrl().modify(|w| w.set_jmp_pin(scl));
+    sm.clear_fifos();
+    sm.restart();
+    sm.set_enable(true);
+
+    let mut transfer: Option<Transfer> = None;
+    let mut seq_no: u16 = 0;
+
+    loop {
+        let word = match select(sm.rx().wait_pull(), STOP.wait()).await {
+            Either::First(word) => word,
+            Either::Second(()) => break,
+        };
+
+        for code in (0..8).map(|n| (word >> (4 * n)) & 0xf) {
+            match code {
+                I2C_LOW | I2C_HIGH => {
+                    if let Some(transfer) = &mut transfer {
+                        transfer.bit(code == I2C_HIGH);
+                    }
+                }
+                I2C_START | I2C_STOP => {
+                    if let Some(event) = transfer.take().and_then(|t| t.event(bus, code == I2C_STOP)) {
+                        let _ = sender.publish::<I2cSniffTopic>(VarSeq::Seq2(seq_no), &event).await;
+                        seq_no = seq_no.wrapping_add(1);
+                    }
+
+                    if code == I2C_START {
+                        transfer = Some(Transfer::new());
+                    }
+                }
+                _ => debug_assert_eq!(code, I2C_NONE),
+            }
+        }
+    }
+
+    sm.set_enable(false);
+    // SAFETY: the state machine is stopped
+    unsafe { common.free_instr(program.used_memory) };
+
+    release();
+}
+
+/// I2C transfer being decoded.
+struct Transfer {
+    timestamp_us: u64,
+    /// Bits of the byte being received followed by its acknowledge, the
+    /// first in the highest bit.
+    bits: u16,
+    received: u8,
+    address: Option<I2cSniffByte>,
+    data: heapless::Vec<I2cSniffByte, SNIFF_MAX_LEN>,
+    count: u32,
+}
+
+impl Transfer {
+    fn new() -> Self {
+        Self {
+            timestamp_us: Instant::now().as_micros(),
+            bits: 0,
+            received: 0,
+            address: None,
+            data: heapless::Vec::new(),
+            count: 0,
+        }
+    }
+
+    fn bit(&mut self, high: bool) {
+        self.bits = (self.bits << 1) | u16::from(high);
+        self.received += 1;
+
+        if self.received < 9 {
+            return;
+        }
+
+        let byte = I2cSniffByte {
+            value: (self.bits >> 1) as u8,
+            ack: self.bits & 1 == 0,
+        };
+        self.bits = 0;
+        self.received = 0;
+
+        if self.address.is_none() {
+            self.address = Some(byte);
+        } else {
+            let _ = self.data.push(byte);
+            self.count += 1;
+        }
+    }
+
+    /// The transfer, ended by a STOP if `stop`. The bit sampled as SCL
+    /// rose before it is left over, and dropped. `None` if no whole
+    /// address was received.
+    fn event(self, bus: usize, stop: bool) -> Option<I2cSniffEvent> {
+        let address = self.address?;
+
+        Some(I2cSniffEvent {
+            bus: bus as u8,
+            timestamp_us: self.timestamp_us,
+            address: address.value >> 1,
+            read: address.value & 1 != 0,
+            ack: address.ack,
+            data: self.data,
+            count: self.count,
+            stop,
+        })
+    }
+}
+
+/// Decode SPI `bus`, selected through pin `cs`, until stopped,
+/// publishing every selection through `sender`.
+pub async fn spi(
+    capture: &mut Capture,
+    bus: usize,
+    cs: u8,
+    polarity: SpiPolarity,
+    phase: SpiPhase,
+    sender: &Sender<AppTx>,
+) {
+    let (common, sm, _) = capture.pio();
+    let (base, miso) = SPI_PINS[bus];
+
+    // Five pins from the first one are sampled on the edge of SCK,
+    // unless deselected, the jump pin being chip select. X marks the
+    // sample.
+    let program = if (polarity == SpiPolarity::IdleLow) == (phase == SpiPhase::CaptureOnFirstTransition) {
+        pio::pio_asm!(
+            "    set x, 4",
+            ".wrap_target",
+            "idle:",
+            "    wait 0 pin 2",
+            "    wait 1 pin 2",
+            "    jmp pin idle",
+            "    in pins, 5",
+            "    in x, 3",
+            ".wrap",
+        )
+        .program
+    } else {
+        pio::pio_asm!(
+            "    set x, 4",
+            ".wrap_target",
+            "idle:",
+            "    wait 1 pin 2",
+            "    wait 0 pin 2",
+            "    jmp pin idle",
+            "    in pins, 5",
+            "    in x, 3",
+            ".wrap",
+        )
+        .program
+    };
+    // Nothing else is loaded between captures, it fits
+    let program = common.load_program(&program);
+
+    read_pins(&[cs, base + miso, base + SPI_SCK, base + SPI_MOSI]);
+    for pin in SPI_OUTPUTS[bus] {
+        pac::IO_BANK0
+            .gpio(pin)
+            .ctrl()
+            .modify(|w| w.set_oeover(pac::io::vals::Oeover::DISABLE));
+    }
+
+    let mut config = Config::default();
+    config.use_program(&program, &[]);
+    config.fifo_join = FifoJoin::RxOnly;
+    config.shift_in = ShiftConfig {
+        auto_fill: true,
+        threshold: 32,
+        direction: ShiftDirection::Right,
+    };
+    sm.set_config(&config);
+    pac::PIO2.sm(0).pinctrl().modify(|w| w.set_in_base(base));
+    pac::PIO2.sm(0).shiftctrl().modify(|w| w.set_in_count(5));
+    pac::PIO2.sm(0).execctrl().modify(|w| w.set_jmp_pin(cs));
+    sm.clear_fifos();
+    sm.restart();
+    sm.set_enable(true);
+
+    let is_selected = || pac::SIO.gpio_in(0).read() & (1 << cs) == 0;
+    let push = pio::InstructionOperands::PUSH {
+        if_full: false,
+        block: false,
+    }
+    .encode();
+    let mut seq_no: u16 = 0;
+
+    'run: loop {
+        while !is_selected() {
+            if STOP.signaled() {
+                break 'run;
+            }
+
+            yield_now().await;
+        }
+
+        let mut selection = Selection::new(miso);
+        let stopped = loop {
+            // Sampled before the FIFO, edges can't be sampled once
+            // deselected
+            let deselected = !is_selected();
+
+            while let Some(word) = sm.rx().try_pull() {
+                selection.receive(word);
+            }
+
+            if deselected {
+                // Samples short of a word wait in the ISR
+                // SAFETY: pushing leaves the state of the program alone
+                unsafe { sm.exec_instr(push) };
+                while let Some(word) = sm.rx().try_pull() {
+                    selection.receive(word);
+                }
+                break false;
+            }
+
+            if STOP.signaled() {
+                break true;
+            }
+
+            yield_now().await;
+        };
+
+        let event = SpiSniffEvent {
+            bus: bus as u8,
+            timestamp_us: selection.timestamp_us,
+            count: selection.count,
+            mosi: selection.mosi,
+            miso: selection.miso,
+        };
+        let _ = sender.publish::<SpiSniffTopic>(VarSeq::Seq2(seq_no), &event).await;
+        seq_no = seq_no.wrapping_add(1);
+
+        if stopped {
+            break;
+        }
+    }
+
+    sm.set_enable(false);
+    // SAFETY: the state machine is stopped
+    unsafe { common.free_instr(program.used_memory) };
+
+    for pin in SPI_OUTPUTS[bus] {
+        pac::IO_BANK0
+            .gpio(pin)
+            .ctrl()
+            .modify(|w| w.set_oeover(pac::io::vals::Oeover::NORMAL));
+    }
+
+    release();
+}
+
+/// SPI selection being decoded.
+struct Selection {
+    timestamp_us: u64,
+    /// Offset of MISO within samples.
+    miso_offset: u8,
+    /// Bits of the bytes being received, the first in the highest bit.
+    mosi_bits: u8,
+    miso_bits: u8,
+    received: u8,
+    count: u32,
+    mosi: heapless::Vec<u8, SNIFF_MAX_LEN>,
+    miso: heapless::Vec<u8, SNIFF_MAX_LEN>,
+}
+
+impl Selection {
+    fn new(miso_offset: u8) -> Self {
+        Self {
+            timestamp_us: Instant::now().as_micros(),
+            miso_offset,
+            mosi_bits: 0,
+            miso_bits: 0,
+            received: 0,
+            count: 0,
+            mosi: heapless::Vec::new(),
+            miso: heapless::Vec::new(),
+        }
+    }
+
+    /// Shift in the samples of `word`, the first in its lowest byte.
+    fn receive(&mut self, word: u32) {
+        for sample in word.to_le_bytes().into_iter().filter(|sample| sample & SPI_SAMPLE != 0) {
+            self.mosi_bits = (self.mosi_bits << 1) | ((sample >> SPI_MOSI) & 1);
+            self.miso_bits = (self.miso_bits << 1) | ((sample >> self.miso_offset) & 1);
+            self.received += 1;
+
+            if self.received == 8 {
+                let _ = self.mosi.push(self.mosi_bits);
+                let _ = self.miso.push(self.miso_bits);
+                self.count += 1;
+                self.received = 0;
+            }
+        }
+    }
+}
+
+/// Connect the input buffers of `pins`, which stay isolated until a
+/// peripheral claims them.
+fn read_pins(pins: &[u8]) {
+    for &pin in pins {
+        pac::PADS_BANK0.gpio(usize::from(pin)).modify(|w| {
+            w.set_ie(true);
+            w.set_iso(false);
+        });
+    }
+}
+
+fn release() {
+    SNIFFED.lock(|cell| cell.set(None));
+}
@@ -0,0 +1,360 @@
+//! SPI target emulation.
+//!
+//! `embassy_rp` only drives the SPI blocks as controllers, a bus
+//! emulating a target has its block switched to slave mode through the
+//! PAC. Bytes are moved by the CPU, which keeps the TX FIFO two frames
+//! ahead of the controller so that responses can follow commands.
+
+use crate::AppTx;
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+use embassy_futures::yield_now;
+use embassy_rp::pac;
+use embassy_sync::blocking_mutex::{self, raw::ThreadModeRawMutex};
+use embassy_sync::signal::Signal;
+use embassy_time::Instant;
+use pico_de_gallo_internal::{
+    NUM_SPI_BUSES, SPI_TARGET_MAX_COMMAND_LEN, SPI_TARGET_MAX_COMMANDS, SPI_TARGET_MAX_LEN, SpiPhase, SpiPolarity,
+    SpiTargetEvent, SpiTargetFail, SpiTargetTopic,
+};
+use postcard_rpc::header::VarSeq;
+use postcard_rpc::server::Sender;
+
+/// IO bank function handing a pin to its SPI block.
+const FUNCSEL_SPI: u8 = 1;
+
+/// Frames queued ahead of the controller. The response to a command
+/// starts in the second frame following it.
+const QUEUED_AHEAD: usize = 2;
+
+struct Command {
+    command: heapless::Vec<u8, SPI_TARGET_MAX_COMMAND_LEN>,
+    response: heapless::Vec<u8, SPI_TARGET_MAX_LEN>,
+}
+
+type SharedResponses = blocking_mutex::Mutex<ThreadModeRawMutex, RefCell<Responses>>;
+
+#[derive(Default)]
+struct Responses {
+    response: heapless::Vec<u8, SPI_TARGET_MAX_LEN>,
+    commands: heapless::Vec<Command, SPI_TARGET_MAX_COMMANDS>,
+}
+
+/// Target emulation state of an SPI bus.
+struct Slot {
+    /// Set from the start of the target until the block is programmed
+    /// as a controller again.
+    running: AtomicBool,
+    stop: Signal<ThreadModeRawMutex, ()>,
+    stopped: Signal<ThreadModeRawMutex, ()>,
+    /// Set when the responses change, so that the frames queued ahead
+    /// of the next selection are queued again.
+    changed: AtomicBool,
+    /// Kept across runs, so that they can be loaded beforehand.
+    responses: SharedResponses,
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Self {
+            running: AtomicBool::new(false),
+            stop: Signal::new(),
+            stopped: Signal::new(),
+            changed: AtomicBool::new(false),
+            responses: blocking_mutex::Mutex::new(RefCell::new(Responses {
+                response: heapless::Vec::new(),
+                commands: heapless::Vec::new(),
+            })),
+        }
+    }
+
+    fn update<R>(&self, f: impl FnOnce(&mut Responses) -> R) -> R {
+        let result = self.responses.lock(|responses| f(&mut responses.borrow_mut()));
+        self.changed.store(true, Ordering::Release);
+        result
+    }
+}
+
+static SLOTS: [Slot; NUM_SPI_BUSES] = [const { Slot::new() }; NUM_SPI_BUSES];
+
+/// Whether `bus` emulates a target, its block being unusable as a
+/// controller.
+pub fn is_running(bus: usize) -> bool {
+    SLOTS[bus].running.load(Ordering::Acquire)
+}
+
+/// Reserve `bus` for a target. Returns `false` if it already has one.
+pub fn claim(bus: usize) -> bool {
+    let slot = &SLOTS[bus];
+    let claimed = slot
+        .running
+        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+        .is_ok();
+
+    if claimed {
+        slot.stop.reset();
+        slot.stopped.reset();
+    }
+
+    claimed
+}
+
+/// Stop the target of `bus`, waiting for it to let go of the block.
+pub async fn stop(bus: usize) {
+    let slot = &SLOTS[bus];
+    slot.stop.signal(());
+    slot.stopped.wait().await;
+}
+
+/// Hand `bus` back to controller requests, once the block has been
+/// programmed again.
+pub fn release(bus: usize) {
+    SLOTS[bus].running.store(false, Ordering::Release);
+}
+
+/// Answer every selection of `bus` with `data`.
+pub fn load(bus: usize, data: &[u8]) -> Result<(), SpiTargetFail> {
+    let data = heapless::Vec::from_slice(data).map_err(|_| SpiTargetFail::InvalidLength)?;
+    SLOTS[bus].update(|responses| responses.response = data);
+
+    Ok(())
+}
+
+/// Answer `command` with `response` on `bus`.
+pub fn add_command(bus: usize, command: &[u8], response: &[u8]) -> Result<(), SpiTargetFail> {
+    let entry = Command {
+        command: heapless::Vec::from_slice(command)
+            .ok()
+            .filter(|command| !command.is_empty())
+            .ok_or(SpiTargetFail::InvalidLength)?,
+        response: heapless::Vec::from_slice(response).map_err(|_| SpiTargetFail::InvalidLength)?,
+    };
+
+    SLOTS[bus].update(|responses| {
+        match responses.commands.iter_mut().find(|c| c.command == entry.command) {
+            Some(existing) => *existing = entry,
+            None => responses.commands.push(entry).map_err(|_| SpiTargetFail::TableFull)?,
+        }
+
+        Ok(())
+    })
+}
+
+/// Forget the response and commands of `bus`.
+pub fn clear(bus: usize) {
+    SLOTS[bus].update(|responses| *responses = Responses::default());
+}
+
+/// Answer as a target on `bus`, selected through GPIO `cs`, until
+/// stopped, publishing every selection through `sender`.
+///
+/// The caller must hold `cs` throughout, and program the block as a
+/// controller again afterwards.
+pub async fn run(bus: usize, cs: usize, polarity: SpiPolarity, phase: SpiPhase, fill: u8, sender: &Sender<AppTx>) {
+    let slot = &SLOTS[bus];
+    let block = Block::take(bus, cs, polarity, phase);
+    let mut seq_no: u16 = 0;
+
+    'run: loop {
+        let mut selection = Selection::default();
+        slot.changed.store(false, Ordering::Release);
+        block.reset();
+        block.queue(&slot.responses, fill, &mut selection);
+
+        while !block.is_selected() {
+            if slot.stop.signaled() {
+                break 'run;
+            }
+
+            // Queued frames are stale
+            if slot.changed.swap(false, Ordering::AcqRel) {
+                selection = Selection::default();
+                block.reset();
+                block.queue(&slot.responses, fill, &mut selection);
+            }
+
+            yield_now().await;
+        }
+
+        loop {
+            // Sampled before the FIFO, frames can't arrive once
+            // deselected
+            let deselected = !block.is_selected();
+
+            if let Some(byte) = block.receive() {
+                selection.receive(&slot.responses, byte);
+                block.queue(&slot.responses, fill, &mut selection);
+                continue;
+            }
+
+            if deselected || slot.stop.signaled() {
+                break;
+            }
+
+            yield_now().await;
+        }
+
+        let event = SpiTargetEvent {
+            bus: bus as u8,
+            timestamp_us: Instant::now().as_micros(),
+            count: selection.received as u32,
+            mosi: selection.mosi,
+            command: selection.matched.map(|(index, _)| index as u8),
+        };
+        let _ = sender.publish::<SpiTargetTopic>(VarSeq::Seq2(seq_no), &event).await;
+        seq_no = seq_no.wrapping_add(1);
+    }
+
+    drop(block);
+    slot.stopped.signal(());
+}
+
+/// Progress of the controller through a selection.
+#[derive(Default)]
+struct Selection {
+    received: usize,
+    queued: usize,
+    mosi: heapless::Vec<u8, SPI_TARGET_MAX_LEN>,
+    /// Command matched and the frame its response starts at.
+    matched: Option<(usize, usize)>,
+}
+
+impl Selection {
+    fn receive(&mut self, responses: &SharedResponses, byte: u8) {
+        let _ = self.mosi.push(byte);
+        self.received += 1;
+
+        if self.matched.is_some() || self.received > SPI_TARGET_MAX_COMMAND_LEN {
+            return;
+        }
+
+        // Shorter commands win, they match first
+        let index = responses.lock(|responses| {
+            responses
+                .borrow()
+                .commands
+                .iter()
+                .position(|c| c.command.as_slice() == self.mosi.as_slice())
+        });
+
+        self.matched = index.map(|index| (index, self.received + QUEUED_AHEAD - 1));
+    }
+
+    /// Byte answered in frame `frame`.
+    fn byte(&self, responses: &Responses, fill: u8, frame: usize) -> u8 {
+        let byte = match self.matched {
+            Some((index, start)) if frame >= start => responses
+                .commands
+                .get(index)
+                .and_then(|c| c.response.get(frame - start)),
+            _ => responses.response.get(frame),
+        };
+
+        byte.copied().unwrap_or(fill)
+    }
+}
+
+/// SPI block switched to slave mode, along with its chip select pin.
+struct Block {
+    regs: pac::spi::Spi,
+    bus: usize,
+    cs: usize,
+    /// Pad and function of `cs`, restored when dropped.
+    cs_pad: pac::pads::regs::GpioCtrl,
+    cs_funcsel: u8,
+    polarity: SpiPolarity,
+    phase: SpiPhase,
+}
+
+impl Block {
+    fn take(bus: usize, cs: usize, polarity: SpiPolarity, phase: SpiPhase) -> Self {
+        let regs = match bus {
+            0 => pac::SPI0,
+            _ => pac::SPI1,
+        };
+
+        let cs_pad = pac::PADS_BANK0.gpio(cs).read();
+        let cs_funcsel = pac::IO_BANK0.gpio(cs).ctrl().read().funcsel();
+
+        // Deselected while left floating
+        pac::PADS_BANK0.gpio(cs).modify(|w| {
+            w.set_ie(true);
+            w.set_od(true);
+            w.set_pue(true);
+            w.set_pde(false);
+            w.set_iso(false);
+        });
+        pac::IO_BANK0.gpio(cs).ctrl().modify(|w| w.set_funcsel(FUNCSEL_SPI));
+
+        Self {
+            regs,
+            bus,
+            cs,
+            cs_pad,
+            cs_funcsel,
+            polarity,
+            phase,
+        }
+    }
+
+    /// Reset the block, the only way to empty its TX FIFO, then
+    /// program it for slave mode.
+    fn reset(&self) {
+        let mut block = pac::resets::regs::Peripherals(0);
+        match self.bus {
+            0 => block.set_spi0(true),
+            _ => block.set_spi1(true),
+        }
+
+        pac::RESETS.reset().modify(|w| w.0 |= block.0);
+        pac::RESETS.reset().modify(|w| w.0 &= !block.0);
+        while pac::RESETS.reset_done().read().0 & block.0 == 0 {}
+
+        self.regs.cr0().write(|w| {
+            w.set_dss(0b0111);
+            w.set_spo(self.polarity == SpiPolarity::IdleHigh);
+            w.set_sph(self.phase == SpiPhase::CaptureOnSecondTransition);
+        });
+        self.regs.cpsr().write(|w| w.set_cpsdvsr(2));
+        self.regs.cr1().write(|w| {
+            w.set_ms(true);
+            w.set_sse(true);
+        });
+    }
+
+    fn is_selected(&self) -> bool {
+        pac::SIO.gpio_in(0).read() & (1 << self.cs) == 0
+    }
+
+    fn receive(&self) -> Option<u8> {
+        self.regs.sr().read().rne().then(|| self.regs.dr().read().data() as u8)
+    }
+
+    /// Queue frames until `QUEUED_AHEAD` of them wait for the
+    /// controller.
+    fn queue(&self, responses: &SharedResponses, fill: u8, selection: &mut Selection) {
+        responses.lock(|responses| {
+            let responses = responses.borrow();
+
+            while selection.queued < selection.received + QUEUED_AHEAD {
+                let byte = selection.byte(&responses, fill, selection.queued);
+                self.regs.dr().write(|w| w.set_data(u16::from(byte)));
+                selection.queued += 1;
+            }
+        });
+    }
+}
+
+impl Drop for Block {
+    fn drop(&mut self) {
+        // Leaves the FIFOs empty for the controller
+        self.reset();
+        self.regs.cr1().write(|w| w.set_sse(false));
+
+        pac::IO_BANK0
+            .gpio(self.cs)
+            .ctrl()
+            .modify(|w| w.set_funcsel(self.cs_funcsel));
+        pac::PADS_BANK0.gpio(self.cs).write_value(self.cs_pad);
+    }
+}
@@ -0,0 +1,368 @@
+//! State machines of PIO1, running programs loaded by the host.
+//!
+//! GPIOs mapped by a state machine are handed to PIO1 from its start
+//! until it's stopped, their `Flex` staying locked meanwhile.
+
+use crate::{Irqs, NUM_GPIOS, Shared, pio_pin, sio_pin};
+use embassy_rp::Peri;
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::gpio::{Flex, Level};
+use embassy_rp::peripherals::PIO1;
+use embassy_rp::pio::{
+    Common, Config, Direction, FifoJoin, LoadedProgram, Pin, Pio, ShiftConfig, ShiftDirection, StateMachine,
+};
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::mutex::MutexGuard;
+use embassy_time::{Duration, with_timeout};
+use fixed::types::U24F8;
+use pico_de_gallo_internal::{
+    PIO_MAX_INSTRUCTIONS, PIO_MAX_WORDS, PIO_NUM_STATE_MACHINES, PioConfig, PioFail, PioFifoJoin, PioPins, PioProgram,
+    PioShift, PioShiftDirection, PioSideSet,
+};
+
+/// Run `$body` with `$sm` bound to state machine `$index` of `$pio`,
+/// each having its own type.
+macro_rules! with_sm {
+    ($pio:expr, $index:expr, |$sm:ident| $body:expr) => {
+        match $index {
+            0 => {
+                let $sm = &mut $pio.sm0;
+                $body
+            }
+            1 => {
+                let $sm = &mut $pio.sm1;
+                $body
+            }
+            2 => {
+                let $sm = &mut $pio.sm2;
+                $body
+            }
+            _ => {
+                let $sm = &mut $pio.sm3;
+                $body
+            }
+        }
+    };
+}
+
+#[derive(Default)]
+struct Slot {
+    program: Option<LoadedProgram<'static, PIO1>>,
+    side_set: PioSideSet,
+    /// Whether the state machine runs, whatever GPIOs it maps.
+    running: bool,
+    /// GPIOs handed to PIO1 while running.
+    pins: heapless::Vec<(usize, MutexGuard<'static, ThreadModeRawMutex, Flex<'static>>), NUM_GPIOS>,
+}
+
+pub struct UserPio {
+    common: Common<'static, PIO1>,
+    sm0: StateMachine<'static, PIO1, 0>,
+    sm1: StateMachine<'static, PIO1, 1>,
+    sm2: StateMachine<'static, PIO1, 2>,
+    sm3: StateMachine<'static, PIO1, 3>,
+    slots: [Slot; PIO_NUM_STATE_MACHINES],
+}
+
+impl UserPio {
+    pub fn new(pio: Peri<'static, PIO1>) -> Self {
+        let Pio {
+            common,
+            sm0,
+            sm1,
+            sm2,
+            sm3,
+            ..
+        } = Pio::new(pio, Irqs);
+
+        Self {
+            common,
+            sm0,
+            sm1,
+            sm2,
+            sm3,
+            slots: Default::default(),
+        }
+    }
+
+    fn slot(&mut self, sm: usize) -> Result<&mut Slot, PioFail> {
+        self.slots.get_mut(sm).ok_or(PioFail::InvalidStateMachine)
+    }
+
+    /// Load `program` for state machine `sm`, freeing the instructions
+    /// of its previous one. Returns the offset it was loaded at.
+    pub fn load(&mut self, sm: usize, program: &PioProgram) -> Result<u8, PioFail> {
+        let slot = self.slots.get_mut(sm).ok_or(PioFail::InvalidStateMachine)?;
+        if slot.running {
+            return Err(PioFail::AlreadyRunning);
+        }
+
+        let side_set = program.side_set;
+        if side_set.bits > 5 {
+            return Err(PioFail::InvalidConfig);
+        }
+
+        // Both wrap ends lie within the program, itself within the
+        // instruction memory
+        let len = program.code.len();
+        if usize::from(program.wrap_source) >= len || usize::from(program.wrap_target) >= len {
+            return Err(PioFail::InvalidConfig);
+        }
+        if let Some(origin) = program.origin {
+            if usize::from(origin) >= PIO_MAX_INSTRUCTIONS {
+                return Err(PioFail::InvalidConfig);
+            }
+            if usize::from(origin) + len > PIO_MAX_INSTRUCTIONS {
+                return Err(PioFail::NoSpace);
+            }
+        }
+
+        if let Some(previous) = slot.program.take() {
+            // SAFETY: the state machine is stopped, nothing runs the
+            // instructions anymore
+            unsafe { self.common.free_instr(previous.used_memory) };
+        }
+
+        // Assembled from no instruction, to be filled in
+        let mut assembled = pio::Assembler::<PIO_MAX_INSTRUCTIONS>::new_with_side_set(pio::SideSet::new(
+            side_set.optional,
+            side_set.pins(),
+            side_set.pindirs,
+        ))
+        .assemble_program();
+        for &instruction in &program.code {
+            assembled.code.push(instruction);
+        }
+        assembled.origin = program.origin;
+        assembled.wrap = pio::Wrap {
+            source: program.wrap_source,
+            target: program.wrap_target,
+        };
+
+        let loaded = self.common.try_load_program(&assembled).map_err(|_| PioFail::NoSpace)?;
+        let origin = loaded.origin;
+
+        let slot = &mut self.slots[sm];
+        slot.program = Some(loaded);
+        slot.side_set = side_set;
+
+        Ok(origin)
+    }
+
+    /// Configure state machine `sm` and start its program, handing it
+    /// the GPIOs it maps.
+    pub fn start(
+        &mut self,
+        sm: usize,
+        config: &PioConfig,
+        gpios: &'static [Shared<Flex<'static>>; NUM_GPIOS],
+    ) -> Result<(), PioFail> {
+        let slot = self.slot(sm)?;
+        if slot.running {
+            return Err(PioFail::AlreadyRunning);
+        }
+        if slot.program.is_none() {
+            return Err(PioFail::NotLoaded);
+        }
+
+        let side_set = PioPins {
+            base: config.side_set_base,
+            count: slot.side_set.pins(),
+        };
+        let ranges = [config.out_pins, config.set_pins, config.in_pins, side_set];
+        let jmp_pin = config.jmp_pin.map(usize::from);
+
+        let mut mapped = u32::from(config.outputs);
+        for range in ranges.into_iter().filter(|range| range.count > 0) {
+            if usize::from(range.base) + usize::from(range.count) > NUM_GPIOS {
+                return Err(PioFail::InvalidPin);
+            }
+
+            mapped |= ((1 << range.count) - 1) << range.base;
+        }
+        if let Some(pin) = jmp_pin {
+            if pin >= NUM_GPIOS {
+                return Err(PioFail::InvalidPin);
+            }
+
+            mapped |= 1 << pin;
+        }
+
+        let divider = clock_divider(config.frequency)?;
+        let shift_in = shift_config(config.shift_in)?;
+        let shift_out = shift_config(config.shift_out)?;
+
+        // Claimed before touching any of them, given back on failure
+        let mut claimed = heapless::Vec::<_, NUM_GPIOS>::new();
+        for (index, gpio) in gpios.iter().enumerate() {
+            if mapped & (1 << index) != 0 {
+                let guard = gpio.try_lock().map_err(|_| PioFail::PinBusy)?;
+                let _ = claimed.push((index, guard));
+            }
+        }
+
+        // SAFETY: the `Flex` of every mapped GPIO is held until the
+        // state machine stops
+        let pins: [Option<Pin<'static, PIO1>>; NUM_GPIOS] = core::array::from_fn(|index| {
+            (mapped & (1 << index) != 0).then(|| unsafe { pio_pin(&mut self.common, index) })
+        });
+        let range = |range: PioPins| -> heapless::Vec<&Pin<'static, PIO1>, NUM_GPIOS> {
+            let start = usize::from(range.base);
+            pins.iter()
+                .skip(start)
+                .take(usize::from(range.count))
+                .flatten()
+                .collect()
+        };
+        let directions = |output: bool| -> heapless::Vec<&Pin<'static, PIO1>, NUM_GPIOS> {
+            pins.iter()
+                .enumerate()
+                .filter(|(index, _)| (config.outputs & (1 << index) != 0) == output)
+                .filter_map(|(_, pin)| pin.as_ref())
+                .collect()
+        };
+        let outputs = directions(true);
+        let inputs = directions(false);
+
+        let mut cfg = Config::default();
+        if let Some(program) = &self.slots[sm].program {
+            cfg.use_program(program, &range(side_set));
+        }
+        if config.out_pins.count > 0 {
+            cfg.set_out_pins(&range(config.out_pins));
+        }
+        if config.set_pins.count > 0 {
+            cfg.set_set_pins(&range(config.set_pins));
+        }
+        if config.in_pins.count > 0 {
+            cfg.set_in_pins(&range(config.in_pins));
+        }
+        if let Some(pin) = jmp_pin.and_then(|pin| pins[pin].as_ref()) {
+            cfg.set_jmp_pin(pin);
+        }
+        cfg.clock_divider = divider;
+        cfg.shift_in = shift_in;
+        cfg.shift_out = shift_out;
+        cfg.fifo_join = match config.fifo_join {
+            PioFifoJoin::Duplex => FifoJoin::Duplex,
+            PioFifoJoin::RxOnly => FifoJoin::RxOnly,
+            PioFifoJoin::TxOnly => FifoJoin::TxOnly,
+        };
+
+        with_sm!(self, sm, |state_machine| {
+            state_machine.set_config(&cfg);
+            state_machine.clear_fifos();
+            state_machine.set_pins(Level::Low, &outputs);
+            state_machine.set_pin_dirs(Direction::Out, &outputs);
+            state_machine.set_pin_dirs(Direction::In, &inputs);
+            state_machine.restart();
+            state_machine.set_enable(true);
+        });
+
+        self.slots[sm].pins = claimed;
+        self.slots[sm].running = true;
+
+        Ok(())
+    }
+
+    /// Stop state machine `sm`, handing its GPIOs back as inputs.
+    pub fn stop(&mut self, sm: usize) -> Result<(), PioFail> {
+        self.slot(sm)?;
+
+        with_sm!(self, sm, |state_machine| state_machine.set_enable(false));
+        self.slots[sm].running = false;
+
+        for (index, mut gpio) in self.slots[sm].pins.drain(..) {
+            gpio.set_as_input();
+            sio_pin(index);
+        }
+
+        Ok(())
+    }
+
+    /// Execute `instruction` on state machine `sm`.
+    pub fn exec(&mut self, sm: usize, instruction: u16) -> Result<(), PioFail> {
+        self.slot(sm)?;
+
+        // SAFETY: the host owns the program and its consequences
+        with_sm!(self, sm, |state_machine| unsafe {
+            state_machine.exec_instr(instruction)
+        });
+
+        Ok(())
+    }
+
+    /// Push `data` into the TX FIFO of state machine `sm` until
+    /// `timeout`. Returns the number of words pushed.
+    pub async fn push(&mut self, sm: usize, data: &[u32], timeout: Duration) -> Result<usize, PioFail> {
+        self.slot(sm)?;
+
+        let mut pushed = 0;
+        with_sm!(self, sm, |state_machine| {
+            let push = async {
+                for &word in data {
+                    state_machine.tx().wait_push(word).await;
+                    pushed += 1;
+                }
+            };
+            let _ = with_timeout(timeout, push).await;
+        });
+
+        Ok(pushed)
+    }
+
+    /// Pull up to `count` words from the RX FIFO of state machine `sm`
+    /// until `timeout`.
+    pub async fn pull(
+        &mut self,
+        sm: usize,
+        count: usize,
+        timeout: Duration,
+    ) -> Result<heapless::Vec<u32, PIO_MAX_WORDS>, PioFail> {
+        self.slot(sm)?;
+        if count > PIO_MAX_WORDS {
+            return Err(PioFail::TooLong);
+        }
+
+        let mut data = heapless::Vec::new();
+        with_sm!(self, sm, |state_machine| {
+            let pull = async {
+                while data.len() < count {
+                    let _ = data.push(state_machine.rx().wait_pull().await);
+                }
+            };
+            let _ = with_timeout(timeout, pull).await;
+        });
+
+        Ok(data)
+    }
+}
+
+/// Divider of the system clock giving `frequency`, in 1/256ths.
+fn clock_divider(frequency: u32) -> Result<U24F8, PioFail> {
+    if frequency == 0 {
+        return Err(PioFail::InvalidConfig);
+    }
+
+    let divider = (u64::from(clk_sys_freq()) * 256).div_ceil(u64::from(frequency));
+    if !(256..=65536 * 256).contains(&divider) {
+        return Err(PioFail::InvalidConfig);
+    }
+
+    Ok(U24F8::from_bits(divider as u32))
+}
+
+fn shift_config(shift: PioShift) -> Result<ShiftConfig, PioFail> {
+    if !(1..=32).contains(&shift.threshold) {
+        return Err(PioFail::InvalidConfig);
+    }
+
+    Ok(ShiftConfig {
+        threshold: shift.threshold,
+        direction: match shift.direction {
+            PioShiftDirection::Left => ShiftDirection::Left,
+            PioShiftDirection::Right => ShiftDirection::Right,
+        },
+        auto_fill: shift.auto,
+    })
+}
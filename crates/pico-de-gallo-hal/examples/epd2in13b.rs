@@ -5,34 +5,31 @@ use embedded_graphics::{
     text::{Baseline, Text, TextStyleBuilder},
 };
 use embedded_hal::delay::DelayNs;
-use embedded_hal_bus::spi::ExclusiveDevice;
 use epd_waveshare::{
     color::*,
     epd2in13_v2::{Display2in13, Epd2in13},
     graphics::DisplayRotation,
     prelude::*,
 };
-use pico_de_gallo_hal::{Hal, SpiPhase, SpiPolarity};
+use pico_de_gallo_hal::{Hal, SpiChipSelect, SpiConfig, SpiPhase, SpiPolarity};
 
 fn main() {
     let mut hal = Hal::new();
 
-    hal.set_config(
-        100_000,
-        10_000_000,
-        SpiPhase::CaptureOnFirstTransition,
-        SpiPolarity::IdleLow,
-    );
+    let spi_config = SpiConfig::builder()
+        .frequency(10_000_000)
+        .phase(SpiPhase::CaptureOnFirstTransition)
+        .polarity(SpiPolarity::IdleLow)
+        .build()
+        .unwrap();
+    hal.set_spi_config(0, spi_config).unwrap();
 
-    let cs = hal.gpio(0);
     let dc = hal.gpio(1);
     let rst = hal.gpio(2);
     let busy = hal.gpio(3);
-    let delay = hal.delay();
-    let spi = hal.spi();
 
-    // One SPI device only on the SPI bus
-    let mut spi = ExclusiveDevice::new(spi, cs, delay).unwrap();
+    // Chip select on GPIO 0, driven by the firmware
+    let mut spi = hal.spi_device(0, SpiChipSelect::new(0));
 
     let mut delay = hal.delay();
     let mut epd2in13 =
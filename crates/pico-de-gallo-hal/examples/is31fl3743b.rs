@@ -5,7 +5,7 @@ use std::time::Duration;
 
 fn main() {
     let hal = Hal::new();
-    let spi = hal.spi();
+    let spi = hal.spi(0);
     let delay = hal.delay();
     let cs = hal.gpio(0);
 
@@ -20,13 +20,13 @@ fn main() {
     // Set global current, check method documentation for more info
     let _ = driver.set_global_current(90);
 
-    let _ = driver.set_led_peak_current_bulk(SWx::SW1, CSy::CS1, &vec![100; 11 * 18]);
+    let _ = driver.set_led_peak_current_bulk(SWx::SW1, CSy::CS1, &[100; 11 * 18]);
 
     // Driver is fully set up, we can now start turning on LEDs!
     // Create a white breathing effect
     loop {
         for brightness in (0..=255_u8).chain((0..=255).rev()) {
-            let _ = driver.set_led_brightness_bulk(SWx::SW1, CSy::CS1, &vec![brightness; 11 * 18]);
+            let _ = driver.set_led_brightness_bulk(SWx::SW1, CSy::CS1, &[brightness; 11 * 18]);
             std::thread::sleep(Duration::from_micros(1));
         }
     }
@@ -5,7 +5,7 @@ use std::time::Duration;
 
 fn main() {
     let hal = Hal::new();
-    let i2c = hal.i2c();
+    let i2c = hal.i2c(0);
     let mut delay = hal.delay();
 
     let mut sensor = Mpu6050::new(i2c, Address::default()).unwrap();
@@ -3,7 +3,7 @@ use shtcx::{PowerMode, shtc3};
 
 fn main() {
     let hal = Hal::new();
-    let i2c = hal.i2c();
+    let i2c = hal.i2c(0);
     let mut delay = hal.delay();
 
     let mut sht = shtc3(i2c);
@@ -1,16 +1,22 @@
-use pico_de_gallo_lib::{GpioState, PicoDeGallo};
+use futures::executor::block_on;
+use futures::lock::Mutex;
+use pico_de_gallo_lib::blocking::PicoDeGallo;
+use pico_de_gallo_lib::{GpioState, I2cAddress, SpiOperation};
+use std::net::ToSocketAddrs;
 use std::sync::Arc;
-use tokio::runtime::{Handle, Runtime};
-use tokio::sync::Mutex;
-use tokio::task::block_in_place;
 
-pub use pico_de_gallo_lib::{SpiPhase, SpiPolarity};
+pub use pico_de_gallo_lib::{
+    ConfigError, Configuration, I2cConfig, I2cConfigBuilder, NUM_I2C_BUSES, NUM_SPI_BUSES,
+    SPI_DEVICE_NAME_MAX_LEN, SPI_MAX_DEVICES, SPI_MAX_OPERATIONS, SpiBitOrder, SpiChipSelect,
+    SpiConfig, SpiConfigBuilder, SpiCsPolarity, SpiPhase, SpiPolarity, SpiWordSize,
+};
 
+/// Library context.
+///
+/// Neither the blocking nor the async traits require any particular
+/// executor, the latter may be used from tokio, smol, async-std, etc.
 pub struct Hal {
     gallo: Arc<Mutex<PicoDeGallo>>,
-    _runtime: Option<Runtime>,
-    handle: Handle,
-    in_async: bool,
 }
 
 impl Default for Hal {
@@ -22,113 +28,101 @@ impl Default for Hal {
 impl Hal {
     /// Instantiate the library context.
     pub fn new() -> Self {
-        Self::new_inner(None)
+        Self::new_inner(PicoDeGallo::new())
     }
 
     /// Instantiate the library context for the device with the given
     /// `serial_number`.
     pub fn new_with_serial_number(serial_number: &str) -> Self {
-        Self::new_inner(Some(serial_number))
+        Self::new_inner(PicoDeGallo::new_with_serial_number(serial_number))
     }
 
-    fn new_inner(serial_number: Option<&str>) -> Self {
-        let (runtime, handle, in_async) = match Handle::try_current() {
-            Ok(handle) => (None, handle, true),
-            Err(_) => {
-                let runtime = Runtime::new().unwrap();
-                let handle = runtime.handle().clone();
-                (Some(runtime), handle, false)
-            }
-        };
+    /// Instantiate the library context for a device shared over TCP,
+    /// e.g. by `gallo serve`, listening on `addr`.
+    pub fn connect_tcp<A: ToSocketAddrs>(addr: A) -> std::io::Result<Self> {
+        Ok(Self::new_inner(PicoDeGallo::connect_tcp(addr)?))
+    }
 
-        let gallo = if in_async {
-            if let Some(serial_number) = serial_number {
-                PicoDeGallo::new_with_serial_number(serial_number)
-            } else {
-                PicoDeGallo::new()
-            }
-        } else {
-            handle.block_on(async {
-                if let Some(serial_number) = serial_number {
-                    PicoDeGallo::new_with_serial_number(serial_number)
-                } else {
-                    PicoDeGallo::new()
-                }
-            })
-        };
+    /// Instantiate the library context for a device shared by the
+    /// broker daemon, e.g. `gallo daemon`, listening on the Unix
+    /// socket at `path`.
+    #[cfg(unix)]
+    pub fn connect_unix<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        Ok(Self::new_inner(PicoDeGallo::connect_unix(path)?))
+    }
 
+    fn new_inner(gallo: PicoDeGallo) -> Self {
         Self {
             gallo: Arc::new(Mutex::new(gallo)),
-            _runtime: runtime,
-            handle,
-            in_async,
         }
     }
 
-    /// Set interface configuration parameters
-    pub fn set_config(
-        &mut self,
-        i2c_frequency: u32,
-        spi_frequency: u32,
-        spi_phase: SpiPhase,
-        spi_polarity: SpiPolarity,
-    ) {
-        if self.in_async {
-            block_in_place(|| {
-                self.set_config_inner(i2c_frequency, spi_frequency, spi_phase, spi_polarity)
-            });
-        } else {
-            self.set_config_inner(i2c_frequency, spi_frequency, spi_phase, spi_polarity);
-        };
+    /// Apply `config` to I2C `bus`, returning the SCL frequency
+    /// actually achieved.
+    pub fn set_i2c_config(&mut self, bus: u8, config: I2cConfig) -> Result<u32, Error> {
+        let gallo = block_on(self.gallo.lock());
+        gallo.i2c_set_config(bus, config).map_err(|_| Error::Other)
     }
 
-    fn set_config_inner(
-        &mut self,
-        i2c_frequency: u32,
-        spi_frequency: u32,
-        spi_phase: SpiPhase,
-        spi_polarity: SpiPolarity,
-    ) {
-        let handle = self.handle.clone();
+    /// Apply `config` to SPI `bus`, returning the SCK frequency
+    /// actually achieved.
+    pub fn set_spi_config(&mut self, bus: u8, config: SpiConfig) -> Result<u32, Error> {
+        let gallo = block_on(self.gallo.lock());
+        gallo.spi_set_config(bus, config).map_err(|_| Error::Other)
+    }
 
-        let gallo = handle.block_on(self.gallo.lock());
-        handle
-            .block_on(gallo.set_config(i2c_frequency, spi_frequency, spi_phase, spi_polarity))
-            .unwrap();
+    /// Current configuration of every I2C and SPI bus.
+    pub fn config(&self) -> Result<Configuration, Error> {
+        let gallo = block_on(self.gallo.lock());
+        gallo.get_config().map_err(|_| Error::Other)
     }
 
     /// Gpio
     pub fn gpio(&self, pin: u8) -> Gpio {
         let gallo = Arc::clone(&self.gallo);
-        let handle = self.handle.clone();
-        Gpio {
-            pin,
-            gallo,
-            handle,
-            in_async: self.in_async,
-        }
+        Gpio { pin, gallo }
     }
 
-    /// I2c
-    pub fn i2c(&self) -> I2c {
+    /// I2c bus numbered by `bus`, from 0 to [`NUM_I2C_BUSES`] excluded
+    pub fn i2c(&self, bus: u8) -> I2c {
         let gallo = Arc::clone(&self.gallo);
-        let handle = self.handle.clone();
-        I2c {
-            gallo,
-            handle,
-            in_async: self.in_async,
-        }
+        I2c { gallo, bus }
     }
 
-    /// Spi
-    pub fn spi(&self) -> Spi {
+    /// Spi bus numbered by `bus`, from 0 to [`NUM_SPI_BUSES`] excluded
+    pub fn spi(&self, bus: u8) -> Spi {
         let gallo = Arc::clone(&self.gallo);
-        let handle = self.handle.clone();
-        Spi {
-            gallo,
-            handle,
-            in_async: self.in_async,
-        }
+        Spi { gallo, bus }
+    }
+
+    /// Spi device on `bus`, with its chip select `cs` driven by the
+    /// firmware
+    pub fn spi_device(&self, bus: u8, cs: SpiChipSelect) -> SpiDevice {
+        let gallo = Arc::clone(&self.gallo);
+        let target = SpiTarget::ChipSelect(bus, cs);
+        SpiDevice { gallo, target }
+    }
+
+    /// Spi device registered in the firmware as `name`, with its own
+    /// chip select and `config` applied on each transaction, so
+    /// devices needing different settings can share the bus.
+    pub fn add_spi_device(
+        &self,
+        bus: u8,
+        name: &str,
+        cs: SpiChipSelect,
+        config: SpiConfig,
+    ) -> Result<SpiDevice, Error> {
+        let slot = {
+            let gallo = block_on(self.gallo.lock());
+            gallo
+                .spi_add_device(bus, name, cs, config)
+                .map_err(|_| Error::Other)?
+        };
+
+        let gallo = Arc::clone(&self.gallo);
+        let target = SpiTarget::Slot(slot.slot);
+        Ok(SpiDevice { gallo, target })
     }
 
     /// Delay
@@ -151,44 +145,6 @@ pub enum Error {
 pub struct Gpio {
     pin: u8,
     gallo: Arc<Mutex<PicoDeGallo>>,
-    handle: Handle,
-    in_async: bool,
-}
-
-impl Gpio {
-    fn set_low_inner(&mut self) -> std::result::Result<(), Error> {
-        let handle = &self.handle;
-        let gallo = handle.block_on(self.gallo.lock());
-        handle
-            .block_on(gallo.gpio_put(self.pin, GpioState::Low))
-            .map_err(|_| Error::Other)
-    }
-
-    fn set_high_inner(&mut self) -> std::result::Result<(), Error> {
-        let handle = &self.handle;
-        let gallo = handle.block_on(self.gallo.lock());
-        handle
-            .block_on(gallo.gpio_put(self.pin, GpioState::High))
-            .map_err(|_| Error::Other)
-    }
-
-    fn is_low_inner(&mut self) -> std::result::Result<bool, Error> {
-        let handle = &self.handle;
-        let gallo = handle.block_on(self.gallo.lock());
-        handle
-            .block_on(gallo.gpio_get(self.pin))
-            .map_err(|_| Error::Other)
-            .map(|s| s == GpioState::Low)
-    }
-
-    fn is_high_inner(&mut self) -> std::result::Result<bool, Error> {
-        let handle = &self.handle;
-        let gallo = handle.block_on(self.gallo.lock());
-        handle
-            .block_on(gallo.gpio_get(self.pin))
-            .map_err(|_| Error::Other)
-            .map(|s| s == GpioState::High)
-    }
 }
 
 impl embedded_hal::digital::Error for Error {
@@ -203,37 +159,35 @@ impl embedded_hal::digital::ErrorType for Gpio {
 
 impl embedded_hal::digital::OutputPin for Gpio {
     fn set_low(&mut self) -> std::result::Result<(), Self::Error> {
-        if self.in_async {
-            block_in_place(|| self.set_low_inner())
-        } else {
-            self.set_low_inner()
-        }
+        let gallo = block_on(self.gallo.lock());
+        gallo
+            .gpio_put(self.pin, GpioState::Low)
+            .map_err(|_| Error::Other)
     }
 
     fn set_high(&mut self) -> std::result::Result<(), Self::Error> {
-        if self.in_async {
-            block_in_place(|| self.set_high_inner())
-        } else {
-            self.set_high_inner()
-        }
+        let gallo = block_on(self.gallo.lock());
+        gallo
+            .gpio_put(self.pin, GpioState::High)
+            .map_err(|_| Error::Other)
     }
 }
 
 impl embedded_hal::digital::InputPin for Gpio {
     fn is_low(&mut self) -> std::result::Result<bool, Self::Error> {
-        if self.in_async {
-            block_in_place(|| self.is_low_inner())
-        } else {
-            self.is_low_inner()
-        }
+        let gallo = block_on(self.gallo.lock());
+        gallo
+            .gpio_get(self.pin)
+            .map_err(|_| Error::Other)
+            .map(|s| s == GpioState::Low)
     }
 
     fn is_high(&mut self) -> std::result::Result<bool, Self::Error> {
-        if self.in_async {
-            block_in_place(|| self.is_high_inner())
-        } else {
-            self.is_high_inner()
-        }
+        let gallo = block_on(self.gallo.lock());
+        gallo
+            .gpio_get(self.pin)
+            .map_err(|_| Error::Other)
+            .map(|s| s == GpioState::High)
     }
 }
 
@@ -241,6 +195,7 @@ impl embedded_hal_async::digital::Wait for Gpio {
     async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
         let gallo = self.gallo.lock().await;
         gallo
+            .as_async()
             .gpio_wait_for_high(self.pin)
             .await
             .map_err(|_| Self::Error::Other)
@@ -249,6 +204,7 @@ impl embedded_hal_async::digital::Wait for Gpio {
     async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
         let gallo = self.gallo.lock().await;
         gallo
+            .as_async()
             .gpio_wait_for_low(self.pin)
             .await
             .map_err(|_| Self::Error::Other)
@@ -257,6 +213,7 @@ impl embedded_hal_async::digital::Wait for Gpio {
     async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
         let gallo = self.gallo.lock().await;
         gallo
+            .as_async()
             .gpio_wait_for_rising_edge(self.pin)
             .await
             .map_err(|_| Self::Error::Other)
@@ -265,6 +222,7 @@ impl embedded_hal_async::digital::Wait for Gpio {
     async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
         let gallo = self.gallo.lock().await;
         gallo
+            .as_async()
             .gpio_wait_for_falling_edge(self.pin)
             .await
             .map_err(|_| Self::Error::Other)
@@ -273,6 +231,7 @@ impl embedded_hal_async::digital::Wait for Gpio {
     async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
         let gallo = self.gallo.lock().await;
         gallo
+            .as_async()
             .gpio_wait_for_any_edge(self.pin)
             .await
             .map_err(|_| Self::Error::Other)
@@ -281,31 +240,67 @@ impl embedded_hal_async::digital::Wait for Gpio {
 
 // ----------------------------- I2c -----------------------------
 
+/// The I2C bus.
+///
+/// `I2c<SevenBitAddress>` and `I2c<TenBitAddress>` are both
+/// implemented.
 pub struct I2c {
     gallo: Arc<Mutex<PicoDeGallo>>,
-    handle: Handle,
-    in_async: bool,
+    bus: u8,
 }
 
 impl I2c {
-    fn transaction_inner(
+    /// Free the bus from a target holding SDA low after an aborted
+    /// transfer, returning whether the bus was left free.
+    pub fn recover(&mut self) -> Result<bool, Error> {
+        let gallo = block_on(self.gallo.lock());
+        gallo.i2c_recover(self.bus).map_err(|_| Error::Other)
+    }
+
+    fn blocking_transaction(
         &mut self,
-        address: embedded_hal::i2c::SevenBitAddress,
+        address: I2cAddress,
         operations: &mut [embedded_hal::i2c::Operation<'_>],
-    ) -> std::result::Result<(), Error> {
-        let handle = &self.handle;
-        let gallo = handle.block_on(self.gallo.lock());
+    ) -> Result<(), Error> {
+        let gallo = block_on(self.gallo.lock());
 
         for op in operations {
             match op {
                 embedded_hal::i2c::Operation::Read(read) => {
-                    let contents = handle
-                        .block_on(gallo.i2c_read(address, read.len() as u16))
+                    let contents = gallo
+                        .i2c_read(self.bus, address, read.len() as u16)
+                        .map_err(|_| Error::Other)?;
+                    read.copy_from_slice(&contents);
+                }
+                embedded_hal::i2c::Operation::Write(write) => gallo
+                    .i2c_write(self.bus, address, write)
+                    .map_err(|_| Error::Other)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn async_transaction(
+        &mut self,
+        address: I2cAddress,
+        operations: &mut [embedded_hal_async::i2c::Operation<'_>],
+    ) -> Result<(), Error> {
+        let gallo = self.gallo.lock().await;
+        let gallo = gallo.as_async();
+
+        for op in operations {
+            match op {
+                embedded_hal_async::i2c::Operation::Read(read) => {
+                    let contents = gallo
+                        .i2c_read(self.bus, address, read.len() as u16)
+                        .await
                         .map_err(|_| Error::Other)?;
                     read.copy_from_slice(&contents);
                 }
-                embedded_hal::i2c::Operation::Write(write) => handle
-                    .block_on(gallo.i2c_write(address, write))
+                embedded_hal_async::i2c::Operation::Write(write) => gallo
+                    .i2c_write(self.bus, address, write)
+                    .await
                     .map_err(|_| Error::Other)?,
             }
         }
@@ -330,11 +325,17 @@ impl embedded_hal::i2c::I2c<embedded_hal::i2c::SevenBitAddress> for I2c {
         address: embedded_hal::i2c::SevenBitAddress,
         operations: &mut [embedded_hal::i2c::Operation<'_>],
     ) -> std::result::Result<(), Self::Error> {
-        if self.in_async {
-            block_in_place(|| self.transaction_inner(address, operations))
-        } else {
-            self.transaction_inner(address, operations)
-        }
+        self.blocking_transaction(I2cAddress::SevenBit(address), operations)
+    }
+}
+
+impl embedded_hal::i2c::I2c<embedded_hal::i2c::TenBitAddress> for I2c {
+    fn transaction(
+        &mut self,
+        address: embedded_hal::i2c::TenBitAddress,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> std::result::Result<(), Self::Error> {
+        self.blocking_transaction(I2cAddress::TenBit(address), operations)
     }
 }
 
@@ -344,59 +345,56 @@ impl embedded_hal_async::i2c::I2c<embedded_hal_async::i2c::SevenBitAddress> for
         address: embedded_hal_async::i2c::SevenBitAddress,
         operations: &mut [embedded_hal_async::i2c::Operation<'_>],
     ) -> std::result::Result<(), Self::Error> {
-        let gallo = self.gallo.lock().await;
-
-        for op in operations {
-            match op {
-                embedded_hal_async::i2c::Operation::Read(read) => {
-                    let contents = gallo
-                        .i2c_read(address, read.len() as u16)
-                        .await
-                        .map_err(|_| Self::Error::Other)?;
-                    read.copy_from_slice(&contents);
-                }
-                embedded_hal_async::i2c::Operation::Write(write) => gallo
-                    .i2c_write(address, write)
-                    .await
-                    .map_err(|_| Self::Error::Other)?,
-            }
-        }
+        self.async_transaction(I2cAddress::SevenBit(address), operations)
+            .await
+    }
+}
 
-        Ok(())
+impl embedded_hal_async::i2c::I2c<embedded_hal_async::i2c::TenBitAddress> for I2c {
+    async fn transaction(
+        &mut self,
+        address: embedded_hal_async::i2c::TenBitAddress,
+        operations: &mut [embedded_hal_async::i2c::Operation<'_>],
+    ) -> std::result::Result<(), Self::Error> {
+        self.async_transaction(I2cAddress::TenBit(address), operations)
+            .await
     }
 }
 
 // ----------------------------- Spi -----------------------------
 
+/// The SPI bus.
+///
+/// `SpiBus<u8>` and `SpiBus<u16>` are both implemented, the latter
+/// requires the bus to be configured with [`SpiWordSize::Sixteen`].
 pub struct Spi {
     gallo: Arc<Mutex<PicoDeGallo>>,
-    handle: Handle,
-    in_async: bool,
+    bus: u8,
 }
 
 impl Spi {
     fn read_inner(&mut self, words: &mut [u8]) -> std::result::Result<(), Error> {
-        let handle = &self.handle;
-        let gallo = handle.block_on(self.gallo.lock());
-        let contents = handle
-            .block_on(gallo.spi_read(words.len() as u16))
+        let gallo = block_on(self.gallo.lock());
+        let contents = gallo
+            .spi_read(self.bus, words.len() as u16)
             .map_err(|_| Error::Other)?;
         words.copy_from_slice(&contents);
         Ok(())
     }
 
     fn write_inner(&mut self, words: &[u8]) -> std::result::Result<(), Error> {
-        let handle = &self.handle;
-        let gallo = handle.block_on(self.gallo.lock());
-        handle
-            .block_on(gallo.spi_write(words))
-            .map_err(|_| Error::Other)
+        let gallo = block_on(self.gallo.lock());
+        gallo.spi_write(self.bus, words).map_err(|_| Error::Other)
     }
+}
+
+fn words_to_bytes(words: &[u16]) -> Vec<u8> {
+    words.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
 
-    fn flush_inner(&mut self) -> std::result::Result<(), Error> {
-        let handle = &self.handle;
-        let gallo = handle.block_on(self.gallo.lock());
-        handle.block_on(gallo.spi_flush()).map_err(|_| Error::Other)
+fn bytes_to_words(bytes: &[u8], words: &mut [u16]) {
+    for (w, b) in words.iter_mut().zip(bytes.chunks_exact(2)) {
+        *w = u16::from_le_bytes([b[0], b[1]]);
     }
 }
 
@@ -412,51 +410,57 @@ impl embedded_hal::spi::ErrorType for Spi {
 
 impl embedded_hal::spi::SpiBus for Spi {
     fn read(&mut self, words: &mut [u8]) -> std::result::Result<(), Self::Error> {
-        if self.in_async {
-            block_in_place(|| self.read_inner(words))
-        } else {
-            self.read_inner(words)
-        }
+        self.read_inner(words)
     }
 
     fn write(&mut self, words: &[u8]) -> std::result::Result<(), Self::Error> {
-        if self.in_async {
-            block_in_place(|| self.write_inner(words))
-        } else {
-            self.write_inner(words)
-        }
+        self.write_inner(words)
     }
 
     fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> std::result::Result<(), Self::Error> {
-        if self.in_async {
-            block_in_place(|| {
-                self.write_inner(write)?;
-                self.read_inner(read)
-            })
-        } else {
-            self.write_inner(write)?;
-            self.read_inner(read)
-        }
+        self.write_inner(write)?;
+        self.read_inner(read)
     }
 
     fn transfer_in_place(&mut self, words: &mut [u8]) -> std::result::Result<(), Self::Error> {
-        if self.in_async {
-            block_in_place(|| {
-                self.write_inner(words)?;
-                self.read_inner(words)
-            })
-        } else {
-            self.write_inner(words)?;
-            self.read_inner(words)
-        }
+        self.write_inner(words)?;
+        self.read_inner(words)
     }
 
     fn flush(&mut self) -> std::result::Result<(), Self::Error> {
-        if self.in_async {
-            block_in_place(|| self.flush_inner())
-        } else {
-            self.flush_inner()
-        }
+        let gallo = block_on(self.gallo.lock());
+        gallo.spi_flush(self.bus).map_err(|_| Error::Other)
+    }
+}
+
+impl embedded_hal::spi::SpiBus<u16> for Spi {
+    fn read(&mut self, words: &mut [u16]) -> std::result::Result<(), Self::Error> {
+        let mut bytes = vec![0; words.len() * 2];
+        self.read_inner(&mut bytes)?;
+        bytes_to_words(&bytes, words);
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u16]) -> std::result::Result<(), Self::Error> {
+        self.write_inner(&words_to_bytes(words))
+    }
+
+    fn transfer(
+        &mut self,
+        read: &mut [u16],
+        write: &[u16],
+    ) -> std::result::Result<(), Self::Error> {
+        embedded_hal::spi::SpiBus::<u16>::write(self, write)?;
+        embedded_hal::spi::SpiBus::<u16>::read(self, read)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u16]) -> std::result::Result<(), Self::Error> {
+        embedded_hal::spi::SpiBus::<u16>::write(self, words)?;
+        embedded_hal::spi::SpiBus::<u16>::read(self, words)
+    }
+
+    fn flush(&mut self) -> std::result::Result<(), Self::Error> {
+        embedded_hal::spi::SpiBus::<u8>::flush(self)
     }
 }
 
@@ -464,7 +468,8 @@ impl embedded_hal_async::spi::SpiBus for Spi {
     async fn read(&mut self, words: &mut [u8]) -> std::result::Result<(), Self::Error> {
         let gallo = self.gallo.lock().await;
         let contents = gallo
-            .spi_read(words.len() as u16)
+            .as_async()
+            .spi_read(self.bus, words.len() as u16)
             .await
             .map_err(|_| Self::Error::Other)?;
         words.copy_from_slice(&contents);
@@ -473,7 +478,11 @@ impl embedded_hal_async::spi::SpiBus for Spi {
 
     async fn write(&mut self, words: &[u8]) -> std::result::Result<(), Self::Error> {
         let gallo = self.gallo.lock().await;
-        gallo.spi_write(words).await.map_err(|_| Self::Error::Other)
+        gallo
+            .as_async()
+            .spi_write(self.bus, words)
+            .await
+            .map_err(|_| Self::Error::Other)
     }
 
     async fn transfer(
@@ -495,7 +504,143 @@ impl embedded_hal_async::spi::SpiBus for Spi {
 
     async fn flush(&mut self) -> std::result::Result<(), Self::Error> {
         let gallo = self.gallo.lock().await;
-        gallo.spi_flush().await.map_err(|_| Self::Error::Other)
+        gallo
+            .as_async()
+            .spi_flush(self.bus)
+            .await
+            .map_err(|_| Self::Error::Other)
+    }
+}
+
+impl embedded_hal_async::spi::SpiBus<u16> for Spi {
+    async fn read(&mut self, words: &mut [u16]) -> std::result::Result<(), Self::Error> {
+        let mut bytes = vec![0; words.len() * 2];
+        embedded_hal_async::spi::SpiBus::<u8>::read(self, &mut bytes).await?;
+        bytes_to_words(&bytes, words);
+        Ok(())
+    }
+
+    async fn write(&mut self, words: &[u16]) -> std::result::Result<(), Self::Error> {
+        embedded_hal_async::spi::SpiBus::<u8>::write(self, &words_to_bytes(words)).await
+    }
+
+    async fn transfer(
+        &mut self,
+        read: &mut [u16],
+        write: &[u16],
+    ) -> std::result::Result<(), Self::Error> {
+        embedded_hal_async::spi::SpiBus::<u16>::write(self, write).await?;
+        embedded_hal_async::spi::SpiBus::<u16>::read(self, read).await
+    }
+
+    async fn transfer_in_place(
+        &mut self,
+        words: &mut [u16],
+    ) -> std::result::Result<(), Self::Error> {
+        embedded_hal_async::spi::SpiBus::<u16>::write(self, words).await?;
+        embedded_hal_async::spi::SpiBus::<u16>::read(self, words).await
+    }
+
+    async fn flush(&mut self) -> std::result::Result<(), Self::Error> {
+        embedded_hal_async::spi::SpiBus::<u8>::flush(self).await
+    }
+}
+
+// ----------------------------- SpiDevice -----------------------------
+
+/// An SPI device whose chip select is driven by the firmware.
+///
+/// Each transaction, delays included, runs in the firmware as a whole,
+/// keeping CS timing independent from USB latency.
+pub struct SpiDevice {
+    gallo: Arc<Mutex<PicoDeGallo>>,
+    target: SpiTarget,
+}
+
+enum SpiTarget {
+    /// Chip select only, using the bus configuration.
+    ChipSelect(u8, SpiChipSelect),
+    /// Device registered in the firmware, with its own configuration.
+    Slot(u8),
+}
+
+fn spi_operations<'a>(
+    operations: &'a [embedded_hal::spi::Operation<'_, u8>],
+) -> Vec<SpiOperation<'a>> {
+    use embedded_hal::spi::Operation;
+
+    operations
+        .iter()
+        .map(|op| match op {
+            Operation::Read(read) => SpiOperation::Read(read.len() as u16),
+            Operation::Write(write) => SpiOperation::Write(write),
+            Operation::Transfer(read, write) => SpiOperation::Transfer {
+                read: read.len() as u16,
+                write,
+            },
+            Operation::TransferInPlace(words) => SpiOperation::TransferInPlace(words),
+            Operation::DelayNs(ns) => SpiOperation::DelayNs(*ns),
+        })
+        .collect()
+}
+
+/// Copy the bytes read by a transaction back into `operations`.
+fn scatter_reads(
+    operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    mut data: &[u8],
+) -> std::result::Result<(), Error> {
+    use embedded_hal::spi::Operation;
+
+    for op in operations {
+        let read: &mut [u8] = match op {
+            Operation::Read(read) | Operation::Transfer(read, _) => read,
+            Operation::TransferInPlace(words) => words,
+            Operation::Write(_) | Operation::DelayNs(_) => continue,
+        };
+
+        let (head, tail) = data.split_at_checked(read.len()).ok_or(Error::Other)?;
+        read.copy_from_slice(head);
+        data = tail;
+    }
+
+    Ok(())
+}
+
+impl embedded_hal::spi::ErrorType for SpiDevice {
+    type Error = Error;
+}
+
+impl embedded_hal::spi::SpiDevice for SpiDevice {
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> std::result::Result<(), Self::Error> {
+        let gallo = block_on(self.gallo.lock());
+        let ops = spi_operations(operations);
+        let data = match self.target {
+            SpiTarget::ChipSelect(bus, cs) => gallo.spi_transaction(bus, cs, &ops),
+            SpiTarget::Slot(slot) => gallo.spi_device_transaction(slot, &ops),
+        }
+        .map_err(|_| Error::Other)?;
+        drop(ops);
+        scatter_reads(operations, &data)
+    }
+}
+
+impl embedded_hal_async::spi::SpiDevice for SpiDevice {
+    async fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal_async::spi::Operation<'_, u8>],
+    ) -> std::result::Result<(), Self::Error> {
+        let gallo = self.gallo.lock().await;
+        let ops = spi_operations(operations);
+        let data = match self.target {
+            SpiTarget::ChipSelect(bus, cs) => gallo.as_async().spi_transaction(bus, cs, &ops).await,
+            SpiTarget::Slot(slot) => gallo.as_async().spi_device_transaction(slot, &ops).await,
+        }
+        .map_err(|_| Self::Error::Other)?;
+        drop(ops);
+        scatter_reads(operations, &data)
     }
 }
 
@@ -511,6 +656,6 @@ impl embedded_hal::delay::DelayNs for Delay {
 
 impl embedded_hal_async::delay::DelayNs for Delay {
     async fn delay_ns(&mut self, ns: u32) {
-        tokio::time::sleep(tokio::time::Duration::from_nanos(ns.into())).await
+        futures_timer::Delay::new(std::time::Duration::from_nanos(ns.into())).await
     }
 }
@@ -7,6 +7,12 @@ use serde::{Deserialize, Serialize};
 pub const MICROSOFT_VID: u16 = 0x045e;
 pub const PICO_DE_GALLO_PID: u16 = 0x067d;
 
+/// Number of I2C buses, numbered from 0.
+pub const NUM_I2C_BUSES: usize = 2;
+
+/// Number of SPI buses, numbered from 0.
+pub const NUM_SPI_BUSES: usize = 2;
+
 // ---
 
 pub type I2cWriteResponse = Result<(), I2cWriteFail>;
@@ -16,38 +22,157 @@ pub type I2cReadResponse<'a> = Result<Vec<u8>, I2cReadFail>;
 #[cfg(not(feature = "use-std"))]
 pub type I2cReadResponse<'a> = Result<&'a [u8], I2cReadFail>;
 
+/// Whether the bus was left free.
+pub type I2cRecoverResponse = Result<bool, I2cRecoverFail>;
+pub type I2cScanResponse = Result<I2cAddressMap, I2cScanFail>;
+
+#[cfg(feature = "use-std")]
+pub type SmbusResponse<'a> = Result<Vec<u8>, SmbusFail>;
+#[cfg(not(feature = "use-std"))]
+pub type SmbusResponse<'a> = Result<&'a [u8], SmbusFail>;
+
+/// Address of the device which answered the Alert Response Address.
+pub type SmbusAlertResponse = Result<u8, SmbusFail>;
+
+/// Slot the trigger was armed in.
+pub type I2cTriggerArmResponse = Result<u8, I2cTriggerArmFail>;
+pub type I2cTriggerDisarmResponse = Result<(), I2cTriggerDisarmFail>;
+
+pub type I2cTargetResponse = Result<(), I2cTargetFail>;
+
+#[cfg(feature = "use-std")]
+pub type I2cTargetDumpResponse<'a> = Result<Vec<u8>, I2cTargetFail>;
+#[cfg(not(feature = "use-std"))]
+pub type I2cTargetDumpResponse<'a> = Result<&'a [u8], I2cTargetFail>;
+
 pub type SpiWriteResponse = Result<(), SpiWriteFail>;
 
+pub type SpiTargetResponse = Result<(), SpiTargetFail>;
+
+/// Whether a device answered the reset with a presence pulse.
+pub type OneWireResetResponse = Result<bool, OneWireFail>;
+/// Level sampled during the slot.
+pub type OneWireBitResponse = Result<bool, OneWireFail>;
+pub type OneWireWriteResponse = Result<(), OneWireFail>;
+
+#[cfg(feature = "use-std")]
+pub type OneWireReadResponse<'a> = Result<Vec<u8>, OneWireFail>;
+#[cfg(not(feature = "use-std"))]
+pub type OneWireReadResponse<'a> = Result<&'a [u8], OneWireFail>;
+
+pub type OneWireSearchResponse =
+    Result<heapless::Vec<OneWireRom, ONEWIRE_SEARCH_MAX_DEVICES>, OneWireFail>;
+
+pub type LedsWriteResponse = Result<(), LedsFail>;
+
+pub type PioResponse = Result<(), PioFail>;
+/// Offset the program was loaded at.
+pub type PioLoadResponse = Result<u8, PioFail>;
+/// Number of words pushed before the timeout.
+pub type PioPushResponse = Result<u16, PioFail>;
+/// Words pulled before the timeout.
+pub type PioPullResponse = Result<heapless::Vec<u32, PIO_MAX_WORDS>, PioFail>;
+
+pub type CaptureResponse = Result<(), CaptureFail>;
+
+#[cfg(feature = "use-std")]
+pub type CaptureReadResponse<'a> = Result<Vec<u8>, CaptureFail>;
+#[cfg(not(feature = "use-std"))]
+pub type CaptureReadResponse<'a> = Result<&'a [u8], CaptureFail>;
+
+pub type SniffResponse = Result<(), SniffFail>;
+
 #[cfg(feature = "use-std")]
 pub type SpiReadResponse<'a> = Result<Vec<u8>, SpiReadFail>;
 #[cfg(not(feature = "use-std"))]
 pub type SpiReadResponse<'a> = Result<&'a [u8], SpiReadFail>;
 
 pub type SpiFlushResponse = Result<(), SpiFlushFail>;
+
+#[cfg(feature = "use-std")]
+pub type SpiTransactionResponse<'a> = Result<Vec<u8>, SpiTransactionFail>;
+#[cfg(not(feature = "use-std"))]
+pub type SpiTransactionResponse<'a> = Result<&'a [u8], SpiTransactionFail>;
+
+pub type SpiAddDeviceResponse = Result<SpiDeviceSlot, SpiAddDeviceFail>;
+pub type SpiRemoveDeviceResponse = Result<(), SpiRemoveDeviceFail>;
+
 pub type GpioGetResponse = Result<GpioState, GpioGetFail>;
 pub type GpioPutResponse = Result<(), GpioPutFail>;
 pub type GpioWaitResponse = Result<(), GpioWaitFail>;
-pub type SetConfigurationResponse = Result<(), SetConfigurationFail>;
+pub type GpioMeasureResponse = Result<GpioMeasurement, GpioMeasureFail>;
+pub type I2cSetConfigResponse = Result<u32, I2cSetConfigFail>;
+pub type SpiSetConfigResponse = Result<u32, SpiSetConfigFail>;
 
 endpoints! {
     list = ENDPOINT_LIST;
-    | EndpointTy         | RequestTy               | ResponseTy               | Path                |
-    | ----------         | ---------               | ----------               | ----                |
-    | PingEndpoint       | u32                     | u32                      | "ping"              |
-    | I2cRead            | I2cReadRequest          | I2cReadResponse<'a>      | "i2c/read"          |
-    | I2cWrite           | I2cWriteRequest<'a>     | I2cWriteResponse         | "i2c/write"         |
-    | SpiRead            | SpiReadRequest          | SpiReadResponse<'a>      | "spi/read"          |
-    | SpiWrite           | SpiWriteRequest<'a>     | SpiWriteResponse         | "spi/write"         |
-    | SpiFlush           | ()                      | SpiFlushResponse         | "spi/flush"         |
-    | GpioGet            | GpioGetRequest          | GpioGetResponse          | "gpio/get"          |
-    | GpioPut            | GpioPutRequest          | GpioPutResponse          | "gpio/put"          |
-    | GpioWaitForHigh    | GpioWaitRequest         | GpioWaitResponse         | "gpio/wait-high"    |
-    | GpioWaitForLow     | GpioWaitRequest         | GpioWaitResponse         | "gpio/wait-low"     |
-    | GpioWaitForRising  | GpioWaitRequest         | GpioWaitResponse         | "gpio/wait-rising"  |
-    | GpioWaitForFalling | GpioWaitRequest         | GpioWaitResponse         | "gpio/wait-falling" |
-    | GpioWaitForAny     | GpioWaitRequest         | GpioWaitResponse         | "gpio/wait-any"     |
-    | SetConfiguration   | SetConfigurationRequest | SetConfigurationResponse | "set-config"        |
-    | Version            | ()                      | VersionInfo              | "version"           |
+    | EndpointTy           | RequestTy                       | ResponseTy                 | Path                     |
+    | ----------           | ---------                       | ----------                 | ----                     |
+    | PingEndpoint         | u32                             | u32                        | "ping"                   |
+    | I2cRead              | I2cReadRequest                  | I2cReadResponse<'a>        | "i2c/read"               |
+    | I2cWrite             | I2cWriteRequest<'a>             | I2cWriteResponse           | "i2c/write"              |
+    | I2cRecover           | I2cRecoverRequest               | I2cRecoverResponse         | "i2c/recover"            |
+    | I2cScan              | I2cScanRequest                  | I2cScanResponse            | "i2c/scan"               |
+    | Smbus                | SmbusRequest<'a>                | SmbusResponse<'b>          | "smbus"                  |
+    | SmbusAlert           | SmbusAlertRequest               | SmbusAlertResponse         | "smbus/alert"            |
+    | I2cTriggerArm        | I2cTriggerArmRequest            | I2cTriggerArmResponse      | "i2c/trigger/arm"        |
+    | I2cTriggerDisarm     | I2cTriggerDisarmRequest         | I2cTriggerDisarmResponse   | "i2c/trigger/disarm"     |
+    | I2cTargetStart       | I2cTargetStartRequest           | I2cTargetResponse          | "i2c/target/start"       |
+    | I2cTargetStop        | I2cTargetStopRequest            | I2cTargetResponse          | "i2c/target/stop"        |
+    | I2cTargetLoad        | I2cTargetLoadRequest<'a>        | I2cTargetResponse          | "i2c/target/load"        |
+    | I2cTargetDump        | I2cTargetDumpRequest            | I2cTargetDumpResponse<'b>  | "i2c/target/dump"        |
+    | SpiRead              | SpiReadRequest                  | SpiReadResponse<'a>        | "spi/read"               |
+    | SpiWrite             | SpiWriteRequest<'a>             | SpiWriteResponse           | "spi/write"              |
+    | SpiFlush             | SpiFlushRequest                 | SpiFlushResponse           | "spi/flush"              |
+    | SpiTransaction       | SpiTransactionRequest<'a>       | SpiTransactionResponse<'b> | "spi/transaction"        |
+    | SpiAddDevice         | SpiAddDeviceRequest<'a>         | SpiAddDeviceResponse       | "spi/device/add"         |
+    | SpiRemoveDevice      | SpiRemoveDeviceRequest          | SpiRemoveDeviceResponse    | "spi/device/remove"      |
+    | SpiDeviceTransaction | SpiDeviceTransactionRequest<'a> | SpiTransactionResponse<'b> | "spi/device/transaction" |
+    | SpiTargetStart       | SpiTargetStartRequest           | SpiTargetResponse          | "spi/target/start"       |
+    | SpiTargetStop        | SpiTargetStopRequest            | SpiTargetResponse          | "spi/target/stop"        |
+    | SpiTargetLoad        | SpiTargetLoadRequest<'a>        | SpiTargetResponse          | "spi/target/load"        |
+    | SpiTargetCommand     | SpiTargetCommandRequest<'a>     | SpiTargetResponse          | "spi/target/command"     |
+    | SpiTargetClear       | SpiTargetClearRequest           | SpiTargetResponse          | "spi/target/clear"       |
+    | OneWireReset         | OneWireResetRequest             | OneWireResetResponse       | "onewire/reset"          |
+    | OneWireBit           | OneWireBitRequest               | OneWireBitResponse         | "onewire/bit"            |
+    | OneWireWrite         | OneWireWriteRequest<'a>         | OneWireWriteResponse       | "onewire/write"          |
+    | OneWireRead          | OneWireReadRequest              | OneWireReadResponse<'b>    | "onewire/read"           |
+    | OneWireSearch        | OneWireSearchRequest            | OneWireSearchResponse      | "onewire/search"         |
+    | LedsWrite            | LedsWriteRequest<'a>            | LedsWriteResponse          | "leds/write"             |
+    | PioLoad              | PioLoadRequest                  | PioLoadResponse            | "pio/load"               |
+    | PioStart             | PioStartRequest                 | PioResponse                | "pio/start"              |
+    | PioStop              | PioStopRequest                  | PioResponse                | "pio/stop"               |
+    | PioExec              | PioExecRequest                  | PioResponse                | "pio/exec"               |
+    | PioPush              | PioPushRequest                  | PioPushResponse            | "pio/push"               |
+    | PioPull              | PioPullRequest                  | PioPullResponse            | "pio/pull"               |
+    | CaptureStart         | CaptureStartRequest             | CaptureResponse            | "capture/start"          |
+    | CaptureStop          | ()                              | CaptureResponse            | "capture/stop"           |
+    | CaptureRead          | CaptureReadRequest              | CaptureReadResponse<'b>    | "capture/read"           |
+    | SniffI2cStart        | SniffI2cStartRequest            | SniffResponse              | "sniff/i2c/start"        |
+    | SniffSpiStart        | SniffSpiStartRequest            | SniffResponse              | "sniff/spi/start"        |
+    | SniffStop            | ()                              | SniffResponse              | "sniff/stop"             |
+    | GpioGet              | GpioGetRequest                  | GpioGetResponse            | "gpio/get"               |
+    | GpioPut              | GpioPutRequest                  | GpioPutResponse            | "gpio/put"               |
+    | GpioWaitForHigh      | GpioWaitRequest                 | GpioWaitResponse           | "gpio/wait-high"         |
+    | GpioWaitForLow       | GpioWaitRequest                 | GpioWaitResponse           | "gpio/wait-low"          |
+    | GpioWaitForRising    | GpioWaitRequest                 | GpioWaitResponse           | "gpio/wait-rising"       |
+    | GpioWaitForFalling   | GpioWaitRequest                 | GpioWaitResponse           | "gpio/wait-falling"      |
+    | GpioWaitForAny       | GpioWaitRequest                 | GpioWaitResponse           | "gpio/wait-any"          |
+    | GpioMeasure          | GpioMeasureRequest              | GpioMeasureResponse        | "gpio/measure"           |
+    | I2cSetConfig         | I2cSetConfigRequest             | I2cSetConfigResponse       | "i2c/set-config"         |
+    | SpiSetConfig         | SpiSetConfigRequest             | SpiSetConfigResponse       | "spi/set-config"         |
+    | GetConfiguration     | ()                              | Configuration              | "get-config"             |
+    | Version              | ()                              | VersionInfo                | "version"                |
+}
+
+// Endpoints served by the host-side broker (see `gallo daemon`), they
+// never reach the firmware.
+endpoints! {
+    list = BROKER_ENDPOINT_LIST;
+    | EndpointTy   | RequestTy | ResponseTy | Path            |
+    | ----------   | --------- | ---------- | ----            |
+    | BrokerLock   | ()        | ()         | "broker/lock"   |
+    | BrokerUnlock | ()        | ()         | "broker/unlock" |
 }
 
 topics! {
@@ -60,15 +185,39 @@ topics! {
 topics! {
     list = TOPICS_OUT_LIST;
     direction = TopicDirection::ToClient;
-    | TopicTy | MessageTy | Path | Cfg |
-    | ------- | --------- | ---- | --- |
+    | TopicTy         | MessageTy       | Path          | Cfg |
+    | -------         | ---------       | ----          | --- |
+    | I2cTriggerTopic | I2cTriggerEvent | "i2c/trigger" |     |
+    | I2cTargetTopic  | I2cTargetEvent  | "i2c/target"  |     |
+    | SpiTargetTopic  | SpiTargetEvent  | "spi/target"  |     |
+    | CaptureTopic    | CaptureEvent    | "capture"     |     |
+    | I2cSniffTopic   | I2cSniffEvent   | "sniff/i2c"   |     |
+    | SpiSniffTopic   | SpiSniffEvent   | "sniff/spi"   |     |
 }
 
 // --- I2C
 
+/// Largest 10-bit I2C address.
+pub const I2C_TEN_BIT_ADDRESS_MAX: u16 = 0x3ff;
+
+/// Address of an I2C target.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum I2cAddress {
+    SevenBit(u8),
+    /// Up to [`I2C_TEN_BIT_ADDRESS_MAX`].
+    TenBit(u16),
+}
+
+impl From<u8> for I2cAddress {
+    fn from(value: u8) -> Self {
+        Self::SevenBit(value)
+    }
+}
+
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
 pub struct I2cReadRequest {
-    pub address: u8,
+    pub bus: u8,
+    pub address: I2cAddress,
     pub count: u16,
 }
 
@@ -77,17 +226,491 @@ pub struct I2cReadFail;
 
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
 pub struct I2cWriteRequest<'a> {
-    pub address: u8,
+    pub bus: u8,
+    pub address: I2cAddress,
     pub contents: &'a [u8],
 }
 
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
 pub struct I2cWriteFail;
 
+/// Clock a target holding SDA low out of its transfer, then issue a
+/// STOP.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cRecoverRequest {
+    pub bus: u8,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cRecoverFail;
+
+/// How each address is probed by an I2C scan.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy, Default)]
+pub enum I2cScanMode {
+    /// Address the target for writing and STOP straight away, no data
+    /// reaches it.
+    #[default]
+    Write,
+    /// Read a byte from the target, for parts ignoring zero-length
+    /// writes. Beware that reading has side effects on some parts.
+    Read,
+}
+
+/// Probe every address of an I2C bus.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cScanRequest {
+    pub bus: u8,
+    pub mode: I2cScanMode,
+    /// Probe reserved addresses too, see [`i2c_address_is_reserved`].
+    pub reserved: bool,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cScanFail;
+
+/// Set of 7-bit I2C addresses, e.g. those which acknowledged a scan.
+///
+/// Address `n` is bit `n % 8` of byte `n / 8`.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct I2cAddressMap(pub [u8; 16]);
+
+impl I2cAddressMap {
+    pub fn insert(&mut self, address: u8) {
+        let address = usize::from(address & 0x7f);
+        self.0[address / 8] |= 1 << (address % 8);
+    }
+
+    pub fn contains(&self, address: u8) -> bool {
+        let address = usize::from(address & 0x7f);
+        self.0[address / 8] & (1 << (address % 8)) != 0
+    }
+
+    /// Addresses in the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=0x7f).filter(|&address| self.contains(address))
+    }
+}
+
+#[cfg(test)]
+mod i2c_address_map_tests {
+    use super::{I2cAddressMap, i2c_address_is_reserved};
+
+    #[test]
+    fn empty() {
+        let map = I2cAddressMap::default();
+        assert!((0..=0xff).all(|address| !map.contains(address)));
+        assert_eq!(map.iter().next(), None);
+    }
+
+    #[test]
+    fn insert_sets_one_bit() {
+        for (address, byte, bit) in [(0x00, 0, 0), (0x07, 0, 7), (0x08, 1, 0), (0x7f, 15, 7)] {
+            let mut map = I2cAddressMap::default();
+            map.insert(address);
+
+            let mut expected = [0; 16];
+            expected[byte] = 1 << bit;
+            assert_eq!(map.0, expected, "{address:#04x}");
+            assert!(map.contains(address));
+            assert!(map.iter().eq([address]));
+        }
+    }
+
+    #[test]
+    fn masks_the_read_bit() {
+        let mut map = I2cAddressMap::default();
+        map.insert(0x80 | 0x50);
+
+        assert_eq!(map, {
+            let mut map = I2cAddressMap::default();
+            map.insert(0x50);
+            map
+        });
+        assert!(map.contains(0x50));
+        assert!(map.contains(0xd0));
+        assert!(map.iter().eq([0x50]));
+    }
+
+    #[test]
+    fn iter_ascending() {
+        let mut map = I2cAddressMap::default();
+        for address in [0x7f, 0x08, 0x50, 0x00, 0x07, 0x50] {
+            map.insert(address);
+        }
+
+        assert!(map.iter().eq([0x00, 0x07, 0x08, 0x50, 0x7f]));
+        assert!(!map.contains(0x09));
+    }
+
+    #[test]
+    fn reserved_addresses() {
+        assert!((0x00..=0x07).all(i2c_address_is_reserved));
+        assert!((0x08..=0x77).all(|address| !i2c_address_is_reserved(address)));
+        assert!((0x78..=0x7f).all(i2c_address_is_reserved));
+    }
+}
+
+/// Whether `address` is reserved by the I2C specification, for general
+/// call, CBUS, high speed mode, 10-bit addressing and the like.
+pub const fn i2c_address_is_reserved(address: u8) -> bool {
+    matches!(address, 0x00..=0x07 | 0x78..=0x7f)
+}
+
+// --- SMBus
+
+/// Longest SMBus block, in bytes.
+pub const SMBUS_BLOCK_MAX_LEN: usize = 32;
+
+/// SMBus protocol, `command` being the first byte written after the
+/// address.
+///
+/// Words go over the wire LSB first.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum SmbusOperation<'a> {
+    /// Address the target and STOP, the R/W bit being the only data.
+    /// Never protected by a PEC.
+    QuickCommand {
+        read: bool,
+    },
+    SendByte(u8),
+    /// Answers one byte.
+    ReceiveByte,
+    WriteByte {
+        command: u8,
+        value: u8,
+    },
+    WriteWord {
+        command: u8,
+        value: u16,
+    },
+    /// Answers one byte.
+    ReadByte {
+        command: u8,
+    },
+    /// Answers two bytes, LSB first.
+    ReadWord {
+        command: u8,
+    },
+    /// Write up to [`SMBUS_BLOCK_MAX_LEN`] bytes, preceded by their
+    /// count.
+    BlockWrite {
+        command: u8,
+        data: &'a [u8],
+    },
+    /// Answers as many bytes as the count sent by the target.
+    BlockRead {
+        command: u8,
+    },
+    /// Write `value` and read a word back in the same transaction.
+    /// Answers two bytes, LSB first.
+    ProcessCall {
+        command: u8,
+        value: u16,
+    },
+}
+
+/// Run an SMBus operation on an I2C bus.
+///
+/// The whole operation runs on the firmware, nothing else is scheduled
+/// between its START and STOP.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SmbusRequest<'a> {
+    pub bus: u8,
+    pub address: u8,
+    /// Append a Packet Error Code to writes, and check the one ending
+    /// reads.
+    pub pec: bool,
+    #[serde(borrow)]
+    pub operation: SmbusOperation<'a>,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum SmbusFail {
+    /// There's no such I2C bus.
+    InvalidBus,
+    /// The bus wasn't idle to begin with.
+    BusBusy,
+    /// The target didn't acknowledge, or held SCL low for too long.
+    NoAcknowledge,
+    /// A block is longer than [`SMBUS_BLOCK_MAX_LEN`].
+    BlockTooLong,
+    /// The PEC sent by the target doesn't match the data.
+    PecMismatch,
+}
+
+impl core::fmt::Display for SmbusFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidBus => write!(f, "invalid I2C bus"),
+            Self::BusBusy => write!(f, "bus busy"),
+            Self::NoAcknowledge => write!(f, "no acknowledge"),
+            Self::BlockTooLong => write!(f, "block longer than {SMBUS_BLOCK_MAX_LEN} bytes"),
+            Self::PecMismatch => write!(f, "PEC mismatch"),
+        }
+    }
+}
+
+/// CRC-8 used as SMBus Packet Error Code, polynomial x^8 + x^2 + x + 1,
+/// continuing from `crc` over `data`.
+pub const fn smbus_pec(mut crc: u8, data: &[u8]) -> u8 {
+    let mut i = 0;
+    while i < data.len() {
+        crc ^= data[i];
+
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+
+        i += 1;
+    }
+
+    crc
+}
+
+#[cfg(test)]
+mod smbus_pec_tests {
+    use super::smbus_pec;
+
+    #[test]
+    fn check_value() {
+        // CRC-8/SMBUS check value
+        assert_eq!(smbus_pec(0, b"123456789"), 0xf4);
+    }
+
+    #[test]
+    fn empty_data_keeps_crc() {
+        assert_eq!(smbus_pec(0, &[]), 0);
+        assert_eq!(smbus_pec(0x5a, &[]), 0x5a);
+    }
+
+    #[test]
+    fn continues_from_crc() {
+        assert_eq!(smbus_pec(smbus_pec(0, b"1234"), b"56789"), 0xf4);
+    }
+
+    #[test]
+    fn read_word_transfer() {
+        // MLX90614 datasheet: read word 0x07 at 0x5a, answering 0x3ad2
+        assert_eq!(smbus_pec(0, &[0xb4, 0x07, 0xb5, 0xd2, 0x3a]), 0x30);
+    }
+
+    #[test]
+    fn zero_over_data_and_pec() {
+        assert_eq!(smbus_pec(0, &[0xb4, 0x07, 0xb5, 0xd2, 0x3a, 0x30]), 0);
+    }
+}
+
+/// SMBus Alert Response Address, read from to find which device
+/// asserts SMBALERT#.
+pub const SMBUS_ALERT_RESPONSE_ADDRESS: u8 = 0x0c;
+
+/// Read the Alert Response Address. The device asserting SMBALERT#
+/// with the lowest address answers its own address and releases the
+/// line, [`SmbusFail::NoAcknowledge`] tells none does.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SmbusAlertRequest {
+    pub bus: u8,
+    /// Check the PEC following the address.
+    pub pec: bool,
+}
+
+// --- I2C triggers
+
+/// Number of I2C triggers which can be armed at once.
+pub const I2C_TRIGGER_MAX_SLOTS: usize = 4;
+
+/// Most bytes a trigger reads.
+pub const I2C_TRIGGER_MAX_LEN: usize = 32;
+
+/// Edge of a GPIO firing a trigger.
+#[derive(Serialize, Deserialize, Schema, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum GpioEdge {
+    Rising,
+    /// Usual for active-low interrupt lines, such as SMBALERT#.
+    #[default]
+    Falling,
+    Any,
+}
+
+/// What a trigger does on the I2C bus when fired.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum I2cTriggerAction {
+    /// Read `count` bytes from `address`, after writing `register`
+    /// with a repeated START if any.
+    Read {
+        address: u8,
+        register: Option<u8>,
+        count: u8,
+    },
+    /// Read the Alert Response Address, see [`SmbusAlertRequest`].
+    /// Repeated while the GPIO stays low and a device answers, so that
+    /// every device asserting SMBALERT# is reported.
+    AlertResponse { pec: bool },
+}
+
+/// Perform `action` on I2C `bus` on every `edge` of GPIO `pin`, each
+/// result being published as an [`I2cTriggerEvent`].
+///
+/// The pin is reserved for the trigger until it's disarmed, other GPIO
+/// requests for it fail.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cTriggerArmRequest {
+    pub pin: u8,
+    pub edge: GpioEdge,
+    pub bus: u8,
+    pub action: I2cTriggerAction,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum I2cTriggerArmFail {
+    InvalidPin,
+    InvalidBus,
+    /// More than [`I2C_TRIGGER_MAX_LEN`] bytes to read.
+    ReadTooLong,
+    /// The pin is in use, e.g. by another trigger.
+    PinBusy,
+    /// All [`I2C_TRIGGER_MAX_SLOTS`] triggers are armed.
+    NoFreeSlot,
+}
+
+impl core::fmt::Display for I2cTriggerArmFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidPin => write!(f, "invalid GPIO pin"),
+            Self::InvalidBus => write!(f, "invalid I2C bus"),
+            Self::ReadTooLong => write!(f, "read longer than {I2C_TRIGGER_MAX_LEN} bytes"),
+            Self::PinBusy => write!(f, "GPIO pin in use"),
+            Self::NoFreeSlot => write!(f, "all {I2C_TRIGGER_MAX_SLOTS} triggers armed"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cTriggerDisarmRequest {
+    pub slot: u8,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cTriggerDisarmFail;
+
+/// Result of a trigger firing.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+pub struct I2cTriggerEvent {
+    pub slot: u8,
+    /// Time of the edge since the firmware started, in µs.
+    pub timestamp_us: u64,
+    /// Bytes read, or the address answered to the Alert Response
+    /// Address.
+    pub result: Result<heapless::Vec<u8, I2C_TRIGGER_MAX_LEN>, SmbusFail>,
+}
+
+// --- I2C target
+
+/// Size of the register file backing an emulated I2C target.
+pub const I2C_TARGET_REGISTERS: usize = 256;
+
+/// Answer as an I2C target at 7-bit `address` on `bus`, until stopped.
+/// The bus can't be used as a controller meanwhile.
+///
+/// The target is backed by a register file. The first byte of a write
+/// selects a register, the following ones are stored from there on.
+/// Reads are answered from the selected register on. Both wrap around
+/// after the last register.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cTargetStartRequest {
+    pub bus: u8,
+    pub address: u8,
+    /// Also listen to the general call address.
+    pub general_call: bool,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cTargetStopRequest {
+    pub bus: u8,
+}
+
+/// Store `data` in the register file of `bus` from `register` on,
+/// whether the target is running or not.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cTargetLoadRequest<'a> {
+    pub bus: u8,
+    pub register: u8,
+    pub data: &'a [u8],
+}
+
+/// Read `count` registers of `bus` from `register` on.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cTargetDumpRequest {
+    pub bus: u8,
+    pub register: u8,
+    pub count: u16,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum I2cTargetFail {
+    /// There's no such I2C bus.
+    InvalidBus,
+    /// The address isn't a 7-bit one, or is reserved.
+    InvalidAddress,
+    AlreadyRunning,
+    NotRunning,
+    /// The access goes past the last register.
+    OutOfRange,
+}
+
+impl core::fmt::Display for I2cTargetFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidBus => write!(f, "invalid I2C bus"),
+            Self::InvalidAddress => write!(f, "invalid target address"),
+            Self::AlreadyRunning => write!(f, "target already running"),
+            Self::NotRunning => write!(f, "target not running"),
+            Self::OutOfRange => write!(f, "past the last of {I2C_TARGET_REGISTERS} registers"),
+        }
+    }
+}
+
+/// Access of the controller to an emulated target.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+pub enum I2cTargetAccess {
+    /// `data` was stored from `register` on. No data means the
+    /// register was only selected.
+    Write {
+        register: u8,
+        data: heapless::Vec<u8, I2C_TARGET_REGISTERS>,
+    },
+    /// `count` registers were read from `register` on.
+    Read { register: u8, count: u16 },
+    /// `data` was sent to the general call address.
+    GeneralCall {
+        data: heapless::Vec<u8, I2C_TARGET_REGISTERS>,
+    },
+    /// The transfer was interrupted, e.g. by the controller losing
+    /// arbitration.
+    Aborted,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+pub struct I2cTargetEvent {
+    pub bus: u8,
+    /// Time of the STOP or repeated START ending the access since the
+    /// firmware started, in µs.
+    pub timestamp_us: u64,
+    pub access: I2cTargetAccess,
+}
+
 // --- SPI
 
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
 pub struct SpiReadRequest {
+    pub bus: u8,
+    /// Number of bytes to read, two per word with 16-bit words.
     pub count: u16,
 }
 
@@ -96,15 +719,1217 @@ pub struct SpiReadFail;
 
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
 pub struct SpiWriteRequest<'a> {
+    pub bus: u8,
+    /// Bytes to write. With 16-bit words, each word is encoded as two
+    /// little-endian bytes, as it's sent back by `SpiRead`.
     pub contents: &'a [u8],
 }
 
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
 pub struct SpiWriteFail;
 
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiFlushRequest {
+    pub bus: u8,
+}
+
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
 pub struct SpiFlushFail;
 
+/// Largest number of operations in a single `SpiTransaction`.
+pub const SPI_MAX_OPERATIONS: usize = 32;
+
+#[cfg(feature = "use-std")]
+pub type SpiOperations<'a> = Vec<SpiOperation<'a>>;
+#[cfg(not(feature = "use-std"))]
+pub type SpiOperations<'a> = heapless::Vec<SpiOperation<'a>, SPI_MAX_OPERATIONS>;
+
+/// Operations run back to back by the firmware while the chip select
+/// is asserted.
+///
+/// Lengths are in bytes, two per word with 16-bit words.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum SpiOperation<'a> {
+    /// Read `count` bytes, shifting out zeros.
+    Read(u16),
+    /// Write the given bytes, discarding what's read.
+    Write(&'a [u8]),
+    /// Write `write` while reading `read` bytes, clocking as many
+    /// bytes as the longest of both.
+    Transfer { read: u16, write: &'a [u8] },
+    /// Write the given bytes, reading as many back.
+    TransferInPlace(&'a [u8]),
+    /// Wait for the given number of nanoseconds.
+    DelayNs(u32),
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum SpiCsPolarity {
+    ActiveLow,
+    ActiveHigh,
+}
+
+/// Chip select driven by the firmware around a transaction.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub struct SpiChipSelect {
+    /// GPIO used as chip select.
+    pub pin: u8,
+    pub polarity: SpiCsPolarity,
+    /// Delay between asserting CS and the first clock, in nanoseconds.
+    pub setup_ns: u32,
+    /// Delay between the last clock and deasserting CS, in nanoseconds.
+    pub hold_ns: u32,
+}
+
+impl SpiChipSelect {
+    /// Active low chip select on GPIO `pin`, without any delays.
+    pub fn new(pin: u8) -> Self {
+        Self {
+            pin,
+            polarity: SpiCsPolarity::ActiveLow,
+            setup_ns: 0,
+            hold_ns: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiTransactionRequest<'a> {
+    pub bus: u8,
+    pub cs: SpiChipSelect,
+    #[serde(borrow)]
+    pub operations: SpiOperations<'a>,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiTransactionFail;
+
+/// Largest number of SPI devices registered at once.
+pub const SPI_MAX_DEVICES: usize = 8;
+
+/// Longest SPI device name, in bytes.
+pub const SPI_DEVICE_NAME_MAX_LEN: usize = 16;
+
+/// Register an SPI device, whose settings are applied by the firmware
+/// on each of its transactions. Registering a name again updates the
+/// device in place, keeping its slot.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiAddDeviceRequest<'a> {
+    pub bus: u8,
+    pub name: &'a str,
+    pub cs: SpiChipSelect,
+    pub config: SpiConfig,
+}
+
+/// Slot an SPI device was registered in.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub struct SpiDeviceSlot {
+    pub slot: u8,
+    /// SCK frequency achieved by the dividers for this device, in Hz.
+    pub actual_frequency: u32,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum SpiAddDeviceFail {
+    /// The name is empty or longer than `max` bytes.
+    InvalidName { max: u32 },
+    /// There's no such SPI bus.
+    InvalidBus,
+    /// The chip select isn't one of the GPIOs.
+    InvalidPin,
+    /// Every slot is already taken.
+    NoFreeSlot,
+    /// The dividers can't produce an SCK frequency close to the one
+    /// requested, supported frequencies are `min..=max` Hz.
+    FrequencyOutOfRange { min: u32, max: u32 },
+}
+
+impl core::fmt::Display for SpiAddDeviceFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidName { max } => {
+                write!(f, "invalid SPI device name, expected 1..={max} bytes")
+            }
+            Self::InvalidBus => write!(f, "invalid SPI bus"),
+            Self::InvalidPin => write!(f, "invalid SPI chip select pin"),
+            Self::NoFreeSlot => write!(f, "no free SPI device slot"),
+            Self::FrequencyOutOfRange { min, max } => {
+                write!(f, "unsupported SPI frequency, expected {min}..={max} Hz")
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiRemoveDeviceRequest {
+    pub slot: u8,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiRemoveDeviceFail;
+
+/// Transaction on a registered SPI device, with its chip select and
+/// settings.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiDeviceTransactionRequest<'a> {
+    pub slot: u8,
+    #[serde(borrow)]
+    pub operations: SpiOperations<'a>,
+}
+
+// --- SPI target
+
+/// Longest response of an emulated SPI target, and most MOSI bytes
+/// reported per selection.
+pub const SPI_TARGET_MAX_LEN: usize = 256;
+
+/// Largest number of commands an emulated SPI target answers.
+pub const SPI_TARGET_MAX_COMMANDS: usize = 8;
+
+/// Longest command of an emulated SPI target.
+pub const SPI_TARGET_MAX_COMMAND_LEN: usize = 8;
+
+/// Answer as an SPI target on `bus`, until stopped. The bus can't be
+/// used as a controller meanwhile.
+///
+/// The controller's chip select goes to GPIO 5 for bus 0 and GPIO 13,
+/// which is GPIO index 5, for bus 1. Its MOSI goes to the RX pin of the
+/// bus and its MISO to the TX pin. Frames are 8-bit, MSB first. The
+/// SPI block requires chip select to be deasserted between frames with
+/// `SpiPhase::CaptureOnFirstTransition`.
+///
+/// While selected, the target answers from its loaded response, then
+/// with `fill`. Once the bytes received match a command, it answers
+/// with the response of that command instead, from the second byte
+/// following the command on.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiTargetStartRequest {
+    pub bus: u8,
+    pub polarity: SpiPolarity,
+    pub phase: SpiPhase,
+    /// Byte answered past the end of responses.
+    pub fill: u8,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiTargetStopRequest {
+    pub bus: u8,
+}
+
+/// Answer every selection on `bus` with `data`, whether the target is
+/// running or not.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiTargetLoadRequest<'a> {
+    pub bus: u8,
+    pub data: &'a [u8],
+}
+
+/// Answer `command` with `response` on `bus`, replacing the response of
+/// an identical command.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiTargetCommandRequest<'a> {
+    pub bus: u8,
+    pub command: &'a [u8],
+    pub response: &'a [u8],
+}
+
+/// Forget the loaded response and every command of `bus`.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SpiTargetClearRequest {
+    pub bus: u8,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum SpiTargetFail {
+    /// There's no such SPI bus.
+    InvalidBus,
+    AlreadyRunning,
+    NotRunning,
+    /// The chip select pin is in use.
+    PinBusy,
+    /// The command or response is empty or too long.
+    InvalidLength,
+    /// Every command slot is taken.
+    TableFull,
+}
+
+impl core::fmt::Display for SpiTargetFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidBus => write!(f, "invalid SPI bus"),
+            Self::AlreadyRunning => write!(f, "target already running"),
+            Self::NotRunning => write!(f, "target not running"),
+            Self::PinBusy => write!(f, "chip select pin busy"),
+            Self::InvalidLength => write!(
+                f,
+                "commands take 1 to {SPI_TARGET_MAX_COMMAND_LEN} bytes, responses up to {SPI_TARGET_MAX_LEN}"
+            ),
+            Self::TableFull => write!(f, "no more than {SPI_TARGET_MAX_COMMANDS} commands"),
+        }
+    }
+}
+
+/// Selection of an emulated SPI target.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+pub struct SpiTargetEvent {
+    pub bus: u8,
+    /// Time chip select was deasserted since the firmware started, in
+    /// µs.
+    pub timestamp_us: u64,
+    /// Number of bytes clocked, `mosi` only holds the first ones.
+    pub count: u32,
+    pub mosi: heapless::Vec<u8, SPI_TARGET_MAX_LEN>,
+    /// The command matched, in the order commands were added.
+    pub command: Option<u8>,
+}
+
+// --- 1-Wire
+
+/// Most bytes read or written by a single 1-Wire request.
+pub const ONEWIRE_MAX_LEN: usize = 256;
+
+/// Most devices reported by a 1-Wire search.
+pub const ONEWIRE_SEARCH_MAX_DEVICES: usize = 32;
+
+/// ROM command addressing a single device by its ROM code.
+pub const ONEWIRE_MATCH_ROM: u8 = 0x55;
+/// ROM command addressing every device at once.
+pub const ONEWIRE_SKIP_ROM: u8 = 0xcc;
+/// ROM command starting a search of every device.
+pub const ONEWIRE_SEARCH_ROM: u8 = 0xf0;
+/// ROM command starting a search of the devices in an alarm state.
+pub const ONEWIRE_ALARM_SEARCH: u8 = 0xec;
+
+/// Issue a reset pulse on the 1-Wire bus of GPIO `pin`, which needs an
+/// external pull-up.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct OneWireResetRequest {
+    pub pin: u8,
+}
+
+/// Run one time slot on the 1-Wire bus of GPIO `pin`, writing `bit`.
+/// Writing a 1 is how a bit is read.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct OneWireBitRequest {
+    pub pin: u8,
+    pub bit: bool,
+}
+
+/// Write `data` on the 1-Wire bus of GPIO `pin`, LSB first.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct OneWireWriteRequest<'a> {
+    pub pin: u8,
+    pub data: &'a [u8],
+    /// Drive the bus high afterwards for this long, in ms, powering
+    /// parasite devices through e.g. a temperature conversion.
+    pub strong_pullup_ms: u16,
+}
+
+/// Read `count` bytes from the 1-Wire bus of GPIO `pin`.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct OneWireReadRequest {
+    pub pin: u8,
+    pub count: u16,
+}
+
+/// Find the ROM codes of the devices on the 1-Wire bus of GPIO `pin`.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct OneWireSearchRequest {
+    pub pin: u8,
+    /// Only find devices in an alarm state.
+    pub alarm: bool,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum OneWireFail {
+    /// There's no such GPIO.
+    InvalidPin,
+    /// The GPIO is in use, e.g. by a trigger.
+    PinBusy,
+    /// The bus stays low, it's shorted or lacks a pull-up.
+    BusLow,
+    /// More than [`ONEWIRE_MAX_LEN`] bytes.
+    TooLong,
+    /// A ROM code found failed its CRC, devices may have come or gone.
+    SearchFailed,
+}
+
+impl core::fmt::Display for OneWireFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidPin => write!(f, "invalid GPIO"),
+            Self::PinBusy => write!(f, "GPIO busy"),
+            Self::BusLow => write!(f, "bus stays low"),
+            Self::TooLong => write!(f, "more than {ONEWIRE_MAX_LEN} bytes"),
+            Self::SearchFailed => write!(f, "search failed"),
+        }
+    }
+}
+
+/// ROM code of a 1-Wire device: family code, 48-bit serial number and
+/// CRC, in the order they're sent.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct OneWireRom(pub [u8; 8]);
+
+impl OneWireRom {
+    pub fn family(&self) -> u8 {
+        self.0[0]
+    }
+
+    pub fn serial(&self) -> u64 {
+        let mut serial = [0; 8];
+        serial[..6].copy_from_slice(&self.0[1..7]);
+        u64::from_le_bytes(serial)
+    }
+
+    /// Whether the last byte is the CRC of the others.
+    pub fn crc_valid(&self) -> bool {
+        onewire_crc8(&self.0) == 0
+    }
+}
+
+/// As in Linux' sysfs, e.g. `28-0316a279c2ff`.
+impl core::fmt::Display for OneWireRom {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:02x}-{:012x}", self.family(), self.serial())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct OneWireRomParseError;
+
+impl core::fmt::Display for OneWireRomParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "expected a ROM code such as 28-0316a279c2ff")
+    }
+}
+
+/// Parses the `Display` form, computing the CRC.
+impl core::str::FromStr for OneWireRom {
+    type Err = OneWireRomParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (family, serial) = s.split_once('-').ok_or(OneWireRomParseError)?;
+        if family.len() != 2 || serial.len() != 12 {
+            return Err(OneWireRomParseError);
+        }
+        // from_str_radix() would take a sign too
+        if !family
+            .bytes()
+            .chain(serial.bytes())
+            .all(|b| b.is_ascii_hexdigit())
+        {
+            return Err(OneWireRomParseError);
+        }
+
+        let family = u8::from_str_radix(family, 16).map_err(|_| OneWireRomParseError)?;
+        let serial = u64::from_str_radix(serial, 16).map_err(|_| OneWireRomParseError)?;
+
+        let mut rom = [0; 8];
+        rom[0] = family;
+        rom[1..7].copy_from_slice(&serial.to_le_bytes()[..6]);
+        rom[7] = onewire_crc8(&rom[..7]);
+
+        Ok(Self(rom))
+    }
+}
+
+/// Dallas/Maxim CRC-8, as ending ROM codes and most scratchpads. Zero
+/// over data followed by its CRC.
+pub fn onewire_crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0x8c
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod onewire_tests {
+    extern crate std;
+
+    use super::{OneWireRom, OneWireRomParseError, onewire_crc8};
+    use std::string::ToString;
+
+    #[test]
+    fn crc8_check_value() {
+        // CRC-8/MAXIM-DOW check value
+        assert_eq!(onewire_crc8(b"123456789"), 0xa1);
+        assert_eq!(onewire_crc8(&[]), 0);
+    }
+
+    #[test]
+    fn crc8_application_note_rom() {
+        // Maxim application note 27
+        let rom = [0x02, 0x1c, 0xb8, 0x01, 0x00, 0x00, 0x00, 0xa2];
+        assert_eq!(onewire_crc8(&rom[..7]), 0xa2);
+        assert_eq!(onewire_crc8(&rom), 0);
+    }
+
+    #[test]
+    fn rom_fields() {
+        let rom = OneWireRom([0x02, 0x1c, 0xb8, 0x01, 0x00, 0x00, 0x00, 0xa2]);
+        assert_eq!(rom.family(), 0x02);
+        assert_eq!(rom.serial(), 0x0001b81c);
+        assert!(rom.crc_valid());
+        assert_eq!(rom.to_string(), "02-00000001b81c");
+
+        let rom = OneWireRom([0x02, 0x1c, 0xb8, 0x01, 0x00, 0x00, 0x00, 0xa3]);
+        assert!(!rom.crc_valid());
+    }
+
+    #[test]
+    fn rom_parses() {
+        let rom: OneWireRom = "28-0316a279c2ff".parse().unwrap();
+        assert_eq!(
+            rom,
+            OneWireRom([0x28, 0xff, 0xc2, 0x79, 0xa2, 0x16, 0x03, 0xcf])
+        );
+        assert!(rom.crc_valid());
+        assert_eq!(rom.to_string(), "28-0316a279c2ff");
+
+        let rom: OneWireRom = "02-00000001B81C".parse().unwrap();
+        assert_eq!(rom.0, [0x02, 0x1c, 0xb8, 0x01, 0x00, 0x00, 0x00, 0xa2]);
+    }
+
+    #[test]
+    fn rom_rejects_malformed() {
+        for s in [
+            "",
+            "280316a279c2ff",
+            "28-0316a279c2f",
+            "28-0316a279c2ff0",
+            "2-0316a279c2ff",
+            "280-316a279c2ff",
+            "2g-0316a279c2ff",
+            "28-0316a279c2fg",
+            "+8-0316a279c2ff",
+            "28-+316a279c2ff",
+            "28-0316a279c2ff-",
+        ] {
+            assert_eq!(s.parse::<OneWireRom>(), Err(OneWireRomParseError), "{s:?}");
+        }
+    }
+}
+
+// --- Addressable LEDs
+
+/// Most bytes shifted out to a chain of LEDs, i.e. 256 RGB or 192 RGBW
+/// LEDs.
+pub const LEDS_MAX_LEN: usize = 768;
+
+/// Shift `data` out to a chain of WS2812 style LEDs on GPIO `pin`, at
+/// 800 kHz and MSB first, then latch it.
+///
+/// The bytes go out as given, i.e. G, R, B for a WS2812 and G, R, B, W
+/// for an SK6812 RGBW. The pin is left driven low.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct LedsWriteRequest<'a> {
+    pub pin: u8,
+    pub data: &'a [u8],
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum LedsFail {
+    /// There's no such GPIO.
+    InvalidPin,
+    /// The GPIO is in use, e.g. by a trigger.
+    PinBusy,
+    /// More than [`LEDS_MAX_LEN`] bytes.
+    TooLong,
+}
+
+impl core::fmt::Display for LedsFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidPin => write!(f, "invalid GPIO"),
+            Self::PinBusy => write!(f, "GPIO busy"),
+            Self::TooLong => write!(f, "more than {LEDS_MAX_LEN} bytes"),
+        }
+    }
+}
+
+/// Color of an RGB LED such as the WS2812.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Bytes in the order a WS2812 shifts them in.
+    pub const fn grb(&self) -> [u8; 3] {
+        [self.g, self.r, self.b]
+    }
+}
+
+/// Color of an RGBW LED such as the SK6812 RGBW.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Rgbw {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub w: u8,
+}
+
+impl Rgbw {
+    pub const fn new(r: u8, g: u8, b: u8, w: u8) -> Self {
+        Self { r, g, b, w }
+    }
+
+    /// Bytes in the order an SK6812 RGBW shifts them in.
+    pub const fn grbw(&self) -> [u8; 4] {
+        [self.g, self.r, self.b, self.w]
+    }
+}
+
+impl From<Rgb> for Rgbw {
+    fn from(rgb: Rgb) -> Self {
+        Self::new(rgb.r, rgb.g, rgb.b, 0)
+    }
+}
+
+// --- PIO
+
+/// Number of state machines running programs of the host, numbered
+/// from 0. They share a single PIO block.
+pub const PIO_NUM_STATE_MACHINES: usize = 4;
+
+/// Size of the instruction memory shared by the state machines.
+pub const PIO_MAX_INSTRUCTIONS: usize = 32;
+
+/// Most words pushed or pulled by a single request.
+pub const PIO_MAX_WORDS: usize = 64;
+
+/// Lowest state machine clock accepted, in Hz, i.e. the largest
+/// divider of the 150 MHz system clock.
+pub const PIO_MIN_FREQUENCY: u32 = 2_289;
+/// Highest state machine clock accepted, in Hz.
+pub const PIO_MAX_FREQUENCY: u32 = 150_000_000;
+
+/// PIO program assembled on the host, e.g. by `pio::pio_asm!`, whose
+/// `pio::Program` converts into it with the `pio` feature.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+pub struct PioProgram {
+    pub code: heapless::Vec<u16, PIO_MAX_INSTRUCTIONS>,
+    /// Offset the program must be loaded at, wherever it fits
+    /// otherwise.
+    pub origin: Option<u8>,
+    /// Instruction after which execution wraps, relative to the start
+    /// of the program.
+    pub wrap_source: u8,
+    /// Instruction execution wraps to, relative to the start of the
+    /// program.
+    pub wrap_target: u8,
+    pub side_set: PioSideSet,
+}
+
+impl PioProgram {
+    /// Program running `code` in a loop, without side-set. `None` if
+    /// it exceeds [`PIO_MAX_INSTRUCTIONS`].
+    pub fn new(code: &[u16]) -> Option<Self> {
+        Some(Self {
+            code: heapless::Vec::from_slice(code).ok()?,
+            origin: None,
+            wrap_source: (code.len() as u8).saturating_sub(1),
+            wrap_target: 0,
+            side_set: PioSideSet::default(),
+        })
+    }
+
+    /// Load the program at `origin`.
+    pub fn origin(mut self, origin: u8) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Wrap from instruction `source` to `target`.
+    pub fn wrap(mut self, source: u8, target: u8) -> Self {
+        self.wrap_source = source;
+        self.wrap_target = target;
+        self
+    }
+
+    /// Side-set settings, see [`PioSideSet`].
+    pub fn side_set(mut self, bits: u8, optional: bool, pindirs: bool) -> Self {
+        self.side_set = PioSideSet {
+            bits,
+            optional,
+            pindirs,
+        };
+        self
+    }
+}
+
+#[cfg(feature = "pio")]
+impl<const N: usize> From<&pio::Program<N>> for PioProgram {
+    fn from(program: &pio::Program<N>) -> Self {
+        const {
+            assert!(
+                N <= PIO_MAX_INSTRUCTIONS,
+                "program longer than PIO_MAX_INSTRUCTIONS"
+            )
+        };
+
+        Self {
+            code: program.code.iter().copied().collect(),
+            origin: program.origin,
+            wrap_source: program.wrap.source,
+            wrap_target: program.wrap.target,
+            side_set: PioSideSet {
+                bits: program.side_set.bits(),
+                optional: program.side_set.optional(),
+                pindirs: program.side_set.pindirs(),
+            },
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pio"))]
+mod pio_tests {
+    use super::{PioProgram, PioSideSet};
+
+    #[test]
+    fn from_pio_program() {
+        let program = pio::pio_asm!(
+            ".side_set 1 opt",
+            ".origin 4",
+            "set pindirs, 1",
+            ".wrap_target",
+            "out pins, 1 side 0",
+            "nop         side 1",
+            ".wrap",
+        )
+        .program;
+        let converted = PioProgram::from(&program);
+
+        assert_eq!(converted.code.as_slice(), program.code.as_slice());
+        assert_eq!(converted.origin, Some(4));
+        assert_eq!((converted.wrap_source, converted.wrap_target), (2, 1));
+        assert_eq!(
+            converted.side_set,
+            PioSideSet {
+                bits: 2,
+                optional: true,
+                pindirs: false,
+            }
+        );
+    }
+}
+
+/// Side-set settings of a program, as `.side_set` declares them.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy, Default)]
+pub struct PioSideSet {
+    /// Bits of every instruction taken by side-set, the enable bit
+    /// included, as `pio::SideSet::bits` counts them.
+    pub bits: u8,
+    /// Instructions only side-set when they say so.
+    pub optional: bool,
+    /// Side-set drives pin directions rather than levels.
+    pub pindirs: bool,
+}
+
+impl PioSideSet {
+    /// Number of pins side-set drives.
+    pub fn pins(&self) -> u8 {
+        self.bits.saturating_sub(u8::from(self.optional))
+    }
+}
+
+/// Load `program` for state machine `sm`, in place of the one it ran
+/// before. The state machine must be stopped.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct PioLoadRequest {
+    pub sm: u8,
+    pub program: PioProgram,
+}
+
+/// Consecutive GPIOs mapped to a state machine, unused when `count` is
+/// 0.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy, Default)]
+pub struct PioPins {
+    pub base: u8,
+    pub count: u8,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum PioShiftDirection {
+    Left,
+    Right,
+}
+
+/// Shift register settings of a state machine.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub struct PioShift {
+    pub direction: PioShiftDirection,
+    /// Push or pull automatically once `threshold` bits are shifted.
+    pub auto: bool,
+    /// Bits shifted before an automatic or conditional push or pull,
+    /// 1 to 32.
+    pub threshold: u8,
+}
+
+impl Default for PioShift {
+    fn default() -> Self {
+        Self {
+            direction: PioShiftDirection::Right,
+            auto: false,
+            threshold: 32,
+        }
+    }
+}
+
+/// Use of the FIFOs, joining them doubles the depth of the one left.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy, Default)]
+pub enum PioFifoJoin {
+    #[default]
+    Duplex,
+    RxOnly,
+    TxOnly,
+}
+
+/// State machine configuration. GPIOs are numbered as for GPIO
+/// requests.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub struct PioConfig {
+    /// State machine clock in Hz, i.e. instructions per second.
+    pub frequency: u32,
+    pub out_pins: PioPins,
+    pub set_pins: PioPins,
+    pub in_pins: PioPins,
+    /// First GPIO driven by side-set, as many as the program declares.
+    pub side_set_base: u8,
+    /// GPIO tested by `jmp pin`.
+    pub jmp_pin: Option<u8>,
+    /// GPIOs starting as outputs, driven low, bit n for GPIO n. Others
+    /// start as inputs.
+    pub outputs: u8,
+    pub shift_in: PioShift,
+    pub shift_out: PioShift,
+    pub fifo_join: PioFifoJoin,
+}
+
+impl Default for PioConfig {
+    fn default() -> Self {
+        Self {
+            frequency: PIO_MAX_FREQUENCY,
+            out_pins: PioPins::default(),
+            set_pins: PioPins::default(),
+            in_pins: PioPins::default(),
+            side_set_base: 0,
+            jmp_pin: None,
+            outputs: 0,
+            shift_in: PioShift::default(),
+            shift_out: PioShift::default(),
+            fifo_join: PioFifoJoin::default(),
+        }
+    }
+}
+
+impl PioConfig {
+    /// Start building a configuration from the defaults.
+    pub fn builder() -> PioConfigBuilder {
+        PioConfigBuilder(Self::default())
+    }
+
+    /// Check whether the state machine clock can be divided down to
+    /// the frequency.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_frequency(self.frequency, PIO_MIN_FREQUENCY, PIO_MAX_FREQUENCY)
+    }
+}
+
+/// Builder for [`PioConfig`].
+#[derive(Debug, Clone, Copy)]
+pub struct PioConfigBuilder(PioConfig);
+
+impl PioConfigBuilder {
+    /// State machine clock in Hz.
+    pub fn frequency(mut self, frequency: u32) -> Self {
+        self.0.frequency = frequency;
+        self
+    }
+
+    /// GPIOs written by `out pins` and `mov pins`.
+    pub fn out_pins(mut self, base: u8, count: u8) -> Self {
+        self.0.out_pins = PioPins { base, count };
+        self
+    }
+
+    /// GPIOs written by `set pins`.
+    pub fn set_pins(mut self, base: u8, count: u8) -> Self {
+        self.0.set_pins = PioPins { base, count };
+        self
+    }
+
+    /// GPIOs read by `in pins` and `wait pin`.
+    pub fn in_pins(mut self, base: u8, count: u8) -> Self {
+        self.0.in_pins = PioPins { base, count };
+        self
+    }
+
+    pub fn side_set_base(mut self, base: u8) -> Self {
+        self.0.side_set_base = base;
+        self
+    }
+
+    pub fn jmp_pin(mut self, pin: u8) -> Self {
+        self.0.jmp_pin = Some(pin);
+        self
+    }
+
+    /// GPIOs starting as outputs, bit n for GPIO n.
+    pub fn outputs(mut self, outputs: u8) -> Self {
+        self.0.outputs = outputs;
+        self
+    }
+
+    pub fn shift_in(mut self, shift: PioShift) -> Self {
+        self.0.shift_in = shift;
+        self
+    }
+
+    pub fn shift_out(mut self, shift: PioShift) -> Self {
+        self.0.shift_out = shift;
+        self
+    }
+
+    pub fn fifo_join(mut self, fifo_join: PioFifoJoin) -> Self {
+        self.0.fifo_join = fifo_join;
+        self
+    }
+
+    /// Validate and return the configuration.
+    pub fn build(self) -> Result<PioConfig, ConfigError> {
+        self.0.validate()?;
+        Ok(self.0)
+    }
+}
+
+/// Configure state machine `sm`, hand it the GPIOs it maps and start
+/// its program from the top.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct PioStartRequest {
+    pub sm: u8,
+    pub config: PioConfig,
+}
+
+/// Stop state machine `sm`, handing its GPIOs back as inputs.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct PioStopRequest {
+    pub sm: u8,
+}
+
+/// Execute `instruction` on state machine `sm` right away, e.g. to set
+/// up its registers.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct PioExecRequest {
+    pub sm: u8,
+    pub instruction: u16,
+}
+
+/// Push `data` into the TX FIFO of state machine `sm`, waiting up to
+/// `timeout_ms` for room.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct PioPushRequest {
+    pub sm: u8,
+    pub data: heapless::Vec<u32, PIO_MAX_WORDS>,
+    pub timeout_ms: u32,
+}
+
+/// Pull `count` words from the RX FIFO of state machine `sm`, waiting
+/// up to `timeout_ms` for them.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct PioPullRequest {
+    pub sm: u8,
+    pub count: u16,
+    pub timeout_ms: u32,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum PioFail {
+    /// There's no such state machine.
+    InvalidStateMachine,
+    /// The program doesn't fit in the instruction memory left, or at
+    /// its origin.
+    NoSpace,
+    /// No program was loaded for the state machine.
+    NotLoaded,
+    AlreadyRunning,
+    /// The configuration maps GPIOs that don't exist.
+    InvalidPin,
+    /// A GPIO is in use, e.g. by a trigger or another state machine.
+    PinBusy,
+    /// The frequency, a shift threshold, the side-set settings, the
+    /// wrap or the origin of the program are out of range.
+    InvalidConfig,
+    /// More than [`PIO_MAX_WORDS`] words.
+    TooLong,
+}
+
+impl core::fmt::Display for PioFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidStateMachine => write!(f, "invalid state machine"),
+            Self::NoSpace => write!(f, "no room for the program"),
+            Self::NotLoaded => write!(f, "no program loaded"),
+            Self::AlreadyRunning => write!(f, "state machine already running"),
+            Self::InvalidPin => write!(f, "invalid GPIO"),
+            Self::PinBusy => write!(f, "GPIO busy"),
+            Self::InvalidConfig => write!(f, "invalid configuration"),
+            Self::TooLong => write!(f, "more than {PIO_MAX_WORDS} words"),
+        }
+    }
+}
+
+// --- Logic analyzer
+
+/// Number of channels sampled, channel n being pin
+/// [`CAPTURE_FIRST_PIN`] + n.
+pub const CAPTURE_CHANNELS: usize = 16;
+
+/// Pin sampled as channel 0.
+pub const CAPTURE_FIRST_PIN: u8 = 2;
+
+/// What each channel is wired to, bus numbers being those of the
+/// requests.
+pub const CAPTURE_CHANNEL_NAMES: [&str; CAPTURE_CHANNELS] = [
+    "i2c0_sda",
+    "i2c0_scl",
+    "spi0_miso",
+    "spi0_cs",
+    "spi0_sck",
+    "spi0_mosi",
+    "gpio0",
+    "gpio1",
+    "gpio2",
+    "gpio3",
+    "gpio4",
+    "gpio5",
+    "gpio6",
+    "gpio7",
+    "i2c1_sda",
+    "i2c1_scl",
+];
+
+/// Most samples held by a capture.
+pub const CAPTURE_MAX_SAMPLES: u32 = 65_536;
+
+/// Most samples read by a single request.
+pub const CAPTURE_MAX_READ: usize = 256;
+
+/// Lowest sample rate accepted, in Hz, i.e. the largest divider of the
+/// 150 MHz system clock.
+pub const CAPTURE_MIN_RATE: u32 = 2_289;
+/// Highest sample rate accepted, in Hz.
+pub const CAPTURE_MAX_RATE: u32 = 50_000_000;
+
+/// Condition starting a capture.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum CaptureTrigger {
+    /// Start sampling right away.
+    Immediate,
+    /// Start sampling once the `count` channels from `first` on read
+    /// `value`, channel `first` in bit 0. With `edge`, the pattern must
+    /// have been absent first, so that one already present doesn't
+    /// trigger.
+    Pattern {
+        first: u8,
+        count: u8,
+        value: u16,
+        edge: bool,
+    },
+}
+
+impl CaptureTrigger {
+    /// Trigger on `channel` going high.
+    pub fn rising(channel: u8) -> Self {
+        Self::Pattern {
+            first: channel,
+            count: 1,
+            value: 1,
+            edge: true,
+        }
+    }
+
+    /// Trigger on `channel` going low.
+    pub fn falling(channel: u8) -> Self {
+        Self::Pattern {
+            first: channel,
+            count: 1,
+            value: 0,
+            edge: true,
+        }
+    }
+
+    /// Trigger on `channel` being `high`, or low.
+    pub fn level(channel: u8, high: bool) -> Self {
+        Self::Pattern {
+            first: channel,
+            count: 1,
+            value: u16::from(high),
+            edge: false,
+        }
+    }
+}
+
+/// Sample every channel `samples` times at `rate` Hz once `trigger`
+/// fires, then publish a [`CaptureEvent`]. The pins are only read, so
+/// that buses and GPIOs keep working meanwhile.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct CaptureStartRequest {
+    pub rate: u32,
+    /// Rounded up to an even number.
+    pub samples: u32,
+    pub trigger: CaptureTrigger,
+}
+
+/// Read `count` samples of the last capture from `offset` on, each a
+/// little endian `u16` with channel n in bit n.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct CaptureReadRequest {
+    pub offset: u32,
+    pub count: u16,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum CaptureFail {
+    /// A capture is waiting for its trigger or sampling.
+    Running,
+    NotRunning,
+    /// The sample rate is out of range.
+    InvalidRate,
+    /// No samples, more than [`CAPTURE_MAX_SAMPLES`], or a read past
+    /// the samples captured or of more than [`CAPTURE_MAX_READ`].
+    InvalidLength,
+    /// The pattern maps channels that don't exist.
+    InvalidTrigger,
+}
+
+impl core::fmt::Display for CaptureFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Running => write!(f, "capture running"),
+            Self::NotRunning => write!(f, "no capture running"),
+            Self::InvalidRate => write!(
+                f,
+                "sample rate not within {CAPTURE_MIN_RATE} to {CAPTURE_MAX_RATE} Hz"
+            ),
+            Self::InvalidLength => write!(f, "invalid number of samples"),
+            Self::InvalidTrigger => write!(f, "invalid trigger channels"),
+        }
+    }
+}
+
+/// End of a capture.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub struct CaptureEvent {
+    /// Samples captured, none if the capture was stopped.
+    pub samples: u32,
+    /// Actual sample rate in Hz, the system clock divided down.
+    pub rate: u32,
+    /// Time sampling ended since the firmware started, in µs.
+    pub timestamp_us: u64,
+}
+
+// --- Bus sniffer
+
+/// Most bytes reported per I2C transfer or SPI selection.
+pub const SNIFF_MAX_LEN: usize = 64;
+
+/// Decode the traffic of another controller on I2C `bus`, publishing an
+/// [`I2cSniffEvent`] per transfer, until stopped. The pins are only
+/// read, and the bus can't be used as a controller meanwhile.
+///
+/// Sniffing shares its state machines with the logic analyzer, only one
+/// of them runs at a time.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SniffI2cStartRequest {
+    pub bus: u8,
+}
+
+/// Decode the traffic of another controller on SPI `bus`, publishing an
+/// [`SpiSniffEvent`] per selection, until stopped. The bus can't be used
+/// as a controller meanwhile, its SCK and MOSI pins being released.
+///
+/// Chip select is read on the pin of the SPI target, see
+/// [`SpiTargetStartRequest`]. Frames are 8-bit, MSB first, sampled on
+/// the edge given by `polarity` and `phase`.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct SniffSpiStartRequest {
+    pub bus: u8,
+    pub polarity: SpiPolarity,
+    pub phase: SpiPhase,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum SniffFail {
+    InvalidBus,
+    /// A sniffer or a capture is running.
+    Running,
+    NotRunning,
+    /// The bus emulates a target, or its chip select pin is in use.
+    BusBusy,
+}
+
+impl core::fmt::Display for SniffFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidBus => write!(f, "invalid bus"),
+            Self::Running => write!(f, "sniffer or capture running"),
+            Self::NotRunning => write!(f, "no sniffer running"),
+            Self::BusBusy => write!(f, "bus or chip select pin in use"),
+        }
+    }
+}
+
+/// Byte of an I2C transfer along with the acknowledge following it.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub struct I2cSniffByte {
+    pub value: u8,
+    pub ack: bool,
+}
+
+/// I2C transfer, from a START to the next repeated START or STOP.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+pub struct I2cSniffEvent {
+    pub bus: u8,
+    /// Time of the START since the firmware started, in µs.
+    pub timestamp_us: u64,
+    /// 7-bit address, or the first byte of a 10-bit address.
+    pub address: u8,
+    pub read: bool,
+    /// Whether a target acknowledged the address.
+    pub ack: bool,
+    /// Bytes following the address, `count` in all.
+    pub data: heapless::Vec<I2cSniffByte, SNIFF_MAX_LEN>,
+    pub count: u32,
+    /// Whether a STOP ended the transfer, rather than a repeated START.
+    pub stop: bool,
+}
+
+/// Selection of an SPI target.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+pub struct SpiSniffEvent {
+    pub bus: u8,
+    /// Time chip select was asserted since the firmware started, in µs.
+    pub timestamp_us: u64,
+    /// Number of bytes clocked, `mosi` and `miso` only hold the first
+    /// ones.
+    pub count: u32,
+    pub mosi: heapless::Vec<u8, SNIFF_MAX_LEN>,
+    pub miso: heapless::Vec<u8, SNIFF_MAX_LEN>,
+}
+
 // --- GPIO
 
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
@@ -112,6 +1937,7 @@ pub struct GpioGetRequest {
     pub pin: u8,
 }
 
+/// There's no such GPIO, or it's in use.
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
 pub struct GpioGetFail;
 
@@ -121,6 +1947,7 @@ pub struct GpioPutRequest {
     pub state: GpioState,
 }
 
+/// There's no such GPIO, or it's in use.
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
 pub struct GpioPutFail;
 
@@ -135,33 +1962,515 @@ pub struct GpioWaitRequest {
     pub pin: u8,
 }
 
+/// There's no such GPIO, or it's in use.
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
 pub struct GpioWaitFail;
 
-// --- Set config
+/// Longest gate time of a GPIO measurement, in ms.
+pub const GPIO_MEASURE_MAX_GATE_MS: u32 = 10_000;
 
+/// Measure the signal on GPIO `pin` for `gate_ms`, counting its rising
+/// edges and sampling its level. The pin is only read, whatever drives
+/// it.
+///
+/// Measuring borrows the state machines of the logic analyzer, it fails
+/// while a capture or the bus sniffer runs.
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
-pub struct SetConfigurationRequest {
-    pub i2c_frequency: u32,
-    pub spi_frequency: u32,
-    pub spi_phase: SpiPhase,
-    pub spi_polarity: SpiPolarity,
+pub struct GpioMeasureRequest {
+    pub pin: u8,
+    pub gate_ms: u32,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum GpioMeasureFail {
+    InvalidPin,
+    /// No gate time, or more than [`GPIO_MEASURE_MAX_GATE_MS`].
+    InvalidGate,
+    /// A measurement, capture or sniffer is running.
+    Running,
+}
+
+impl core::fmt::Display for GpioMeasureFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidPin => write!(f, "invalid pin"),
+            Self::InvalidGate => {
+                write!(f, "gate time not within 1 to {GPIO_MEASURE_MAX_GATE_MS} ms")
+            }
+            Self::Running => write!(f, "measurement, capture or sniffer running"),
+        }
+    }
+}
+
+/// Counts of a GPIO measurement, from which its frequency, duty cycle
+/// and pulse width follow.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub struct GpioMeasurement {
+    /// Rising edges counted over the gate time.
+    pub edges: u32,
+    /// Actual gate time, in µs.
+    pub gate_us: u64,
+    /// Level samples taken over the gate time, and those reading high.
+    pub samples: u32,
+    pub high_samples: u32,
+}
+
+impl GpioMeasurement {
+    /// Frequency in Hz, to within one edge over the gate time. 0
+    /// without gate time.
+    pub fn frequency(&self) -> f64 {
+        if self.gate_us == 0 {
+            return 0.0;
+        }
+
+        f64::from(self.edges) * 1e6 / self.gate_us as f64
+    }
+
+    /// Period in seconds, `None` without edges or gate time.
+    pub fn period(&self) -> Option<f64> {
+        let frequency = self.frequency();
+        (frequency > 0.0).then(|| 1.0 / frequency)
+    }
+
+    /// Fraction of the time the signal was high, from 0 to 1.
+    pub fn duty_cycle(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+
+        f64::from(self.high_samples) / f64::from(self.samples)
+    }
+
+    /// Average time the signal stays high per period, in seconds.
+    /// `None` without edges or gate time.
+    pub fn pulse_width(&self) -> Option<f64> {
+        self.period().map(|period| period * self.duty_cycle())
+    }
+}
+
+#[cfg(test)]
+mod gpio_measurement_tests {
+    use super::GpioMeasurement;
+
+    #[test]
+    fn one_kilohertz_quarter_duty() {
+        let measurement = GpioMeasurement {
+            edges: 1000,
+            gate_us: 1_000_000,
+            samples: 10_000,
+            high_samples: 2_500,
+        };
+
+        assert_eq!(measurement.frequency(), 1000.0);
+        assert_eq!(measurement.period(), Some(1e-3));
+        assert_eq!(measurement.duty_cycle(), 0.25);
+        assert_eq!(measurement.pulse_width(), Some(250e-6));
+    }
+
+    #[test]
+    fn no_edges() {
+        let measurement = GpioMeasurement {
+            edges: 0,
+            gate_us: 100_000,
+            samples: 1_000,
+            high_samples: 1_000,
+        };
+
+        assert_eq!(measurement.frequency(), 0.0);
+        assert_eq!(measurement.period(), None);
+        assert_eq!(measurement.duty_cycle(), 1.0);
+        assert_eq!(measurement.pulse_width(), None);
+    }
+
+    #[test]
+    fn no_gate_time() {
+        let measurement = GpioMeasurement {
+            edges: 3,
+            gate_us: 0,
+            samples: 0,
+            high_samples: 0,
+        };
+
+        assert_eq!(measurement.frequency(), 0.0);
+        assert_eq!(measurement.period(), None);
+        assert_eq!(measurement.duty_cycle(), 0.0);
+        assert_eq!(measurement.pulse_width(), None);
+    }
+}
+
+// --- Configuration
+
+/// Lowest I2C frequency accepted, in Hz.
+pub const I2C_MIN_FREQUENCY: u32 = 10_000;
+/// Highest I2C frequency accepted (Fast-mode Plus), in Hz.
+pub const I2C_MAX_FREQUENCY: u32 = 1_000_000;
+/// Lowest SPI frequency the dividers can reach from the 150 MHz
+/// peripheral clock, in Hz.
+pub const SPI_MIN_FREQUENCY: u32 = 2_307;
+/// Highest SPI frequency, half the 150 MHz peripheral clock, in Hz.
+pub const SPI_MAX_FREQUENCY: u32 = 75_000_000;
+
+/// Reasons for rejecting a configuration.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConfigError {
+    /// Frequency outside of the `min..=max` range supported by the bus.
+    FrequencyOutOfRange { frequency: u32, min: u32, max: u32 },
+}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::FrequencyOutOfRange {
+                frequency,
+                min,
+                max,
+            } => {
+                write!(f, "frequency {frequency} Hz out of range {min}..={max} Hz")
+            }
+        }
+    }
+}
+
+impl core::error::Error for ConfigError {}
+
+fn check_frequency(frequency: u32, min: u32, max: u32) -> Result<(), ConfigError> {
+    if (min..=max).contains(&frequency) {
+        Ok(())
+    } else {
+        Err(ConfigError::FrequencyOutOfRange {
+            frequency,
+            min,
+            max,
+        })
+    }
+}
+
+/// I2C bus configuration.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub struct I2cConfig {
+    /// SCL frequency in Hz.
+    pub frequency: u32,
+}
+
+impl Default for I2cConfig {
+    fn default() -> Self {
+        Self { frequency: 100_000 }
+    }
+}
+
+impl I2cConfig {
+    /// Start building a configuration from the defaults.
+    pub fn builder() -> I2cConfigBuilder {
+        I2cConfigBuilder(Self::default())
+    }
+
+    /// Check whether the configuration can be applied to the bus.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_frequency(self.frequency, I2C_MIN_FREQUENCY, I2C_MAX_FREQUENCY)
+    }
+}
+
+/// Builder for [`I2cConfig`].
+#[derive(Debug, Clone, Copy)]
+pub struct I2cConfigBuilder(I2cConfig);
+
+impl I2cConfigBuilder {
+    /// SCL frequency in Hz.
+    pub fn frequency(mut self, frequency: u32) -> Self {
+        self.0.frequency = frequency;
+        self
+    }
+
+    /// Validate and return the configuration.
+    pub fn build(self) -> Result<I2cConfig, ConfigError> {
+        self.0.validate()?;
+        Ok(self.0)
+    }
 }
 
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+pub struct I2cSetConfigRequest {
+    pub bus: u8,
+    pub config: I2cConfig,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum I2cSetConfigFail {
+    /// There's no such I2C bus.
+    InvalidBus,
+    /// The dividers can't produce an SCL frequency close to the one
+    /// requested, supported frequencies are `min..=max` Hz.
+    FrequencyOutOfRange { min: u32, max: u32 },
+}
+
+impl core::fmt::Display for I2cSetConfigFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidBus => write!(f, "invalid I2C bus"),
+            Self::FrequencyOutOfRange { min, max } => {
+                write!(f, "unsupported I2C frequency, expected {min}..={max} Hz")
+            }
+        }
+    }
+}
+
+/// SPI bus configuration.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub struct SpiConfig {
+    /// SCK frequency in Hz.
+    pub frequency: u32,
+    pub phase: SpiPhase,
+    pub polarity: SpiPolarity,
+    pub word_size: SpiWordSize,
+    pub bit_order: SpiBitOrder,
+}
+
+impl Default for SpiConfig {
+    fn default() -> Self {
+        Self {
+            frequency: 1_000_000,
+            phase: SpiPhase::CaptureOnFirstTransition,
+            polarity: SpiPolarity::IdleLow,
+            word_size: SpiWordSize::Eight,
+            bit_order: SpiBitOrder::MsbFirst,
+        }
+    }
+}
+
+impl SpiConfig {
+    /// Start building a configuration from the defaults.
+    pub fn builder() -> SpiConfigBuilder {
+        SpiConfigBuilder(Self::default())
+    }
+
+    /// Check whether the configuration can be applied to the bus.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_frequency(self.frequency, SPI_MIN_FREQUENCY, SPI_MAX_FREQUENCY)
+    }
+}
+
+/// Builder for [`SpiConfig`].
+#[derive(Debug, Clone, Copy)]
+pub struct SpiConfigBuilder(SpiConfig);
+
+impl SpiConfigBuilder {
+    /// SCK frequency in Hz.
+    pub fn frequency(mut self, frequency: u32) -> Self {
+        self.0.frequency = frequency;
+        self
+    }
+
+    pub fn phase(mut self, phase: SpiPhase) -> Self {
+        self.0.phase = phase;
+        self
+    }
+
+    pub fn polarity(mut self, polarity: SpiPolarity) -> Self {
+        self.0.polarity = polarity;
+        self
+    }
+
+    pub fn word_size(mut self, word_size: SpiWordSize) -> Self {
+        self.0.word_size = word_size;
+        self
+    }
+
+    pub fn bit_order(mut self, bit_order: SpiBitOrder) -> Self {
+        self.0.bit_order = bit_order;
+        self
+    }
+
+    /// Validate and return the configuration.
+    pub fn build(self) -> Result<SpiConfig, ConfigError> {
+        self.0.validate()?;
+        Ok(self.0)
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn defaults() {
+        assert_eq!(I2cConfig::default().frequency, 100_000);
+        assert_eq!(I2cConfig::builder().build(), Ok(I2cConfig::default()));
+
+        let spi = SpiConfig::default();
+        assert_eq!(spi.frequency, 1_000_000);
+        assert_eq!(spi.phase, SpiPhase::CaptureOnFirstTransition);
+        assert_eq!(spi.polarity, SpiPolarity::IdleLow);
+        assert_eq!(spi.word_size, SpiWordSize::Eight);
+        assert_eq!(spi.bit_order, SpiBitOrder::MsbFirst);
+        assert_eq!(SpiConfig::builder().build(), Ok(spi));
+    }
+
+    #[test]
+    fn builder_sets_fields() {
+        let spi = SpiConfig::builder()
+            .frequency(8_000_000)
+            .phase(SpiPhase::CaptureOnSecondTransition)
+            .polarity(SpiPolarity::IdleHigh)
+            .word_size(SpiWordSize::Sixteen)
+            .bit_order(SpiBitOrder::LsbFirst)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            spi,
+            SpiConfig {
+                frequency: 8_000_000,
+                phase: SpiPhase::CaptureOnSecondTransition,
+                polarity: SpiPolarity::IdleHigh,
+                word_size: SpiWordSize::Sixteen,
+                bit_order: SpiBitOrder::LsbFirst,
+            }
+        );
+    }
+
+    #[test]
+    fn i2c_frequency_bounds() {
+        for frequency in [I2C_MIN_FREQUENCY, 400_000, I2C_MAX_FREQUENCY] {
+            assert!(
+                I2cConfig::builder().frequency(frequency).build().is_ok(),
+                "{frequency}"
+            );
+        }
+
+        for frequency in [0, I2C_MIN_FREQUENCY - 1, I2C_MAX_FREQUENCY + 1] {
+            assert_eq!(
+                I2cConfig::builder().frequency(frequency).build(),
+                Err(ConfigError::FrequencyOutOfRange {
+                    frequency,
+                    min: I2C_MIN_FREQUENCY,
+                    max: I2C_MAX_FREQUENCY,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn spi_frequency_bounds() {
+        for frequency in [SPI_MIN_FREQUENCY, 1_000_000, SPI_MAX_FREQUENCY] {
+            assert!(
+                SpiConfig::builder().frequency(frequency).build().is_ok(),
+                "{frequency}"
+            );
+        }
+
+        for frequency in [0, SPI_MIN_FREQUENCY - 1, SPI_MAX_FREQUENCY + 1] {
+            assert_eq!(
+                SpiConfig::builder().frequency(frequency).build(),
+                Err(ConfigError::FrequencyOutOfRange {
+                    frequency,
+                    min: SPI_MIN_FREQUENCY,
+                    max: SPI_MAX_FREQUENCY,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn validate_checks_fields_set_directly() {
+        let config = I2cConfig { frequency: 5_000 };
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::FrequencyOutOfRange {
+                frequency: 5_000,
+                min: I2C_MIN_FREQUENCY,
+                max: I2C_MAX_FREQUENCY,
+            })
+        );
+
+        let config = SpiConfig {
+            frequency: 100_000_000,
+            ..SpiConfig::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::FrequencyOutOfRange {
+                frequency: 100_000_000,
+                min: SPI_MIN_FREQUENCY,
+                max: SPI_MAX_FREQUENCY,
+            })
+        );
+    }
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
 pub enum SpiPhase {
     CaptureOnFirstTransition = 0,
     CaptureOnSecondTransition = 1,
 }
 
-#[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
 pub enum SpiPolarity {
     IdleLow = 0,
     IdleHigh = 1,
 }
 
+/// Number of bits per SPI frame.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum SpiWordSize {
+    Eight = 8,
+    Sixteen = 16,
+}
+
+impl SpiWordSize {
+    /// Number of bytes taken by each word in requests and responses.
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::Eight => 1,
+            Self::Sixteen => 2,
+        }
+    }
+}
+
+/// Order in which the bits of each word are shifted out.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum SpiBitOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
-pub struct SetConfigurationFail;
+pub struct SpiSetConfigRequest {
+    pub bus: u8,
+    pub config: SpiConfig,
+}
+
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub enum SpiSetConfigFail {
+    /// There's no such SPI bus.
+    InvalidBus,
+    /// The dividers can't produce an SCK frequency close to the one
+    /// requested, supported frequencies are `min..=max` Hz.
+    FrequencyOutOfRange { min: u32, max: u32 },
+}
+
+impl core::fmt::Display for SpiSetConfigFail {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidBus => write!(f, "invalid SPI bus"),
+            Self::FrequencyOutOfRange { min, max } => {
+                write!(f, "unsupported SPI frequency, expected {min}..={max} Hz")
+            }
+        }
+    }
+}
+
+/// Current configuration of every bus, indexed by bus number.
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone, Copy)]
+pub struct Configuration {
+    /// I2C configurations, as requested.
+    pub i2c: [I2cConfig; NUM_I2C_BUSES],
+    /// SPI configurations, as requested.
+    pub spi: [SpiConfig; NUM_SPI_BUSES],
+    /// SCL frequencies achieved by the dividers, in Hz.
+    pub i2c_actual_frequency: [u32; NUM_I2C_BUSES],
+    /// SCK frequencies achieved by the dividers, in Hz.
+    pub spi_actual_frequency: [u32; NUM_SPI_BUSES],
+}
 
 // --- Version
 #[derive(Serialize, Deserialize, Schema, Debug, PartialEq)]
@@ -1,4 +1,4 @@
-use pico_de_gallo_lib::PicoDeGallo;
+use pico_de_gallo_lib::{I2cScanMode, PicoDeGallo, i2c_address_is_reserved};
 
 #[tokio::main]
 pub async fn main() {
@@ -15,6 +15,11 @@ pub async fn main() {
 }
 
 async fn run(gallo: &PicoDeGallo) {
+    let Ok(found) = gallo.i2c_scan(0, I2cScanMode::Write, false).await else {
+        println!("Scan failed");
+        return;
+    };
+
     let mut high = 0;
     print!(
         r#"
@@ -24,29 +29,22 @@ async fn run(gallo: &PicoDeGallo) {
     );
 
     for address in 0..=0x7f_u8 {
-        match address {
-            0x00..=0x07 | 0x78..=0x7f => {
-                print!("RR ");
-            }
-
-            _ => {
-                let result = gallo.i2c_read(address, 1).await;
-                if result.is_ok() {
-                    print!("{:02x} ", address);
-                } else {
-                    print!("-- ");
-                }
-            }
+        if i2c_address_is_reserved(address) {
+            print!("RR ");
+        } else if found.contains(address) {
+            print!("{:02x} ", address);
+        } else {
+            print!("-- ");
         }
 
         if address & 0x0f == 0x0f {
             high += 1;
-            print!("\n");
+            println!();
 
             if high < 8 {
                 print!("{:x} ", high);
             }
         }
     }
-    print!("\n");
+    println!();
 }
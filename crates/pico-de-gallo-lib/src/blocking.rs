@@ -0,0 +1,792 @@
+//! Blocking client for Pico de Gallo.
+//!
+//! Every method blocks the calling thread until the device responds.
+//! No async runtime is required, nor is it an issue to call these
+//! methods from within one, other than blocking it.
+
+use crate::frame::{read_frame_blocking, write_frame_blocking};
+use crate::spawn::ThreadSpawn;
+use crate::{
+    Capture, CaptureEvent, CaptureFail, CaptureTrigger, Configuration, GpioEdge, GpioMeasureFail, GpioMeasurement,
+    GpioState, I2cAddress, I2cAddressMap, I2cConfig, I2cScanMode, I2cSniffEvent, I2cTargetEvent, I2cTargetFail,
+    I2cTriggerAction, I2cTriggerArmFail, I2cTriggerDisarmFail, I2cTriggerEvent, LedsFail, OneWireFail, OneWireRom,
+    PicoDeGalloError, PioConfig, PioFail, PioProgram, Rgb, Rgbw, SmbusFail, SmbusOperation, SniffFail,
+    SpiAddDeviceFail, SpiChipSelect, SpiConfig, SpiDeviceSlot, SpiOperation, SpiPhase, SpiPolarity,
+    SpiRemoveDeviceFail, SpiSniffEvent, SpiTargetEvent, SpiTargetFail, SpiTransactionFail, VersionInfo,
+};
+use futures::executor::block_on;
+use pico_de_gallo_internal::{
+    GpioGetFail, GpioPutFail, GpioWaitFail, I2cReadFail, I2cRecoverFail, I2cScanFail, I2cSetConfigFail, I2cWriteFail,
+    SpiFlushFail, SpiReadFail, SpiSetConfigFail, SpiWriteFail,
+};
+use postcard_rpc::host_client::{WireRx, WireTx};
+use serde::de::DeserializeOwned;
+use std::convert::Infallible;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+
+/// Blocking counterpart of [`crate::PicoDeGallo`].
+#[derive(Clone)]
+pub struct PicoDeGallo {
+    gallo: crate::PicoDeGallo,
+}
+
+impl Default for PicoDeGallo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PicoDeGallo {
+    /// Create a new instance for the Pico de Gallo device.
+    ///
+    /// See [`crate::PicoDeGallo::new`].
+    pub fn new() -> Self {
+        Self {
+            gallo: crate::PicoDeGallo::new(),
+        }
+    }
+
+    /// Create a new instance for the Pico de Gallo device with the
+    /// given serial number.
+    pub fn new_with_serial_number(serial_number: &str) -> Self {
+        Self {
+            gallo: crate::PicoDeGallo::new_with_serial_number(serial_number),
+        }
+    }
+
+    /// Connect to a Pico de Gallo device shared over TCP, e.g. by
+    /// `gallo serve`, listening on `addr`.
+    pub fn connect_tcp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Self::new_with_stream(stream)
+    }
+
+    /// Connect to a Pico de Gallo device shared by the broker daemon,
+    /// e.g. `gallo daemon`, listening on the Unix socket at `path`.
+    #[cfg(unix)]
+    pub fn connect_unix<P: AsRef<std::path::Path>>(path: P) -> io::Result<Self> {
+        Self::new_with_stream(std::os::unix::net::UnixStream::connect(path)?)
+    }
+
+    fn new_with_stream<S: Stream>(stream: S) -> io::Result<Self> {
+        let rx = StreamRx(stream.try_clone()?);
+        let tx = StreamTx(stream);
+
+        Ok(Self {
+            gallo: crate::PicoDeGallo::new_with_wire(tx, rx, ThreadSpawn),
+        })
+    }
+
+    /// The async client this one is wrapping.
+    ///
+    /// Its workers run on threads of their own, so it can be used
+    /// with any executor.
+    pub fn as_async(&self) -> &crate::PicoDeGallo {
+        &self.gallo
+    }
+
+    /// Block until the client has closed the connection.
+    pub fn wait_closed(&self) {
+        block_on(self.gallo.wait_closed())
+    }
+
+    /// Take exclusive access to the device.
+    ///
+    /// See [`crate::PicoDeGallo::lock`].
+    pub fn lock(&self) -> Result<BusLock<'_>, PicoDeGalloError<Infallible>> {
+        Ok(BusLock(block_on(self.gallo.lock())?))
+    }
+
+    /// Ping endpoint.
+    ///
+    /// Only used for testing purposes. Send a `u32` and get the same
+    /// `u32` as a response.
+    pub fn ping(&self, id: u32) -> Result<u32, PicoDeGalloError<Infallible>> {
+        block_on(self.gallo.ping(id))
+    }
+
+    /// Read `count` bytes from the I2C device at `address` on `bus`.
+    pub fn i2c_read(
+        &self,
+        bus: u8,
+        address: impl Into<I2cAddress>,
+        count: u16,
+    ) -> Result<Vec<u8>, PicoDeGalloError<I2cReadFail>> {
+        block_on(self.gallo.i2c_read(bus, address, count))
+    }
+
+    /// Write `contents` to the I2C device at `address` on `bus`.
+    pub fn i2c_write(
+        &self,
+        bus: u8,
+        address: impl Into<I2cAddress>,
+        contents: &[u8],
+    ) -> Result<(), PicoDeGalloError<I2cWriteFail>> {
+        block_on(self.gallo.i2c_write(bus, address, contents))
+    }
+
+    /// Free I2C `bus` from a target holding SDA low, returning whether
+    /// the bus was left free.
+    pub fn i2c_recover(&self, bus: u8) -> Result<bool, PicoDeGalloError<I2cRecoverFail>> {
+        block_on(self.gallo.i2c_recover(bus))
+    }
+
+    /// Probe every address of I2C `bus` as per `mode`, returning those
+    /// which acknowledged.
+    pub fn i2c_scan(
+        &self,
+        bus: u8,
+        mode: I2cScanMode,
+        reserved: bool,
+    ) -> Result<I2cAddressMap, PicoDeGalloError<I2cScanFail>> {
+        block_on(self.gallo.i2c_scan(bus, mode, reserved))
+    }
+
+    /// Run an SMBus `operation` with the target at `address` on I2C
+    /// `bus`, returning the bytes it answers.
+    ///
+    /// The operation runs at 100 kHz from START to STOP on the
+    /// firmware, whatever the bus configuration. When `pec` is set, a
+    /// Packet Error Code is appended to writes and checked at the end
+    /// of reads.
+    pub fn smbus(
+        &self,
+        bus: u8,
+        address: u8,
+        operation: SmbusOperation<'_>,
+        pec: bool,
+    ) -> Result<Vec<u8>, PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus(bus, address, operation, pec))
+    }
+
+    /// SMBus quick command, the R/W bit being the only data.
+    pub fn smbus_quick_command(&self, bus: u8, address: u8, read: bool) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus_quick_command(bus, address, read))
+    }
+
+    /// SMBus send byte.
+    pub fn smbus_send_byte(
+        &self,
+        bus: u8,
+        address: u8,
+        value: u8,
+        pec: bool,
+    ) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus_send_byte(bus, address, value, pec))
+    }
+
+    /// SMBus receive byte.
+    pub fn smbus_receive_byte(&self, bus: u8, address: u8, pec: bool) -> Result<u8, PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus_receive_byte(bus, address, pec))
+    }
+
+    /// SMBus write byte, `value` going to `command`.
+    pub fn smbus_write_byte(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        value: u8,
+        pec: bool,
+    ) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus_write_byte(bus, address, command, value, pec))
+    }
+
+    /// SMBus read byte from `command`.
+    pub fn smbus_read_byte(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        pec: bool,
+    ) -> Result<u8, PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus_read_byte(bus, address, command, pec))
+    }
+
+    /// SMBus write word, `value` going to `command`.
+    pub fn smbus_write_word(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        value: u16,
+        pec: bool,
+    ) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus_write_word(bus, address, command, value, pec))
+    }
+
+    /// SMBus read word from `command`.
+    pub fn smbus_read_word(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        pec: bool,
+    ) -> Result<u16, PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus_read_word(bus, address, command, pec))
+    }
+
+    /// SMBus block write of up to [`crate::SMBUS_BLOCK_MAX_LEN`]
+    /// bytes to `command`.
+    pub fn smbus_block_write(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        data: &[u8],
+        pec: bool,
+    ) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus_block_write(bus, address, command, data, pec))
+    }
+
+    /// SMBus block read from `command`, returning as many bytes as the
+    /// target says it has.
+    pub fn smbus_block_read(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        pec: bool,
+    ) -> Result<Vec<u8>, PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus_block_read(bus, address, command, pec))
+    }
+
+    /// SMBus process call, writing `value` to `command` and returning
+    /// the word the target answers.
+    pub fn smbus_process_call(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        value: u16,
+        pec: bool,
+    ) -> Result<u16, PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus_process_call(bus, address, command, value, pec))
+    }
+
+    /// Read the SMBus Alert Response Address on `bus`, returning the
+    /// address of the device asserting SMBALERT#, if any.
+    pub fn smbus_alert_response(&self, bus: u8, pec: bool) -> Result<Option<u8>, PicoDeGalloError<SmbusFail>> {
+        block_on(self.gallo.smbus_alert_response(bus, pec))
+    }
+
+    /// Perform `action` on I2C `bus` on every `edge` of GPIO `pin`,
+    /// until disarmed. Returns the slot of the trigger.
+    ///
+    /// See [`crate::PicoDeGallo::i2c_trigger_arm`].
+    pub fn i2c_trigger_arm(
+        &self,
+        pin: u8,
+        edge: GpioEdge,
+        bus: u8,
+        action: I2cTriggerAction,
+    ) -> Result<u8, PicoDeGalloError<I2cTriggerArmFail>> {
+        block_on(self.gallo.i2c_trigger_arm(pin, edge, bus, action))
+    }
+
+    /// Disarm the trigger in `slot`, releasing its pin.
+    pub fn i2c_trigger_disarm(&self, slot: u8) -> Result<(), PicoDeGalloError<I2cTriggerDisarmFail>> {
+        block_on(self.gallo.i2c_trigger_disarm(slot))
+    }
+
+    /// Subscribe to the events of every armed I2C trigger, holding up
+    /// to `depth` of them until received.
+    pub fn i2c_trigger_events(&self, depth: usize) -> Result<Events<I2cTriggerEvent>, PicoDeGalloError<Infallible>> {
+        block_on(self.gallo.i2c_trigger_events(depth)).map(Events)
+    }
+
+    /// Answer as a target at 7-bit `address` on I2C `bus` until
+    /// stopped.
+    ///
+    /// See [`crate::PicoDeGallo::i2c_target_start`].
+    pub fn i2c_target_start(
+        &self,
+        bus: u8,
+        address: u8,
+        general_call: bool,
+    ) -> Result<(), PicoDeGalloError<I2cTargetFail>> {
+        block_on(self.gallo.i2c_target_start(bus, address, general_call))
+    }
+
+    /// Stop the target on I2C `bus`, handing it back to the controller.
+    pub fn i2c_target_stop(&self, bus: u8) -> Result<(), PicoDeGalloError<I2cTargetFail>> {
+        block_on(self.gallo.i2c_target_stop(bus))
+    }
+
+    /// Store `data` in the target register file of I2C `bus`, from
+    /// `register` on.
+    pub fn i2c_target_load(&self, bus: u8, register: u8, data: &[u8]) -> Result<(), PicoDeGalloError<I2cTargetFail>> {
+        block_on(self.gallo.i2c_target_load(bus, register, data))
+    }
+
+    /// Read `count` bytes of the target register file of I2C `bus`,
+    /// from `register` on.
+    pub fn i2c_target_dump(
+        &self,
+        bus: u8,
+        register: u8,
+        count: u16,
+    ) -> Result<Vec<u8>, PicoDeGalloError<I2cTargetFail>> {
+        block_on(self.gallo.i2c_target_dump(bus, register, count))
+    }
+
+    /// Subscribe to the accesses of controllers to every running
+    /// target, holding up to `depth` of them until received.
+    pub fn i2c_target_events(&self, depth: usize) -> Result<Events<I2cTargetEvent>, PicoDeGalloError<Infallible>> {
+        block_on(self.gallo.i2c_target_events(depth)).map(Events)
+    }
+
+    /// Read `count` bytes from SPI `bus`.
+    pub fn spi_read(&self, bus: u8, count: u16) -> Result<Vec<u8>, PicoDeGalloError<SpiReadFail>> {
+        block_on(self.gallo.spi_read(bus, count))
+    }
+
+    /// Write `contents` to SPI `bus`.
+    pub fn spi_write(&self, bus: u8, contents: &[u8]) -> Result<(), PicoDeGalloError<SpiWriteFail>> {
+        block_on(self.gallo.spi_write(bus, contents))
+    }
+
+    /// Flush SPI `bus`.
+    pub fn spi_flush(&self, bus: u8) -> Result<(), PicoDeGalloError<SpiFlushFail>> {
+        block_on(self.gallo.spi_flush(bus))
+    }
+
+    /// Run `operations` on SPI `bus` with the chip select `cs` asserted.
+    ///
+    /// See [`crate::PicoDeGallo::spi_transaction`].
+    pub fn spi_transaction(
+        &self,
+        bus: u8,
+        cs: SpiChipSelect,
+        operations: &[SpiOperation<'_>],
+    ) -> Result<Vec<u8>, PicoDeGalloError<SpiTransactionFail>> {
+        block_on(self.gallo.spi_transaction(bus, cs, operations))
+    }
+
+    /// Register an SPI device named `name` on `bus`.
+    ///
+    /// See [`crate::PicoDeGallo::spi_add_device`].
+    pub fn spi_add_device(
+        &self,
+        bus: u8,
+        name: &str,
+        cs: SpiChipSelect,
+        config: SpiConfig,
+    ) -> Result<SpiDeviceSlot, PicoDeGalloError<SpiAddDeviceFail>> {
+        block_on(self.gallo.spi_add_device(bus, name, cs, config))
+    }
+
+    /// Unregister the SPI device in `slot`.
+    pub fn spi_remove_device(&self, slot: u8) -> Result<(), PicoDeGalloError<SpiRemoveDeviceFail>> {
+        block_on(self.gallo.spi_remove_device(slot))
+    }
+
+    /// Run `operations` on the SPI device in `slot`.
+    ///
+    /// See [`crate::PicoDeGallo::spi_device_transaction`].
+    pub fn spi_device_transaction(
+        &self,
+        slot: u8,
+        operations: &[SpiOperation<'_>],
+    ) -> Result<Vec<u8>, PicoDeGalloError<SpiTransactionFail>> {
+        block_on(self.gallo.spi_device_transaction(slot, operations))
+    }
+
+    /// Answer as a target on SPI `bus` until stopped.
+    ///
+    /// See [`crate::PicoDeGallo::spi_target_start`].
+    pub fn spi_target_start(
+        &self,
+        bus: u8,
+        polarity: SpiPolarity,
+        phase: SpiPhase,
+        fill: u8,
+    ) -> Result<(), PicoDeGalloError<SpiTargetFail>> {
+        block_on(self.gallo.spi_target_start(bus, polarity, phase, fill))
+    }
+
+    /// Stop the target on SPI `bus`, handing it back to the controller.
+    pub fn spi_target_stop(&self, bus: u8) -> Result<(), PicoDeGalloError<SpiTargetFail>> {
+        block_on(self.gallo.spi_target_stop(bus))
+    }
+
+    /// Answer every selection of the target on SPI `bus` with `data`.
+    pub fn spi_target_load(&self, bus: u8, data: &[u8]) -> Result<(), PicoDeGalloError<SpiTargetFail>> {
+        block_on(self.gallo.spi_target_load(bus, data))
+    }
+
+    /// Answer `command` with `response` on SPI `bus`.
+    pub fn spi_target_command(
+        &self,
+        bus: u8,
+        command: &[u8],
+        response: &[u8],
+    ) -> Result<(), PicoDeGalloError<SpiTargetFail>> {
+        block_on(self.gallo.spi_target_command(bus, command, response))
+    }
+
+    /// Forget the response and every command of the target on SPI
+    /// `bus`.
+    pub fn spi_target_clear(&self, bus: u8) -> Result<(), PicoDeGalloError<SpiTargetFail>> {
+        block_on(self.gallo.spi_target_clear(bus))
+    }
+
+    /// Subscribe to the selections of every running SPI target, holding
+    /// up to `depth` of them until received.
+    pub fn spi_target_events(&self, depth: usize) -> Result<Events<SpiTargetEvent>, PicoDeGalloError<Infallible>> {
+        block_on(self.gallo.spi_target_events(depth)).map(Events)
+    }
+
+    /// Get the current state of GPIO numbered by `pin`.
+    pub fn gpio_get(&self, pin: u8) -> Result<GpioState, PicoDeGalloError<GpioGetFail>> {
+        block_on(self.gallo.gpio_get(pin))
+    }
+
+    /// Set the GPIO numbered by `pin` to state `state`.
+    pub fn gpio_put(&self, pin: u8, state: GpioState) -> Result<(), PicoDeGalloError<GpioPutFail>> {
+        block_on(self.gallo.gpio_put(pin, state))
+    }
+
+    /// Block until GPIO numbered by `pin` reaches `High` state.
+    pub fn gpio_wait_for_high(&self, pin: u8) -> Result<(), PicoDeGalloError<GpioWaitFail>> {
+        block_on(self.gallo.gpio_wait_for_high(pin))
+    }
+
+    /// Block until GPIO numbered by `pin` reaches `Low` state.
+    pub fn gpio_wait_for_low(&self, pin: u8) -> Result<(), PicoDeGalloError<GpioWaitFail>> {
+        block_on(self.gallo.gpio_wait_for_low(pin))
+    }
+
+    /// Block until a rising edge on the GPIO numbered by `pin`.
+    pub fn gpio_wait_for_rising_edge(&self, pin: u8) -> Result<(), PicoDeGalloError<GpioWaitFail>> {
+        block_on(self.gallo.gpio_wait_for_rising_edge(pin))
+    }
+
+    /// Block until a falling edge on the GPIO numbered by `pin`.
+    pub fn gpio_wait_for_falling_edge(&self, pin: u8) -> Result<(), PicoDeGalloError<GpioWaitFail>> {
+        block_on(self.gallo.gpio_wait_for_falling_edge(pin))
+    }
+
+    /// Block until either a rising edge or a falling edge on the GPIO
+    /// numbered by `pin`.
+    pub fn gpio_wait_for_any_edge(&self, pin: u8) -> Result<(), PicoDeGalloError<GpioWaitFail>> {
+        block_on(self.gallo.gpio_wait_for_any_edge(pin))
+    }
+
+    /// Measure the signal on the GPIO numbered by `pin` for `gate_ms`.
+    ///
+    /// See [`crate::PicoDeGallo::gpio_measure`].
+    pub fn gpio_measure(&self, pin: u8, gate_ms: u32) -> Result<GpioMeasurement, PicoDeGalloError<GpioMeasureFail>> {
+        block_on(self.gallo.gpio_measure(pin, gate_ms))
+    }
+
+    /// Frequency in Hz of the signal on the GPIO numbered by `pin`.
+    pub fn measure_frequency(&self, pin: u8, gate_ms: u32) -> Result<f64, PicoDeGalloError<GpioMeasureFail>> {
+        block_on(self.gallo.measure_frequency(pin, gate_ms))
+    }
+
+    /// Period in seconds of the signal on the GPIO numbered by `pin`.
+    pub fn measure_period(&self, pin: u8, gate_ms: u32) -> Result<Option<f64>, PicoDeGalloError<GpioMeasureFail>> {
+        block_on(self.gallo.measure_period(pin, gate_ms))
+    }
+
+    /// Fraction of the time the GPIO numbered by `pin` was high.
+    pub fn measure_duty_cycle(&self, pin: u8, gate_ms: u32) -> Result<f64, PicoDeGalloError<GpioMeasureFail>> {
+        block_on(self.gallo.measure_duty_cycle(pin, gate_ms))
+    }
+
+    /// Average high time in seconds of the pulses on the GPIO numbered
+    /// by `pin`.
+    pub fn measure_pulse_width(&self, pin: u8, gate_ms: u32) -> Result<Option<f64>, PicoDeGalloError<GpioMeasureFail>> {
+        block_on(self.gallo.measure_pulse_width(pin, gate_ms))
+    }
+
+    /// Apply `config` to I2C `bus`, returning the SCL frequency
+    /// actually achieved.
+    pub fn i2c_set_config(&self, bus: u8, config: I2cConfig) -> Result<u32, PicoDeGalloError<I2cSetConfigFail>> {
+        block_on(self.gallo.i2c_set_config(bus, config))
+    }
+
+    /// Apply `config` to SPI `bus`, returning the SCK frequency
+    /// actually achieved.
+    pub fn spi_set_config(&self, bus: u8, config: SpiConfig) -> Result<u32, PicoDeGalloError<SpiSetConfigFail>> {
+        block_on(self.gallo.spi_set_config(bus, config))
+    }
+
+    /// Get the current configuration of every I2C and SPI bus.
+    pub fn get_config(&self) -> Result<Configuration, PicoDeGalloError<Infallible>> {
+        block_on(self.gallo.get_config())
+    }
+
+    /// Issue a reset pulse on the 1-Wire bus of GPIO `pin`. Returns
+    /// whether a device answered with a presence pulse.
+    pub fn onewire_reset(&self, pin: u8) -> Result<bool, PicoDeGalloError<OneWireFail>> {
+        block_on(self.gallo.onewire_reset(pin))
+    }
+
+    /// Reset the 1-Wire bus of GPIO `pin` and address the device with
+    /// ROM code `rom`, or every device without one.
+    ///
+    /// See [`crate::PicoDeGallo::onewire_select`].
+    pub fn onewire_select(&self, pin: u8, rom: Option<OneWireRom>) -> Result<bool, PicoDeGalloError<OneWireFail>> {
+        block_on(self.gallo.onewire_select(pin, rom))
+    }
+
+    /// Write `bit` in one time slot on the 1-Wire bus of GPIO `pin`.
+    pub fn onewire_write_bit(&self, pin: u8, bit: bool) -> Result<(), PicoDeGalloError<OneWireFail>> {
+        block_on(self.gallo.onewire_write_bit(pin, bit))
+    }
+
+    /// Read a bit in one time slot on the 1-Wire bus of GPIO `pin`.
+    pub fn onewire_read_bit(&self, pin: u8) -> Result<bool, PicoDeGalloError<OneWireFail>> {
+        block_on(self.gallo.onewire_read_bit(pin))
+    }
+
+    /// Write `data` on the 1-Wire bus of GPIO `pin`.
+    pub fn onewire_write(&self, pin: u8, data: &[u8]) -> Result<(), PicoDeGalloError<OneWireFail>> {
+        block_on(self.gallo.onewire_write(pin, data))
+    }
+
+    /// Write `data` on the 1-Wire bus of GPIO `pin`, then drive it high
+    /// for `strong_pullup_ms`.
+    pub fn onewire_write_then_power(
+        &self,
+        pin: u8,
+        data: &[u8],
+        strong_pullup_ms: u16,
+    ) -> Result<(), PicoDeGalloError<OneWireFail>> {
+        block_on(self.gallo.onewire_write_then_power(pin, data, strong_pullup_ms))
+    }
+
+    /// Read `count` bytes from the 1-Wire bus of GPIO `pin`.
+    pub fn onewire_read(&self, pin: u8, count: u16) -> Result<Vec<u8>, PicoDeGalloError<OneWireFail>> {
+        block_on(self.gallo.onewire_read(pin, count))
+    }
+
+    /// Find the ROM codes of the devices on the 1-Wire bus of GPIO
+    /// `pin`.
+    pub fn onewire_search(&self, pin: u8) -> Result<Vec<OneWireRom>, PicoDeGalloError<OneWireFail>> {
+        block_on(self.gallo.onewire_search(pin))
+    }
+
+    /// Find the ROM codes of the devices in an alarm state on the
+    /// 1-Wire bus of GPIO `pin`.
+    pub fn onewire_alarm_search(&self, pin: u8) -> Result<Vec<OneWireRom>, PicoDeGalloError<OneWireFail>> {
+        block_on(self.gallo.onewire_alarm_search(pin))
+    }
+
+    /// Set a chain of WS2812 style RGB LEDs on GPIO `pin`.
+    pub fn leds_write(&self, pin: u8, colors: &[Rgb]) -> Result<(), PicoDeGalloError<LedsFail>> {
+        block_on(self.gallo.leds_write(pin, colors))
+    }
+
+    /// Set a chain of SK6812 style RGBW LEDs on GPIO `pin`.
+    pub fn leds_write_rgbw(&self, pin: u8, colors: &[Rgbw]) -> Result<(), PicoDeGalloError<LedsFail>> {
+        block_on(self.gallo.leds_write_rgbw(pin, colors))
+    }
+
+    /// Shift `data` out as is to a chain of LEDs on GPIO `pin`.
+    pub fn leds_write_raw(&self, pin: u8, data: &[u8]) -> Result<(), PicoDeGalloError<LedsFail>> {
+        block_on(self.gallo.leds_write_raw(pin, data))
+    }
+
+    /// Load `program` for state machine `sm`, which must be stopped.
+    ///
+    /// See [`crate::PicoDeGallo::pio_load`].
+    pub fn pio_load(&self, sm: u8, program: &PioProgram) -> Result<u8, PicoDeGalloError<PioFail>> {
+        block_on(self.gallo.pio_load(sm, program))
+    }
+
+    /// Configure state machine `sm` and start its program from the top.
+    pub fn pio_start(&self, sm: u8, config: PioConfig) -> Result<(), PicoDeGalloError<PioFail>> {
+        block_on(self.gallo.pio_start(sm, config))
+    }
+
+    /// Stop state machine `sm`, handing its GPIOs back as inputs.
+    pub fn pio_stop(&self, sm: u8) -> Result<(), PicoDeGalloError<PioFail>> {
+        block_on(self.gallo.pio_stop(sm))
+    }
+
+    /// Execute `instruction` on state machine `sm` right away.
+    pub fn pio_exec(&self, sm: u8, instruction: u16) -> Result<(), PicoDeGalloError<PioFail>> {
+        block_on(self.gallo.pio_exec(sm, instruction))
+    }
+
+    /// Push `data` into the TX FIFO of state machine `sm`. Returns the
+    /// number of words pushed.
+    pub fn pio_push(&self, sm: u8, data: &[u32], timeout_ms: u32) -> Result<usize, PicoDeGalloError<PioFail>> {
+        block_on(self.gallo.pio_push(sm, data, timeout_ms))
+    }
+
+    /// Pull `count` words from the RX FIFO of state machine `sm`.
+    pub fn pio_pull(&self, sm: u8, count: usize, timeout_ms: u32) -> Result<Vec<u32>, PicoDeGalloError<PioFail>> {
+        block_on(self.gallo.pio_pull(sm, count, timeout_ms))
+    }
+
+    /// Sample every channel `samples` times at `rate` Hz once `trigger`
+    /// fires.
+    ///
+    /// See [`crate::PicoDeGallo::capture_start`].
+    pub fn capture_start(
+        &self,
+        rate: u32,
+        samples: u32,
+        trigger: CaptureTrigger,
+    ) -> Result<(), PicoDeGalloError<CaptureFail>> {
+        block_on(self.gallo.capture_start(rate, samples, trigger))
+    }
+
+    /// Stop the capture running, which then ends without samples.
+    pub fn capture_stop(&self) -> Result<(), PicoDeGalloError<CaptureFail>> {
+        block_on(self.gallo.capture_stop())
+    }
+
+    /// Read `count` samples of the last capture from `offset` on.
+    pub fn capture_read(&self, offset: u32, count: u32) -> Result<Vec<u16>, PicoDeGalloError<CaptureFail>> {
+        block_on(self.gallo.capture_read(offset, count))
+    }
+
+    /// Subscribe to the end of captures, holding up to `depth` of them
+    /// until received.
+    pub fn capture_events(&self, depth: usize) -> Result<Events<CaptureEvent>, PicoDeGalloError<Infallible>> {
+        block_on(self.gallo.capture_events(depth)).map(Events)
+    }
+
+    /// Run a capture, wait for it to end and read its samples.
+    ///
+    /// See [`crate::PicoDeGallo::capture`].
+    pub fn capture(
+        &self,
+        rate: u32,
+        samples: u32,
+        trigger: CaptureTrigger,
+    ) -> Result<Capture, PicoDeGalloError<CaptureFail>> {
+        block_on(self.gallo.capture(rate, samples, trigger))
+    }
+
+    /// Decode the traffic of another controller on I2C `bus`, until
+    /// stopped.
+    ///
+    /// See [`crate::PicoDeGallo::sniff_i2c_start`].
+    pub fn sniff_i2c_start(&self, bus: u8) -> Result<(), PicoDeGalloError<SniffFail>> {
+        block_on(self.gallo.sniff_i2c_start(bus))
+    }
+
+    /// Decode the traffic of another controller on SPI `bus`, until
+    /// stopped.
+    ///
+    /// See [`crate::PicoDeGallo::sniff_spi_start`].
+    pub fn sniff_spi_start(
+        &self,
+        bus: u8,
+        polarity: SpiPolarity,
+        phase: SpiPhase,
+    ) -> Result<(), PicoDeGalloError<SniffFail>> {
+        block_on(self.gallo.sniff_spi_start(bus, polarity, phase))
+    }
+
+    /// Stop the sniffer running, handing its bus back to the controller.
+    pub fn sniff_stop(&self) -> Result<(), PicoDeGalloError<SniffFail>> {
+        block_on(self.gallo.sniff_stop())
+    }
+
+    /// Subscribe to the I2C transfers sniffed, holding up to `depth` of
+    /// them until received.
+    pub fn i2c_sniff_events(&self, depth: usize) -> Result<Events<I2cSniffEvent>, PicoDeGalloError<Infallible>> {
+        block_on(self.gallo.i2c_sniff_events(depth)).map(Events)
+    }
+
+    /// Subscribe to the SPI selections sniffed, holding up to `depth` of
+    /// them until received.
+    pub fn spi_sniff_events(&self, depth: usize) -> Result<Events<SpiSniffEvent>, PicoDeGalloError<Infallible>> {
+        block_on(self.gallo.spi_sniff_events(depth)).map(Events)
+    }
+
+    /// Get the firmware version from the Pico de Gallo device.
+    pub fn version(&self) -> Result<VersionInfo, PicoDeGalloError<Infallible>> {
+        block_on(self.gallo.version())
+    }
+}
+
+/// Messages published by the device, see e.g.
+/// [`PicoDeGallo::i2c_trigger_events`].
+pub struct Events<T>(crate::Events<T>);
+
+impl<T: DeserializeOwned> Events<T> {
+    /// Wait for the next event, `None` once the device is gone.
+    pub fn recv(&mut self) -> Option<T> {
+        block_on(self.0.recv())
+    }
+}
+
+/// Exclusive access to a shared device, see [`PicoDeGallo::lock`].
+pub struct BusLock<'a>(crate::BusLock<'a>);
+
+impl BusLock<'_> {
+    /// Release the lock, letting other clients access the device again.
+    pub fn unlock(self) -> Result<(), PicoDeGalloError<Infallible>> {
+        block_on(self.0.unlock())
+    }
+}
+
+// ----------------------------- Streams -----------------------------
+
+/// A byte stream which can be split into two handles, one for each
+/// direction.
+trait Stream: Read + Write + Send + Sized + 'static {
+    fn try_clone(&self) -> io::Result<Self>;
+    fn shutdown(&self) -> io::Result<()>;
+}
+
+impl Stream for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        TcpStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+#[cfg(unix)]
+impl Stream for std::os::unix::net::UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        std::os::unix::net::UnixStream::try_clone(self)
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        std::os::unix::net::UnixStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+// Each worker runs on a thread of its own, blocking in the middle of
+// the futures below only blocks that worker.
+
+struct StreamTx<S: Stream>(S);
+
+impl<S: Stream> WireTx for StreamTx<S> {
+    type Error = io::Error;
+
+    async fn send(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+        write_frame_blocking(&mut self.0, &data)
+    }
+}
+
+impl<S: Stream> Drop for StreamTx<S> {
+    fn drop(&mut self) {
+        // Wake up the receiving worker, otherwise stuck reading
+        let _ = self.0.shutdown();
+    }
+}
+
+struct StreamRx<S: Stream>(S);
+
+impl<S: Stream> WireRx for StreamRx<S> {
+    type Error = io::Error;
+
+    async fn receive(&mut self) -> Result<Vec<u8>, Self::Error> {
+        read_frame_blocking(&mut self.0)
+    }
+}
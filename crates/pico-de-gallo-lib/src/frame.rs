@@ -0,0 +1,59 @@
+//! Framing of postcard-rpc frames over byte streams, e.g. TCP or a
+//! Unix socket.
+//!
+//! Each frame is prefixed by its length as a little-endian `u32`.
+
+use std::io;
+
+/// Largest frame accepted from the stream.
+const MAX_FRAME_SIZE: usize = 64 * 1024;
+
+fn frame_len(len: u32) -> io::Result<usize> {
+    let len = len as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too long"));
+    }
+
+    Ok(len)
+}
+
+fn frame_prefix(frame: &[u8]) -> io::Result<[u8; 4]> {
+    let len = u32::try_from(frame.len()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too long"))?;
+    Ok(len.to_le_bytes())
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) async fn read_frame<R: tokio::io::AsyncRead + Unpin>(reader: &mut R) -> io::Result<Vec<u8>> {
+    use tokio::io::AsyncReadExt;
+
+    let len = frame_len(reader.read_u32_le().await?)?;
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame).await?;
+    Ok(frame)
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) async fn write_frame<W: tokio::io::AsyncWrite + Unpin>(writer: &mut W, frame: &[u8]) -> io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    writer.write_all(&frame_prefix(frame)?).await?;
+    writer.write_all(frame).await?;
+    writer.flush().await
+}
+
+#[cfg(feature = "blocking")]
+pub(crate) fn read_frame_blocking<R: io::Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+
+    let mut frame = vec![0; frame_len(u32::from_le_bytes(len))?];
+    reader.read_exact(&mut frame)?;
+    Ok(frame)
+}
+
+#[cfg(feature = "blocking")]
+pub(crate) fn write_frame_blocking<W: io::Write>(writer: &mut W, frame: &[u8]) -> io::Result<()> {
+    writer.write_all(&frame_prefix(frame)?)?;
+    writer.write_all(frame)?;
+    writer.flush()
+}
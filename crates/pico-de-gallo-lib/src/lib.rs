@@ -1,20 +1,79 @@
 use nusb::DeviceInfo;
 use pico_de_gallo_internal::{
-    GpioGet, GpioGetFail, GpioGetRequest, GpioPut, GpioPutFail, GpioPutRequest, GpioWaitFail, GpioWaitForAny,
-    GpioWaitForFalling, GpioWaitForHigh, GpioWaitForLow, GpioWaitForRising, GpioWaitRequest, I2cRead, I2cReadFail,
-    I2cReadRequest, I2cWrite, I2cWriteFail, I2cWriteRequest, MICROSOFT_VID, PICO_DE_GALLO_PID, SetConfiguration,
-    SetConfigurationFail, SetConfigurationRequest, SpiFlush, SpiFlushFail, SpiRead, SpiReadFail, SpiReadRequest,
-    SpiWrite, SpiWriteFail, SpiWriteRequest, Version,
+    BrokerLock, BrokerUnlock, CaptureRead, CaptureReadRequest, CaptureStart, CaptureStartRequest, CaptureStop,
+    CaptureTopic, GetConfiguration, GpioGet, GpioGetFail, GpioGetRequest, GpioMeasure, GpioMeasureRequest, GpioPut,
+    GpioPutFail, GpioPutRequest, GpioWaitFail, GpioWaitForAny, GpioWaitForFalling, GpioWaitForHigh, GpioWaitForLow,
+    GpioWaitForRising, GpioWaitRequest, I2cRead, I2cReadFail, I2cReadRequest, I2cRecover, I2cRecoverFail,
+    I2cRecoverRequest, I2cScan, I2cScanFail, I2cScanRequest, I2cSetConfig, I2cSetConfigRequest, I2cSniffTopic,
+    I2cTargetDump, I2cTargetDumpRequest, I2cTargetLoad, I2cTargetLoadRequest, I2cTargetStart, I2cTargetStartRequest,
+    I2cTargetStop, I2cTargetStopRequest, I2cTargetTopic, I2cTriggerArm, I2cTriggerArmRequest, I2cTriggerDisarm,
+    I2cTriggerDisarmRequest, I2cTriggerTopic, I2cWrite, I2cWriteFail, I2cWriteRequest, LedsWrite, LedsWriteRequest,
+    MICROSOFT_VID, OneWireBit, OneWireBitRequest, OneWireRead, OneWireReadRequest, OneWireReset, OneWireResetRequest,
+    OneWireSearch, OneWireSearchRequest, OneWireWrite, OneWireWriteRequest, PICO_DE_GALLO_PID, PioExec, PioExecRequest,
+    PioLoad, PioLoadRequest, PioPull, PioPullRequest, PioPush, PioPushRequest, PioStart, PioStartRequest, PioStop,
+    PioStopRequest, Smbus, SmbusAlert, SmbusAlertRequest, SmbusRequest, SniffI2cStart, SniffI2cStartRequest,
+    SniffSpiStart, SniffSpiStartRequest, SniffStop, SpiAddDevice, SpiAddDeviceRequest, SpiDeviceTransaction,
+    SpiDeviceTransactionRequest, SpiFlush, SpiFlushFail, SpiFlushRequest, SpiRead, SpiReadFail, SpiReadRequest,
+    SpiRemoveDevice, SpiRemoveDeviceRequest, SpiSetConfig, SpiSetConfigRequest, SpiSniffTopic, SpiTargetClear,
+    SpiTargetClearRequest, SpiTargetCommand, SpiTargetCommandRequest, SpiTargetLoad, SpiTargetLoadRequest,
+    SpiTargetStart, SpiTargetStartRequest, SpiTargetStop, SpiTargetStopRequest, SpiTargetTopic, SpiTransaction,
+    SpiTransactionRequest, SpiWrite, SpiWriteFail, SpiWriteRequest, Version,
 };
 
-pub use pico_de_gallo_internal::{GpioState, SpiPhase, SpiPolarity, VersionInfo};
+pub use pico_de_gallo_internal::{
+    CAPTURE_CHANNEL_NAMES, CAPTURE_CHANNELS, CAPTURE_FIRST_PIN, CAPTURE_MAX_RATE, CAPTURE_MAX_READ,
+    CAPTURE_MAX_SAMPLES, CAPTURE_MIN_RATE, CaptureEvent, CaptureFail, CaptureTrigger, ConfigError, Configuration,
+    GPIO_MEASURE_MAX_GATE_MS, GpioEdge, GpioMeasureFail, GpioMeasurement, GpioState, I2C_MAX_FREQUENCY,
+    I2C_MIN_FREQUENCY, I2C_TARGET_REGISTERS, I2C_TEN_BIT_ADDRESS_MAX, I2C_TRIGGER_MAX_LEN, I2C_TRIGGER_MAX_SLOTS,
+    I2cAddress, I2cAddressMap, I2cConfig, I2cConfigBuilder, I2cScanMode, I2cSetConfigFail, I2cSniffByte, I2cSniffEvent,
+    I2cTargetAccess, I2cTargetEvent, I2cTargetFail, I2cTriggerAction, I2cTriggerArmFail, I2cTriggerDisarmFail,
+    I2cTriggerEvent, LEDS_MAX_LEN, LedsFail, NUM_I2C_BUSES, NUM_SPI_BUSES, ONEWIRE_ALARM_SEARCH, ONEWIRE_MATCH_ROM,
+    ONEWIRE_MAX_LEN, ONEWIRE_SEARCH_MAX_DEVICES, ONEWIRE_SEARCH_ROM, ONEWIRE_SKIP_ROM, OneWireFail, OneWireRom,
+    OneWireRomParseError, PIO_MAX_FREQUENCY, PIO_MAX_INSTRUCTIONS, PIO_MAX_WORDS, PIO_MIN_FREQUENCY,
+    PIO_NUM_STATE_MACHINES, PioConfig, PioConfigBuilder, PioFail, PioFifoJoin, PioPins, PioProgram, PioShift,
+    PioShiftDirection, PioSideSet, Rgb, Rgbw, SMBUS_ALERT_RESPONSE_ADDRESS, SMBUS_BLOCK_MAX_LEN, SNIFF_MAX_LEN,
+    SPI_DEVICE_NAME_MAX_LEN, SPI_MAX_DEVICES, SPI_MAX_FREQUENCY, SPI_MAX_OPERATIONS, SPI_MIN_FREQUENCY,
+    SPI_TARGET_MAX_COMMAND_LEN, SPI_TARGET_MAX_COMMANDS, SPI_TARGET_MAX_LEN, SmbusFail, SmbusOperation, SniffFail,
+    SpiAddDeviceFail, SpiBitOrder, SpiChipSelect, SpiConfig, SpiConfigBuilder, SpiCsPolarity, SpiDeviceSlot,
+    SpiOperation, SpiPhase, SpiPolarity, SpiRemoveDeviceFail, SpiSetConfigFail, SpiSniffEvent, SpiTargetEvent,
+    SpiTargetFail, SpiTransactionFail, SpiWordSize, VersionInfo, i2c_address_is_reserved, onewire_crc8, smbus_pec,
+};
+#[cfg(feature = "tokio")]
+pub use remote::RemoteServer;
 
+use futures::lock::{Mutex, OwnedMutexGuard};
 use postcard_rpc::{
+    Topic,
     header::VarSeqKind,
-    host_client::{HostClient, HostErr},
+    host_client::{HostClient, HostErr, MultiSubRxError, MultiSubscription, WireRx, WireSpawn, WireTx},
     standard_icd::{ERROR_PATH, PingEndpoint, WireError},
 };
+use serde::de::DeserializeOwned;
 use std::convert::Infallible;
+use std::sync::Arc;
+#[cfg(feature = "tokio")]
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(any(feature = "tokio", feature = "blocking"))]
+mod frame;
+pub mod pmbus;
+#[cfg(feature = "tokio")]
+mod remote;
+mod spawn;
+mod usb;
+
+/// Default path of the Unix socket used by the broker daemon, in
+/// `$XDG_RUNTIME_DIR` or, when it isn't set, the temporary directory.
+#[cfg(unix)]
+pub fn default_broker_socket() -> std::path::PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(std::path::PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(std::env::temp_dir)
+        .join("pico-de-gallo.sock")
+}
 
 #[derive(Debug)]
 pub enum PicoDeGalloError<E> {
@@ -42,9 +101,21 @@ impl<T, E> FlattenErr for Result<T, E> {
     }
 }
 
+/// First `N` bytes answered to an SMBus operation, as an array.
+///
+/// Responses may have been relayed by a server, hence aren't trusted to
+/// be as long as the operation implies.
+fn smbus_response<const N: usize>(data: Vec<u8>) -> Result<[u8; N], PicoDeGalloError<SmbusFail>> {
+    data.get(..N)
+        .and_then(|data| data.try_into().ok())
+        .ok_or(PicoDeGalloError::Comms(HostErr::BadResponse))
+}
+
 #[derive(Clone)]
 pub struct PicoDeGallo {
     pub client: HostClient<WireError>,
+    // Held by a `BusLock` until the broker acknowledged its release
+    bus_lock: Arc<Mutex<()>>,
 }
 
 impl Default for PicoDeGallo {
@@ -78,8 +149,50 @@ impl PicoDeGallo {
     }
 
     fn new_inner<F: FnMut(&DeviceInfo) -> bool>(func: F) -> Self {
-        let client = HostClient::new_raw_nusb(func, ERROR_PATH, 8, VarSeqKind::Seq2);
-        Self { client }
+        let (tx, rx) = usb::open(func).expect("should have found nusb device");
+        Self::new_with_wire(tx, rx, spawn::ThreadSpawn)
+    }
+
+    fn new_with_wire(tx: impl WireTx, rx: impl WireRx, spawn: impl WireSpawn) -> Self {
+        let client = HostClient::new_with_wire(tx, rx, spawn, VarSeqKind::Seq2, ERROR_PATH, 8);
+        Self {
+            client,
+            bus_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Connect to a Pico de Gallo device shared over TCP, e.g. by
+    /// `gallo serve`, listening on `addr`.
+    ///
+    /// The connection is served by the current tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub async fn connect_tcp<A: ToSocketAddrs>(addr: A) -> std::io::Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+        Ok(Self::new_with_stream(stream))
+    }
+
+    /// Connect to a Pico de Gallo device shared by the broker daemon,
+    /// e.g. `gallo daemon`, listening on the Unix socket at `path`.
+    ///
+    /// Unlike a direct USB connection, any number of clients can
+    /// share a device through the broker. Use [`PicoDeGallo::lock`]
+    /// to keep other clients off the bus during multi-step sequences.
+    ///
+    /// The connection is served by the current tokio runtime.
+    #[cfg(all(unix, feature = "tokio"))]
+    pub async fn connect_unix<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        let stream = tokio::net::UnixStream::connect(path).await?;
+        Ok(Self::new_with_stream(stream))
+    }
+
+    #[cfg(feature = "tokio")]
+    fn new_with_stream<S>(stream: S) -> Self
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static,
+    {
+        let (rx, tx) = tokio::io::split(stream);
+        Self::new_with_wire(remote::StreamTx(tx), remote::StreamRx(rx), spawn::TokioSpawn)
     }
 
     /// Wait until the client has closed the connection.
@@ -87,6 +200,34 @@ impl PicoDeGallo {
         self.client.wait_closed().await;
     }
 
+    /// Take exclusive access to the device.
+    ///
+    /// When the device is shared by a broker, requests from other
+    /// clients are held back until the returned [`BusLock`] is
+    /// released, which makes multi-step sequences atomic. Waits for
+    /// the lock in case another client, or another task sharing this
+    /// client, currently owns it.
+    ///
+    /// A device connected directly over USB can't be shared, hence
+    /// it is always exclusive and this is a no-op.
+    pub async fn lock(&self) -> Result<BusLock<'_>, PicoDeGalloError<Infallible>> {
+        // Waits for the release of a previous lock to be acknowledged,
+        // lest the broker sees it after this lock request
+        let guard = self.bus_lock.clone().lock_owned().await;
+
+        match self.client.send_resp::<BrokerLock>(&()).await {
+            Ok(()) => Ok(BusLock {
+                gallo: self,
+                guard: Some(guard),
+            }),
+            Err(HostErr::Wire(WireError::UnknownKey)) => Ok(BusLock {
+                gallo: self,
+                guard: None,
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Ping endpoint.
     ///
     /// Only used for testing purposes. Send a `u32` and get the same
@@ -95,47 +236,537 @@ impl PicoDeGallo {
         Ok(self.client.send_resp::<PingEndpoint>(&id).await?)
     }
 
-    /// Read `count` bytes from the I2C device at `address`.
+    /// Read `count` bytes from the I2C device at `address` on `bus`.
+    ///
+    /// `address` is either a plain `u8` for 7-bit addresses or an
+    /// [`I2cAddress`].
     ///
     /// An arbitrary limit of `u16::MAX` is imposed currently, that
     /// may change in the future.
-    pub async fn i2c_read(&self, address: u8, count: u16) -> Result<Vec<u8>, PicoDeGalloError<I2cReadFail>> {
+    pub async fn i2c_read(
+        &self,
+        bus: u8,
+        address: impl Into<I2cAddress>,
+        count: u16,
+    ) -> Result<Vec<u8>, PicoDeGalloError<I2cReadFail>> {
+        let address = address.into();
+        self.client
+            .send_resp::<I2cRead>(&I2cReadRequest { bus, address, count })
+            .await?
+            .flatten()
+    }
+
+    /// Write `contents` to the I2C device at `address` on `bus`.
+    pub async fn i2c_write(
+        &self,
+        bus: u8,
+        address: impl Into<I2cAddress>,
+        contents: &[u8],
+    ) -> Result<(), PicoDeGalloError<I2cWriteFail>> {
+        let address = address.into();
+        self.client
+            .send_resp::<I2cWrite>(&I2cWriteRequest { bus, address, contents })
+            .await?
+            .flatten()
+    }
+
+    /// Free I2C `bus` from a target holding SDA low after an aborted
+    /// transfer.
+    ///
+    /// SCL is clocked up to nine times until the target lets go of
+    /// SDA, followed by a STOP. Returns whether the bus was left free.
+    pub async fn i2c_recover(&self, bus: u8) -> Result<bool, PicoDeGalloError<I2cRecoverFail>> {
+        self.client
+            .send_resp::<I2cRecover>(&I2cRecoverRequest { bus })
+            .await?
+            .flatten()
+    }
+
+    /// Probe every address of I2C `bus` as per `mode`, returning those
+    /// which acknowledged.
+    ///
+    /// Reserved addresses are skipped unless `reserved` is set, see
+    /// [`i2c_address_is_reserved`].
+    pub async fn i2c_scan(
+        &self,
+        bus: u8,
+        mode: I2cScanMode,
+        reserved: bool,
+    ) -> Result<I2cAddressMap, PicoDeGalloError<I2cScanFail>> {
+        self.client
+            .send_resp::<I2cScan>(&I2cScanRequest { bus, mode, reserved })
+            .await?
+            .flatten()
+    }
+
+    /// Run an SMBus `operation` with the target at `address` on I2C
+    /// `bus`, returning the bytes it answers.
+    ///
+    /// The operation runs at 100 kHz from START to STOP on the
+    /// firmware, whatever the bus configuration. When `pec` is set, a
+    /// Packet Error Code is appended to writes and checked at the end
+    /// of reads.
+    pub async fn smbus(
+        &self,
+        bus: u8,
+        address: u8,
+        operation: SmbusOperation<'_>,
+        pec: bool,
+    ) -> Result<Vec<u8>, PicoDeGalloError<SmbusFail>> {
+        self.client
+            .send_resp::<Smbus>(&SmbusRequest {
+                bus,
+                address,
+                pec,
+                operation,
+            })
+            .await?
+            .flatten()
+    }
+
+    /// SMBus quick command, the R/W bit being the only data.
+    pub async fn smbus_quick_command(
+        &self,
+        bus: u8,
+        address: u8,
+        read: bool,
+    ) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        self.smbus(bus, address, SmbusOperation::QuickCommand { read }, false)
+            .await
+            .map(|_| ())
+    }
+
+    /// SMBus send byte.
+    pub async fn smbus_send_byte(
+        &self,
+        bus: u8,
+        address: u8,
+        value: u8,
+        pec: bool,
+    ) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        self.smbus(bus, address, SmbusOperation::SendByte(value), pec)
+            .await
+            .map(|_| ())
+    }
+
+    /// SMBus receive byte.
+    pub async fn smbus_receive_byte(&self, bus: u8, address: u8, pec: bool) -> Result<u8, PicoDeGalloError<SmbusFail>> {
+        self.smbus(bus, address, SmbusOperation::ReceiveByte, pec)
+            .await
+            .and_then(smbus_response)
+            .map(|[value]| value)
+    }
+
+    /// SMBus write byte, `value` going to `command`.
+    pub async fn smbus_write_byte(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        value: u8,
+        pec: bool,
+    ) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        self.smbus(bus, address, SmbusOperation::WriteByte { command, value }, pec)
+            .await
+            .map(|_| ())
+    }
+
+    /// SMBus read byte from `command`.
+    pub async fn smbus_read_byte(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        pec: bool,
+    ) -> Result<u8, PicoDeGalloError<SmbusFail>> {
+        self.smbus(bus, address, SmbusOperation::ReadByte { command }, pec)
+            .await
+            .and_then(smbus_response)
+            .map(|[value]| value)
+    }
+
+    /// SMBus write word, `value` going to `command`.
+    pub async fn smbus_write_word(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        value: u16,
+        pec: bool,
+    ) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        self.smbus(bus, address, SmbusOperation::WriteWord { command, value }, pec)
+            .await
+            .map(|_| ())
+    }
+
+    /// SMBus read word from `command`.
+    pub async fn smbus_read_word(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        pec: bool,
+    ) -> Result<u16, PicoDeGalloError<SmbusFail>> {
+        self.smbus(bus, address, SmbusOperation::ReadWord { command }, pec)
+            .await
+            .and_then(smbus_response)
+            .map(u16::from_le_bytes)
+    }
+
+    /// SMBus block write of up to [`SMBUS_BLOCK_MAX_LEN`] bytes to
+    /// `command`.
+    pub async fn smbus_block_write(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        data: &[u8],
+        pec: bool,
+    ) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        self.smbus(bus, address, SmbusOperation::BlockWrite { command, data }, pec)
+            .await
+            .map(|_| ())
+    }
+
+    /// SMBus block read from `command`, returning as many bytes as the
+    /// target says it has.
+    pub async fn smbus_block_read(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        pec: bool,
+    ) -> Result<Vec<u8>, PicoDeGalloError<SmbusFail>> {
+        self.smbus(bus, address, SmbusOperation::BlockRead { command }, pec)
+            .await
+    }
+
+    /// SMBus process call, writing `value` to `command` and returning
+    /// the word the target answers.
+    pub async fn smbus_process_call(
+        &self,
+        bus: u8,
+        address: u8,
+        command: u8,
+        value: u16,
+        pec: bool,
+    ) -> Result<u16, PicoDeGalloError<SmbusFail>> {
+        self.smbus(bus, address, SmbusOperation::ProcessCall { command, value }, pec)
+            .await
+            .and_then(smbus_response)
+            .map(u16::from_le_bytes)
+    }
+
+    /// Read the SMBus Alert Response Address on `bus`, returning the
+    /// address of the device asserting SMBALERT#, if any. That device
+    /// then releases the line.
+    ///
+    /// When several devices assert SMBALERT#, the one with the lowest
+    /// address answers first.
+    pub async fn smbus_alert_response(&self, bus: u8, pec: bool) -> Result<Option<u8>, PicoDeGalloError<SmbusFail>> {
+        match self
+            .client
+            .send_resp::<SmbusAlert>(&SmbusAlertRequest { bus, pec })
+            .await?
+        {
+            Ok(address) => Ok(Some(address)),
+            Err(SmbusFail::NoAcknowledge) => Ok(None),
+            Err(e) => Err(PicoDeGalloError::Endpoint(e)),
+        }
+    }
+
+    /// Perform `action` on I2C `bus` on every `edge` of GPIO `pin`,
+    /// until disarmed. Returns the slot of the trigger, which tags its
+    /// events.
+    ///
+    /// Results are published as [`I2cTriggerEvent`]s, subscribe to them
+    /// with [`PicoDeGallo::i2c_trigger_events`] beforehand so that none
+    /// is missed. Other GPIO requests for `pin` fail while it's armed.
+    pub async fn i2c_trigger_arm(
+        &self,
+        pin: u8,
+        edge: GpioEdge,
+        bus: u8,
+        action: I2cTriggerAction,
+    ) -> Result<u8, PicoDeGalloError<I2cTriggerArmFail>> {
         self.client
-            .send_resp::<I2cRead>(&I2cReadRequest { address, count })
+            .send_resp::<I2cTriggerArm>(&I2cTriggerArmRequest { pin, edge, bus, action })
             .await?
             .flatten()
     }
 
-    /// Write `contents` to the I2C device at `address`.
-    pub async fn i2c_write(&self, address: u8, contents: &[u8]) -> Result<(), PicoDeGalloError<I2cWriteFail>> {
+    /// Disarm the trigger in `slot`, releasing its pin.
+    pub async fn i2c_trigger_disarm(&self, slot: u8) -> Result<(), PicoDeGalloError<I2cTriggerDisarmFail>> {
         self.client
-            .send_resp::<I2cWrite>(&I2cWriteRequest { address, contents })
+            .send_resp::<I2cTriggerDisarm>(&I2cTriggerDisarmRequest { slot })
             .await?
             .flatten()
     }
 
-    /// Read `count` bytes from the SPI bus.
+    /// Subscribe to the events of every armed I2C trigger, holding up
+    /// to `depth` of them until received.
+    pub async fn i2c_trigger_events(
+        &self,
+        depth: usize,
+    ) -> Result<Events<I2cTriggerEvent>, PicoDeGalloError<Infallible>> {
+        self.subscribe::<I2cTriggerTopic>(depth).await
+    }
+
+    /// Answer as a target at 7-bit `address` on I2C `bus`, from a
+    /// register file of [`I2C_TARGET_REGISTERS`] bytes, until stopped.
+    /// With `general_call`, writes to the general call address are
+    /// reported too.
+    ///
+    /// The first byte a controller writes selects the register, the
+    /// following ones are stored from there on and reads answer from
+    /// the selected register on, wrapping around after the last one.
+    /// Every access is published as an [`I2cTargetEvent`], see
+    /// [`PicoDeGallo::i2c_target_events`]. Controller requests on `bus`
+    /// fail until the target is stopped.
+    pub async fn i2c_target_start(
+        &self,
+        bus: u8,
+        address: u8,
+        general_call: bool,
+    ) -> Result<(), PicoDeGalloError<I2cTargetFail>> {
+        self.client
+            .send_resp::<I2cTargetStart>(&I2cTargetStartRequest {
+                bus,
+                address,
+                general_call,
+            })
+            .await?
+            .flatten()
+    }
+
+    /// Stop the target on I2C `bus`, handing it back to the controller.
+    /// Its register file is kept.
+    pub async fn i2c_target_stop(&self, bus: u8) -> Result<(), PicoDeGalloError<I2cTargetFail>> {
+        self.client
+            .send_resp::<I2cTargetStop>(&I2cTargetStopRequest { bus })
+            .await?
+            .flatten()
+    }
+
+    /// Store `data` in the target register file of I2C `bus`, from
+    /// `register` on. Possible whether the target runs or not.
+    pub async fn i2c_target_load(
+        &self,
+        bus: u8,
+        register: u8,
+        data: &[u8],
+    ) -> Result<(), PicoDeGalloError<I2cTargetFail>> {
+        self.client
+            .send_resp::<I2cTargetLoad>(&I2cTargetLoadRequest { bus, register, data })
+            .await?
+            .flatten()
+    }
+
+    /// Read `count` bytes of the target register file of I2C `bus`,
+    /// from `register` on.
+    pub async fn i2c_target_dump(
+        &self,
+        bus: u8,
+        register: u8,
+        count: u16,
+    ) -> Result<Vec<u8>, PicoDeGalloError<I2cTargetFail>> {
+        self.client
+            .send_resp::<I2cTargetDump>(&I2cTargetDumpRequest { bus, register, count })
+            .await?
+            .flatten()
+    }
+
+    /// Subscribe to the accesses of controllers to every running
+    /// target, holding up to `depth` of them until received.
+    pub async fn i2c_target_events(
+        &self,
+        depth: usize,
+    ) -> Result<Events<I2cTargetEvent>, PicoDeGalloError<Infallible>> {
+        self.subscribe::<I2cTargetTopic>(depth).await
+    }
+
+    /// Read `count` bytes from SPI `bus`.
     ///
     /// An arbitrary limit of `u16::MAX` is imposed currently, that
     /// may change in the future.
-    pub async fn spi_read(&self, count: u16) -> Result<Vec<u8>, PicoDeGalloError<SpiReadFail>> {
+    pub async fn spi_read(&self, bus: u8, count: u16) -> Result<Vec<u8>, PicoDeGalloError<SpiReadFail>> {
+        self.client
+            .send_resp::<SpiRead>(&SpiReadRequest { bus, count })
+            .await?
+            .flatten()
+    }
+
+    /// Write `contents` to SPI `bus`.
+    pub async fn spi_write(&self, bus: u8, contents: &[u8]) -> Result<(), PicoDeGalloError<SpiWriteFail>> {
+        self.client
+            .send_resp::<SpiWrite>(&SpiWriteRequest { bus, contents })
+            .await?
+            .flatten()
+    }
+
+    /// Flush SPI `bus`.
+    pub async fn spi_flush(&self, bus: u8) -> Result<(), PicoDeGalloError<SpiFlushFail>> {
+        self.client
+            .send_resp::<SpiFlush>(&SpiFlushRequest { bus })
+            .await?
+            .flatten()
+    }
+
+    /// Run `operations` back to back on SPI `bus` with the chip select
+    /// `cs` asserted, all of it in the firmware.
+    ///
+    /// Returns the bytes read by every operation, concatenated in
+    /// order. Up to [`SPI_MAX_OPERATIONS`] operations may be given and
+    /// up to 512 bytes read at once.
+    pub async fn spi_transaction(
+        &self,
+        bus: u8,
+        cs: SpiChipSelect,
+        operations: &[SpiOperation<'_>],
+    ) -> Result<Vec<u8>, PicoDeGalloError<SpiTransactionFail>> {
         self.client
-            .send_resp::<SpiRead>(&SpiReadRequest { count })
+            .send_resp::<SpiTransaction>(&SpiTransactionRequest {
+                bus,
+                cs,
+                operations: operations.to_vec(),
+            })
             .await?
             .flatten()
     }
 
-    /// Write `contents` to the SPI bus.
-    pub async fn spi_write(&self, contents: &[u8]) -> Result<(), PicoDeGalloError<SpiWriteFail>> {
+    /// Register an SPI device named `name` on `bus`, with its own chip
+    /// select and bus settings.
+    ///
+    /// The firmware applies `config` on each transaction on the
+    /// returned slot, see [`Self::spi_device_transaction`], so devices
+    /// with different modes and clock rates can share the bus. Adding
+    /// a name again updates that device and returns the same slot. Up
+    /// to [`SPI_MAX_DEVICES`] devices may be registered.
+    pub async fn spi_add_device(
+        &self,
+        bus: u8,
+        name: &str,
+        cs: SpiChipSelect,
+        config: SpiConfig,
+    ) -> Result<SpiDeviceSlot, PicoDeGalloError<SpiAddDeviceFail>> {
         self.client
-            .send_resp::<SpiWrite>(&SpiWriteRequest { contents })
+            .send_resp::<SpiAddDevice>(&SpiAddDeviceRequest { bus, name, cs, config })
             .await?
             .flatten()
     }
 
-    /// Flush the SPI interface.
-    pub async fn spi_flush(&self) -> Result<(), PicoDeGalloError<SpiFlushFail>> {
-        self.client.send_resp::<SpiFlush>(&()).await?.flatten()
+    /// Unregister the SPI device in `slot`.
+    pub async fn spi_remove_device(&self, slot: u8) -> Result<(), PicoDeGalloError<SpiRemoveDeviceFail>> {
+        self.client
+            .send_resp::<SpiRemoveDevice>(&SpiRemoveDeviceRequest { slot })
+            .await?
+            .flatten()
+    }
+
+    /// Run `operations` on the SPI device in `slot`, with its chip
+    /// select and settings.
+    ///
+    /// Same as [`Self::spi_transaction`] otherwise.
+    pub async fn spi_device_transaction(
+        &self,
+        slot: u8,
+        operations: &[SpiOperation<'_>],
+    ) -> Result<Vec<u8>, PicoDeGalloError<SpiTransactionFail>> {
+        self.client
+            .send_resp::<SpiDeviceTransaction>(&SpiDeviceTransactionRequest {
+                slot,
+                operations: operations.to_vec(),
+            })
+            .await?
+            .flatten()
+    }
+
+    /// Answer as a target on SPI `bus` until stopped, in the given mode
+    /// and with `fill` past the end of responses.
+    ///
+    /// The controller's chip select goes to GPIO 5 for bus 0 and GPIO
+    /// 13, which is GPIO index 5, for bus 1. Its MOSI goes to the RX pin
+    /// of the bus and its MISO to the TX pin. With
+    /// [`SpiPhase::CaptureOnFirstTransition`], chip select must be
+    /// deasserted between bytes.
+    ///
+    /// Selections are answered with the response set by
+    /// [`PicoDeGallo::spi_target_load`], or that of a command added by
+    /// [`PicoDeGallo::spi_target_command`] from the second byte
+    /// following it. The bytes of every selection are published as a
+    /// [`SpiTargetEvent`], see [`PicoDeGallo::spi_target_events`].
+    /// Controller requests on `bus` fail until the target is stopped.
+    ///
+    /// Bytes are moved by the firmware as they're clocked, controllers
+    /// much faster than 1 MHz may see `fill` in place of responses.
+    pub async fn spi_target_start(
+        &self,
+        bus: u8,
+        polarity: SpiPolarity,
+        phase: SpiPhase,
+        fill: u8,
+    ) -> Result<(), PicoDeGalloError<SpiTargetFail>> {
+        self.client
+            .send_resp::<SpiTargetStart>(&SpiTargetStartRequest {
+                bus,
+                polarity,
+                phase,
+                fill,
+            })
+            .await?
+            .flatten()
+    }
+
+    /// Stop the target on SPI `bus`, handing it back to the controller.
+    /// Its responses are kept.
+    pub async fn spi_target_stop(&self, bus: u8) -> Result<(), PicoDeGalloError<SpiTargetFail>> {
+        self.client
+            .send_resp::<SpiTargetStop>(&SpiTargetStopRequest { bus })
+            .await?
+            .flatten()
+    }
+
+    /// Answer every selection of the target on SPI `bus` with `data`,
+    /// up to [`SPI_TARGET_MAX_LEN`] bytes. Possible whether the target
+    /// runs or not.
+    pub async fn spi_target_load(&self, bus: u8, data: &[u8]) -> Result<(), PicoDeGalloError<SpiTargetFail>> {
+        self.client
+            .send_resp::<SpiTargetLoad>(&SpiTargetLoadRequest { bus, data })
+            .await?
+            .flatten()
+    }
+
+    /// Answer `command` with `response` on SPI `bus`, replacing the
+    /// response of an identical command. Up to
+    /// [`SPI_TARGET_MAX_COMMANDS`] commands can be added.
+    pub async fn spi_target_command(
+        &self,
+        bus: u8,
+        command: &[u8],
+        response: &[u8],
+    ) -> Result<(), PicoDeGalloError<SpiTargetFail>> {
+        self.client
+            .send_resp::<SpiTargetCommand>(&SpiTargetCommandRequest { bus, command, response })
+            .await?
+            .flatten()
+    }
+
+    /// Forget the response and every command of the target on SPI
+    /// `bus`.
+    pub async fn spi_target_clear(&self, bus: u8) -> Result<(), PicoDeGalloError<SpiTargetFail>> {
+        self.client
+            .send_resp::<SpiTargetClear>(&SpiTargetClearRequest { bus })
+            .await?
+            .flatten()
+    }
+
+    /// Subscribe to the selections of every running SPI target, holding
+    /// up to `depth` of them until received.
+    pub async fn spi_target_events(
+        &self,
+        depth: usize,
+    ) -> Result<Events<SpiTargetEvent>, PicoDeGalloError<Infallible>> {
+        self.subscribe::<SpiTargetTopic>(depth).await
     }
 
     /// Get the current state of GPIO numbered by `pin`.
@@ -209,27 +840,539 @@ impl PicoDeGallo {
             .flatten()
     }
 
-    /// Set configuration parameters for I2C and SPI interfaces.
-    pub async fn set_config(
+    /// Measure the signal on the GPIO numbered by `pin` for `gate_ms`,
+    /// up to [`GPIO_MEASURE_MAX_GATE_MS`], answering once done. Its
+    /// rising edges are counted and its level sampled at a third of the
+    /// system clock, see [`GpioMeasurement`] for what follows.
+    ///
+    /// The pin is only read, it may be driven by Pico de Gallo itself.
+    /// Measuring borrows the state machines of the logic analyzer.
+    pub async fn gpio_measure(
+        &self,
+        pin: u8,
+        gate_ms: u32,
+    ) -> Result<GpioMeasurement, PicoDeGalloError<GpioMeasureFail>> {
+        self.client
+            .send_resp::<GpioMeasure>(&GpioMeasureRequest { pin, gate_ms })
+            .await?
+            .flatten()
+    }
+
+    /// Frequency in Hz of the signal on the GPIO numbered by `pin`,
+    /// counting edges for `gate_ms`.
+    pub async fn measure_frequency(&self, pin: u8, gate_ms: u32) -> Result<f64, PicoDeGalloError<GpioMeasureFail>> {
+        Ok(self.gpio_measure(pin, gate_ms).await?.frequency())
+    }
+
+    /// Period in seconds of the signal on the GPIO numbered by `pin`,
+    /// over `gate_ms`. `None` if it didn't toggle.
+    pub async fn measure_period(
+        &self,
+        pin: u8,
+        gate_ms: u32,
+    ) -> Result<Option<f64>, PicoDeGalloError<GpioMeasureFail>> {
+        Ok(self.gpio_measure(pin, gate_ms).await?.period())
+    }
+
+    /// Fraction of `gate_ms` the GPIO numbered by `pin` was high, from
+    /// 0 to 1.
+    pub async fn measure_duty_cycle(&self, pin: u8, gate_ms: u32) -> Result<f64, PicoDeGalloError<GpioMeasureFail>> {
+        Ok(self.gpio_measure(pin, gate_ms).await?.duty_cycle())
+    }
+
+    /// Average high time in seconds of the pulses on the GPIO numbered
+    /// by `pin`, over `gate_ms`. `None` if it didn't toggle.
+    pub async fn measure_pulse_width(
+        &self,
+        pin: u8,
+        gate_ms: u32,
+    ) -> Result<Option<f64>, PicoDeGalloError<GpioMeasureFail>> {
+        Ok(self.gpio_measure(pin, gate_ms).await?.pulse_width())
+    }
+
+    /// Apply `config` to I2C `bus`.
+    ///
+    /// Returns the SCL frequency actually achieved, in Hz, as the
+    /// dividers can't produce every frequency exactly. The rise time
+    /// of SCL lowers it further. Configurations failing
+    /// [`I2cConfig::validate`] are rejected by the firmware.
+    pub async fn i2c_set_config(&self, bus: u8, config: I2cConfig) -> Result<u32, PicoDeGalloError<I2cSetConfigFail>> {
+        self.client
+            .send_resp::<I2cSetConfig>(&I2cSetConfigRequest { bus, config })
+            .await?
+            .flatten()
+    }
+
+    /// Apply `config` to SPI `bus`.
+    ///
+    /// Returns the SCK frequency actually achieved, in Hz, as the
+    /// prescalers can't produce every frequency exactly. Configurations
+    /// failing [`SpiConfig::validate`] are rejected by the firmware.
+    pub async fn spi_set_config(&self, bus: u8, config: SpiConfig) -> Result<u32, PicoDeGalloError<SpiSetConfigFail>> {
+        self.client
+            .send_resp::<SpiSetConfig>(&SpiSetConfigRequest { bus, config })
+            .await?
+            .flatten()
+    }
+
+    /// Issue a reset pulse on the 1-Wire bus of GPIO `pin`, which
+    /// needs an external pull-up. Returns whether a device answered with
+    /// a presence pulse.
+    pub async fn onewire_reset(&self, pin: u8) -> Result<bool, PicoDeGalloError<OneWireFail>> {
+        self.client
+            .send_resp::<OneWireReset>(&OneWireResetRequest { pin })
+            .await?
+            .flatten()
+    }
+
+    /// Reset the 1-Wire bus of GPIO `pin` and address the device with
+    /// ROM code `rom`, or every device without one, so that a function
+    /// command follows. Returns whether a device answered the reset,
+    /// nothing is addressed otherwise.
+    pub async fn onewire_select(
+        &self,
+        pin: u8,
+        rom: Option<OneWireRom>,
+    ) -> Result<bool, PicoDeGalloError<OneWireFail>> {
+        if !self.onewire_reset(pin).await? {
+            return Ok(false);
+        }
+
+        match rom {
+            Some(OneWireRom(rom)) => {
+                let mut command = [ONEWIRE_MATCH_ROM; 9];
+                command[1..].copy_from_slice(&rom);
+                self.onewire_write(pin, &command).await?;
+            }
+            None => self.onewire_write(pin, &[ONEWIRE_SKIP_ROM]).await?,
+        }
+
+        Ok(true)
+    }
+
+    /// Write `bit` in one time slot on the 1-Wire bus of GPIO `pin`.
+    pub async fn onewire_write_bit(&self, pin: u8, bit: bool) -> Result<(), PicoDeGalloError<OneWireFail>> {
+        self.client
+            .send_resp::<OneWireBit>(&OneWireBitRequest { pin, bit })
+            .await?
+            .flatten()
+            .map(|_| ())
+    }
+
+    /// Read a bit in one time slot on the 1-Wire bus of GPIO `pin`.
+    pub async fn onewire_read_bit(&self, pin: u8) -> Result<bool, PicoDeGalloError<OneWireFail>> {
+        self.client
+            .send_resp::<OneWireBit>(&OneWireBitRequest { pin, bit: true })
+            .await?
+            .flatten()
+    }
+
+    /// Write `data` on the 1-Wire bus of GPIO `pin`, up to
+    /// [`ONEWIRE_MAX_LEN`] bytes.
+    pub async fn onewire_write(&self, pin: u8, data: &[u8]) -> Result<(), PicoDeGalloError<OneWireFail>> {
+        self.onewire_write_then_power(pin, data, 0).await
+    }
+
+    /// Write `data` on the 1-Wire bus of GPIO `pin`, then drive it high
+    /// for `strong_pullup_ms`, powering parasite devices through e.g. a
+    /// temperature conversion.
+    pub async fn onewire_write_then_power(
         &self,
-        i2c_frequency: u32,
-        spi_frequency: u32,
-        spi_phase: SpiPhase,
-        spi_polarity: SpiPolarity,
-    ) -> Result<(), PicoDeGalloError<SetConfigurationFail>> {
+        pin: u8,
+        data: &[u8],
+        strong_pullup_ms: u16,
+    ) -> Result<(), PicoDeGalloError<OneWireFail>> {
         self.client
-            .send_resp::<SetConfiguration>(&SetConfigurationRequest {
-                i2c_frequency,
-                spi_frequency,
-                spi_phase,
-                spi_polarity,
+            .send_resp::<OneWireWrite>(&OneWireWriteRequest {
+                pin,
+                data,
+                strong_pullup_ms,
             })
             .await?
             .flatten()
     }
 
+    /// Read `count` bytes from the 1-Wire bus of GPIO `pin`, up to
+    /// [`ONEWIRE_MAX_LEN`].
+    pub async fn onewire_read(&self, pin: u8, count: u16) -> Result<Vec<u8>, PicoDeGalloError<OneWireFail>> {
+        self.client
+            .send_resp::<OneWireRead>(&OneWireReadRequest { pin, count })
+            .await?
+            .flatten()
+    }
+
+    /// Find the ROM codes of the devices on the 1-Wire bus of GPIO
+    /// `pin`, up to [`ONEWIRE_SEARCH_MAX_DEVICES`] of them.
+    pub async fn onewire_search(&self, pin: u8) -> Result<Vec<OneWireRom>, PicoDeGalloError<OneWireFail>> {
+        self.client
+            .send_resp::<OneWireSearch>(&OneWireSearchRequest { pin, alarm: false })
+            .await?
+            .flatten()
+            .map(|roms| roms.to_vec())
+    }
+
+    /// Find the ROM codes of the devices in an alarm state on the
+    /// 1-Wire bus of GPIO `pin`.
+    pub async fn onewire_alarm_search(&self, pin: u8) -> Result<Vec<OneWireRom>, PicoDeGalloError<OneWireFail>> {
+        self.client
+            .send_resp::<OneWireSearch>(&OneWireSearchRequest { pin, alarm: true })
+            .await?
+            .flatten()
+            .map(|roms| roms.to_vec())
+    }
+
+    /// Set a chain of WS2812 style RGB LEDs on GPIO `pin`, from the
+    /// first LED onwards, up to 256 of them. The pin is left driven low.
+    pub async fn leds_write(&self, pin: u8, colors: &[Rgb]) -> Result<(), PicoDeGalloError<LedsFail>> {
+        let data = colors.iter().flat_map(Rgb::grb).collect::<Vec<_>>();
+        self.leds_write_raw(pin, &data).await
+    }
+
+    /// Set a chain of SK6812 style RGBW LEDs on GPIO `pin`, from the
+    /// first LED onwards, up to 192 of them.
+    pub async fn leds_write_rgbw(&self, pin: u8, colors: &[Rgbw]) -> Result<(), PicoDeGalloError<LedsFail>> {
+        let data = colors.iter().flat_map(Rgbw::grbw).collect::<Vec<_>>();
+        self.leds_write_raw(pin, &data).await
+    }
+
+    /// Shift `data` out as is to a chain of LEDs on GPIO `pin`, at
+    /// 800 kHz, then latch it. For LEDs taking their colors in another
+    /// order. Up to [`LEDS_MAX_LEN`] bytes.
+    pub async fn leds_write_raw(&self, pin: u8, data: &[u8]) -> Result<(), PicoDeGalloError<LedsFail>> {
+        self.client
+            .send_resp::<LedsWrite>(&LedsWriteRequest { pin, data })
+            .await?
+            .flatten()
+    }
+
+    /// Load `program` for state machine `sm`, which must be stopped.
+    /// Returns the offset it was loaded at.
+    ///
+    /// Programs assembled with the `pio` crate convert into a
+    /// [`PioProgram`] with the `pio` feature:
+    ///
+    /// ```
+    /// # use pico_de_gallo_lib::{PicoDeGallo, PicoDeGalloError, PioFail, PioProgram};
+    /// # async fn load(gallo: &PicoDeGallo) -> Result<(), PicoDeGalloError<PioFail>> {
+    /// let program = pio::pio_asm!(
+    ///     ".side_set 1",
+    ///     "out pins, 1 side 0",
+    ///     "nop         side 1",
+    /// )
+    /// .program;
+    ///
+    /// gallo.pio_load(0, &PioProgram::from(&program)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn pio_load(&self, sm: u8, program: &PioProgram) -> Result<u8, PicoDeGalloError<PioFail>> {
+        self.client
+            .send_resp::<PioLoad>(&PioLoadRequest {
+                sm,
+                program: program.clone(),
+            })
+            .await?
+            .flatten()
+    }
+
+    /// Configure state machine `sm` and start its program from the top.
+    /// The GPIOs it maps are its own until it's stopped.
+    pub async fn pio_start(&self, sm: u8, config: PioConfig) -> Result<(), PicoDeGalloError<PioFail>> {
+        self.client
+            .send_resp::<PioStart>(&PioStartRequest { sm, config })
+            .await?
+            .flatten()
+    }
+
+    /// Stop state machine `sm`, handing its GPIOs back as inputs.
+    pub async fn pio_stop(&self, sm: u8) -> Result<(), PicoDeGalloError<PioFail>> {
+        self.client
+            .send_resp::<PioStop>(&PioStopRequest { sm })
+            .await?
+            .flatten()
+    }
+
+    /// Execute `instruction` on state machine `sm` right away.
+    pub async fn pio_exec(&self, sm: u8, instruction: u16) -> Result<(), PicoDeGalloError<PioFail>> {
+        self.client
+            .send_resp::<PioExec>(&PioExecRequest { sm, instruction })
+            .await?
+            .flatten()
+    }
+
+    /// Push `data` into the TX FIFO of state machine `sm`, waiting up
+    /// to `timeout_ms` for room at each [`PIO_MAX_WORDS`] words.
+    /// Returns the number of words pushed, fewer than given if the
+    /// state machine stopped pulling.
+    pub async fn pio_push(&self, sm: u8, data: &[u32], timeout_ms: u32) -> Result<usize, PicoDeGalloError<PioFail>> {
+        let mut pushed = 0;
+
+        for chunk in data.chunks(PIO_MAX_WORDS) {
+            let count = self
+                .client
+                .send_resp::<PioPush>(&PioPushRequest {
+                    sm,
+                    data: chunk.iter().copied().collect(),
+                    timeout_ms,
+                })
+                .await?
+                .flatten()?;
+
+            pushed += usize::from(count);
+            if usize::from(count) < chunk.len() {
+                break;
+            }
+        }
+
+        Ok(pushed)
+    }
+
+    /// Pull `count` words from the RX FIFO of state machine `sm`,
+    /// waiting up to `timeout_ms` for each [`PIO_MAX_WORDS`] of them.
+    /// Fewer words are returned if the state machine stopped pushing.
+    pub async fn pio_pull(&self, sm: u8, count: usize, timeout_ms: u32) -> Result<Vec<u32>, PicoDeGalloError<PioFail>> {
+        let mut data = Vec::with_capacity(count);
+
+        while data.len() < count {
+            let chunk = (count - data.len()).min(PIO_MAX_WORDS);
+            let words = self
+                .client
+                .send_resp::<PioPull>(&PioPullRequest {
+                    sm,
+                    count: chunk as u16,
+                    timeout_ms,
+                })
+                .await?
+                .flatten()?;
+
+            data.extend_from_slice(&words);
+            if words.len() < chunk {
+                break;
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// Sample the [`CAPTURE_CHANNELS`] channels `samples` times at
+    /// `rate` Hz once `trigger` fires, up to [`CAPTURE_MAX_SAMPLES`].
+    /// Channel n is pin [`CAPTURE_FIRST_PIN`] + n, wired as named by
+    /// [`CAPTURE_CHANNEL_NAMES`].
+    ///
+    /// The end of the capture is published as a [`CaptureEvent`], see
+    /// [`PicoDeGallo::capture_events`], its samples are then read by
+    /// [`PicoDeGallo::capture_read`]. Pins are only read, buses and
+    /// GPIOs keep working meanwhile.
+    pub async fn capture_start(
+        &self,
+        rate: u32,
+        samples: u32,
+        trigger: CaptureTrigger,
+    ) -> Result<(), PicoDeGalloError<CaptureFail>> {
+        self.client
+            .send_resp::<CaptureStart>(&CaptureStartRequest { rate, samples, trigger })
+            .await?
+            .flatten()
+    }
+
+    /// Stop the capture running, which then ends without samples.
+    pub async fn capture_stop(&self) -> Result<(), PicoDeGalloError<CaptureFail>> {
+        self.client.send_resp::<CaptureStop>(&()).await?.flatten()
+    }
+
+    /// Read `count` samples of the last capture from `offset` on, in
+    /// requests of up to [`CAPTURE_MAX_READ`] samples. Channel n is in
+    /// bit n.
+    pub async fn capture_read(&self, offset: u32, count: u32) -> Result<Vec<u16>, PicoDeGalloError<CaptureFail>> {
+        let mut samples = Vec::with_capacity(count as usize);
+
+        while samples.len() < count as usize {
+            let chunk = (count as usize - samples.len()).min(CAPTURE_MAX_READ);
+            let data = self
+                .client
+                .send_resp::<CaptureRead>(&CaptureReadRequest {
+                    offset: offset + samples.len() as u32,
+                    count: chunk as u16,
+                })
+                .await?
+                .flatten()?;
+
+            // A short reply would otherwise have this ask for the rest forever
+            if data.len() != chunk * 2 {
+                return Err(PicoDeGalloError::Comms(HostErr::BadResponse));
+            }
+
+            samples.extend(
+                data.chunks_exact(2)
+                    .map(|sample| u16::from_le_bytes([sample[0], sample[1]])),
+            );
+        }
+
+        Ok(samples)
+    }
+
+    /// Subscribe to the end of captures, holding up to `depth` of them
+    /// until received.
+    pub async fn capture_events(&self, depth: usize) -> Result<Events<CaptureEvent>, PicoDeGalloError<Infallible>> {
+        self.subscribe::<CaptureTopic>(depth).await
+    }
+
+    /// Run a capture as [`PicoDeGallo::capture_start`] does, wait for
+    /// it to end and read its samples. Stopping it from another handle
+    /// by [`PicoDeGallo::capture_stop`] returns no samples.
+    pub async fn capture(
+        &self,
+        rate: u32,
+        samples: u32,
+        trigger: CaptureTrigger,
+    ) -> Result<Capture, PicoDeGalloError<CaptureFail>> {
+        let mut events = self
+            .capture_events(1)
+            .await
+            .map_err(|_| PicoDeGalloError::Comms(HostErr::Closed))?;
+
+        self.capture_start(rate, samples, trigger).await?;
+
+        let event = events.recv().await.ok_or(PicoDeGalloError::Comms(HostErr::Closed))?;
+
+        Ok(Capture {
+            rate: event.rate,
+            samples: self.capture_read(0, event.samples).await?,
+        })
+    }
+
+    /// Decode the traffic of another controller on I2C `bus`, without
+    /// taking part, until stopped by [`PicoDeGallo::sniff_stop`]. Every
+    /// transfer is published as an [`I2cSniffEvent`], see
+    /// [`PicoDeGallo::i2c_sniff_events`].
+    ///
+    /// Sniffing borrows the state machines of the logic analyzer, so
+    /// only one bus is sniffed at a time, and not during a capture.
+    pub async fn sniff_i2c_start(&self, bus: u8) -> Result<(), PicoDeGalloError<SniffFail>> {
+        self.client
+            .send_resp::<SniffI2cStart>(&SniffI2cStartRequest { bus })
+            .await?
+            .flatten()
+    }
+
+    /// Decode the traffic of another controller on SPI `bus`, sampling
+    /// on the edge given by `polarity` and `phase`, until stopped by
+    /// [`PicoDeGallo::sniff_stop`]. Every selection is published as an
+    /// [`SpiSniffEvent`], see [`PicoDeGallo::spi_sniff_events`].
+    pub async fn sniff_spi_start(
+        &self,
+        bus: u8,
+        polarity: SpiPolarity,
+        phase: SpiPhase,
+    ) -> Result<(), PicoDeGalloError<SniffFail>> {
+        self.client
+            .send_resp::<SniffSpiStart>(&SniffSpiStartRequest { bus, polarity, phase })
+            .await?
+            .flatten()
+    }
+
+    /// Stop the sniffer running, handing its bus back to the controller.
+    pub async fn sniff_stop(&self) -> Result<(), PicoDeGalloError<SniffFail>> {
+        self.client.send_resp::<SniffStop>(&()).await?.flatten()
+    }
+
+    /// Subscribe to the I2C transfers sniffed, holding up to `depth` of
+    /// them until received.
+    pub async fn i2c_sniff_events(&self, depth: usize) -> Result<Events<I2cSniffEvent>, PicoDeGalloError<Infallible>> {
+        self.subscribe::<I2cSniffTopic>(depth).await
+    }
+
+    /// Subscribe to the SPI selections sniffed, holding up to `depth` of
+    /// them until received.
+    pub async fn spi_sniff_events(&self, depth: usize) -> Result<Events<SpiSniffEvent>, PicoDeGalloError<Infallible>> {
+        self.subscribe::<SpiSniffTopic>(depth).await
+    }
+
+    /// Get the current configuration of every I2C and SPI bus.
+    pub async fn get_config(&self) -> Result<Configuration, PicoDeGalloError<Infallible>> {
+        Ok(self.client.send_resp::<GetConfiguration>(&()).await?)
+    }
+
     /// Get the firmware version from the Pico de Gallo device.
     pub async fn version(&self) -> Result<VersionInfo, PicoDeGalloError<Infallible>> {
         Ok(self.client.send_resp::<Version>(&()).await?)
     }
+
+    /// Subscribe to topic `T`, holding up to `depth` of its messages
+    /// until received.
+    async fn subscribe<T: Topic>(&self, depth: usize) -> Result<Events<T::Message>, PicoDeGalloError<Infallible>>
+    where
+        T::Message: DeserializeOwned,
+    {
+        match self.client.subscribe_multi::<T>(depth).await {
+            Ok(sub) => Ok(Events { sub }),
+            Err(_) => Err(PicoDeGalloError::Comms(HostErr::Closed)),
+        }
+    }
+}
+
+/// Messages published by the device, see e.g.
+/// [`PicoDeGallo::i2c_trigger_events`].
+pub struct Events<T> {
+    sub: MultiSubscription<T>,
+}
+
+impl<T: DeserializeOwned> Events<T> {
+    /// Wait for the next event, `None` once the device is gone.
+    ///
+    /// Events which didn't fit in the subscription's depth are
+    /// skipped.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            match self.sub.recv().await {
+                Ok(event) => return Some(event),
+                Err(MultiSubRxError::Lagged(_)) => continue,
+                Err(MultiSubRxError::IoClosed) => return None,
+            }
+        }
+    }
+}
+
+/// Samples of a capture, see [`PicoDeGallo::capture`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    /// Actual sample rate in Hz.
+    pub rate: u32,
+    /// Channel n in bit n, none if the capture was stopped.
+    pub samples: Vec<u16>,
+}
+
+/// Exclusive access to a shared device, see [`PicoDeGallo::lock`].
+///
+/// The lock is released by [`BusLock::unlock`] or, in the background,
+/// when it is dropped. Either way, the next [`PicoDeGallo::lock`] on
+/// the same client waits until the release went through.
+pub struct BusLock<'a> {
+    gallo: &'a PicoDeGallo,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl BusLock<'_> {
+    /// Release the lock, letting other clients access the device again.
+    pub async fn unlock(mut self) -> Result<(), PicoDeGalloError<Infallible>> {
+        if let Some(guard) = self.guard.take() {
+            let unlocked = self.gallo.client.send_resp::<BrokerUnlock>(&()).await;
+            drop(guard);
+            unlocked?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for BusLock<'_> {
+    fn drop(&mut self) {
+        if let Some(guard) = self.guard.take() {
+            let client = self.gallo.client.clone();
+            std::thread::spawn(move || {
+                let _ = futures::executor::block_on(client.send_resp::<BrokerUnlock>(&()));
+                drop(guard);
+            });
+        }
+    }
 }
@@ -0,0 +1,715 @@
+//! PMBus on top of SMBus.
+//!
+//! Conversions from and to the PMBus data formats and decoding of the
+//! `STATUS_*` registers are plain functions, usable with the blocking
+//! client too. [`Device`] reads telemetry straight into engineering
+//! units.
+
+use crate::{PicoDeGallo, PicoDeGalloError, SmbusFail};
+
+macro_rules! commands {
+    ($($name:ident = $code:literal,)*) => {
+        /// Standard PMBus command codes, as per part II of the
+        /// specification.
+        pub mod command {
+            $(pub const $name: u8 = $code;)*
+        }
+
+        /// Name of the standard command `code`, `None` for
+        /// manufacturer specific and reserved codes.
+        pub fn command_name(code: u8) -> Option<&'static str> {
+            match code {
+                $($code => Some(stringify!($name)),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+commands! {
+    PAGE = 0x00,
+    OPERATION = 0x01,
+    ON_OFF_CONFIG = 0x02,
+    CLEAR_FAULTS = 0x03,
+    PHASE = 0x04,
+    PAGE_PLUS_WRITE = 0x05,
+    PAGE_PLUS_READ = 0x06,
+    ZONE_CONFIG = 0x07,
+    ZONE_ACTIVE = 0x08,
+    WRITE_PROTECT = 0x10,
+    STORE_DEFAULT_ALL = 0x11,
+    RESTORE_DEFAULT_ALL = 0x12,
+    STORE_DEFAULT_CODE = 0x13,
+    RESTORE_DEFAULT_CODE = 0x14,
+    STORE_USER_ALL = 0x15,
+    RESTORE_USER_ALL = 0x16,
+    STORE_USER_CODE = 0x17,
+    RESTORE_USER_CODE = 0x18,
+    CAPABILITY = 0x19,
+    QUERY = 0x1a,
+    SMBALERT_MASK = 0x1b,
+    VOUT_MODE = 0x20,
+    VOUT_COMMAND = 0x21,
+    VOUT_TRIM = 0x22,
+    VOUT_CAL_OFFSET = 0x23,
+    VOUT_MAX = 0x24,
+    VOUT_MARGIN_HIGH = 0x25,
+    VOUT_MARGIN_LOW = 0x26,
+    VOUT_TRANSITION_RATE = 0x27,
+    VOUT_DROOP = 0x28,
+    VOUT_SCALE_LOOP = 0x29,
+    VOUT_SCALE_MONITOR = 0x2a,
+    VOUT_MIN = 0x2b,
+    COEFFICIENTS = 0x30,
+    POUT_MAX = 0x31,
+    MAX_DUTY = 0x32,
+    FREQUENCY_SWITCH = 0x33,
+    POWER_MODE = 0x34,
+    VIN_ON = 0x35,
+    VIN_OFF = 0x36,
+    INTERLEAVE = 0x37,
+    IOUT_CAL_GAIN = 0x38,
+    IOUT_CAL_OFFSET = 0x39,
+    FAN_CONFIG_1_2 = 0x3a,
+    FAN_COMMAND_1 = 0x3b,
+    FAN_COMMAND_2 = 0x3c,
+    FAN_CONFIG_3_4 = 0x3d,
+    FAN_COMMAND_3 = 0x3e,
+    FAN_COMMAND_4 = 0x3f,
+    VOUT_OV_FAULT_LIMIT = 0x40,
+    VOUT_OV_FAULT_RESPONSE = 0x41,
+    VOUT_OV_WARN_LIMIT = 0x42,
+    VOUT_UV_WARN_LIMIT = 0x43,
+    VOUT_UV_FAULT_LIMIT = 0x44,
+    VOUT_UV_FAULT_RESPONSE = 0x45,
+    IOUT_OC_FAULT_LIMIT = 0x46,
+    IOUT_OC_FAULT_RESPONSE = 0x47,
+    IOUT_OC_LV_FAULT_LIMIT = 0x48,
+    IOUT_OC_LV_FAULT_RESPONSE = 0x49,
+    IOUT_OC_WARN_LIMIT = 0x4a,
+    IOUT_UC_FAULT_LIMIT = 0x4b,
+    IOUT_UC_FAULT_RESPONSE = 0x4c,
+    OT_FAULT_LIMIT = 0x4f,
+    OT_FAULT_RESPONSE = 0x50,
+    OT_WARN_LIMIT = 0x51,
+    UT_WARN_LIMIT = 0x52,
+    UT_FAULT_LIMIT = 0x53,
+    UT_FAULT_RESPONSE = 0x54,
+    VIN_OV_FAULT_LIMIT = 0x55,
+    VIN_OV_FAULT_RESPONSE = 0x56,
+    VIN_OV_WARN_LIMIT = 0x57,
+    VIN_UV_WARN_LIMIT = 0x58,
+    VIN_UV_FAULT_LIMIT = 0x59,
+    VIN_UV_FAULT_RESPONSE = 0x5a,
+    IIN_OC_FAULT_LIMIT = 0x5b,
+    IIN_OC_FAULT_RESPONSE = 0x5c,
+    IIN_OC_WARN_LIMIT = 0x5d,
+    POWER_GOOD_ON = 0x5e,
+    POWER_GOOD_OFF = 0x5f,
+    TON_DELAY = 0x60,
+    TON_RISE = 0x61,
+    TON_MAX_FAULT_LIMIT = 0x62,
+    TON_MAX_FAULT_RESPONSE = 0x63,
+    TOFF_DELAY = 0x64,
+    TOFF_FALL = 0x65,
+    TOFF_MAX_WARN_LIMIT = 0x66,
+    POUT_OP_FAULT_LIMIT = 0x68,
+    POUT_OP_FAULT_RESPONSE = 0x69,
+    POUT_OP_WARN_LIMIT = 0x6a,
+    PIN_OP_WARN_LIMIT = 0x6b,
+    STATUS_BYTE = 0x78,
+    STATUS_WORD = 0x79,
+    STATUS_VOUT = 0x7a,
+    STATUS_IOUT = 0x7b,
+    STATUS_INPUT = 0x7c,
+    STATUS_TEMPERATURE = 0x7d,
+    STATUS_CML = 0x7e,
+    STATUS_OTHER = 0x7f,
+    STATUS_MFR_SPECIFIC = 0x80,
+    STATUS_FANS_1_2 = 0x81,
+    STATUS_FANS_3_4 = 0x82,
+    READ_EIN = 0x86,
+    READ_EOUT = 0x87,
+    READ_VIN = 0x88,
+    READ_IIN = 0x89,
+    READ_VCAP = 0x8a,
+    READ_VOUT = 0x8b,
+    READ_IOUT = 0x8c,
+    READ_TEMPERATURE_1 = 0x8d,
+    READ_TEMPERATURE_2 = 0x8e,
+    READ_TEMPERATURE_3 = 0x8f,
+    READ_FAN_SPEED_1 = 0x90,
+    READ_FAN_SPEED_2 = 0x91,
+    READ_FAN_SPEED_3 = 0x92,
+    READ_FAN_SPEED_4 = 0x93,
+    READ_DUTY_CYCLE = 0x94,
+    READ_FREQUENCY = 0x95,
+    READ_POUT = 0x96,
+    READ_PIN = 0x97,
+    PMBUS_REVISION = 0x98,
+    MFR_ID = 0x99,
+    MFR_MODEL = 0x9a,
+    MFR_REVISION = 0x9b,
+    MFR_LOCATION = 0x9c,
+    MFR_DATE = 0x9d,
+    MFR_SERIAL = 0x9e,
+    APP_PROFILE_SUPPORT = 0x9f,
+    MFR_VIN_MIN = 0xa0,
+    MFR_VIN_MAX = 0xa1,
+    MFR_IIN_MAX = 0xa2,
+    MFR_PIN_MAX = 0xa3,
+    MFR_VOUT_MIN = 0xa4,
+    MFR_VOUT_MAX = 0xa5,
+    MFR_IOUT_MAX = 0xa6,
+    MFR_POUT_MAX = 0xa7,
+    MFR_TAMBIENT_MAX = 0xa8,
+    MFR_TAMBIENT_MIN = 0xa9,
+    MFR_EFFICIENCY_LL = 0xaa,
+    MFR_EFFICIENCY_HL = 0xab,
+    MFR_PIN_ACCURACY = 0xac,
+    IC_DEVICE_ID = 0xad,
+    IC_DEVICE_REV = 0xae,
+    MFR_MAX_TEMP_1 = 0xc0,
+    MFR_MAX_TEMP_2 = 0xc1,
+    MFR_MAX_TEMP_3 = 0xc2,
+}
+
+// ----------------------------- Formats -----------------------------
+
+/// Value of a LINEAR11 word, an 11-bit two's complement mantissa
+/// scaled by a 5-bit two's complement exponent.
+pub fn linear11_to_f64(raw: u16) -> f64 {
+    let exponent = (raw as i16) >> 11;
+    let mantissa = ((raw << 5) as i16) >> 5;
+
+    f64::from(mantissa) * 2f64.powi(exponent.into())
+}
+
+/// LINEAR11 word closest to `value`, with the smallest exponent
+/// keeping the mantissa in range. Out of range values saturate.
+pub fn f64_to_linear11(value: f64) -> u16 {
+    let (mantissa, exponent) = (-16..=15)
+        .map(|exponent| ((value / 2f64.powi(exponent)).round(), exponent))
+        .find(|(mantissa, _)| (-1024.0..=1023.0).contains(mantissa))
+        .unwrap_or((value.signum() * 1023.0, 15));
+
+    ((exponent as u16 & 0x1f) << 11) | (mantissa as i16 as u16 & 0x7ff)
+}
+
+/// Value of a LINEAR16 word, unsigned and scaled by the exponent from
+/// `VOUT_MODE`.
+pub fn linear16_to_f64(raw: u16, exponent: i8) -> f64 {
+    f64::from(raw) * 2f64.powi(exponent.into())
+}
+
+/// LINEAR16 word closest to `value` for the exponent from `VOUT_MODE`.
+/// Out of range values saturate.
+pub fn f64_to_linear16(value: f64, exponent: i8) -> u16 {
+    (value / 2f64.powi(exponent.into()))
+        .round()
+        .clamp(0.0, f64::from(u16::MAX)) as u16
+}
+
+/// Value of an IEEE 754 half precision word.
+pub fn half_to_f64(raw: u16) -> f64 {
+    let sign = if raw & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((raw >> 10) & 0x1f);
+    let fraction = f64::from(raw & 0x3ff);
+
+    match exponent {
+        0 => sign * fraction * 2f64.powi(-24),
+        0x1f if fraction == 0.0 => sign * f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => sign * (1.0 + fraction / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+/// DIRECT format coefficients, from the datasheet or `COEFFICIENTS`.
+///
+/// A value `X` is sent as `Y = (m * X + b) * 10^R`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coefficients {
+    pub m: i16,
+    pub b: i16,
+    pub r: i8,
+}
+
+impl Coefficients {
+    /// Value of the DIRECT word `raw`.
+    pub fn decode(&self, raw: u16) -> f64 {
+        let y = f64::from(raw as i16);
+        (y * 10f64.powi(-i32::from(self.r)) - f64::from(self.b)) / f64::from(self.m)
+    }
+
+    /// DIRECT word closest to `value`. Out of range values saturate.
+    pub fn encode(&self, value: f64) -> u16 {
+        let y = (f64::from(self.m) * value + f64::from(self.b)) * 10f64.powi(self.r.into());
+        y.round().clamp(f64::from(i16::MIN), f64::from(i16::MAX)) as i16 as u16
+    }
+}
+
+/// Format of output voltages, as reported by `VOUT_MODE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoutMode {
+    /// LINEAR16 with the given exponent.
+    Linear { exponent: i8 },
+    /// VID codes, whose table is specific to the device.
+    Vid { code: u8 },
+    /// DIRECT, with coefficients from the datasheet or `COEFFICIENTS`.
+    Direct,
+    /// IEEE 754 half precision.
+    Ieee754Half,
+    /// Reserved mode.
+    Reserved(u8),
+}
+
+impl VoutMode {
+    /// Decode the `VOUT_MODE` byte. Bit 7, telling whether some
+    /// commands are relative to `VOUT_COMMAND`, is ignored.
+    pub fn from_byte(byte: u8) -> Self {
+        let parameter = byte & 0x1f;
+
+        match (byte >> 5) & 0x03 {
+            0 => Self::Linear {
+                // Sign-extend the 5-bit exponent
+                exponent: ((parameter << 3) as i8) >> 3,
+            },
+            1 => Self::Vid { code: parameter },
+            2 => Self::Direct,
+            _ if parameter == 0 => Self::Ieee754Half,
+            _ => Self::Reserved(byte),
+        }
+    }
+
+    /// Voltage of the word `raw`. `None` for VID and DIRECT, which
+    /// need more than `VOUT_MODE` to be decoded.
+    pub fn decode(&self, raw: u16) -> Option<f64> {
+        match *self {
+            Self::Linear { exponent } => Some(linear16_to_f64(raw, exponent)),
+            Self::Ieee754Half => Some(half_to_f64(raw)),
+            Self::Vid { .. } | Self::Direct | Self::Reserved(_) => None,
+        }
+    }
+}
+
+// ----------------------------- Status -----------------------------
+
+const STATUS_WORD_BITS: [Option<&str>; 16] = [
+    Some("NONE_OF_THE_ABOVE"),
+    Some("CML"),
+    Some("TEMPERATURE"),
+    Some("VIN_UV_FAULT"),
+    Some("IOUT_OC_FAULT"),
+    Some("VOUT_OV_FAULT"),
+    Some("OFF"),
+    Some("BUSY"),
+    Some("UNKNOWN"),
+    Some("OTHER"),
+    Some("FANS"),
+    Some("POWER_GOOD#"),
+    Some("MFR_SPECIFIC"),
+    Some("INPUT"),
+    Some("IOUT_POUT"),
+    Some("VOUT"),
+];
+
+const STATUS_VOUT_BITS: [Option<&str>; 8] = [
+    Some("VOUT_TRACKING_ERROR"),
+    Some("TOFF_MAX_WARNING"),
+    Some("TON_MAX_FAULT"),
+    Some("VOUT_MAX_MIN_WARNING"),
+    Some("VOUT_UV_FAULT"),
+    Some("VOUT_UV_WARNING"),
+    Some("VOUT_OV_WARNING"),
+    Some("VOUT_OV_FAULT"),
+];
+
+const STATUS_IOUT_BITS: [Option<&str>; 8] = [
+    Some("POUT_OP_WARNING"),
+    Some("POUT_OP_FAULT"),
+    Some("POWER_LIMITING"),
+    Some("CURRENT_SHARE_FAULT"),
+    Some("IOUT_UC_FAULT"),
+    Some("IOUT_OC_WARNING"),
+    Some("IOUT_OC_LV_FAULT"),
+    Some("IOUT_OC_FAULT"),
+];
+
+const STATUS_INPUT_BITS: [Option<&str>; 8] = [
+    Some("PIN_OP_WARNING"),
+    Some("IIN_OC_WARNING"),
+    Some("IIN_OC_FAULT"),
+    Some("UNIT_OFF_LOW_INPUT"),
+    Some("VIN_UV_FAULT"),
+    Some("VIN_UV_WARNING"),
+    Some("VIN_OV_WARNING"),
+    Some("VIN_OV_FAULT"),
+];
+
+const STATUS_TEMPERATURE_BITS: [Option<&str>; 8] = [
+    None,
+    None,
+    None,
+    None,
+    Some("UT_FAULT"),
+    Some("UT_WARNING"),
+    Some("OT_WARNING"),
+    Some("OT_FAULT"),
+];
+
+const STATUS_CML_BITS: [Option<&str>; 8] = [
+    Some("OTHER_MEMORY_OR_LOGIC_FAULT"),
+    Some("OTHER_COMMUNICATION_FAULT"),
+    None,
+    Some("PROCESSOR_FAULT"),
+    Some("MEMORY_FAULT"),
+    Some("PEC_FAILED"),
+    Some("INVALID_DATA"),
+    Some("INVALID_COMMAND"),
+];
+
+const STATUS_OTHER_BITS: [Option<&str>; 8] = [
+    Some("FIRST_TO_ASSERT_SMBALERT"),
+    Some("OUTPUT_ORING_FAULT"),
+    Some("INPUT_B_ORING_FAULT"),
+    Some("INPUT_A_ORING_FAULT"),
+    Some("INPUT_B_FUSE_FAULT"),
+    Some("INPUT_A_FUSE_FAULT"),
+    None,
+    None,
+];
+
+const STATUS_FANS_1_2_BITS: [Option<&str>; 8] = [
+    Some("AIRFLOW_WARNING"),
+    Some("AIRFLOW_FAULT"),
+    Some("FAN_2_SPEED_OVERRIDDEN"),
+    Some("FAN_1_SPEED_OVERRIDDEN"),
+    Some("FAN_2_WARNING"),
+    Some("FAN_1_WARNING"),
+    Some("FAN_2_FAULT"),
+    Some("FAN_1_FAULT"),
+];
+
+const STATUS_FANS_3_4_BITS: [Option<&str>; 8] = [
+    None,
+    None,
+    Some("FAN_4_SPEED_OVERRIDDEN"),
+    Some("FAN_3_SPEED_OVERRIDDEN"),
+    Some("FAN_4_WARNING"),
+    Some("FAN_3_WARNING"),
+    Some("FAN_4_FAULT"),
+    Some("FAN_3_FAULT"),
+];
+
+/// Names of the bits set in `value`, read from the status register
+/// `command`, from the most significant one down.
+///
+/// `STATUS_BYTE` is the low byte of `STATUS_WORD`. Nothing is decoded
+/// for `STATUS_MFR_SPECIFIC` and other commands, whose bits are
+/// specific to the device.
+pub fn status_flags(command: u8, value: u16) -> Vec<&'static str> {
+    let bits: &[Option<&'static str>] = match command {
+        command::STATUS_BYTE => &STATUS_WORD_BITS[..8],
+        command::STATUS_WORD => &STATUS_WORD_BITS,
+        command::STATUS_VOUT => &STATUS_VOUT_BITS,
+        command::STATUS_IOUT => &STATUS_IOUT_BITS,
+        command::STATUS_INPUT => &STATUS_INPUT_BITS,
+        command::STATUS_TEMPERATURE => &STATUS_TEMPERATURE_BITS,
+        command::STATUS_CML => &STATUS_CML_BITS,
+        command::STATUS_OTHER => &STATUS_OTHER_BITS,
+        command::STATUS_FANS_1_2 => &STATUS_FANS_1_2_BITS,
+        command::STATUS_FANS_3_4 => &STATUS_FANS_3_4_BITS,
+        _ => &[],
+    };
+
+    bits.iter()
+        .enumerate()
+        .rev()
+        .filter(|&(bit, _)| value & (1 << bit) != 0)
+        .filter_map(|(_, name)| *name)
+        .collect()
+}
+
+// ----------------------------- Device -----------------------------
+
+/// PMBus device at `address` on an I2C bus.
+pub struct Device<'a> {
+    gallo: &'a PicoDeGallo,
+    bus: u8,
+    address: u8,
+    pec: bool,
+}
+
+impl<'a> Device<'a> {
+    /// Device at `address` on I2C `bus`, checking a PEC on every
+    /// transaction when `pec` is set.
+    pub fn new(gallo: &'a PicoDeGallo, bus: u8, address: u8, pec: bool) -> Self {
+        Self {
+            gallo,
+            bus,
+            address,
+            pec,
+        }
+    }
+
+    /// Select the page, i.e. output, subsequent commands apply to.
+    pub async fn set_page(&self, page: u8) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        self.write_byte(command::PAGE, page).await
+    }
+
+    pub async fn write_byte(&self, command: u8, value: u8) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        self.gallo
+            .smbus_write_byte(self.bus, self.address, command, value, self.pec)
+            .await
+    }
+
+    pub async fn read_byte(&self, command: u8) -> Result<u8, PicoDeGalloError<SmbusFail>> {
+        self.gallo
+            .smbus_read_byte(self.bus, self.address, command, self.pec)
+            .await
+    }
+
+    pub async fn write_word(&self, command: u8, value: u16) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        self.gallo
+            .smbus_write_word(self.bus, self.address, command, value, self.pec)
+            .await
+    }
+
+    pub async fn read_word(&self, command: u8) -> Result<u16, PicoDeGalloError<SmbusFail>> {
+        self.gallo
+            .smbus_read_word(self.bus, self.address, command, self.pec)
+            .await
+    }
+
+    /// Read a block, such as `MFR_ID`, as text.
+    pub async fn read_string(&self, command: u8) -> Result<String, PicoDeGalloError<SmbusFail>> {
+        let data = self
+            .gallo
+            .smbus_block_read(self.bus, self.address, command, self.pec)
+            .await?;
+
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+
+    pub async fn vout_mode(&self) -> Result<VoutMode, PicoDeGalloError<SmbusFail>> {
+        self.read_byte(command::VOUT_MODE).await.map(VoutMode::from_byte)
+    }
+
+    /// Read a command holding a voltage in the format given by
+    /// `VOUT_MODE`, e.g. `READ_VOUT` or `VOUT_COMMAND`. `None` if that
+    /// format can't be decoded without more information, see
+    /// [`VoutMode::decode`].
+    pub async fn read_vout_format(&self, command: u8) -> Result<Option<f64>, PicoDeGalloError<SmbusFail>> {
+        let mode = self.vout_mode().await?;
+        let raw = self.read_word(command).await?;
+
+        Ok(mode.decode(raw))
+    }
+
+    /// Read a command in LINEAR11 format, e.g. `READ_IOUT`.
+    pub async fn read_linear11(&self, command: u8) -> Result<f64, PicoDeGalloError<SmbusFail>> {
+        self.read_word(command).await.map(linear11_to_f64)
+    }
+
+    /// Read a command in DIRECT format, scaled by `coefficients`.
+    pub async fn read_direct(
+        &self,
+        command: u8,
+        coefficients: &Coefficients,
+    ) -> Result<f64, PicoDeGalloError<SmbusFail>> {
+        self.read_word(command).await.map(|raw| coefficients.decode(raw))
+    }
+
+    /// Output voltage in V, see [`Device::read_vout_format`].
+    pub async fn read_vout(&self) -> Result<Option<f64>, PicoDeGalloError<SmbusFail>> {
+        self.read_vout_format(command::READ_VOUT).await
+    }
+
+    /// Input voltage in V.
+    pub async fn read_vin(&self) -> Result<f64, PicoDeGalloError<SmbusFail>> {
+        self.read_linear11(command::READ_VIN).await
+    }
+
+    /// Output current in A.
+    pub async fn read_iout(&self) -> Result<f64, PicoDeGalloError<SmbusFail>> {
+        self.read_linear11(command::READ_IOUT).await
+    }
+
+    /// Input current in A.
+    pub async fn read_iin(&self) -> Result<f64, PicoDeGalloError<SmbusFail>> {
+        self.read_linear11(command::READ_IIN).await
+    }
+
+    /// Output power in W.
+    pub async fn read_pout(&self) -> Result<f64, PicoDeGalloError<SmbusFail>> {
+        self.read_linear11(command::READ_POUT).await
+    }
+
+    /// Input power in W.
+    pub async fn read_pin(&self) -> Result<f64, PicoDeGalloError<SmbusFail>> {
+        self.read_linear11(command::READ_PIN).await
+    }
+
+    /// Temperature in °C from sensor `sensor`, 1 to 3.
+    pub async fn read_temperature(&self, sensor: u8) -> Result<f64, PicoDeGalloError<SmbusFail>> {
+        let command = match sensor {
+            2 => command::READ_TEMPERATURE_2,
+            3 => command::READ_TEMPERATURE_3,
+            _ => command::READ_TEMPERATURE_1,
+        };
+
+        self.read_linear11(command).await
+    }
+
+    /// Read the status register `command`, returning its raw value
+    /// along with the names of the bits set, see [`status_flags`].
+    pub async fn read_status(&self, command: u8) -> Result<(u16, Vec<&'static str>), PicoDeGalloError<SmbusFail>> {
+        let value = match command {
+            command::STATUS_WORD => self.read_word(command).await?,
+            _ => self.read_byte(command).await?.into(),
+        };
+
+        Ok((value, status_flags(command, value)))
+    }
+
+    /// Clear every latched fault.
+    pub async fn clear_faults(&self) -> Result<(), PicoDeGalloError<SmbusFail>> {
+        self.gallo
+            .smbus_send_byte(self.bus, self.address, command::CLEAR_FAULTS, self.pec)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear11_decodes() {
+        assert_eq!(linear11_to_f64(0x0000), 0.0);
+        // Exponent -10, mantissa 1000
+        assert_eq!(linear11_to_f64(0xb3e8), 0.9765625);
+        // Exponent -4, mantissa -1
+        assert_eq!(linear11_to_f64(0xe7ff), -0.0625);
+        // Exponent 0, mantissa -1
+        assert_eq!(linear11_to_f64(0x07ff), -1.0);
+        // Exponent 0, mantissa -1024
+        assert_eq!(linear11_to_f64(0x0400), -1024.0);
+        // Exponent 15, mantissa 1023
+        assert_eq!(linear11_to_f64(0x7bff), 1023.0 * 32768.0);
+        // Exponent -16, mantissa 1
+        assert_eq!(linear11_to_f64(0x8001), 2f64.powi(-16));
+    }
+
+    #[test]
+    fn linear11_encodes() {
+        // Exponent -14, mantissa -1024
+        assert_eq!(f64_to_linear11(-0.0625), 0x9400);
+        // Exponent -7, mantissa 1000
+        assert_eq!(f64_to_linear11(7.8125), 0xcbe8);
+        // Below 2^-7, precision is bound by the smallest exponent
+        assert_eq!(f64_to_linear11(0.001), 0x8042);
+        assert_eq!(linear11_to_f64(f64_to_linear11(0.0)), 0.0);
+    }
+
+    #[test]
+    fn linear11_saturates() {
+        assert_eq!(f64_to_linear11(1e12), 0x7bff);
+        assert_eq!(linear11_to_f64(f64_to_linear11(-1e12)), -1023.0 * 32768.0);
+    }
+
+    #[test]
+    fn linear11_round_trips() {
+        // Every LINEAR11 value has an exact encoding
+        for raw in 0..=u16::MAX {
+            let value = linear11_to_f64(raw);
+            assert_eq!(linear11_to_f64(f64_to_linear11(value)), value, "{raw:#06x}");
+        }
+
+        for value in [12.0, -3.3, 0.5, 1000.0, -250.75] {
+            let decoded = linear11_to_f64(f64_to_linear11(value));
+            assert!((decoded - value).abs() <= value.abs() / 1024.0, "{value}");
+        }
+    }
+
+    #[test]
+    fn linear16_converts() {
+        assert_eq!(linear16_to_f64(0x0200, -9), 1.0);
+        assert_eq!(linear16_to_f64(0x1a66, -9), 13.19921875);
+        assert_eq!(linear16_to_f64(0x0003, 2), 12.0);
+        assert_eq!(f64_to_linear16(1.0, -9), 0x0200);
+        assert_eq!(f64_to_linear16(3.3, -9), 0x069a);
+    }
+
+    #[test]
+    fn linear16_saturates() {
+        assert_eq!(f64_to_linear16(-1.0, -9), 0);
+        assert_eq!(f64_to_linear16(1000.0, -9), u16::MAX);
+    }
+
+    #[test]
+    fn linear16_round_trips() {
+        for raw in 0..=u16::MAX {
+            assert_eq!(f64_to_linear16(linear16_to_f64(raw, -9), -9), raw);
+        }
+    }
+
+    #[test]
+    fn half_decodes() {
+        assert_eq!(half_to_f64(0x0000), 0.0);
+        assert_eq!(half_to_f64(0x3c00), 1.0);
+        assert_eq!(half_to_f64(0xc000), -2.0);
+        assert_eq!(half_to_f64(0x3555), 0.333251953125);
+        assert_eq!(half_to_f64(0x7bff), 65504.0);
+        // Smallest normal and subnormal numbers
+        assert_eq!(half_to_f64(0x8400), -(2f64.powi(-14)));
+        assert_eq!(half_to_f64(0x0001), 2f64.powi(-24));
+        assert_eq!(half_to_f64(0x7c00), f64::INFINITY);
+        assert_eq!(half_to_f64(0xfc00), f64::NEG_INFINITY);
+        assert!(half_to_f64(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn direct_converts() {
+        let coefficients = Coefficients { m: 1, b: 0, r: 2 };
+        assert_eq!(coefficients.encode(12.34), 1234);
+        assert!((coefficients.decode(1234) - 12.34).abs() < 1e-9);
+
+        let coefficients = Coefficients { m: 5, b: -10, r: -1 };
+        assert_eq!(coefficients.encode(100.0), 49);
+        assert!((coefficients.decode(49) - 100.0).abs() < 1e-9);
+
+        // Y is two's complement
+        let coefficients = Coefficients { m: 1, b: 0, r: 0 };
+        assert_eq!(coefficients.decode(0xfff6), -10.0);
+        assert_eq!(coefficients.encode(-10.0), 0xfff6);
+    }
+
+    #[test]
+    fn direct_saturates() {
+        let coefficients = Coefficients { m: 1, b: 0, r: 0 };
+        assert_eq!(coefficients.encode(40000.0), 0x7fff);
+        assert_eq!(coefficients.encode(-40000.0), 0x8000);
+    }
+
+    #[test]
+    fn vout_mode_decodes() {
+        assert_eq!(VoutMode::from_byte(0x17), VoutMode::Linear { exponent: -9 });
+        assert_eq!(VoutMode::from_byte(0x97), VoutMode::Linear { exponent: -9 });
+        assert_eq!(VoutMode::from_byte(0x0f), VoutMode::Linear { exponent: 15 });
+        assert_eq!(VoutMode::from_byte(0x10), VoutMode::Linear { exponent: -16 });
+        assert_eq!(VoutMode::from_byte(0x1f), VoutMode::Linear { exponent: -1 });
+        assert_eq!(VoutMode::from_byte(0x21), VoutMode::Vid { code: 1 });
+        assert_eq!(VoutMode::from_byte(0x40), VoutMode::Direct);
+        assert_eq!(VoutMode::from_byte(0x60), VoutMode::Ieee754Half);
+        assert_eq!(VoutMode::from_byte(0x61), VoutMode::Reserved(0x61));
+    }
+
+    #[test]
+    fn vout_mode_decodes_words() {
+        assert_eq!(VoutMode::Linear { exponent: -9 }.decode(0x0200), Some(1.0));
+        assert_eq!(VoutMode::Ieee754Half.decode(0x3c00), Some(1.0));
+        assert_eq!(VoutMode::Direct.decode(0x0200), None);
+        assert_eq!(VoutMode::Vid { code: 1 }.decode(0x0200), None);
+    }
+}
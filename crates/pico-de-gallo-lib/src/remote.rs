@@ -0,0 +1,297 @@
+//! Remote access to a Pico de Gallo device.
+//!
+//! postcard-rpc frames are exchanged over a byte stream (e.g. TCP or
+//! a Unix socket), each one prefixed by its length as a little-endian
+//! `u32`.
+
+use crate::PicoDeGallo;
+use crate::frame::{read_frame, write_frame};
+use pico_de_gallo_internal::{BrokerLock, BrokerUnlock, ENDPOINT_LIST, TOPICS_IN_LIST, TOPICS_OUT_LIST};
+use postcard_rpc::{
+    Endpoint, Key,
+    header::{VarHeader, VarKey, VarSeq},
+    host_client::{HostErr, MultiSubRxError, RpcFrame, WireRx, WireTx},
+    standard_icd::{ERROR_KEY, WireError},
+};
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tokio::io::{AsyncRead, AsyncWrite, ReadHalf, WriteHalf};
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::sync::{RwLock, mpsc};
+use tokio::task::JoinSet;
+
+/// Number of frames queued towards a single remote client.
+const OUTGOING_DEPTH: usize = 32;
+
+// ----------------------------- Client side -----------------------------
+
+pub(crate) struct StreamTx<S>(pub(crate) WriteHalf<S>);
+
+impl<S: AsyncRead + AsyncWrite + Send + 'static> WireTx for StreamTx<S> {
+    type Error = io::Error;
+
+    async fn send(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+        write_frame(&mut self.0, &data).await
+    }
+}
+
+pub(crate) struct StreamRx<S>(pub(crate) ReadHalf<S>);
+
+impl<S: AsyncRead + AsyncWrite + Send + 'static> WireRx for StreamRx<S> {
+    type Error = io::Error;
+
+    async fn receive(&mut self) -> Result<Vec<u8>, Self::Error> {
+        read_frame(&mut self.0).await
+    }
+}
+
+// ----------------------------- Server side -----------------------------
+
+/// Shares a Pico de Gallo device with remote clients.
+///
+/// Every request received from a client is forwarded to the device
+/// and the response is sent back to the same client. Messages
+/// published by the device are sent to every connected client.
+///
+/// Requests from different clients may be interleaved, unless a client
+/// takes the bus lock with [`PicoDeGallo::lock`]. While the lock is
+/// held, requests from every other client are delayed until it is
+/// released or the client disconnects. Locks taken by a client are
+/// counted, the bus being released with the last one.
+#[derive(Clone)]
+pub struct RemoteServer {
+    gallo: PicoDeGallo,
+    seq: Arc<AtomicU32>,
+    bus: Arc<RwLock<()>>,
+}
+
+impl RemoteServer {
+    /// Create a new server sharing `gallo`.
+    pub fn new(gallo: PicoDeGallo) -> Self {
+        Self {
+            gallo,
+            seq: Arc::new(AtomicU32::new(0)),
+            bus: Arc::new(RwLock::new(())),
+        }
+    }
+
+    /// Accept TCP connections on `addr` and serve each one of them
+    /// until the device is disconnected.
+    pub async fn serve_tcp<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
+        let listener = TcpListener::bind(addr).await?;
+
+        loop {
+            let (stream, _) = tokio::select! {
+                _ = self.gallo.wait_closed() => return Err(device_closed()),
+                accepted = listener.accept() => accepted?,
+            };
+
+            stream.set_nodelay(true)?;
+
+            let server = self.clone();
+            tokio::spawn(async move {
+                let _ = server.serve_connection(stream).await;
+            });
+        }
+    }
+
+    /// Accept connections on the Unix socket at `path` and serve each
+    /// one of them until the device is disconnected.
+    ///
+    /// A stale socket left behind at `path` is removed first, but
+    /// neither another kind of file nor a socket still being served.
+    /// Only the current user may connect to the socket.
+    #[cfg(unix)]
+    pub async fn serve_unix<P: AsRef<std::path::Path>>(&self, path: P) -> io::Result<()> {
+        use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+        let path = path.as_ref();
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if !metadata.file_type().is_socket() {
+                return Err(io::Error::new(io::ErrorKind::AlreadyExists, "not a socket"));
+            }
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, "socket already served"));
+            }
+            std::fs::remove_file(path)?;
+        }
+
+        let listener = tokio::net::UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+
+        loop {
+            let (stream, _) = tokio::select! {
+                _ = self.gallo.wait_closed() => return Err(device_closed()),
+                accepted = listener.accept() => accepted?,
+            };
+
+            let server = self.clone();
+            tokio::spawn(async move {
+                let _ = server.serve_connection(stream).await;
+            });
+        }
+    }
+
+    /// Serve a single client connected through `stream`.
+    ///
+    /// Returns once the client closes the connection.
+    pub async fn serve_connection<S>(&self, stream: S) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (mut reader, mut writer) = tokio::io::split(stream);
+        let (tx, mut rx) = mpsc::channel::<Vec<u8>>(OUTGOING_DEPTH);
+        let mut tasks = JoinSet::new();
+        let mut exclusive = None;
+        let mut locks = 0usize;
+
+        tasks.spawn(async move {
+            while let Some(frame) = rx.recv().await {
+                if write_frame(&mut writer, &frame).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        for &(_, key) in TOPICS_OUT_LIST.topics {
+            let mut sub = self
+                .gallo
+                .client
+                .subscribe_multi_raw(key, OUTGOING_DEPTH)
+                .await
+                .map_err(|_| device_closed())?;
+            let tx = tx.clone();
+
+            tasks.spawn(async move {
+                loop {
+                    match sub.recv().await {
+                        Ok(frame) => {
+                            if tx.send(frame.to_bytes()).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(MultiSubRxError::Lagged(_)) => continue,
+                        Err(MultiSubRxError::IoClosed) => break,
+                    }
+                }
+            });
+        }
+
+        loop {
+            while tasks.try_join_next().is_some() {}
+
+            let frame = tokio::select! {
+                _ = self.gallo.wait_closed() => return Err(device_closed()),
+                frame = read_frame(&mut reader) => match frame {
+                    Ok(frame) => frame,
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                    Err(e) => return Err(e),
+                },
+            };
+
+            let Some((header, body)) = VarHeader::take_from_slice(&frame) else {
+                continue;
+            };
+            let frame = RpcFrame {
+                header,
+                body: body.to_vec(),
+            };
+
+            if header.key == VarKey::Key8(BrokerLock::REQ_KEY) {
+                if locks == 0 {
+                    exclusive = Some(self.bus.clone().write_owned().await);
+                }
+                locks += 1;
+
+                let _ = tx.send(empty_frame(BrokerLock::RESP_KEY, header.seq_no)).await;
+            } else if header.key == VarKey::Key8(BrokerUnlock::REQ_KEY) {
+                locks = locks.saturating_sub(1);
+                if locks == 0 {
+                    exclusive = None;
+                }
+
+                let _ = tx.send(empty_frame(BrokerUnlock::RESP_KEY, header.seq_no)).await;
+            } else if let Some(&(_, _, resp_key)) = ENDPOINT_LIST
+                .endpoints
+                .iter()
+                .find(|(_, req_key, _)| VarKey::Key8(*req_key) == header.key)
+            {
+                let server = self.clone();
+                let tx = tx.clone();
+                let shared = exclusive.is_none().then(|| self.bus.clone());
+
+                tasks.spawn(async move {
+                    let _shared = match shared {
+                        Some(bus) => Some(bus.read_owned().await),
+                        None => None,
+                    };
+
+                    if let Some(resp) = server.forward(frame, resp_key).await {
+                        let _ = tx.send(resp).await;
+                    }
+                });
+            } else if TOPICS_IN_LIST
+                .topics
+                .iter()
+                .any(|(_, key)| VarKey::Key8(*key) == header.key)
+            {
+                self.gallo
+                    .client
+                    .publish_raw(frame)
+                    .await
+                    .map_err(|_| device_closed())?;
+            } else {
+                let resp = error_frame(header.seq_no, &WireError::UnknownKey);
+                let _ = tx.send(resp).await;
+            }
+        }
+    }
+
+    /// Forward a request to the device and return the serialized
+    /// response, keeping the sequence number chosen by the client.
+    async fn forward(&self, mut frame: RpcFrame, resp_key: Key) -> Option<Vec<u8>> {
+        // Clients pick their own sequence numbers, replace them with
+        // our own so concurrent clients can't collide with each other.
+        let seq_no = frame.header.seq_no;
+        frame.header.seq_no = VarSeq::Seq4(self.seq.fetch_add(1, Ordering::Relaxed));
+
+        match self.gallo.client.send_resp_raw(frame, resp_key).await {
+            Ok(resp) => {
+                let resp = RpcFrame {
+                    header: VarHeader {
+                        key: resp.header.key,
+                        seq_no,
+                    },
+                    body: resp.body,
+                };
+                Some(resp.to_bytes())
+            }
+            Err(HostErr::Wire(e)) => Some(error_frame(seq_no, &e)),
+            Err(_) => None,
+        }
+    }
+}
+
+fn empty_frame(key: Key, seq_no: VarSeq) -> Vec<u8> {
+    let header = VarHeader {
+        key: VarKey::Key8(key),
+        seq_no,
+    };
+    header.write_to_vec()
+}
+
+fn error_frame(seq_no: VarSeq, err: &WireError) -> Vec<u8> {
+    let frame = RpcFrame {
+        header: VarHeader {
+            key: VarKey::Key8(ERROR_KEY),
+            seq_no,
+        },
+        body: postcard::to_stdvec(err).expect("Allocations should not ever fail"),
+    };
+    frame.to_bytes()
+}
+
+fn device_closed() -> io::Error {
+    io::Error::new(io::ErrorKind::NotConnected, "device disconnected")
+}
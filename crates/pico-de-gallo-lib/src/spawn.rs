@@ -0,0 +1,25 @@
+use postcard_rpc::host_client::WireSpawn;
+
+/// Runs every worker on a thread of its own, independently of
+/// whichever executor the caller happens to use, if any.
+pub(crate) struct ThreadSpawn;
+
+impl WireSpawn for ThreadSpawn {
+    fn spawn(&mut self, fut: impl Future<Output = ()> + Send + 'static) {
+        std::thread::Builder::new()
+            .name("pico-de-gallo".into())
+            .spawn(move || futures::executor::block_on(fut))
+            .expect("should have spawned worker thread");
+    }
+}
+
+/// Runs every worker as a task on the current tokio runtime.
+#[cfg(feature = "tokio")]
+pub(crate) struct TokioSpawn;
+
+#[cfg(feature = "tokio")]
+impl WireSpawn for TokioSpawn {
+    fn spawn(&mut self, fut: impl Future<Output = ()> + Send + 'static) {
+        core::mem::drop(tokio::spawn(fut));
+    }
+}
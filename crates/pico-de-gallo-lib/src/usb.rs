@@ -0,0 +1,142 @@
+//! USB transport for Pico de Gallo.
+//!
+//! Frames are exchanged over the bulk endpoints of the vendor
+//! specific interface. `nusb` completes transfers from its own event
+//! thread, hence this transport works with any executor.
+
+use nusb::DeviceInfo;
+use nusb::transfer::{Direction, EndpointType, Queue, RequestBuffer, TransferError};
+use postcard_rpc::host_client::{WireRx, WireTx};
+
+/// Largest IN transfer.
+const MAX_TRANSFER_SIZE: usize = 1024;
+
+/// IN transfers kept in flight, so frames keep coming while the
+/// previous one is being processed.
+const IN_FLIGHT_REQS: usize = 4;
+
+/// Consecutive IN errors tolerated before giving up.
+const MAX_STALL_RETRIES: usize = 10;
+
+/// Open the first device matching `func` and return both directions
+/// of its vendor specific interface.
+pub(crate) fn open<F: FnMut(&DeviceInfo) -> bool>(func: F) -> Result<(UsbTx, UsbRx), String> {
+    let info = nusb::list_devices()
+        .map_err(|e| format!("Error listing devices: {e:?}"))?
+        .find(func)
+        .ok_or_else(|| String::from("Failed to find matching nusb device!"))?;
+
+    // Interfaces can't be enumerated with WinUSB, the vendor specific
+    // interface is the first one anyway.
+    #[cfg(not(target_os = "windows"))]
+    let interface_id = info
+        .interfaces()
+        .find(|i| i.class() == 0xFF)
+        .map(|i| i.interface_number())
+        .ok_or_else(|| String::from("Failed to find matching interface!"))?;
+
+    #[cfg(target_os = "windows")]
+    let interface_id = 0;
+
+    let device = info.open().map_err(|e| format!("Failed opening device: {e:?}"))?;
+    let interface = device
+        .claim_interface(interface_id)
+        .map_err(|e| format!("Failed claiming interface: {e:?}"))?;
+
+    let mut max_packet_size = None;
+    let mut ep_in = None;
+    let mut ep_out = None;
+
+    for alt in interface.descriptors() {
+        for ep in alt.endpoints().filter(|e| e.transfer_type() == EndpointType::Bulk) {
+            match ep.direction() {
+                Direction::Out => {
+                    max_packet_size = Some(ep.max_packet_size());
+                    ep_out = Some(ep.address());
+                }
+                Direction::In => ep_in = Some(ep.address()),
+            }
+        }
+    }
+
+    let ep_out = ep_out.ok_or("Failed to find OUT EP")?;
+    let ep_in = ep_in.ok_or("Failed to find IN EP")?;
+
+    let tx = UsbTx {
+        queue: interface.bulk_out_queue(ep_out),
+        max_packet_size,
+    };
+    let rx = UsbRx {
+        queue: interface.bulk_in_queue(ep_in),
+        consecutive_errs: 0,
+    };
+
+    Ok((tx, rx))
+}
+
+pub(crate) struct UsbTx {
+    queue: Queue<Vec<u8>>,
+    max_packet_size: Option<usize>,
+}
+
+impl WireTx for UsbTx {
+    type Error = TransferError;
+
+    async fn send(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+        // A frame filling up its last packet is terminated by a ZLP
+        let needs_zlp = self.max_packet_size.is_none_or(|mps| data.len().is_multiple_of(mps));
+
+        self.queue.submit(data);
+        if needs_zlp {
+            self.queue.submit(Vec::new());
+        }
+
+        self.queue.next_complete().await.status?;
+        if needs_zlp {
+            self.queue.next_complete().await.status?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) struct UsbRx {
+    queue: Queue<RequestBuffer>,
+    consecutive_errs: usize,
+}
+
+impl WireRx for UsbRx {
+    type Error = TransferError;
+
+    async fn receive(&mut self) -> Result<Vec<u8>, Self::Error> {
+        loop {
+            for _ in self.queue.pending()..IN_FLIGHT_REQS {
+                self.queue.submit(RequestBuffer::new(MAX_TRANSFER_SIZE));
+            }
+
+            let completion = self.queue.next_complete().await;
+
+            match completion.status {
+                Ok(()) => {
+                    self.consecutive_errs = 0;
+                    return Ok(completion.data);
+                }
+                Err(e @ (TransferError::Stall | TransferError::Unknown))
+                    if self.consecutive_errs < MAX_STALL_RETRIES =>
+                {
+                    self.consecutive_errs += 1;
+
+                    // Drain the transfers still in flight before
+                    // clearing the halt condition.
+                    self.queue.cancel_all();
+                    while self.queue.pending() > 0 {
+                        let _ = self.queue.next_complete().await;
+                    }
+
+                    self.queue.clear_halt().map_err(|_| e)?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
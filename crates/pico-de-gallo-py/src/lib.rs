@@ -0,0 +1,1023 @@
+use pico_de_gallo_lib as lib;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3_async_runtimes::tokio::{future_into_py, get_runtime};
+use std::borrow::Cow;
+use std::fmt::{Debug, Display};
+
+// ----------------------------- Exceptions -----------------------------
+
+create_exception!(
+    pico_de_gallo,
+    PicoDeGalloError,
+    PyException,
+    "Base class of all Pico de Gallo errors."
+);
+create_exception!(
+    pico_de_gallo,
+    CommsError,
+    PicoDeGalloError,
+    "Communication with the device failed."
+);
+create_exception!(
+    pico_de_gallo,
+    I2cError,
+    PicoDeGalloError,
+    "An I2C operation failed."
+);
+create_exception!(
+    pico_de_gallo,
+    SpiError,
+    PicoDeGalloError,
+    "An SPI operation failed."
+);
+create_exception!(
+    pico_de_gallo,
+    GpioError,
+    PicoDeGalloError,
+    "A GPIO operation failed."
+);
+create_exception!(
+    pico_de_gallo,
+    ConfigError,
+    PicoDeGalloError,
+    "The configuration is invalid or setting it failed."
+);
+
+fn to_py_err<E: Debug>(err: lib::PicoDeGalloError<E>, endpoint: fn(String) -> PyErr) -> PyErr {
+    match err {
+        lib::PicoDeGalloError::Comms(e) => CommsError::new_err(format!("{:?}", e)),
+        lib::PicoDeGalloError::Endpoint(e) => endpoint(format!("{:?}", e)),
+    }
+}
+
+fn comms_error<E: Debug>(err: lib::PicoDeGalloError<E>) -> PyErr {
+    to_py_err(err, CommsError::new_err)
+}
+
+fn i2c_error<E: Debug>(err: lib::PicoDeGalloError<E>) -> PyErr {
+    to_py_err(err, I2cError::new_err)
+}
+
+fn spi_error<E: Debug>(err: lib::PicoDeGalloError<E>) -> PyErr {
+    to_py_err(err, SpiError::new_err)
+}
+
+fn gpio_error<E: Debug>(err: lib::PicoDeGalloError<E>) -> PyErr {
+    to_py_err(err, GpioError::new_err)
+}
+
+fn config_error<E: Display>(err: lib::PicoDeGalloError<E>) -> PyErr {
+    match err {
+        lib::PicoDeGalloError::Comms(e) => CommsError::new_err(format!("{:?}", e)),
+        lib::PicoDeGalloError::Endpoint(e) => ConfigError::new_err(e.to_string()),
+    }
+}
+
+/// Run `fut` to completion on the shared runtime, releasing the GIL
+/// meanwhile.
+fn block_on<F>(py: Python<'_>, fut: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    py.allow_threads(|| get_runtime().block_on(fut))
+}
+
+fn open(serial_number: Option<&str>) -> lib::PicoDeGallo {
+    match serial_number {
+        Some(serial_number) => lib::PicoDeGallo::new_with_serial_number(serial_number),
+        None => lib::PicoDeGallo::new(),
+    }
+}
+
+fn connect_error(err: std::io::Error) -> PyErr {
+    CommsError::new_err(err.to_string())
+}
+
+fn check_bus(bus: u8, count: usize) -> PyResult<u8> {
+    if usize::from(bus) < count {
+        Ok(bus)
+    } else {
+        Err(PyValueError::new_err(format!(
+            "no bus numbered {bus}, expected 0..{count}"
+        )))
+    }
+}
+
+// ----------------------------- Types -----------------------------
+
+/// SPI clock phase.
+#[pyclass(module = "pico_de_gallo", eq, eq_int)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum SpiPhase {
+    /// Data captured on the first clock transition, CPHA=0
+    CaptureOnFirstTransition,
+    /// Data captured on the second clock transition, CPHA=1
+    CaptureOnSecondTransition,
+}
+
+impl From<SpiPhase> for lib::SpiPhase {
+    fn from(value: SpiPhase) -> Self {
+        match value {
+            SpiPhase::CaptureOnFirstTransition => lib::SpiPhase::CaptureOnFirstTransition,
+            SpiPhase::CaptureOnSecondTransition => lib::SpiPhase::CaptureOnSecondTransition,
+        }
+    }
+}
+
+impl From<lib::SpiPhase> for SpiPhase {
+    fn from(value: lib::SpiPhase) -> Self {
+        match value {
+            lib::SpiPhase::CaptureOnFirstTransition => SpiPhase::CaptureOnFirstTransition,
+            lib::SpiPhase::CaptureOnSecondTransition => SpiPhase::CaptureOnSecondTransition,
+        }
+    }
+}
+
+/// SPI clock polarity.
+#[pyclass(module = "pico_de_gallo", eq, eq_int)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum SpiPolarity {
+    /// Clock idles low, CPOL=0
+    IdleLow,
+    /// Clock idles high, CPOL=1
+    IdleHigh,
+}
+
+impl From<SpiPolarity> for lib::SpiPolarity {
+    fn from(value: SpiPolarity) -> Self {
+        match value {
+            SpiPolarity::IdleLow => lib::SpiPolarity::IdleLow,
+            SpiPolarity::IdleHigh => lib::SpiPolarity::IdleHigh,
+        }
+    }
+}
+
+impl From<lib::SpiPolarity> for SpiPolarity {
+    fn from(value: lib::SpiPolarity) -> Self {
+        match value {
+            lib::SpiPolarity::IdleLow => SpiPolarity::IdleLow,
+            lib::SpiPolarity::IdleHigh => SpiPolarity::IdleHigh,
+        }
+    }
+}
+
+/// I2C bus configuration.
+#[pyclass(module = "pico_de_gallo", frozen, get_all)]
+#[derive(Clone, Copy)]
+pub struct I2cConfig {
+    /// SCL frequency in Hz
+    frequency: u32,
+}
+
+#[pymethods]
+impl I2cConfig {
+    #[new]
+    #[pyo3(signature = (frequency=lib::I2cConfig::default().frequency))]
+    fn new(frequency: u32) -> PyResult<Self> {
+        lib::I2cConfig::builder()
+            .frequency(frequency)
+            .build()
+            .map(Into::into)
+            .map_err(|e| ConfigError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("I2cConfig(frequency={})", self.frequency)
+    }
+}
+
+impl From<lib::I2cConfig> for I2cConfig {
+    fn from(value: lib::I2cConfig) -> Self {
+        Self {
+            frequency: value.frequency,
+        }
+    }
+}
+
+impl From<I2cConfig> for lib::I2cConfig {
+    fn from(value: I2cConfig) -> Self {
+        Self {
+            frequency: value.frequency,
+        }
+    }
+}
+
+/// Order in which the bits of each SPI word are shifted out.
+#[pyclass(module = "pico_de_gallo", eq, eq_int)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum SpiBitOrder {
+    /// Most significant bit first
+    MsbFirst,
+    /// Least significant bit first
+    LsbFirst,
+}
+
+impl From<SpiBitOrder> for lib::SpiBitOrder {
+    fn from(value: SpiBitOrder) -> Self {
+        match value {
+            SpiBitOrder::MsbFirst => lib::SpiBitOrder::MsbFirst,
+            SpiBitOrder::LsbFirst => lib::SpiBitOrder::LsbFirst,
+        }
+    }
+}
+
+impl From<lib::SpiBitOrder> for SpiBitOrder {
+    fn from(value: lib::SpiBitOrder) -> Self {
+        match value {
+            lib::SpiBitOrder::MsbFirst => SpiBitOrder::MsbFirst,
+            lib::SpiBitOrder::LsbFirst => SpiBitOrder::LsbFirst,
+        }
+    }
+}
+
+fn word_size(bits: u8) -> PyResult<lib::SpiWordSize> {
+    match bits {
+        8 => Ok(lib::SpiWordSize::Eight),
+        16 => Ok(lib::SpiWordSize::Sixteen),
+        _ => Err(ConfigError::new_err(format!(
+            "unsupported word size {bits}, expected 8 or 16"
+        ))),
+    }
+}
+
+/// SPI bus configuration.
+#[pyclass(module = "pico_de_gallo", frozen, get_all)]
+#[derive(Clone, Copy)]
+pub struct SpiConfig {
+    /// SCK frequency in Hz
+    frequency: u32,
+    phase: SpiPhase,
+    polarity: SpiPolarity,
+    /// Bits per word, 8 or 16
+    word_size: u8,
+    bit_order: SpiBitOrder,
+}
+
+#[pymethods]
+impl SpiConfig {
+    #[new]
+    #[pyo3(signature = (
+        frequency=lib::SpiConfig::default().frequency,
+        phase=SpiPhase::CaptureOnFirstTransition,
+        polarity=SpiPolarity::IdleLow,
+        word_size=8,
+        bit_order=SpiBitOrder::MsbFirst,
+    ))]
+    fn new(
+        frequency: u32,
+        phase: SpiPhase,
+        polarity: SpiPolarity,
+        word_size: u8,
+        bit_order: SpiBitOrder,
+    ) -> PyResult<Self> {
+        lib::SpiConfig::builder()
+            .frequency(frequency)
+            .phase(phase.into())
+            .polarity(polarity.into())
+            .word_size(self::word_size(word_size)?)
+            .bit_order(bit_order.into())
+            .build()
+            .map(Into::into)
+            .map_err(|e| ConfigError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SpiConfig(frequency={}, phase=SpiPhase.{:?}, polarity=SpiPolarity.{:?}, word_size={}, bit_order=SpiBitOrder.{:?})",
+            self.frequency,
+            lib::SpiPhase::from(self.phase),
+            lib::SpiPolarity::from(self.polarity),
+            self.word_size,
+            lib::SpiBitOrder::from(self.bit_order)
+        )
+    }
+}
+
+impl From<lib::SpiConfig> for SpiConfig {
+    fn from(value: lib::SpiConfig) -> Self {
+        Self {
+            frequency: value.frequency,
+            phase: value.phase.into(),
+            polarity: value.polarity.into(),
+            word_size: value.word_size as u8,
+            bit_order: value.bit_order.into(),
+        }
+    }
+}
+
+impl From<SpiConfig> for lib::SpiConfig {
+    fn from(value: SpiConfig) -> Self {
+        Self {
+            frequency: value.frequency,
+            phase: value.phase.into(),
+            polarity: value.polarity.into(),
+            // Validated when the config was created
+            word_size: word_size(value.word_size).unwrap_or(lib::SpiWordSize::Eight),
+            bit_order: value.bit_order.into(),
+        }
+    }
+}
+
+/// Firmware version.
+#[pyclass(module = "pico_de_gallo", frozen, get_all)]
+pub struct VersionInfo {
+    major: u16,
+    minor: u16,
+    patch: u32,
+}
+
+#[pymethods]
+impl VersionInfo {
+    fn __repr__(&self) -> String {
+        format!(
+            "VersionInfo(major={}, minor={}, patch={})",
+            self.major, self.minor, self.patch
+        )
+    }
+
+    fn __str__(&self) -> String {
+        format!("{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl From<lib::VersionInfo> for VersionInfo {
+    fn from(value: lib::VersionInfo) -> Self {
+        Self {
+            major: value.major,
+            minor: value.minor,
+            patch: value.patch,
+        }
+    }
+}
+
+// ----------------------------- Blocking API -----------------------------
+
+/// A Pico de Gallo device.
+///
+/// Without a `serial_number`, the first device found is used.
+#[pyclass(module = "pico_de_gallo")]
+pub struct PicoDeGallo {
+    gallo: lib::PicoDeGallo,
+}
+
+#[pymethods]
+impl PicoDeGallo {
+    #[new]
+    #[pyo3(signature = (serial_number=None))]
+    fn new(serial_number: Option<&str>) -> Self {
+        Self {
+            gallo: open(serial_number),
+        }
+    }
+
+    /// Connect to a device shared with `gallo serve` at `address`.
+    #[staticmethod]
+    fn connect_tcp(py: Python<'_>, address: String) -> PyResult<Self> {
+        let gallo = block_on(py, lib::PicoDeGallo::connect_tcp(address)).map_err(connect_error)?;
+        Ok(Self { gallo })
+    }
+
+    /// Connect to a device shared with `gallo daemon` on the Unix
+    /// socket at `path`.
+    #[cfg(unix)]
+    #[staticmethod]
+    fn connect_unix(py: Python<'_>, path: std::path::PathBuf) -> PyResult<Self> {
+        let gallo = block_on(py, lib::PicoDeGallo::connect_unix(path)).map_err(connect_error)?;
+        Ok(Self { gallo })
+    }
+
+    /// Send `id` to the device and get it back.
+    fn ping(&self, py: Python<'_>, id: u32) -> PyResult<u32> {
+        block_on(py, self.gallo.ping(id)).map_err(comms_error)
+    }
+
+    /// Get the firmware version.
+    fn version(&self, py: Python<'_>) -> PyResult<VersionInfo> {
+        block_on(py, self.gallo.version())
+            .map(Into::into)
+            .map_err(comms_error)
+    }
+
+    /// The I2C bus numbered by `bus`.
+    #[pyo3(signature = (bus=0))]
+    fn i2c(&self, bus: u8) -> PyResult<I2c> {
+        Ok(I2c {
+            gallo: self.gallo.clone(),
+            bus: check_bus(bus, lib::NUM_I2C_BUSES)?,
+        })
+    }
+
+    /// The SPI bus numbered by `bus`.
+    #[pyo3(signature = (bus=0))]
+    fn spi(&self, bus: u8) -> PyResult<Spi> {
+        Ok(Spi {
+            gallo: self.gallo.clone(),
+            bus: check_bus(bus, lib::NUM_SPI_BUSES)?,
+        })
+    }
+
+    /// The GPIO numbered by `pin`, 0 through 7.
+    fn gpio(&self, pin: u8) -> Gpio {
+        Gpio {
+            gallo: self.gallo.clone(),
+            pin,
+        }
+    }
+}
+
+/// The I2C bus of a Pico de Gallo device.
+#[pyclass(module = "pico_de_gallo")]
+pub struct I2c {
+    gallo: lib::PicoDeGallo,
+    bus: u8,
+}
+
+#[pymethods]
+impl I2c {
+    /// The bus number.
+    #[getter]
+    fn bus(&self) -> u8 {
+        self.bus
+    }
+
+    /// Read `count` bytes from the device at `address`.
+    fn read<'py>(&self, py: Python<'py>, address: u8, count: u16) -> PyResult<Cow<'py, [u8]>> {
+        block_on(py, self.gallo.i2c_read(self.bus, address, count))
+            .map(Cow::Owned)
+            .map_err(i2c_error)
+    }
+
+    /// Write `data` to the device at `address`.
+    fn write(&self, py: Python<'_>, address: u8, data: &[u8]) -> PyResult<()> {
+        block_on(py, self.gallo.i2c_write(self.bus, address, data)).map_err(i2c_error)
+    }
+
+    /// Write `data` to the device at `address`, then read `count`
+    /// bytes back.
+    fn write_read<'py>(
+        &self,
+        py: Python<'py>,
+        address: u8,
+        data: &[u8],
+        count: u16,
+    ) -> PyResult<Cow<'py, [u8]>> {
+        self.write(py, address, data)?;
+        self.read(py, address, count)
+    }
+
+    /// Apply `config` to the bus, returning the SCL frequency actually
+    /// achieved.
+    fn set_config(&self, py: Python<'_>, config: I2cConfig) -> PyResult<u32> {
+        block_on(py, self.gallo.i2c_set_config(self.bus, config.into())).map_err(config_error)
+    }
+
+    /// The current configuration of the bus.
+    fn config(&self, py: Python<'_>) -> PyResult<I2cConfig> {
+        block_on(py, self.gallo.get_config())
+            .map(|c| c.i2c[usize::from(self.bus)].into())
+            .map_err(comms_error)
+    }
+
+    /// The SCL frequency actually achieved, in Hz.
+    fn actual_frequency(&self, py: Python<'_>) -> PyResult<u32> {
+        block_on(py, self.gallo.get_config())
+            .map(|c| c.i2c_actual_frequency[usize::from(self.bus)])
+            .map_err(comms_error)
+    }
+}
+
+/// The SPI bus of a Pico de Gallo device.
+#[pyclass(module = "pico_de_gallo")]
+pub struct Spi {
+    gallo: lib::PicoDeGallo,
+    bus: u8,
+}
+
+#[pymethods]
+impl Spi {
+    /// The bus number.
+    #[getter]
+    fn bus(&self) -> u8 {
+        self.bus
+    }
+
+    /// Read `count` bytes.
+    fn read<'py>(&self, py: Python<'py>, count: u16) -> PyResult<Cow<'py, [u8]>> {
+        block_on(py, self.gallo.spi_read(self.bus, count))
+            .map(Cow::Owned)
+            .map_err(spi_error)
+    }
+
+    /// Write `data`.
+    fn write(&self, py: Python<'_>, data: &[u8]) -> PyResult<()> {
+        block_on(py, self.gallo.spi_write(self.bus, data)).map_err(spi_error)
+    }
+
+    /// Write `data`, then read `count` bytes.
+    fn write_read<'py>(
+        &self,
+        py: Python<'py>,
+        data: &[u8],
+        count: u16,
+    ) -> PyResult<Cow<'py, [u8]>> {
+        self.write(py, data)?;
+        self.read(py, count)
+    }
+
+    /// Flush the SPI interface.
+    fn flush(&self, py: Python<'_>) -> PyResult<()> {
+        block_on(py, self.gallo.spi_flush(self.bus)).map_err(spi_error)
+    }
+
+    /// Apply `config` to the bus, returning the SCK frequency actually
+    /// achieved.
+    fn set_config(&self, py: Python<'_>, config: SpiConfig) -> PyResult<u32> {
+        block_on(py, self.gallo.spi_set_config(self.bus, config.into())).map_err(config_error)
+    }
+
+    /// The current configuration of the bus.
+    fn config(&self, py: Python<'_>) -> PyResult<SpiConfig> {
+        block_on(py, self.gallo.get_config())
+            .map(|c| c.spi[usize::from(self.bus)].into())
+            .map_err(comms_error)
+    }
+
+    /// The SCK frequency actually achieved, in Hz.
+    fn actual_frequency(&self, py: Python<'_>) -> PyResult<u32> {
+        block_on(py, self.gallo.get_config())
+            .map(|c| c.spi_actual_frequency[usize::from(self.bus)])
+            .map_err(comms_error)
+    }
+}
+
+/// A GPIO of a Pico de Gallo device.
+#[pyclass(module = "pico_de_gallo")]
+pub struct Gpio {
+    gallo: lib::PicoDeGallo,
+    pin: u8,
+}
+
+#[pymethods]
+impl Gpio {
+    /// The GPIO number.
+    #[getter]
+    fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// Whether the GPIO is high.
+    fn get(&self, py: Python<'_>) -> PyResult<bool> {
+        block_on(py, self.gallo.gpio_get(self.pin))
+            .map(|s| s == lib::GpioState::High)
+            .map_err(gpio_error)
+    }
+
+    /// Drive the GPIO high if `high`, low otherwise.
+    fn put(&self, py: Python<'_>, high: bool) -> PyResult<()> {
+        let state = if high {
+            lib::GpioState::High
+        } else {
+            lib::GpioState::Low
+        };
+        block_on(py, self.gallo.gpio_put(self.pin, state)).map_err(gpio_error)
+    }
+
+    /// Wait for the GPIO to be high.
+    fn wait_for_high(&self, py: Python<'_>) -> PyResult<()> {
+        block_on(py, self.gallo.gpio_wait_for_high(self.pin)).map_err(gpio_error)
+    }
+
+    /// Wait for the GPIO to be low.
+    fn wait_for_low(&self, py: Python<'_>) -> PyResult<()> {
+        block_on(py, self.gallo.gpio_wait_for_low(self.pin)).map_err(gpio_error)
+    }
+
+    /// Wait for a rising edge on the GPIO.
+    fn wait_for_rising_edge(&self, py: Python<'_>) -> PyResult<()> {
+        block_on(py, self.gallo.gpio_wait_for_rising_edge(self.pin)).map_err(gpio_error)
+    }
+
+    /// Wait for a falling edge on the GPIO.
+    fn wait_for_falling_edge(&self, py: Python<'_>) -> PyResult<()> {
+        block_on(py, self.gallo.gpio_wait_for_falling_edge(self.pin)).map_err(gpio_error)
+    }
+
+    /// Wait for either a rising or a falling edge on the GPIO.
+    fn wait_for_any_edge(&self, py: Python<'_>) -> PyResult<()> {
+        block_on(py, self.gallo.gpio_wait_for_any_edge(self.pin)).map_err(gpio_error)
+    }
+}
+
+// ----------------------------- asyncio API -----------------------------
+
+/// A Pico de Gallo device for use with asyncio, every method returns
+/// an awaitable.
+///
+/// Without a `serial_number`, the first device found is used.
+#[pyclass(module = "pico_de_gallo")]
+pub struct AsyncPicoDeGallo {
+    gallo: lib::PicoDeGallo,
+}
+
+#[pymethods]
+impl AsyncPicoDeGallo {
+    #[new]
+    #[pyo3(signature = (serial_number=None))]
+    fn new(serial_number: Option<&str>) -> Self {
+        Self {
+            gallo: open(serial_number),
+        }
+    }
+
+    /// Connect to a device shared with `gallo serve` at `address`.
+    #[staticmethod]
+    fn connect_tcp(py: Python<'_>, address: String) -> PyResult<Bound<'_, PyAny>> {
+        future_into_py(py, async move {
+            let gallo = lib::PicoDeGallo::connect_tcp(address)
+                .await
+                .map_err(connect_error)?;
+            Ok(Self { gallo })
+        })
+    }
+
+    /// Connect to a device shared with `gallo daemon` on the Unix
+    /// socket at `path`.
+    #[cfg(unix)]
+    #[staticmethod]
+    fn connect_unix(py: Python<'_>, path: std::path::PathBuf) -> PyResult<Bound<'_, PyAny>> {
+        future_into_py(py, async move {
+            let gallo = lib::PicoDeGallo::connect_unix(path)
+                .await
+                .map_err(connect_error)?;
+            Ok(Self { gallo })
+        })
+    }
+
+    /// Send `id` to the device and get it back.
+    fn ping<'py>(&self, py: Python<'py>, id: u32) -> PyResult<Bound<'py, PyAny>> {
+        let gallo = self.gallo.clone();
+        future_into_py(py, async move { gallo.ping(id).await.map_err(comms_error) })
+    }
+
+    /// Get the firmware version.
+    fn version<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let gallo = self.gallo.clone();
+        future_into_py(py, async move {
+            gallo
+                .version()
+                .await
+                .map(VersionInfo::from)
+                .map_err(comms_error)
+        })
+    }
+
+    /// The I2C bus numbered by `bus`.
+    #[pyo3(signature = (bus=0))]
+    fn i2c(&self, bus: u8) -> PyResult<AsyncI2c> {
+        Ok(AsyncI2c {
+            gallo: self.gallo.clone(),
+            bus: check_bus(bus, lib::NUM_I2C_BUSES)?,
+        })
+    }
+
+    /// The SPI bus numbered by `bus`.
+    #[pyo3(signature = (bus=0))]
+    fn spi(&self, bus: u8) -> PyResult<AsyncSpi> {
+        Ok(AsyncSpi {
+            gallo: self.gallo.clone(),
+            bus: check_bus(bus, lib::NUM_SPI_BUSES)?,
+        })
+    }
+
+    /// The GPIO numbered by `pin`, 0 through 7.
+    fn gpio(&self, pin: u8) -> AsyncGpio {
+        AsyncGpio {
+            gallo: self.gallo.clone(),
+            pin,
+        }
+    }
+}
+
+/// The I2C bus of a Pico de Gallo device, for use with asyncio.
+#[pyclass(module = "pico_de_gallo")]
+pub struct AsyncI2c {
+    gallo: lib::PicoDeGallo,
+    bus: u8,
+}
+
+#[pymethods]
+impl AsyncI2c {
+    /// The bus number.
+    #[getter]
+    fn bus(&self) -> u8 {
+        self.bus
+    }
+
+    /// Read `count` bytes from the device at `address`.
+    fn read<'py>(&self, py: Python<'py>, address: u8, count: u16) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo
+                .i2c_read(bus, address, count)
+                .await
+                .map(Cow::<[u8]>::Owned)
+                .map_err(i2c_error)
+        })
+    }
+
+    /// Write `data` to the device at `address`.
+    fn write<'py>(
+        &self,
+        py: Python<'py>,
+        address: u8,
+        data: Vec<u8>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo
+                .i2c_write(bus, address, &data)
+                .await
+                .map_err(i2c_error)
+        })
+    }
+
+    /// Write `data` to the device at `address`, then read `count`
+    /// bytes back.
+    fn write_read<'py>(
+        &self,
+        py: Python<'py>,
+        address: u8,
+        data: Vec<u8>,
+        count: u16,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo
+                .i2c_write(bus, address, &data)
+                .await
+                .map_err(i2c_error)?;
+            gallo
+                .i2c_read(bus, address, count)
+                .await
+                .map(Cow::<[u8]>::Owned)
+                .map_err(i2c_error)
+        })
+    }
+
+    /// Apply `config` to the bus, returning the SCL frequency actually
+    /// achieved.
+    fn set_config<'py>(&self, py: Python<'py>, config: I2cConfig) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo
+                .i2c_set_config(bus, config.into())
+                .await
+                .map_err(config_error)
+        })
+    }
+
+    /// The current configuration of the bus.
+    fn config<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo
+                .get_config()
+                .await
+                .map(|c| I2cConfig::from(c.i2c[usize::from(bus)]))
+                .map_err(comms_error)
+        })
+    }
+
+    /// The SCL frequency actually achieved, in Hz.
+    fn actual_frequency<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo
+                .get_config()
+                .await
+                .map(|c| c.i2c_actual_frequency[usize::from(bus)])
+                .map_err(comms_error)
+        })
+    }
+}
+
+/// The SPI bus of a Pico de Gallo device, for use with asyncio.
+#[pyclass(module = "pico_de_gallo")]
+pub struct AsyncSpi {
+    gallo: lib::PicoDeGallo,
+    bus: u8,
+}
+
+#[pymethods]
+impl AsyncSpi {
+    /// The bus number.
+    #[getter]
+    fn bus(&self) -> u8 {
+        self.bus
+    }
+
+    /// Read `count` bytes.
+    fn read<'py>(&self, py: Python<'py>, count: u16) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo
+                .spi_read(bus, count)
+                .await
+                .map(Cow::<[u8]>::Owned)
+                .map_err(spi_error)
+        })
+    }
+
+    /// Write `data`.
+    fn write<'py>(&self, py: Python<'py>, data: Vec<u8>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo.spi_write(bus, &data).await.map_err(spi_error)
+        })
+    }
+
+    /// Write `data`, then read `count` bytes.
+    fn write_read<'py>(
+        &self,
+        py: Python<'py>,
+        data: Vec<u8>,
+        count: u16,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo.spi_write(bus, &data).await.map_err(spi_error)?;
+            gallo
+                .spi_read(bus, count)
+                .await
+                .map(Cow::<[u8]>::Owned)
+                .map_err(spi_error)
+        })
+    }
+
+    /// Flush the SPI interface.
+    fn flush<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(
+            py,
+            async move { gallo.spi_flush(bus).await.map_err(spi_error) },
+        )
+    }
+
+    /// Apply `config` to the bus, returning the SCK frequency actually
+    /// achieved.
+    fn set_config<'py>(&self, py: Python<'py>, config: SpiConfig) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo
+                .spi_set_config(bus, config.into())
+                .await
+                .map_err(config_error)
+        })
+    }
+
+    /// The current configuration of the bus.
+    fn config<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo
+                .get_config()
+                .await
+                .map(|c| SpiConfig::from(c.spi[usize::from(bus)]))
+                .map_err(comms_error)
+        })
+    }
+
+    /// The SCK frequency actually achieved, in Hz.
+    fn actual_frequency<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, bus) = (self.gallo.clone(), self.bus);
+        future_into_py(py, async move {
+            gallo
+                .get_config()
+                .await
+                .map(|c| c.spi_actual_frequency[usize::from(bus)])
+                .map_err(comms_error)
+        })
+    }
+}
+
+/// A GPIO of a Pico de Gallo device, for use with asyncio.
+#[pyclass(module = "pico_de_gallo")]
+pub struct AsyncGpio {
+    gallo: lib::PicoDeGallo,
+    pin: u8,
+}
+
+#[pymethods]
+impl AsyncGpio {
+    /// The GPIO number.
+    #[getter]
+    fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// Whether the GPIO is high.
+    fn get<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, pin) = (self.gallo.clone(), self.pin);
+        future_into_py(py, async move {
+            gallo
+                .gpio_get(pin)
+                .await
+                .map(|s| s == lib::GpioState::High)
+                .map_err(gpio_error)
+        })
+    }
+
+    /// Drive the GPIO high if `high`, low otherwise.
+    fn put<'py>(&self, py: Python<'py>, high: bool) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, pin) = (self.gallo.clone(), self.pin);
+        let state = if high {
+            lib::GpioState::High
+        } else {
+            lib::GpioState::Low
+        };
+        future_into_py(py, async move {
+            gallo.gpio_put(pin, state).await.map_err(gpio_error)
+        })
+    }
+
+    /// Wait for the GPIO to be high.
+    fn wait_for_high<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, pin) = (self.gallo.clone(), self.pin);
+        future_into_py(py, async move {
+            gallo.gpio_wait_for_high(pin).await.map_err(gpio_error)
+        })
+    }
+
+    /// Wait for the GPIO to be low.
+    fn wait_for_low<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, pin) = (self.gallo.clone(), self.pin);
+        future_into_py(py, async move {
+            gallo.gpio_wait_for_low(pin).await.map_err(gpio_error)
+        })
+    }
+
+    /// Wait for a rising edge on the GPIO.
+    fn wait_for_rising_edge<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, pin) = (self.gallo.clone(), self.pin);
+        future_into_py(py, async move {
+            gallo
+                .gpio_wait_for_rising_edge(pin)
+                .await
+                .map_err(gpio_error)
+        })
+    }
+
+    /// Wait for a falling edge on the GPIO.
+    fn wait_for_falling_edge<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, pin) = (self.gallo.clone(), self.pin);
+        future_into_py(py, async move {
+            gallo
+                .gpio_wait_for_falling_edge(pin)
+                .await
+                .map_err(gpio_error)
+        })
+    }
+
+    /// Wait for either a rising or a falling edge on the GPIO.
+    fn wait_for_any_edge<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (gallo, pin) = (self.gallo.clone(), self.pin);
+        future_into_py(py, async move {
+            gallo.gpio_wait_for_any_edge(pin).await.map_err(gpio_error)
+        })
+    }
+}
+
+// ----------------------------- Module -----------------------------
+
+/// Python bindings for Pico de Gallo.
+#[pymodule]
+fn pico_de_gallo(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+
+    m.add_class::<PicoDeGallo>()?;
+    m.add_class::<I2c>()?;
+    m.add_class::<Spi>()?;
+    m.add_class::<Gpio>()?;
+    m.add_class::<AsyncPicoDeGallo>()?;
+    m.add_class::<AsyncI2c>()?;
+    m.add_class::<AsyncSpi>()?;
+    m.add_class::<AsyncGpio>()?;
+    m.add_class::<SpiPhase>()?;
+    m.add_class::<SpiPolarity>()?;
+    m.add_class::<SpiBitOrder>()?;
+    m.add_class::<I2cConfig>()?;
+    m.add_class::<SpiConfig>()?;
+    m.add_class::<VersionInfo>()?;
+
+    m.add("PicoDeGalloError", py.get_type::<PicoDeGalloError>())?;
+    m.add("CommsError", py.get_type::<CommsError>())?;
+    m.add("I2cError", py.get_type::<I2cError>())?;
+    m.add("SpiError", py.get_type::<SpiError>())?;
+    m.add("GpioError", py.get_type::<GpioError>())?;
+    m.add("ConfigError", py.get_type::<ConfigError>())?;
+
+    Ok(())
+}